@@ -1,4 +1,5 @@
 use parking_lot::Mutex;
+use std::mem;
 use std::sync::Arc;
 
 use async_recursion::async_recursion;
@@ -9,13 +10,16 @@ use syntax::program::code::{
     EffectType, Effects, ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression,
 };
 use syntax::program::function::{CodeBody, FinalizedCodeBody};
+use syntax::program::r#struct::{ARRAY, I64};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
 use syntax::SimpleVariableManager;
 
 use crate::check_impl_call::check_impl_call;
+use crate::check_lambda::check_lambda;
 use crate::check_method_call::check_method_call;
 use crate::check_operator::check_operator;
+use crate::check_try::{check_try, find_try};
 use crate::{get_return, CodeVerifier};
 
 /// Verifies a block of code, linking all method calls and types, and making sure the code is ready to compile.
@@ -28,6 +32,13 @@ pub async fn verify_code(
     let mut body = Vec::default();
     let mut found_end = false;
     for line in code.expressions {
+        // A line using ? checks the option and returns early, which takes a few expressions
+        if find_try(&line).is_some() {
+            let label = format!("{}try{}", code.label, body.len());
+            body.extend(check_try(code_verifier, variables, line, label).await?);
+            continue;
+        }
+
         match &line.effect.types {
             EffectType::CompareJump(_, _, _) => found_end = true,
             EffectType::Jump(_) => found_end = true,
@@ -52,7 +63,7 @@ pub async fn verify_code(
 }
 
 /// Checks to make sure the return type matches in the code block.
-async fn check_return_type(
+pub async fn check_return_type(
     line: ExpressionType,
     code_verifier: &CodeVerifier<'_>,
     body: &mut Vec<FinalizedExpression>,
@@ -71,7 +82,7 @@ async fn check_return_type(
 
     let last_effect = body.pop().unwrap();
     let last_effect_type;
-    if let Some(found) = get_return(&last_effect.effect.types, variables, syntax).await {
+    if let Some(found) = get_return(&last_effect.effect, variables, syntax).await? {
         last_effect_type = found;
     } else {
         // This is an if/for/while block, skip it
@@ -110,6 +121,9 @@ pub async fn verify_effect(
     if let Some(found) = finalize_basic(&effect).await {
         return Ok(found);
     }
+    if let Some(elements) = array_elements(&effect) {
+        return verify_array(code_verifier, variables, effect.span, elements).await;
+    }
 
     let output = match effect.types {
         EffectType::Paren(inner) => verify_effect(code_verifier, variables, *inner).await?,
@@ -117,16 +131,34 @@ pub async fn verify_effect(
             effect.span.clone(),
             FinalizedEffectType::CodeBody(verify_code(code_verifier, &mut variables.clone(), body, false).await?),
         ),
-        EffectType::Set(first, second) => FinalizedEffects::new(
-            effect.span.clone(),
-            FinalizedEffectType::Set(
-                Box::new(verify_effect(code_verifier, variables, *first).await?),
-                Box::new(verify_effect(code_verifier, variables, *second).await?),
-            ),
-        ),
+        EffectType::Set(first, second) => {
+            let first = verify_effect(code_verifier, variables, *first).await?;
+            let mut second = verify_effect(code_verifier, variables, *second).await?;
+            if let Some(target) = get_return(&first, variables, &code_verifier.syntax).await? {
+                coerce_literal(&mut second, &target)?;
+            }
+            // Variables keep the type they were made with
+            if let FinalizedEffectType::LoadVariable(name) = &first.types {
+                let target = variables.variables.get(name).cloned();
+                let found = get_return(&second, variables, &code_verifier.syntax).await?;
+                if let (Some(target), Some(found)) = (target, found) {
+                    if !found.of_type(&target, code_verifier.syntax.clone()).await {
+                        return Err(second.span.make_error(ParsingMessage::MismatchedTypes(found, target)));
+                    }
+                }
+            }
+            FinalizedEffects::new(
+                Span::merge(effect.span, Span::merge(first.span, second.span)),
+                FinalizedEffectType::Set(Box::new(first), Box::new(second)),
+            )
+        }
+        EffectType::Operation(operation, _) if operation == "{}?" => {
+            return Err(effect.span.make_error(ParsingMessage::MisplacedTry));
+        }
         EffectType::Operation(_, _) => check_operator(code_verifier, variables, effect).await?,
         EffectType::ImplementationCall(_, _, _, _) => check_impl_call(code_verifier, variables, effect).await?,
         EffectType::MethodCall(_, _, _, _) => check_method_call(code_verifier, variables, effect).await?,
+        EffectType::Lambda(_, _) => check_lambda(code_verifier, variables, effect).await?,
         EffectType::CompareJump(effect, first, second) => FinalizedEffects::new(
             effect.span.clone(),
             FinalizedEffectType::CompareJump(
@@ -135,46 +167,65 @@ pub async fn verify_effect(
                 second,
             ),
         ),
-        EffectType::CreateStruct(target, effects) => verify_create_struct(code_verifier, target, effects, variables).await?,
+        EffectType::CreateStruct(target, effects, base) => {
+            verify_create_struct(code_verifier, target, effects, base, variables).await?
+        }
+        EffectType::Range(start, end) => verify_range(code_verifier, variables, effect.span, *start, *end).await?,
         EffectType::Load(inner_effect, target) => {
             let output = verify_effect(code_verifier, variables, *inner_effect).await?;
-            let types = get_return(&output.types, variables, &code_verifier.syntax).await.unwrap();
+            let Some(types) = get_return(&output, variables, &code_verifier.syntax).await? else {
+                return Err(output.span.make_error(ParsingMessage::UnexpectedVoid));
+            };
+            // Values without the field, like number literals, can't be loaded from
+            if let Some(structure) = types.inner_struct_safe() {
+                if !structure.fields.iter().any(|field| field.field.name == target) {
+                    return Err(effect.span.make_error(ParsingMessage::UnknownField(target)));
+                }
+            }
 
-            FinalizedEffects::new(effect.span.clone(), FinalizedEffectType::Load(Box::new(output), target.clone(), types))
+            FinalizedEffects::new(
+                Span::merge(effect.span, output.span),
+                FinalizedEffectType::Load(Box::new(output), target.clone(), types),
+            )
         }
         EffectType::CreateVariable(name, inner_effect) => {
             let effect = verify_effect(code_verifier, variables, *inner_effect).await?;
             let found;
-            if let Some(temp_found) = get_return(&effect.types, variables, &code_verifier.syntax).await {
+            if let Some(temp_found) = get_return(&effect, variables, &code_verifier.syntax).await? {
                 found = temp_found;
             } else {
                 return Err(effect.span.make_error(ParsingMessage::UnexpectedVoid));
             };
 
+            // Copy types live on the stack instead of being heap allocated
+            let effect = match effect.types {
+                FinalizedEffectType::HeapStore(inner)
+                    if found.inner_struct_safe().map_or(false, |structure| structure.data.is_copy()) =>
+                {
+                    FinalizedEffects::new(effect.span, FinalizedEffectType::StackStore(inner))
+                }
+                types => FinalizedEffects::new(effect.span, types),
+            };
+
             variables.variables.insert(name.clone(), found.clone());
             FinalizedEffects::new(
                 effect.span.clone(),
                 FinalizedEffectType::CreateVariable(name.clone(), Box::new(effect), found),
             )
         }
-        EffectType::CreateArray(effects) => {
-            let mut output = Vec::default();
-            for effect in effects {
-                output.push(verify_effect(code_verifier, variables, effect).await?);
-            }
-
-            let types = match output.first() {
-                Some(found) => get_return(&found.types, variables, &code_verifier.syntax).await,
-                None => None,
-            };
-
-            check_type(&types, &output, variables, code_verifier, &effect.span).await?;
-
-            // TODO add this
-            panic!("Array creation through operators not finished yet!");
-            //FinalizedEffects::new(effect.span.clone(), store(FinalizedEffectType::CreateArray(types, output)))
+        // Handled by finalize_basic and array_elements
+        EffectType::CreateArray(_)
+        | EffectType::NOP
+        | EffectType::Jump(_)
+        | EffectType::LoadVariable(_)
+        | EffectType::Float(_)
+        | EffectType::Int(_)
+        | EffectType::UInt(_)
+        | EffectType::Bool(_)
+        | EffectType::String(_)
+        | EffectType::Char(_) => {
+            return Err(effect.span.make_error(ParsingMessage::ShouldntSee("Basic effect or array in verify_effect")));
         }
-        _ => unreachable!(),
     };
 
     return Ok(output);
@@ -189,6 +240,10 @@ async fn finalize_basic(effects: &Effects) -> Option<FinalizedEffects> {
             EffectType::Jump(jumping) => FinalizedEffectType::Jump(jumping.clone()),
             EffectType::LoadVariable(variable) => FinalizedEffectType::LoadVariable(variable.clone()),
             EffectType::Float(float) => store(FinalizedEffectType::Float(*float)),
+            // Literals are never negative, so negative integers come from folding constants like 2 - 3
+            EffectType::Int(int) if *int < 0 => {
+                store(FinalizedEffectType::Number(*int as u64, FinalizedTypes::Struct(I64.clone())))
+            }
             EffectType::Int(int) => store(FinalizedEffectType::UInt(*int as u64)),
             EffectType::UInt(uint) => store(FinalizedEffectType::UInt(*uint)),
             EffectType::Bool(bool) => store(FinalizedEffectType::Bool(*bool)),
@@ -204,12 +259,17 @@ async fn verify_create_struct(
     code_verifier: &mut CodeVerifier<'_>,
     target: UnparsedType,
     effects: Vec<(String, Effects)>,
+    base: Option<Box<Effects>>,
     variables: &mut SimpleVariableManager,
 ) -> Result<FinalizedEffects, ParsingError> {
+    let type_span = target.get_span();
     let target = Syntax::parse_type(code_verifier.syntax.clone(), code_verifier.resolver.boxed_clone(), target, vec![])
         .await?
         .finalize(code_verifier.syntax.clone())
         .await;
+    if let Some(structure) = target.inner_struct_safe() {
+        code_verifier.syntax.lock().check_deprecated(&structure.data.attributes, &structure.data.name, &type_span);
+    }
 
     let mut generics = code_verifier.process_manager.generics.clone();
     let mut final_effects = vec![];
@@ -226,11 +286,15 @@ async fn verify_create_struct(
         if i == fields.len() {
             return Err(effect.span.make_error(ParsingMessage::UnknownField(field_name)));
         }
+        if final_effects.iter().any(|(index, _)| *index == i) {
+            return Err(effect.span.make_error(ParsingMessage::DuplicateField(field_name)));
+        }
 
         let error = effect.span.clone();
-        let final_effect = verify_effect(code_verifier, variables, effect).await?;
-        get_return(&final_effect.types, variables, &code_verifier.syntax)
-            .await
+        let mut final_effect = verify_effect(code_verifier, variables, effect).await?;
+        coerce_literal(&mut final_effect, &fields[i].field.field_type)?;
+        get_return(&final_effect, variables, &code_verifier.syntax)
+            .await?
             .unwrap()
             .resolve_generic(&fields[i].field.field_type, &code_verifier.syntax, &mut generics, error)
             .await?;
@@ -238,8 +302,23 @@ async fn verify_create_struct(
     }
 
     //degeneric_type_fields(&mut target, &mut generics, &code_verifier.syntax).await;
+    let span = final_effects.iter().fold(Span::default(), |span, (_, effect)| Span::merge(span, effect.span));
+
+    if let Some(base) = base {
+        copy_base_fields(code_verifier, variables, &target, *base, &mut final_effects).await?;
+    }
+
+    // Any field left out of the new expression uses its default value, if it has one
+    for (i, field) in fields.iter().enumerate() {
+        if let Some(default_value) = &field.default_value {
+            if !final_effects.iter().any(|(index, _)| *index == i) {
+                final_effects.push((i, default_value.clone()));
+            }
+        }
+    }
+
     return Ok(FinalizedEffects::new(
-        Span::default(),
+        span,
         FinalizedEffectType::CreateStruct(
             Some(Box::new(FinalizedEffects::new(Span::default(), FinalizedEffectType::HeapAllocate(target.clone())))),
             target,
@@ -248,26 +327,337 @@ async fn verify_create_struct(
     ));
 }
 
-/// Checks if two types are the same
-async fn check_type(
-    types: &Option<FinalizedTypes>,
+/// Copies every field left out of a new expression from its base, like new Foo { x: 5, ..base }.
+/// The base must be the same type, including its generics, and is only evaluated once.
+async fn copy_base_fields(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    target: &FinalizedTypes,
+    base: Effects,
+    final_effects: &mut Vec<(usize, FinalizedEffects)>,
+) -> Result<(), ParsingError> {
+    let base = verify_effect(code_verifier, variables, base).await?;
+    let Some(base_type) = get_return(&base, variables, &code_verifier.syntax).await? else {
+        return Err(base.span.make_error(ParsingMessage::UnexpectedVoid));
+    };
+    if base_type.dereferenced() != target {
+        return Err(base.span.make_error(ParsingMessage::MismatchedTypes(base_type, target.clone())));
+    }
+
+    // A base that no field is copied from would never be evaluated
+    if target.get_fields().iter().enumerate().all(|(i, _)| final_effects.iter().any(|(index, _)| *index == i)) {
+        return Err(base.span.make_error(ParsingMessage::UnusedBase(target.clone())));
+    }
+
+    // The first copied field stores the base in a variable for the rest to load from,
+    // named after where the base is so nested new expressions each get their own
+    let name = format!("$base{}", base.span.start);
+    let mut base = Some(base);
+    for (i, field) in target.get_fields().iter().enumerate() {
+        if final_effects.iter().any(|(index, _)| *index == i) {
+            continue;
+        }
+
+        let loading = match base.take() {
+            Some(base) => FinalizedEffects::new(
+                base.span,
+                FinalizedEffectType::CreateVariable(name.clone(), Box::new(base), base_type.clone()),
+            ),
+            None => FinalizedEffects::new(Span::default(), FinalizedEffectType::LoadVariable(name.clone())),
+        };
+        final_effects.push((
+            i,
+            FinalizedEffects::new(
+                loading.span,
+                FinalizedEffectType::Load(Box::new(loading), field.field.name.clone(), base_type.clone()),
+            ),
+        ));
+    }
+    return Ok(());
+}
+
+/// Lowers a start..end range into a core Range struct over the type of its bounds.
+/// A literal bound takes the type of the other bound, so 0..length ranges over length's type.
+async fn verify_range(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    span: Span,
+    start: Effects,
+    end: Effects,
+) -> Result<FinalizedEffects, ParsingError> {
+    let mut start = verify_effect(code_verifier, variables, start).await?;
+    let mut end = verify_effect(code_verifier, variables, end).await?;
+    let Some(end_type) = get_return(&end, variables, &code_verifier.syntax).await? else {
+        return Err(end.span.make_error(ParsingMessage::UnexpectedVoid));
+    };
+    coerce_literal(&mut start, &end_type)?;
+    let Some(types) = get_return(&start, variables, &code_verifier.syntax).await? else {
+        return Err(start.span.make_error(ParsingMessage::UnexpectedVoid));
+    };
+    coerce_literal(&mut end, &types)?;
+
+    let end_type = get_return(&end, variables, &code_verifier.syntax).await?.unwrap();
+    if !end_type.of_type(&types, code_verifier.syntax.clone()).await {
+        return Err(end.span.make_error(ParsingMessage::MismatchedTypes(end_type, types)));
+    }
+
+    let range = Syntax::parse_type(
+        code_verifier.syntax.clone(),
+        code_verifier.resolver.boxed_clone(),
+        UnparsedType::Basic(span, "range::Range".to_string()),
+        vec![],
+    )
+    .await?
+    .finalize(code_verifier.syntax.clone())
+    .await;
+    let target = FinalizedTypes::GenericType(Box::new(range), vec![types]);
+
+    // The fields are found by name so their order in the core file doesn't matter
+    let field = |name: &str| target.get_fields().iter().position(|field| field.field.name == name).unwrap();
+    let fields = vec![(field("current"), start), (field("end"), end)];
+    return Ok(FinalizedEffects::new(
+        span,
+        FinalizedEffectType::CreateStruct(
+            Some(Box::new(FinalizedEffects::new(Span::default(), FinalizedEffectType::HeapAllocate(target.clone())))),
+            target,
+            fields,
+        ),
+    ));
+}
+
+/// Verifies an array literal, which becomes a core Array of the type every element conforms to
+async fn verify_array(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    span: Span,
+    effects: Vec<Effects>,
+) -> Result<FinalizedEffects, ParsingError> {
+    // Empty arrays have no elements to take a type from
+    if effects.is_empty() {
+        return Err(span.make_error(ParsingMessage::UninferableArray));
+    }
+
+    let mut output = Vec::default();
+    for effect in effects {
+        output.push(verify_effect(code_verifier, variables, effect).await?);
+    }
+
+    let types = unify_types(&output, variables, code_verifier).await?;
+    let types = array_type(code_verifier, span, types).await?;
+    return Ok(FinalizedEffects::new(span, FinalizedEffectType::CreateArray(Some(types), output)));
+}
+
+/// Gets the core Array type holding the element type
+async fn array_type(
+    code_verifier: &CodeVerifier<'_>,
+    span: Span,
+    element: FinalizedTypes,
+) -> Result<FinalizedTypes, ParsingError> {
+    let array = Syntax::parse_type(
+        code_verifier.syntax.clone(),
+        code_verifier.resolver.boxed_clone(),
+        UnparsedType::Basic(span, ARRAY.to_string()),
+        vec![],
+    )
+    .await?
+    .finalize(code_verifier.syntax.clone())
+    .await;
+    return Ok(FinalizedTypes::GenericType(Box::new(array), vec![element]));
+}
+
+/// Verifies an argument of a call. Empty arrays are allowed, and take the type of the argument they're passed as
+/// once the called function is found, see adopt_array_type.
+pub async fn verify_argument(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    effect: Effects,
+) -> Result<FinalizedEffects, ParsingError> {
+    if array_elements(&effect).map_or(false, |elements| elements.is_empty()) {
+        return Ok(FinalizedEffects::new(effect.span, FinalizedEffectType::CreateArray(None, vec![])));
+    }
+    return verify_effect(code_verifier, variables, effect).await;
+}
+
+/// Gets the elements of an array literal. The parser makes [a, b] the operation [{} wrapping the elements,
+/// with the closing bracket parsed as the operation {}] around the last element, and [] the operation [].
+fn array_elements(effect: &Effects) -> Option<Vec<Effects>> {
+    let EffectType::Operation(operation, values) = &effect.types else {
+        return None;
+    };
+    if operation == "[]" && values.is_empty() {
+        return Some(vec![]);
+    }
+    if operation != "[{}" || values.len() != 1 {
+        return None;
+    }
+
+    let mut elements = match &values[0].types {
+        EffectType::CreateArray(elements) => elements.clone(),
+        _ => values.clone(),
+    };
+    let mut last = elements.pop().unwrap();
+    // A trailing comma leaves the closing bracket on its own
+    if matches!(&last.types, EffectType::Operation(operation, values) if operation == "]" && values.is_empty()) {
+        return Some(elements);
+    }
+    if !take_array_end(&mut last) {
+        return None;
+    }
+    elements.push(last);
+    return Some(elements);
+}
+
+/// Removes the closing bracket of an array from its last element, which is the {}] operation on the right
+/// of any operators in it, like 1 + {}](2) in [1 + 2]. Returns false if there's no closing bracket.
+fn take_array_end(effect: &mut Effects) -> bool {
+    let EffectType::Operation(operation, values) = &mut effect.types else {
+        return false;
+    };
+    if operation == "{}]" && values.len() == 1 {
+        let inner = values.pop().unwrap();
+        *effect = inner;
+        return true;
+    }
+    return operation.ends_with("{}") && values.last_mut().map_or(false, take_array_end);
+}
+
+/// Gives an array literal passed as an argument the argument's array type, so empty arrays get a type
+/// and elements can be passed as a trait they implement. Does nothing to other arguments.
+pub async fn adopt_array_type(
+    argument: &mut FinalizedEffects,
+    target: &FinalizedTypes,
+    syntax: &Arc<Mutex<Syntax>>,
+    variables: &SimpleVariableManager,
+) -> Result<(), ParsingError> {
+    let FinalizedEffectType::CreateArray(types, values) = &mut argument.types else {
+        return Ok(());
+    };
+    let element = match target.dereferenced().inner_generic_type() {
+        Some((base, bounds)) if base.name_safe().as_deref() == Some(ARRAY) && bounds.len() == 1 => bounds[0].clone(),
+        // The argument isn't an array, which checking the argument's type reports if the array has a type
+        _ if types.is_some() => return Ok(()),
+        _ => return Err(argument.span.make_error(ParsingMessage::UninferableArray)),
+    };
+    // Generic arguments take their type from the array instead
+    if element.name_safe().is_none() {
+        return match types {
+            Some(_) => Ok(()),
+            None => Err(argument.span.make_error(ParsingMessage::UninferableArray)),
+        };
+    }
+
+    for value in values.iter_mut() {
+        let Some(found) = get_return(value, variables, syntax).await? else {
+            return Err(value.span.make_error(ParsingMessage::UnexpectedVoid));
+        };
+        if found == element {
+            continue;
+        }
+        if !found.of_type(&element, syntax.clone()).await {
+            return Err(value.span.make_error(ParsingMessage::MismatchedTypes(found, element)));
+        }
+        if element.is_trait() {
+            let inner = FinalizedEffects::new(value.span, FinalizedEffectType::NOP);
+            let inner = mem::replace(value, inner);
+            *value =
+                FinalizedEffects::new(inner.span, FinalizedEffectType::Downcast(Box::new(inner), element.clone(), vec![]));
+        }
+    }
+    *types = Some(target.dereferenced().clone());
+    return Ok(());
+}
+
+/// Finds the type every effect conforms to, or errors on the first effect that doesn't fit
+async fn unify_types(
     output: &Vec<FinalizedEffects>,
     variables: &SimpleVariableManager,
     code_verifier: &CodeVerifier<'_>,
-    span: &Span,
-) -> Result<(), ParsingError> {
-    if let Some(found) = types {
-        for checking in output {
-            let returning = get_return(&checking.types, variables, &code_verifier.syntax).await.unwrap();
-            if !returning.of_type(found, code_verifier.syntax.clone()).await {
-                return Err(span.make_error(ParsingMessage::MismatchedTypes(returning, found.clone())));
+) -> Result<FinalizedTypes, ParsingError> {
+    let mut types = Vec::default();
+    for checking in output {
+        match get_return(&checking, variables, &code_verifier.syntax).await? {
+            Some(found) => types.push(found),
+            None => return Err(checking.span.make_error(ParsingMessage::UnexpectedVoid)),
+        }
+    }
+
+    // Any element's type can be the array's type as long as every other element is of that type,
+    // which lets structs be mixed with the traits they implement.
+    'candidates: for candidate in types.iter().skip(1) {
+        for found in &types {
+            if !found.of_type(candidate, code_verifier.syntax.clone()).await {
+                continue 'candidates;
             }
         }
+        return Ok(candidate.clone());
     }
-    return Ok(());
+
+    // The first element's type is checked last, so if it doesn't fit the first element that doesn't match it is reported
+    let first = types.first().unwrap();
+    for (found, checking) in types.iter().zip(output) {
+        if !found.of_type(first, code_verifier.syntax.clone()).await {
+            return Err(checking.span.make_error(ParsingMessage::MismatchedTypes(found.clone(), first.clone())));
+        }
+    }
+    return Ok(first.clone());
 }
 
 /// Shorthand for storing an effect on the heap
-fn store(effect: FinalizedEffectType) -> FinalizedEffectType {
+pub fn store(effect: FinalizedEffectType) -> FinalizedEffectType {
     return FinalizedEffectType::HeapStore(Box::new(FinalizedEffects::new(Span::default(), effect)));
 }
+
+/// Gives an untyped integer literal the type of the number it's used with, so f64_var + 1 works without a cast.
+/// Negative constants folded from code like 2 - 3 are literals too.
+/// Returns false if the effect isn't a literal or the target isn't a number, and errors if the literal doesn't fit.
+pub fn coerce_literal(effect: &mut FinalizedEffects, target: &FinalizedTypes) -> Result<bool, ParsingError> {
+    let target = target.dereferenced();
+    let (Some((minimum, maximum)), Some(value)) = (literal_range(target), literal_value(effect)) else {
+        return Ok(false);
+    };
+
+    if value < minimum || value > maximum {
+        return Err(effect.span.make_error(ParsingMessage::LiteralOutOfRange(value, target.clone())));
+    }
+    effect.types = match target.inner_struct().data.name.as_str() {
+        // Numbers keep negative values as an i64's bits, which can't be told apart from large values when made a float
+        "f64" if value < 0 => store(FinalizedEffectType::Float(value as f64)),
+        "f32" if value < 0 => return Ok(false),
+        _ => store(FinalizedEffectType::Number(value as u64, target.clone())),
+    };
+    return Ok(true);
+}
+
+/// Gets the value of a checked integer literal, including the negative ones made by folding, or None if it isn't one
+pub fn literal_value(effect: &FinalizedEffects) -> Option<i128> {
+    return match &effect.types {
+        FinalizedEffectType::HeapStore(inner) => match &inner.types {
+            FinalizedEffectType::UInt(value) => Some(*value as i128),
+            FinalizedEffectType::Number(value, types) if types.inner_struct().data.name == "i64" => {
+                Some(*value as i64 as i128)
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+}
+
+/// The smallest and largest integer literal that fits in the type, or None if it isn't a number
+pub fn literal_range(types: &FinalizedTypes) -> Option<(i128, i128)> {
+    let FinalizedTypes::Struct(structure) = types.dereferenced() else {
+        return None;
+    };
+    return match structure.data.name.as_str() {
+        "i64" => Some((i64::MIN as i128, i64::MAX as i128)),
+        "i32" => Some((i32::MIN as i128, i32::MAX as i128)),
+        "i16" => Some((i16::MIN as i128, i16::MAX as i128)),
+        "i8" => Some((i8::MIN as i128, i8::MAX as i128)),
+        "u64" => Some((0, u64::MAX as i128)),
+        "u32" => Some((0, u32::MAX as i128)),
+        "u16" => Some((0, u16::MAX as i128)),
+        "u8" => Some((0, u8::MAX as i128)),
+        // Floats can't hold every integer exactly, but they can get close enough
+        "f64" | "f32" => Some((i64::MIN as i128, u64::MAX as i128)),
+        _ => None,
+    };
+}
@@ -15,7 +15,7 @@ use syntax::program::function::{
 };
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
-use syntax::{is_modifier, Modifier, ProcessManager, SimpleVariableManager};
+use syntax::{is_modifier, Attribute, Modifier, ProcessManager, SimpleVariableManager};
 
 /// Verifies a function and returns its code, which is verified seperate to prevent deadlocks
 pub async fn verify_function(
@@ -35,6 +35,7 @@ pub async fn verify_function(
                 field_type: field.field.field_type.finalize(syntax.clone()).await,
                 name: field.field.name,
             },
+            default_value: None,
         };
         if include_refs {
             field.field.field_type = FinalizedTypes::Reference(Box::new(field.field.field_type));
@@ -50,6 +51,16 @@ pub async fn verify_function(
         None
     };
 
+    // The library of an extern function, which the program has to be linked with
+    if let Some(link) = Attribute::find_attribute("link", &function.data.attributes) {
+        let library =
+            link_library(link).ok_or_else(|| function.data.span.make_error(ParsingMessage::InvalidLinkAttribute))?;
+        let mut locked = syntax.lock();
+        if !locked.link_libraries.iter().any(|(found, _)| *found == library) {
+            locked.link_libraries.push((library, function.data.span));
+        }
+    }
+
     // Return the codeless finalized function
     let codeless = CodelessFinalizedFunction {
         generics: finalize_generics(syntax, resolver, &function.generics).await?,
@@ -65,6 +76,16 @@ pub async fn verify_function(
     return Ok((codeless, function.code));
 }
 
+/// Gets the library a #[link(name = "...")] attribute names, or None if it doesn't name one
+fn link_library(attribute: &Attribute) -> Option<String> {
+    let (key, name) = attribute.as_string_attribute()?.split_once('=')?;
+    let name = name.trim().trim_matches('"');
+    if key.trim() != "name" || name.is_empty() {
+        return None;
+    }
+    return Some(name.to_string());
+}
+
 /// Verifies the code of a function
 pub async fn verify_function_code(
     process_manager: &TypesChecker,
@@ -1,4 +1,6 @@
+use parking_lot::Mutex;
 use std::mem;
+use std::sync::Arc;
 
 use data::tokens::Span;
 use syntax::async_util::AsyncDataGetter;
@@ -8,9 +10,9 @@ use syntax::program::r#struct::VOID;
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
 use syntax::top_element_manager::ImplWaiter;
-use syntax::SimpleVariableManager;
+use syntax::{Attribute, SimpleVariableManager, TopElement};
 
-use crate::check_code::verify_effect;
+use crate::check_code::{coerce_literal, literal_range, verify_effect};
 use crate::check_method_call::check_function;
 use crate::degeneric::degeneric_header;
 use crate::{get_return, CodeVerifier};
@@ -37,17 +39,40 @@ pub async fn check_impl_call(
         unreachable!()
     }
 
-    // Get the return type, or VOID if there is none
-    let calling_type;
-    let done_calling: Option<Box<FinalizedEffects>>;
-    if matches!(calling.types, EffectType::NOP) {
-        calling_type = FinalizedTypes::Struct(VOID.clone());
-        done_calling = None;
+    let done_calling = if matches!(calling.types, EffectType::NOP) {
+        None
     } else {
-        let calling_effect = verify_effect(code_verifier, variables, *calling.clone()).await?;
-        calling_type = get_return(&calling_effect.types, variables, &code_verifier.syntax).await.unwrap();
-        done_calling = Some(Box::new(calling_effect));
-    }
+        Some(Box::new(verify_effect(code_verifier, variables, *calling).await?))
+    };
+    return check_finalized_impl_call(
+        code_verifier,
+        variables,
+        effect.span,
+        done_calling,
+        traits,
+        method,
+        finalized_effects,
+    )
+    .await;
+}
+
+/// Checks an implementation call whose calling effect and arguments were already checked,
+/// like the operands of an operator that couldn't be folded
+pub async fn check_finalized_impl_call(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    span: Span,
+    mut done_calling: Option<Box<FinalizedEffects>>,
+    traits: String,
+    method: String,
+    mut finalized_effects: Vec<FinalizedEffects>,
+) -> Result<FinalizedEffects, ParsingError> {
+    // Get the return type, or VOID if there is none
+    let calling_span = done_calling.as_ref().map_or(Span::default(), |calling| calling.span);
+    let calling_type = match &done_calling {
+        Some(calling) => get_return(calling, variables, &code_verifier.syntax).await?.unwrap(),
+        None => FinalizedTypes::Struct(VOID.clone()),
+    };
 
     // Get the trait
     if let Ok(trait_type) = Syntax::get_struct(
@@ -59,6 +84,13 @@ pub async fn check_impl_call(
     .await
     {
         let trait_type = trait_type.finalize(code_verifier.syntax.clone()).await;
+        let is_operator = trait_type.inner_struct().data.is_operator();
+        // Operators look through references, so self.count + 1 finds the impl for the field's type
+        let mut calling_type = if is_operator { calling_type.dereferenced().clone() } else { calling_type };
+        if is_operator {
+            coerce_operands(&mut done_calling, &mut calling_type, &mut finalized_effects, variables, &code_verifier.syntax)
+                .await?;
+        }
         // Simple container for all the data that needs to be stored
         let mut impl_checker = ImplCheckerData {
             calling: done_calling,
@@ -71,26 +103,37 @@ pub async fn check_impl_call(
         };
 
         // Check if the trait_type matches the calling_type. If so, it's a virtual call (a method call on a trait)
-        if let Some(found) = check_virtual_type(&mut impl_checker, &effect.span).await? {
+        if let Some(found) = check_virtual_type(&mut impl_checker, &span).await? {
             return Ok(found);
         }
 
         // If not, wait for an impl to be parsed that fits the criteria
-        let mut output = None;
-        while output.is_none() && !impl_checker.code_verifier.syntax.lock().finished_impls() {
-            // TODO switch this to some kind of pipeline instead of rechecking them all every single time
-            output = try_get_impl(&impl_checker, &effect.span).await?;
+        if let Some(output) = try_get_impl(&impl_checker, &span).await? {
+            return Ok(output);
         }
 
-        // Failed to find an impl
-        if output.is_none() {
-            output = try_get_impl(&impl_checker, &effect.span).await?;
-            if output.is_none() {
-                return Err(calling.span.make_error(ParsingMessage::NoTraitImpl(calling_type, trait_type)));
+        // Failed to find an impl, so operators point at the whole operation and say which types were used
+        if let Some(operator) = Attribute::find_attribute("operation", &trait_type.inner_struct().data.attributes)
+            .and_then(Attribute::as_operator)
+        {
+            let other = match impl_checker.finalized_effects.first() {
+                Some(found) => get_return(found, impl_checker.variables, &impl_checker.code_verifier.syntax).await?,
+                None => None,
+            };
+            // Two different kinds of numbers need a cast, so point at the operand that has to change
+            if let Some(other) = &other {
+                if literal_range(&calling_type).is_some() && literal_range(other).is_some() {
+                    let operand = impl_checker.finalized_effects.first().unwrap().span;
+                    return Err(operand.make_error(ParsingMessage::MismatchedOperands(
+                        operator,
+                        calling_type,
+                        other.dereferenced().clone(),
+                    )));
+                }
             }
+            return Err(span.make_error(ParsingMessage::UnknownOperator(operator, calling_type, other)));
         }
-
-        return Ok(output.unwrap());
+        return Err(calling_span.make_error(ParsingMessage::NoTraitImpl(calling_type, trait_type)));
     }
     panic!("Screwed up trait! {} for {:?}", traits, code_verifier.resolver.imports());
 }
@@ -190,35 +233,71 @@ async fn check_virtual_type(data: &mut ImplCheckerData<'_>, token: &Span) -> Res
     return Ok(None);
 }
 
-/// Tries to get an implementation matching the types passed in
+/// Waits for an implementation matching the types passed in, returning None if all of them are parsed without one.
+/// The same waiter is kept until then, so impls that don't fit the arguments aren't checked again.
 async fn try_get_impl(data: &ImplCheckerData<'_>, span: &Span) -> Result<Option<FinalizedEffects>, ParsingError> {
-    let result = ImplWaiter {
-        syntax: data.code_verifier.syntax.clone(),
-        base_type: data.calling_type.clone(),
-        trait_type: data.trait_type.clone(),
-        error: span.make_error(ParsingMessage::NoTraitImpl(data.calling_type.clone(), data.trait_type.clone())),
+    let mut waiter = ImplWaiter::new(
+        data.code_verifier.syntax.clone(),
+        data.calling_type.clone(),
+        data.trait_type.clone(),
+        span.make_error(ParsingMessage::NoTraitImpl(data.calling_type.clone(), data.trait_type.clone())),
+    );
+    loop {
+        let result = match (&mut waiter).await {
+            Ok(result) => result,
+            // Operators without an implementation get an error naming the operator and both types instead
+            Err(error)
+                if matches!(error.message, ParsingMessage::NoTraitImpl(_, _))
+                    && data.trait_type.inner_struct().data.is_operator() =>
+            {
+                return Ok(None)
+            }
+            Err(error) => return Err(error),
+        };
+
+        for temp in result.iter().flat_map(|(_, inner)| inner) {
+            if temp.name.split("::").last().unwrap() == data.method || data.method.is_empty() {
+                let method = AsyncDataGetter::new(data.code_verifier.syntax.clone(), temp.clone()).await;
+
+                match check_function(
+                    data.calling.clone(),
+                    method.clone(),
+                    data.finalized_effects.clone(),
+                    &data.code_verifier.syntax,
+                    &data.variables,
+                    vec![],
+                    span,
+                )
+                .await
+                {
+                    Ok(found) => return Ok(Some(found)),
+                    Err(_error) => {}
+                };
+            }
+        }
     }
-    .await?;
+}
 
-    for temp in result.iter().flat_map(|(_, inner)| inner) {
-        if temp.name.split("::").last().unwrap() == data.method || data.method.is_empty() {
-            let method = AsyncDataGetter::new(data.code_verifier.syntax.clone(), temp.clone()).await;
+/// Gives an untyped integer literal on either side of an operator the type of the other side.
+/// The right side is tried first, so 1 + 2 stays a u64.
+async fn coerce_operands(
+    calling: &mut Option<Box<FinalizedEffects>>,
+    calling_type: &mut FinalizedTypes,
+    arguments: &mut Vec<FinalizedEffects>,
+    variables: &SimpleVariableManager,
+    syntax: &Arc<Mutex<Syntax>>,
+) -> Result<(), ParsingError> {
+    let (Some(calling), Some(argument)) = (calling, arguments.first_mut()) else {
+        return Ok(());
+    };
+    if coerce_literal(argument, calling_type)? {
+        return Ok(());
+    }
 
-            match check_function(
-                data.calling.clone(),
-                method.clone(),
-                data.finalized_effects.clone(),
-                &data.code_verifier.syntax,
-                &data.variables,
-                vec![],
-                span,
-            )
-            .await
-            {
-                Ok(found) => return Ok(Some(found)),
-                Err(_error) => {}
-            };
+    if let Some(other) = get_return(argument, variables, syntax).await? {
+        if coerce_literal(calling, &other)? {
+            *calling_type = other.dereferenced().clone();
         }
     }
-    return Ok(None);
+    return Ok(());
 }
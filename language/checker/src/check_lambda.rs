@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::iter;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use syntax::async_util::UnparsedType;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
+use syntax::program::code::{
+    EffectType, Effects, Expression, ExpressionType, FinalizedEffects, FinalizedField, FinalizedMemberField,
+};
+use syntax::program::function::{CodeBody, CodelessFinalizedFunction, FunctionData};
+use syntax::program::r#struct::{FinalizedStruct, StructData};
+use syntax::program::syntax::Syntax;
+use syntax::program::types::FinalizedTypes;
+use syntax::{FinishedTraitImplementor, ProcessManager, SimpleVariableManager};
+
+use crate::check_code::{check_return_type, verify_code, verify_effect};
+use crate::{get_return, CodeVerifier};
+
+/// Checks a lambda by turning it into a struct holding every variable it captures.
+/// That struct implements Fn with the lambda's body as the call function, and the lambda becomes creating the struct.
+pub async fn check_lambda(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    effect: Effects,
+) -> Result<FinalizedEffects, ParsingError> {
+    let span = effect.span;
+    let (arguments, body) = match effect.types {
+        EffectType::Lambda(arguments, body) => (arguments, body),
+        _ => unreachable!(),
+    };
+
+    if arguments.len() != 1 {
+        return Err(span.make_error(ParsingMessage::LambdaArguments(arguments.len())));
+    }
+    let (argument_name, argument_type) = arguments.into_iter().next().unwrap();
+    let argument_type =
+        Syntax::parse_type(code_verifier.syntax.clone(), code_verifier.resolver.boxed_clone(), argument_type, vec![])
+            .await?
+            .finalize(code_verifier.syntax.clone())
+            .await;
+
+    let mut captures = Vec::default();
+    find_captures(&body, &argument_name, variables, &mut captures)?;
+    // Loading self has to come last so the other captures are loaded from the lambda and not the captured self
+    captures.sort_by_key(|capture| capture == "self");
+
+    let include_refs = code_verifier.process_manager.include_refs;
+    let fields = captures
+        .iter()
+        .map(|capture| {
+            let field_type = strip_references(variables.variables[capture].clone());
+            make_field(capture.clone(), reference(field_type, include_refs))
+        })
+        .collect();
+
+    // Each lambda gets its own struct, named after where it's declared
+    let name = format!("lambda::{}_{}", span.file, span.start);
+    let mut data = Arc::new(StructData::new(Vec::default(), Vec::default(), 0, span, name.clone()));
+    Syntax::add_struct(&code_verifier.syntax, &mut data);
+    let structure = Arc::new(FinalizedStruct { generics: IndexMap::default(), fields, data: data.clone() });
+    code_verifier.syntax.lock().structures.add_data(data, structure.clone());
+    let lambda_type = FinalizedTypes::Struct(structure);
+
+    // The body copies every capture out of the lambda into a variable, then returns the lambda's value
+    let mut lines = Vec::default();
+    for capture in &captures {
+        let lambda = Effects::new(span, EffectType::LoadVariable("self".to_string()));
+        let load = Effects::new(span, EffectType::Load(Box::new(lambda), capture.clone()));
+        lines.push(Expression::new(
+            ExpressionType::Line,
+            Effects::new(span, EffectType::CreateVariable(capture.clone(), Box::new(load))),
+        ));
+    }
+    lines.push(Expression::new(ExpressionType::Return(span), *body));
+
+    let mut lambda_variables = SimpleVariableManager {
+        variables: HashMap::from([
+            ("self".to_string(), reference(lambda_type.clone(), include_refs)),
+            (argument_name.clone(), reference(argument_type.clone(), include_refs)),
+        ]),
+    };
+    let mut lambda_verifier = CodeVerifier {
+        process_manager: code_verifier.process_manager,
+        resolver: code_verifier.resolver.boxed_clone(),
+        return_type: None,
+        syntax: code_verifier.syntax.clone(),
+    };
+    let mut code =
+        verify_code(&mut lambda_verifier, &mut lambda_variables, CodeBody::new(lines, name.clone()), true).await?;
+
+    // The return type is whatever the body returns, which is then checked like any other function's return
+    let last = &code.expressions.last().unwrap().effect;
+    let returning = match get_return(last, &lambda_variables, &code_verifier.syntax).await? {
+        Some(found) => strip_references(found),
+        None => return Err(last.span.make_error(ParsingMessage::UnexpectedVoid)),
+    };
+    lambda_verifier.return_type = Some(returning.clone());
+    code.returns = check_return_type(
+        ExpressionType::Return(span),
+        &lambda_verifier,
+        &mut code.expressions,
+        &lambda_variables,
+        &code_verifier.syntax,
+    )
+    .await?;
+
+    let mut function_data = Arc::new(FunctionData::new(Vec::default(), 0, format!("{}::call", name), span));
+    Syntax::add_function(&code_verifier.syntax, &mut function_data);
+    let codeless = CodelessFinalizedFunction {
+        generics: IndexMap::default(),
+        arguments: vec![
+            make_field("self".to_string(), reference(lambda_type.clone(), include_refs)),
+            make_field(argument_name, reference(argument_type.clone(), include_refs)),
+        ],
+        return_type: Some(returning.clone()),
+        data: function_data.clone(),
+        parent: Some(lambda_type.clone()),
+    };
+    code_verifier.syntax.lock().functions.add_data(function_data.clone(), Arc::new(codeless.clone()));
+    Syntax::add_compiling(
+        code_verifier.process_manager.cloned(),
+        Arc::new(codeless.add_code(code)),
+        &code_verifier.syntax,
+        true,
+    )
+    .await;
+
+    // Implement Fn<Argument, Return> for the lambda
+    let function_trait = Syntax::get_struct(
+        code_verifier.syntax.clone(),
+        ("function::Fn".to_string(), span),
+        code_verifier.resolver.boxed_clone(),
+        vec![],
+    )
+    .await?
+    .finalize(code_verifier.syntax.clone())
+    .await;
+    let target = FinalizedTypes::GenericType(Box::new(function_trait), vec![argument_type, returning]);
+    let implementor = FinishedTraitImplementor {
+        chalk_type: Arc::new(Syntax::make_impldatum(&IndexMap::default(), &target, &lambda_type)),
+        target,
+        base: lambda_type,
+        generics: IndexMap::default(),
+        attributes: Vec::default(),
+        functions: vec![function_data],
+    };
+
+    {
+        let mut locked = code_verifier.syntax.lock();
+        locked.add_implementation(Arc::new(implementor));
+    }
+
+    let captured = captures
+        .into_iter()
+        .map(|capture| (capture.clone(), Effects::new(span, EffectType::LoadVariable(capture))))
+        .collect();
+    return verify_effect(
+        code_verifier,
+        variables,
+        Effects::new(span, EffectType::CreateStruct(UnparsedType::Basic(span, name), captured, None)),
+    )
+    .await;
+}
+
+/// Finds every variable from the surrounding code that the lambda uses.
+/// Captured variables are copied into the lambda, so setting one is an error.
+fn find_captures(
+    effect: &Effects,
+    argument: &String,
+    variables: &SimpleVariableManager,
+    captures: &mut Vec<String>,
+) -> Result<(), ParsingError> {
+    let inner: Vec<&Effects> = match &effect.types {
+        EffectType::LoadVariable(name) => {
+            if name != argument && variables.variables.contains_key(name) && !captures.contains(name) {
+                captures.push(name.clone());
+            }
+            return Ok(());
+        }
+        EffectType::Set(target, value) => {
+            if let EffectType::LoadVariable(name) = &target.types {
+                if name != argument && variables.variables.contains_key(name) {
+                    return Err(target.span.make_error(ParsingMessage::MutatedCapture(name.clone())));
+                }
+            }
+            vec![&**target, &**value]
+        }
+        EffectType::Range(start, end) => vec![&**start, &**end],
+        EffectType::Paren(inner)
+        | EffectType::CreateVariable(_, inner)
+        | EffectType::CompareJump(inner, _, _)
+        | EffectType::Load(inner, _)
+        | EffectType::Lambda(_, inner) => vec![&**inner],
+        EffectType::CodeBody(body) => body.expressions.iter().map(|expression| &expression.effect).collect(),
+        EffectType::ImplementationCall(calling, _, _, arguments) => iter::once(&**calling).chain(arguments).collect(),
+        EffectType::MethodCall(calling, _, arguments, _) => {
+            calling.iter().map(|calling| &**calling).chain(arguments).collect()
+        }
+        EffectType::Operation(_, arguments) | EffectType::CreateArray(arguments) => arguments.iter().collect(),
+        EffectType::CreateStruct(_, fields, base) => {
+            fields.iter().map(|(_, value)| value).chain(base.iter().map(|base| &**base)).collect()
+        }
+        _ => Vec::default(),
+    };
+
+    for effect in inner {
+        find_captures(effect, argument, variables, captures)?;
+    }
+    return Ok(());
+}
+
+/// Makes a field with no modifiers or attributes
+fn make_field(name: String, field_type: FinalizedTypes) -> FinalizedMemberField {
+    return FinalizedMemberField {
+        modifiers: 0,
+        attributes: Vec::default(),
+        field: FinalizedField { field_type, name },
+        default_value: None,
+    };
+}
+
+/// Wraps the type in a reference if references are being included
+fn reference(types: FinalizedTypes, include_refs: bool) -> FinalizedTypes {
+    return if include_refs { FinalizedTypes::Reference(Box::new(types)) } else { types };
+}
+
+/// Removes any references around the type, since lambdas store and return the values themselves
+fn strip_references(types: FinalizedTypes) -> FinalizedTypes {
+    return match types {
+        FinalizedTypes::Reference(inner) => strip_references(*inner),
+        types => types,
+    };
+}
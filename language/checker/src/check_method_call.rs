@@ -1,4 +1,5 @@
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use data::tokens::Span;
@@ -9,9 +10,9 @@ use syntax::program::function::{CodelessFinalizedFunction, FunctionData};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
 use syntax::top_element_manager::TraitImplWaiter;
-use syntax::{is_modifier, FinishedTraitImplementor, Modifier, SimpleVariableManager};
+use syntax::{FinishedTraitImplementor, SimpleVariableManager};
 
-use crate::check_code::verify_effect;
+use crate::check_code::{adopt_array_type, verify_argument, verify_effect};
 use crate::{get_return, CodeVerifier};
 
 /// Checks a method call to make sure it's valid
@@ -26,7 +27,7 @@ pub async fn check_method_call(
     let explicit_generics;
     if let EffectType::MethodCall(new_calling, new_method, effects, new_explicit_generics) = effect.types {
         for effect in effects {
-            finalized_effects.push(verify_effect(code_verifier, variables, effect).await?)
+            finalized_effects.push(verify_argument(code_verifier, variables, effect).await?)
         }
         calling = new_calling;
         function = new_method;
@@ -40,6 +41,15 @@ pub async fn check_method_call(
         None => None,
     };
 
+    // The span of the whole call, including the calling effect and the arguments, so errors cover the full expression
+    let mut span = effect.span;
+    if let Some(calling) = &calling {
+        span = Span::merge(span, calling.span);
+    }
+    for argument in &finalized_effects {
+        span = Span::merge(span, argument.span);
+    }
+
     let mut final_returning = vec![];
     for value in explicit_generics {
         let span = value.get_span();
@@ -54,7 +64,7 @@ pub async fn check_method_call(
 
     // Finds methods based off the calling type.
     let method = if let Some(calling) = calling.clone() {
-        let return_type: FinalizedTypes = get_return(&calling.types, variables, &code_verifier.syntax).await.unwrap();
+        let return_type: FinalizedTypes = get_return(&calling, variables, &code_verifier.syntax).await?.unwrap();
         // TODO fix up the errors here
         if final_returning.len() > 0 {
             panic!("Generic bounds added to non-generic type!");
@@ -64,7 +74,6 @@ pub async fn check_method_call(
         if return_type.inner_struct_safe().is_none() {
             // Looking for the method
             if let Some(mut found) = return_type.find_method(&function) {
-                let span = calling.span.clone();
                 finalized_effects.insert(0, calling);
                 let mut output = vec![];
                 for (found_trait, function) in &mut found {
@@ -85,19 +94,25 @@ pub async fn check_method_call(
                 }
 
                 let (found_trait, found) = output.pop().unwrap();
+                if found.arguments.len() != finalized_effects.len() {
+                    return Err(span.make_error(ParsingMessage::WrongArgumentCount(
+                        found.arguments.len() as u64,
+                        finalized_effects.len() as u64,
+                    )));
+                }
 
                 return Ok(FinalizedEffects::new(
-                    effect.span.clone(),
+                    span.clone(),
                     FinalizedEffectType::GenericMethodCall(found, found_trait.clone(), finalized_effects),
                 ));
             }
         }
 
         // If it's a trait, handle virtual method calls.
-        if is_modifier(return_type.inner_struct().data.modifiers, Modifier::Trait) {
+        if return_type.is_trait() {
             let method = Syntax::get_function(
                 code_verifier.syntax.clone(),
-                (format!("{}::{}", return_type.inner_struct().data.name, function), effect.span.clone()),
+                (format!("{}::{}", return_type.inner_struct().data.name, function), span.clone()),
                 code_verifier.resolver.boxed_clone(),
                 false,
             )
@@ -105,11 +120,12 @@ pub async fn check_method_call(
             let function = AsyncDataGetter::new(code_verifier.syntax.clone(), method).await;
 
             let calling = Some(Box::new(calling));
-            check_args(&function, &calling, &finalized_effects, &code_verifier.syntax, variables, &effect.span).await?;
+            check_args(&function, &calling, &mut finalized_effects, &code_verifier.syntax, variables, &span).await?;
+            code_verifier.syntax.lock().check_deprecated(&function.data.attributes, &function.data.name, &span);
 
             let index = return_type.inner_struct().data.functions.iter().position(|found| *found == function.data).unwrap();
             return Ok(FinalizedEffects::new(
-                effect.span.clone(),
+                span.clone(),
                 FinalizedEffectType::VirtualCall(index, function, calling.unwrap(), finalized_effects),
             ));
         }
@@ -143,7 +159,7 @@ pub async fn check_method_call(
                     &code_verifier.syntax,
                     variables,
                     final_returning.clone(),
-                    &effect.span,
+                    &span,
                 )
                 .await
             };
@@ -162,29 +178,30 @@ pub async fn check_method_call(
                 return Ok(found);
             }
 
-            // If it's not a trait method call, try to find a self-impl method call
-            for implementor in Syntax::get_struct_impl(code_verifier.syntax.clone(), return_type.clone()).await {
-                for impl_function in &implementor.functions {
-                    if impl_function.name.split("::").last().unwrap() == function {
-                        let method = AsyncDataGetter::new(code_verifier.syntax.clone(), impl_function.clone()).await;
-                        match check_function(
-                            calling.clone(),
-                            method,
-                            finalized_effects.clone(),
-                            &code_verifier.syntax,
-                            variables,
-                            final_returning.clone(),
-                            &effect.span,
-                        )
-                        .await
-                        {
-                            Ok(result) => return Ok(result),
-                            Err(error) => eprintln!("Error: {}", error.message),
-                        }
-                    }
+            // If it's not a trait method call, try to find the self-impl method that best fits the arguments
+            let mut arg_types = vec![return_type.clone()];
+            for argument in &finalized_effects {
+                match get_return(argument, variables, &code_verifier.syntax).await? {
+                    Some(found) => arg_types.push(found),
+                    None => return Err(argument.span.make_error(ParsingMessage::UnexpectedVoid)),
                 }
             }
-            return Err(ParsingError::new(effect.span.clone(), ParsingMessage::NoImpl(return_type, function.clone())));
+            if let Some(found) =
+                Syntax::resolve_overloaded_function(&function, &arg_types, &span, &code_verifier.syntax).await?
+            {
+                let method = AsyncDataGetter::new(code_verifier.syntax.clone(), found).await;
+                return check_function(
+                    calling,
+                    method,
+                    finalized_effects,
+                    &code_verifier.syntax,
+                    variables,
+                    final_returning,
+                    &span,
+                )
+                .await;
+            }
+            return Err(ParsingError::new(span.clone(), ParsingMessage::NoImpl(return_type, function.clone())));
         }
     } else {
         if function.contains("::") {
@@ -215,7 +232,7 @@ pub async fn check_method_call(
                                 &code_verifier.syntax,
                                 variables,
                                 final_returning.clone(),
-                                &effect.span,
+                                &span,
                             )
                             .await
                             {
@@ -230,7 +247,7 @@ pub async fn check_method_call(
 
         Syntax::get_function(
             code_verifier.syntax.clone(),
-            (function, effect.span.clone()),
+            (function, span.clone()),
             code_verifier.resolver.boxed_clone(),
             true,
         )
@@ -245,7 +262,7 @@ pub async fn check_method_call(
         &code_verifier.syntax,
         variables,
         final_returning,
-        &effect.span,
+        &span,
     )
     .await;
 }
@@ -255,13 +272,16 @@ pub async fn check_method_call(
 pub async fn check_function(
     calling: Option<Box<FinalizedEffects>>,
     function: Arc<CodelessFinalizedFunction>,
-    effects: Vec<FinalizedEffects>,
+    mut effects: Vec<FinalizedEffects>,
     syntax: &Arc<Mutex<Syntax>>,
     variables: &SimpleVariableManager,
     explicit_generics: Vec<(FinalizedTypes, Span)>,
     span: &Span,
 ) -> Result<FinalizedEffects, ParsingError> {
-    check_args(&function, &calling, &effects, syntax, variables, span).await?;
+    check_args(&function, &calling, &mut effects, syntax, variables, span).await?;
+    // Degenericed copies have a $ after the original name
+    let name = function.data.name.split('$').next().unwrap();
+    syntax.lock().check_deprecated(&function.data.attributes, name, span);
 
     return Ok(FinalizedEffects::new(
         span.clone(),
@@ -273,7 +293,7 @@ pub async fn check_function(
 pub async fn check_args(
     function: &Arc<CodelessFinalizedFunction>,
     calling: &Option<Box<FinalizedEffects>>,
-    args: &Vec<FinalizedEffects>,
+    args: &mut Vec<FinalizedEffects>,
     syntax: &Arc<Mutex<Syntax>>,
     variables: &SimpleVariableManager,
     span: &Span,
@@ -283,20 +303,25 @@ pub async fn check_args(
         length += 1;
     }
     if function.arguments.len() != length {
-        return Err(span.make_error(ParsingMessage::MissingArgument(function.arguments.len() as u64, length as u64)));
+        return Err(span.make_error(ParsingMessage::WrongArgumentCount(function.arguments.len() as u64, length as u64)));
     }
 
+    // The type each generic argument was given, so arguments with the same generic, like both sides of T + T, match.
+    // Generic arguments, like a T in a generic function's code, are checked once that function is degenericed.
+    let mut generics: HashMap<&String, FinalizedTypes> = HashMap::new();
     for i in 0..function.arguments.len() {
         let types = if calling.is_some() {
             if i == 0 {
                 calling.as_ref().unwrap()
             } else {
+                adopt_array_type(&mut args[i - 1], &function.arguments[i].field.field_type, syntax, variables).await?;
                 &args[i - 1]
             }
         } else {
+            adopt_array_type(&mut args[i], &function.arguments[i].field.field_type, syntax, variables).await?;
             &args[i]
         };
-        let mut arg_return_type = get_return(&types.types, variables, syntax).await;
+        let mut arg_return_type = get_return(types, variables, syntax).await?;
         if !arg_return_type.is_some() {
             return Err(span.make_error(ParsingMessage::UnexpectedVoid));
         }
@@ -306,6 +331,18 @@ pub async fn check_args(
         if !arg_return_type.of_type(base_field_type, syntax.clone()).await {
             return Err(span.make_error(ParsingMessage::MismatchedTypes(arg_return_type.clone(), base_field_type.clone())));
         }
+
+        if let FinalizedTypes::Generic(name, _) = base_field_type.dereferenced() {
+            match generics.get(name) {
+                Some(found) if !found.is_generic() && !arg_return_type.is_generic() && found != arg_return_type => {
+                    return Err(span.make_error(ParsingMessage::MismatchedTypes(arg_return_type.clone(), found.clone())));
+                }
+                Some(_) => {}
+                None => {
+                    generics.insert(name, arg_return_type.clone());
+                }
+            }
+        }
     }
 
     return Ok(());
@@ -8,7 +8,8 @@ use syntax::program::code::{EffectType, Effects, FinalizedEffects};
 use syntax::program::r#struct::StructData;
 use syntax::{Attribute, SimpleVariableManager};
 
-use crate::check_code::verify_effect;
+use crate::check_code::{literal_value, verify_effect};
+use crate::check_impl_call::check_finalized_impl_call;
 use crate::CodeVerifier;
 
 /// Checks if an operator call is valid
@@ -17,6 +18,7 @@ pub async fn check_operator(
     variables: &mut SimpleVariableManager,
     effect: Effects,
 ) -> Result<FinalizedEffects, ParsingError> {
+    let effect = order_operations(code_verifier, effect).await;
     let operation;
     let mut values;
     if let EffectType::Operation(new_operation, new_values) = effect.types {
@@ -36,6 +38,25 @@ pub async fn check_operator(
         OperationGetter { syntax: code_verifier.syntax.clone(), operation: vec![operation], error }.await?
     };
 
+    // Arithmetic on integer literals is done now instead of at runtime
+    match fold_constant(code_verifier, variables, &operation, &mut values, &effect.span).await? {
+        Some(Folded::Literal(folded)) => return Ok(folded),
+        // The operands were already checked, so they're used as they are instead of being checked again
+        Some(Folded::Operands(first, second)) => {
+            return check_finalized_impl_call(
+                code_verifier,
+                variables,
+                effect.span,
+                Some(Box::new(first)),
+                operation.name.clone(),
+                String::default(),
+                vec![second],
+            )
+            .await;
+        }
+        None => {}
+    }
+
     if Attribute::find_attribute("operation", &operation.attributes).unwrap().as_string_attribute().unwrap().contains("{+}")
     {
         if !matches!(values.first().unwrap().types, EffectType::CreateArray(_)) {
@@ -64,6 +85,170 @@ pub async fn check_operator(
     .await;
 }
 
+/// The result of trying to fold an operation on integer literals
+enum Folded {
+    /// The operation was folded into a single literal
+    Literal(FinalizedEffects),
+    /// The checked operands, if the operation couldn't be folded
+    Operands(FinalizedEffects, FinalizedEffects),
+}
+
+/// Folds +, -, *, / or % on two integer literals, like 2 + 3, into a single literal.
+/// Results below zero are i64 literals, so 2 - 3 is -1 instead of wrapping around a u64.
+/// Returns None if the operands aren't integer literals. If the result fits in neither a u64 nor an i64,
+/// which is warned about, or it divides by zero, the checked operands are returned instead.
+async fn fold_constant(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    operation: &StructData,
+    values: &mut Vec<Effects>,
+    span: &Span,
+) -> Result<Option<Folded>, ParsingError> {
+    let attribute = Attribute::find_attribute("operation", &operation.attributes).unwrap();
+    let Some(folding) = folding(&attribute.as_string_attribute().unwrap()) else {
+        return Ok(None);
+    };
+    if values.len() != 2 || !values.iter().all(is_constant) {
+        return Ok(None);
+    }
+
+    // The operands are checked first so any operations in them are folded
+    let first = verify_effect(code_verifier, variables, values.remove(0)).await?;
+    let second = verify_effect(code_verifier, variables, values.remove(0)).await?;
+    let (Some(first_value), Some(second_value)) = (literal_value(&first), literal_value(&second)) else {
+        return Ok(Some(Folded::Operands(first, second)));
+    };
+
+    let operator = attribute.as_operator().unwrap();
+    // Dividing by zero is left for the runtime to deal with
+    if second_value == 0 && (operator == "/" || operator == "%") {
+        return Ok(Some(Folded::Operands(first, second)));
+    }
+    return match folding(first_value, second_value) {
+        Some(value) if value >= i64::MIN as i128 && value <= u64::MAX as i128 => {
+            let value = i64::try_from(value).map_or(EffectType::UInt(value as u64), EffectType::Int);
+            Ok(Some(Folded::Literal(verify_effect(code_verifier, variables, Effects::new(*span, value)).await?)))
+        }
+        _ => {
+            let warning = ParsingMessage::IntegerOverflow(first_value, operator, second_value);
+            code_verifier.syntax.lock().add_warning(span.make_error(warning));
+            Ok(Some(Folded::Operands(first, second)))
+        }
+    };
+}
+
+/// Gets how an operation is folded, or None if it can't be
+fn folding(operation: &str) -> Option<fn(i128, i128) -> Option<i128>> {
+    return match operation {
+        "{}+{}" => Some(i128::checked_add),
+        "{}-{}" => Some(i128::checked_sub),
+        "{}*{}" => Some(i128::checked_mul),
+        "{}/{}" => Some(i128::checked_div),
+        "{}%{}" => Some(i128::checked_rem),
+        _ => None,
+    };
+}
+
+/// Checks if an effect is made of only integer literals and foldable operations on them.
+/// Other operations, like the comparison in 5 / 2 - 1 != 1, aren't constant since they don't give an integer.
+fn is_constant(effect: &Effects) -> bool {
+    return match &effect.types {
+        EffectType::Int(_) | EffectType::UInt(_) => true,
+        EffectType::Paren(inner) => is_constant(inner),
+        EffectType::Operation(operation, values) => folding(operation).is_some() && values.iter().all(is_constant),
+        _ => false,
+    };
+}
+
+/// Orders a chain of operations with a value on each side, like 10 - 4 * 2 != 2, by their priorities.
+/// The parser nests each operation in the one after it, and combine_operation only swaps an operation with the one
+/// nested in it, so the != would otherwise be left inside the - as 10 - (4 * 2 != 2).
+/// Chains with an operation that isn't found are left as they were parsed, for checking to report.
+async fn order_operations(code_verifier: &CodeVerifier<'_>, effect: Effects) -> Effects {
+    let mut values = Vec::default();
+    let mut operations = Vec::default();
+    flatten_operations(effect, &mut values, &mut operations);
+
+    // Equal priorities that parse left nest each operation in the one after it, like the parser does
+    let mut priorities = vec![(0, true); operations.len()];
+    if operations.len() > 1 {
+        for (i, (operation, span)) in operations.iter().enumerate() {
+            let error = span.make_error(ParsingMessage::UnknownOperation(operation.clone()));
+            let getter = OperationGetter { syntax: code_verifier.syntax.clone(), operation: vec![operation.clone()], error };
+            match getter.await {
+                Ok(found) => priorities[i] = (priority(&found), parse_left(&found)),
+                Err(_) => {
+                    priorities = vec![(0, true); operations.len()];
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut values = values.into_iter();
+    let mut ordered = vec![values.next().unwrap()];
+    let mut waiting: Vec<usize> = Vec::default();
+    for (i, value) in values.enumerate() {
+        // Operations before this one that bind tighter are finished first
+        while let Some(&last) = waiting.last() {
+            let ((last_priority, last_left), (priority, _)) = (priorities[last], priorities[i]);
+            if last_priority < priority || (last_priority == priority && last_left) {
+                break;
+            }
+            waiting.pop();
+            join_operation(&mut ordered, &operations[last]);
+        }
+        waiting.push(i);
+        ordered.push(value);
+    }
+    while let Some(last) = waiting.pop() {
+        join_operation(&mut ordered, &operations[last]);
+    }
+    return ordered.pop().unwrap();
+}
+
+/// Splits a chain of operations with a value on each side into its values and operations, in the order they're written
+fn flatten_operations(effect: Effects, values: &mut Vec<Effects>, operations: &mut Vec<(String, Span)>) {
+    match effect.types {
+        EffectType::Operation(operation, mut inner) if is_binary(&operation) && inner.len() == 2 => {
+            let second = inner.pop().unwrap();
+            flatten_operations(inner.pop().unwrap(), values, operations);
+            operations.push((operation, effect.span));
+            flatten_operations(second, values, operations);
+        }
+        types => values.push(Effects::new(effect.span, types)),
+    }
+}
+
+/// Replaces the last two values with the operation on them
+fn join_operation(values: &mut Vec<Effects>, (operation, span): &(String, Span)) {
+    let second = values.pop().unwrap();
+    let first = values.pop().unwrap();
+    values.push(Effects::new(*span, EffectType::Operation(operation.clone(), vec![first, second])));
+}
+
+/// Checks if an operation has a value on each side and nothing else, like {}+{} but not {}[{}
+fn is_binary(operation: &str) -> bool {
+    return operation.len() > 4
+        && operation.starts_with("{}")
+        && operation.ends_with("{}")
+        && !operation[2..operation.len() - 2].contains(['{', '[', ']']);
+}
+
+/// The priority of an operation, higher priorities being done first
+fn priority(operation: &StructData) -> i64 {
+    return Attribute::find_attribute("priority", &operation.attributes)
+        .map(|inner| inner.as_int_attribute().unwrap_or(0))
+        .unwrap_or(0);
+}
+
+/// If an operation is done after operations of the same priority following it
+fn parse_left(operation: &StructData) -> bool {
+    return Attribute::find_attribute("parse_left", &operation.attributes)
+        .map(|inner| inner.as_bool_attribute().unwrap_or(false))
+        .unwrap_or(false);
+}
+
 /// Checks if two operations can be combined
 async fn combine_operation(
     operation: &String,
@@ -208,15 +393,9 @@ pub fn operator_pratt_parsing(
     token: Span,
     inner_token: Span,
 ) -> Option<Arc<StructData>> {
-    let op_priority = Attribute::find_attribute("priority", &found.attributes)
-        .map(|inner| inner.as_int_attribute().unwrap_or(0))
-        .unwrap_or(0);
-    let op_parse_left = Attribute::find_attribute("parse_left", &found.attributes)
-        .map(|inner| inner.as_bool_attribute().unwrap_or(false))
-        .unwrap_or(false);
-    let lhs_priority = Attribute::find_attribute("priority", &inner_data.attributes)
-        .map(|inner| inner.as_int_attribute().unwrap_or(0))
-        .unwrap_or(0);
+    let op_priority = priority(found);
+    let op_parse_left = parse_left(found);
+    let lhs_priority = priority(inner_data);
 
     return if lhs_priority < op_priority || (!op_parse_left && lhs_priority == op_priority) {
         if inner_array {
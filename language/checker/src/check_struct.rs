@@ -1,26 +1,40 @@
-use crate::finalize_generics;
+use crate::check_code::verify_effect;
+use crate::degeneric::degeneric_type_no_generic_types;
 use crate::output::TypesChecker;
+use crate::{finalize_generics, get_return, CodeVerifier};
 use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use syntax::async_util::NameResolver;
-use syntax::errors::ParsingError;
-use syntax::program::code::{FinalizedField, FinalizedMemberField};
-use syntax::program::r#struct::{FinalizedStruct, UnfinalizedStruct};
+use syntax::async_util::{NameResolver, UnparsedType};
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
+use syntax::program::code::{Effects, FinalizedEffects, FinalizedField, FinalizedMemberField};
+use syntax::program::r#struct::{FinalizedStruct, StructData, UnfinalizedStruct};
 use syntax::program::syntax::Syntax;
-use syntax::program::types::FinalizedTypes;
+use syntax::program::types::{FinalizedTypes, Types};
+use syntax::SimpleVariableManager;
 
 /// Verifies if a struct is valid
 pub async fn verify_struct(
-    _process_manager: &TypesChecker,
+    process_manager: &TypesChecker,
     structure: UnfinalizedStruct,
     resolver: &dyn NameResolver,
     syntax: &Arc<Mutex<Syntax>>,
     include_refs: bool,
 ) -> Result<FinalizedStruct, ParsingError> {
-    let mut finalized_fields = Vec::default();
+    // A child's fields start with its parent's, so it has the parent's layout and its fields load like its own
+    let mut finalized_fields = match structure.parent {
+        Some(parent) => verify_parent(&structure.data, parent, resolver, syntax).await?,
+        None => Vec::default(),
+    };
     for field in structure.fields {
         let field = field.await?;
         let mut field_type = field.field.field_type.finalize(syntax.clone()).await;
+        let default_value = match field.default_value {
+            Some(default_value) => {
+                Some(verify_default(process_manager, resolver, syntax, &field_type, default_value).await?)
+            }
+            None => None,
+        };
         if include_refs {
             field_type = FinalizedTypes::Reference(Box::new(field_type));
         }
@@ -28,6 +42,7 @@ pub async fn verify_struct(
             modifiers: field.modifiers,
             attributes: field.attributes,
             field: FinalizedField { field_type, name: field.field.name },
+            default_value,
         })
     }
 
@@ -39,3 +54,71 @@ pub async fn verify_struct(
 
     return Ok(output);
 }
+
+/// Finds the fields of the struct's parent, erroring if the parent extends the struct again somewhere up the chain.
+/// The chain is checked before waiting on the parent, because a cycle would never finish finalizing.
+async fn verify_parent(
+    data: &Arc<StructData>,
+    parent: UnparsedType,
+    resolver: &dyn NameResolver,
+    syntax: &Arc<Mutex<Syntax>>,
+) -> Result<Vec<FinalizedMemberField>, ParsingError> {
+    let span = parent.get_span();
+    let parent = Syntax::parse_type(syntax.clone(), resolver.boxed_clone(), parent, vec![]).await?;
+    {
+        let mut locked = syntax.lock();
+        // A generic parent like Base<T> is in the chain as the struct it's made from
+        let name = match &parent {
+            Types::GenericType(base, _) => base.name(),
+            parent => parent.name(),
+        };
+        locked.struct_parents.insert(data.name.clone(), name);
+        // Each struct has a single parent, so following the chain up finds any cycle.
+        // Visited structs are skipped so a cycle further up the chain, which reports its own error, ends the search.
+        let mut visited = HashSet::new();
+        let mut current = &data.name;
+        while let Some(next) = locked.struct_parents.get(current) {
+            if *next == data.name {
+                return Err(span.make_error(ParsingMessage::CyclicInheritance(data.name.clone())));
+            }
+            if !visited.insert(next) {
+                break;
+            }
+            current = next;
+        }
+    }
+
+    let parent = parent.finalize(syntax.clone()).await;
+    let mut fields = parent.inner_struct().fields.clone();
+    // The parent's generics are replaced by the types the struct gave them
+    if let FinalizedTypes::GenericType(base, bounds) = &parent {
+        let generics = base.inner_struct().generics.keys().cloned().zip(bounds.iter().cloned()).collect::<HashMap<_, _>>();
+        for field in &mut fields {
+            degeneric_type_no_generic_types(&mut field.field.field_type, &generics, syntax).await;
+        }
+    }
+    return Ok(fields);
+}
+
+/// Verifies a field's default value, making sure it's of the field's type
+async fn verify_default(
+    process_manager: &TypesChecker,
+    resolver: &dyn NameResolver,
+    syntax: &Arc<Mutex<Syntax>>,
+    field_type: &FinalizedTypes,
+    default_value: Effects,
+) -> Result<FinalizedEffects, ParsingError> {
+    let span = default_value.span;
+    let mut code_verifier =
+        CodeVerifier { process_manager, resolver: resolver.boxed_clone(), return_type: None, syntax: syntax.clone() };
+    // Default values can't reference any variables
+    let mut variables = SimpleVariableManager { variables: HashMap::default() };
+    let default_value = verify_effect(&mut code_verifier, &mut variables, default_value).await?;
+    return match get_return(&default_value, &variables, syntax).await? {
+        Some(found) if !found.of_type(field_type, syntax.clone()).await => {
+            Err(span.make_error(ParsingMessage::MismatchedTypes(found, field_type.clone())))
+        }
+        Some(_) => Ok(default_value),
+        None => Err(span.make_error(ParsingMessage::UnexpectedVoid)),
+    };
+}
@@ -0,0 +1,117 @@
+use data::tokens::Span;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
+use syntax::program::code::{
+    EffectType, Effects, Expression, ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression,
+};
+use syntax::program::function::FinalizedCodeBody;
+use syntax::program::types::FinalizedTypes;
+use syntax::SimpleVariableManager;
+
+use crate::check_code::{check_return_type, store, verify_effect};
+use crate::CodeVerifier;
+
+/// Finds the option a line uses ? on, if it's a line like option?; or let value = option?;
+pub fn find_try(line: &Expression) -> Option<&Effects> {
+    if line.expression_type != ExpressionType::Line {
+        return None;
+    }
+    let effect = match &line.effect.types {
+        EffectType::CreateVariable(_, inner) => inner,
+        _ => &line.effect,
+    };
+    return match &effect.types {
+        EffectType::Operation(operation, values) if operation == "{}?" && values.len() == 1 => values.first(),
+        _ => None,
+    };
+}
+
+/// Checks a line using ?, which returns none if the option is none and otherwise unwraps it.
+/// The label is used to name the option's variable and the blocks checking it, so it must be unique in the function.
+pub async fn check_try(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    line: Expression,
+    label: String,
+) -> Result<Vec<FinalizedExpression>, ParsingError> {
+    let span = line.effect.span;
+    let (name, option) = match line.effect.types {
+        EffectType::CreateVariable(name, inner) => (Some(name), *inner),
+        types => (None, Effects::new(span, types)),
+    };
+    let option = match option.types {
+        EffectType::Operation(_, mut values) => values.remove(0),
+        _ => unreachable!(),
+    };
+
+    let returning = match &code_verifier.return_type {
+        Some(returning) if is_option(returning) => returning.clone(),
+        _ => return Err(span.make_error(ParsingMessage::TryOutsideOption)),
+    };
+
+    // The option is kept in a variable so it's only evaluated once
+    let holder = format!("$try{}", label);
+    let option = verify_effect(
+        code_verifier,
+        variables,
+        Effects::new(option.span, EffectType::CreateVariable(holder.clone(), Box::new(option))),
+    )
+    .await?;
+    let option_type = variables.variables.get(&holder).unwrap().clone();
+    if !is_option(&option_type) {
+        return Err(span.make_error(ParsingMessage::TryOnNonOption(option_type)));
+    }
+    let mut output = vec![FinalizedExpression::new(ExpressionType::Line, option)];
+
+    // Returns none from the function if the option is none, which has to be made again as the return type
+    let call = |method: &str| {
+        let holder = Effects::new(span, EffectType::LoadVariable(holder.clone()));
+        return Effects::new(span, EffectType::MethodCall(Some(Box::new(holder)), method.to_string(), vec![], vec![]));
+    };
+    let check = verify_effect(code_verifier, variables, call("is_none")).await?;
+    let data = returning.get_fields().iter().position(|field| field.field.name == "data").unwrap();
+    let none = FinalizedEffects::new(
+        span,
+        FinalizedEffectType::CreateStruct(
+            Some(Box::new(FinalizedEffects::new(Span::default(), FinalizedEffectType::HeapAllocate(returning.clone())))),
+            returning,
+            vec![(data, FinalizedEffects::new(span, store(FinalizedEffectType::UInt(0))))],
+        ),
+    );
+    let mut returning_none = vec![FinalizedExpression::new(ExpressionType::Return(span), none)];
+    check_return_type(ExpressionType::Return(span), code_verifier, &mut returning_none, variables, &code_verifier.syntax)
+        .await?;
+
+    let then = FinalizedCodeBody::new(returning_none, format!("{}then", label), true);
+    let top = FinalizedCodeBody::new(
+        vec![
+            FinalizedExpression::new(
+                ExpressionType::Line,
+                FinalizedEffects::new(
+                    span,
+                    FinalizedEffectType::CompareJump(Box::new(check), then.label.clone(), format!("{}end", label)),
+                ),
+            ),
+            FinalizedExpression::new(ExpressionType::Line, FinalizedEffects::new(span, FinalizedEffectType::CodeBody(then))),
+        ],
+        label,
+        false,
+    );
+    output.push(FinalizedExpression::new(
+        ExpressionType::Line,
+        FinalizedEffects::new(span, FinalizedEffectType::CodeBody(top)),
+    ));
+
+    // Otherwise the value is unwrapped into the variable, if there is one
+    if let Some(name) = name {
+        let value = Effects::new(span, EffectType::CreateVariable(name, Box::new(call("unwrap"))));
+        output.push(FinalizedExpression::new(ExpressionType::Line, verify_effect(code_verifier, variables, value).await?));
+    }
+    return Ok(output);
+}
+
+/// Checks if the type is a core Option
+fn is_option(types: &FinalizedTypes) -> bool {
+    return types
+        .inner_struct_safe()
+        .map_or(false, |structure| structure.data.name.split('$').next().unwrap() == "option::Option");
+}
@@ -13,10 +13,10 @@ use data::tokens::Span;
 use syntax::async_util::AsyncDataGetter;
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::code::{FinalizedEffectType, FinalizedEffects, FinalizedMemberField};
-use syntax::program::function::{display_parenless, CodelessFinalizedFunction, FinalizedCodeBody, FunctionData};
+use syntax::program::function::{CodelessFinalizedFunction, FinalizedCodeBody, FunctionData};
 use syntax::program::r#struct::{FinalizedStruct, StructData};
 use syntax::program::syntax::Syntax;
-use syntax::program::types::FinalizedTypes;
+use syntax::program::types::{mangle_generic_name, FinalizedTypes};
 use syntax::top_element_manager::ImplWaiter;
 use syntax::{ProcessManager, SimpleVariableManager, TopElement};
 
@@ -35,17 +35,25 @@ pub async fn degeneric_effect(
     match effect {
         FinalizedEffectType::CreateVariable(name, value, types) => {
             degeneric_effect(&mut value.types, syntax, process_manager, variables, span).await?;
-            *types = get_return(&value.types, variables, syntax).await.unwrap();
+            *types = get_return(value, variables, syntax).await?.unwrap();
             degeneric_type(types, process_manager.generics(), syntax).await;
             variables.variables.insert(name.clone(), types.clone());
         }
         FinalizedEffectType::CompareJump(effect, _, _) => {
             degeneric_effect(&mut effect.types, syntax, process_manager, variables, span).await?
         }
+        FinalizedEffectType::MatchArm(effect, arms) => {
+            degeneric_effect(&mut effect.types, syntax, process_manager, variables, span).await?;
+            for (types, arm) in arms {
+                degeneric_type(types, process_manager.generics(), syntax).await;
+                degeneric_effect(&mut arm.types, syntax, process_manager, variables, span).await?;
+            }
+        }
         FinalizedEffectType::CodeBody(body) => degeneric_code_body(body, process_manager, variables, syntax).await?,
         FinalizedEffectType::FunctionCall(calling, function, arguments, explicit_generics) => {
-            if let Some(found) = calling {
-                arguments.insert(0, *found.clone());
+            // The calling effect is the first argument from here on, so it isn't kept in both places
+            if let Some(found) = calling.take() {
+                arguments.insert(0, *found);
             }
             for argument in &mut *arguments {
                 degeneric_effect(&mut argument.types, syntax, process_manager, variables, span).await?;
@@ -59,13 +67,17 @@ pub async fn degeneric_effect(
             let mut before_arguments = function.arguments.clone();
             let mut degenericing_process_manager = process_manager.cloned();
             degenericing_process_manager.mut_generics().clear();
+            // Explicit generics are known up front, so the arguments are resolved against them instead of guessed
+            for ((generic, _bounds), (explicit_generic, _)) in function.generics.iter().zip(explicit_generics.iter()) {
+                degenericing_process_manager.mut_generics().insert(generic.clone(), explicit_generic.clone());
+            }
 
             for i in 0..before_arguments.len() {
                 before_arguments[i]
                     .field
                     .field_type
                     .resolve_generic(
-                        &get_return(&arguments[i].types, variables, syntax).await.unwrap(),
+                        &get_return(&arguments[i], variables, syntax).await?.unwrap(),
                         syntax,
                         degenericing_process_manager.mut_generics(),
                         span.clone(),
@@ -89,6 +101,7 @@ pub async fn degeneric_effect(
                 syntax,
                 variables,
                 explicit_generics.clone(),
+                span,
             )
             .await?;
 
@@ -103,22 +116,25 @@ pub async fn degeneric_effect(
             let mut calling = arguments.remove(0);
             degeneric_effect(&mut calling.types, syntax, process_manager, variables, span).await?;
 
-            let implementor = get_return(&calling.types, variables, syntax).await.unwrap();
-            let implementation = ImplWaiter {
-                syntax: syntax.clone(),
-                base_type: implementor.clone(),
-                trait_type: types.clone(),
-                error: Span::default().make_error(ParsingMessage::ShouldntSee("Degeneric generic method call")),
-            }
+            let implementor = get_return(&calling, variables, syntax).await?.unwrap();
+            let implementation = ImplWaiter::new(
+                syntax.clone(),
+                implementor.clone(),
+                types.clone(),
+                span.make_error(ParsingMessage::NoTraitImpl(implementor.clone(), types.clone())),
+            )
             .await?;
+            let (_, functions) = Syntax::select_implementation(implementation, span)?;
 
             let name = function.data.name.split("::").last().unwrap();
-            let function =
-                implementation.iter().flat_map(|(_, inner)| inner).find(|inner| inner.name.ends_with(&name)).unwrap();
+            let function = match functions.iter().find(|inner| inner.name.split("::").last().unwrap() == name) {
+                Some(found) => found,
+                None => return Err(span.make_error(ParsingMessage::NoImpl(implementor, name.to_string()))),
+            };
             arguments.insert(0, calling.clone());
             let function = AsyncDataGetter::new(syntax.clone(), function.clone()).await;
             let function =
-                degeneric_function(function.clone(), process_manager.cloned(), &arguments, syntax, variables, vec![])
+                degeneric_function(function.clone(), process_manager.cloned(), &arguments, syntax, variables, vec![], span)
                     .await?;
             for argument in &mut *arguments {
                 degeneric_effect(&mut argument.types, syntax, process_manager, variables, span).await?;
@@ -152,6 +168,14 @@ pub async fn degeneric_effect(
             }
             degeneric_type(types, &type_generics, syntax).await;
         }
+        FinalizedEffectType::CreateArray(types, values) => {
+            for value in values {
+                degeneric_effect(&mut value.types, syntax, process_manager, variables, span).await?;
+            }
+            if let Some(types) = types {
+                degeneric_type(types, process_manager.generics(), syntax).await;
+            }
+        }
         FinalizedEffectType::VirtualCall(_, function, calling, arguments) => {
             arguments.insert(0, *calling.clone());
             // TODO figure out generic virtual functions
@@ -185,24 +209,24 @@ pub async fn degeneric_effect(
             degeneric_effect(effect, syntax, process_manager, variables, span).await?;
         }
         FinalizedEffectType::Downcast(base, target, functions) => {
-            let impl_functions = ImplWaiter {
-                syntax: syntax.clone(),
-                trait_type: target.clone(),
-                base_type: get_return(&base.types, variables, syntax).await.unwrap(),
-                error: Span::default().make_error(ParsingMessage::ShouldntSee("Downcasting failed")),
-            }
+            let base_type = get_return(base, variables, syntax).await?.unwrap();
+            let impl_functions = ImplWaiter::new(
+                syntax.clone(),
+                base_type.clone(),
+                target.clone(),
+                span.make_error(ParsingMessage::NoTraitImpl(base_type, target.clone())),
+            )
             .await?;
-            if impl_functions.is_empty() {
-                return Err(span.make_error(ParsingMessage::ShouldntSee("Downcast")));
-            }
+            let (implementor, impl_functions) = Syntax::select_implementation(impl_functions, span)?;
 
             let mut manager = process_manager.cloned();
-            let base_types = get_return(&base.types, variables, syntax).await.unwrap();
-            impl_functions[0].0.base.resolve_generic(&base_types, syntax, manager.mut_generics(), span.clone()).await?;
+            let base_types = get_return(base, variables, syntax).await?.unwrap();
+            implementor.base.resolve_generic(&base_types, syntax, manager.mut_generics(), span.clone()).await?;
 
-            for function in &impl_functions[0].1 {
+            for function in &impl_functions {
                 let function = AsyncDataGetter::new(syntax.clone(), function.clone()).await;
-                let function = degeneric_function(function, manager.cloned(), &vec![], syntax, variables, vec![]).await?;
+                let function =
+                    degeneric_function(function, manager.cloned(), &vec![], syntax, variables, vec![], span).await?;
                 functions.push(function)
             }
 
@@ -232,7 +256,7 @@ pub async fn degeneric_arguments(
     process_manager: &dyn ProcessManager,
 ) -> Result<(), ParsingError> {
     for i in 0..base_arguments.len() {
-        let arg_return_type = get_return(&arguments[i].types, variables, syntax).await.unwrap();
+        let arg_return_type = get_return(&arguments[i], variables, syntax).await?.unwrap();
         let base_field_type = &base_arguments[i].field.field_type;
         // Only downcast if an implementation was found and it's not generic. Don't downcast if they're of the same type.
         if !arg_return_type.of_type_sync(base_field_type, None).0
@@ -267,6 +291,7 @@ pub async fn degeneric_function(
     syntax: &Arc<Mutex<Syntax>>,
     variables: &SimpleVariableManager,
     explicit_generics: Vec<(FinalizedTypes, Span)>,
+    span: &Span,
 ) -> Result<Arc<CodelessFinalizedFunction>, ParsingError> {
     if !explicit_generics.is_empty() {
         // Replace each generic with its explicitly given value
@@ -278,16 +303,24 @@ pub async fn degeneric_function(
             .map(|((generic, _bounds), explicit_generic)| (generic.clone(), explicit_generic.0))
             .collect::<HashMap<_, _>>();
     } else if function.arguments.len() != 0 && arguments.len() != 0 {
+        // Calls are checked before they're degenericed, but make sure every argument has a value before indexing them
+        if function.arguments.len() != arguments.len() {
+            return Err(
+                span.make_error(ParsingMessage::WrongArgumentCount(function.arguments.len() as u64, arguments.len() as u64))
+            );
+        }
+
         // Figure out what each generic actually is by comparing the input arguments to the function's arguments.
         // Downcasting ignores arguments to try and only half-degeneric, so if the arguments are empty then skip this.
         for i in 0..function.arguments.len() {
-            let argument_type = get_return(&arguments[i].types, variables, syntax).await.unwrap();
+            let argument_type = get_return(&arguments[i], variables, syntax).await?.unwrap();
 
             function.arguments[i]
                 .field
                 .field_type
                 .resolve_generic(&argument_type, syntax, manager.mut_generics(), arguments[i].span.clone())
-                .await?;
+                .await
+                .map_err(|error| error.chain(ParsingMessage::WhileInstantiating(function.data.name.clone()), *span))?;
         }
     }
 
@@ -297,17 +330,25 @@ pub async fn degeneric_function(
         degeneric_type(generic, &generics, syntax).await;
     }
 
+    // Stop recursive generics like List<List<...>> from instantiating forever.
+    let max_depth = syntax.lock().async_manager.max_generic_depth;
+    for (_, generic) in manager.generics() {
+        if generic.depth() > max_depth {
+            return Err(span.make_error(ParsingMessage::GenericDepthExceeded(generic.clone(), max_depth)));
+        }
+    }
+
     // Now all the generic types have been resolved, it's time to replace them with
     // their solidified versions.
     // Degenericed function names have a $ separating the name and the generics.
+    // The generics are sorted by name so the same generics always give the same name.
     let name = if manager.generics().is_empty() {
         function.data.name.split("$").next().unwrap().to_string()
     } else {
-        format!(
-            "{}${}",
-            function.data.name.split("$").next().unwrap(),
-            display_parenless(&manager.generics().values().collect(), "_")
-        )
+        let mut generics = manager.generics().iter().collect::<Vec<_>>();
+        generics.sort_by(|(first, _), (second, _)| first.cmp(second));
+        let generics = generics.into_iter().map(|(_, generic)| generic.clone()).collect::<Vec<_>>();
+        mangle_generic_name(function.data.name.split("$").next().unwrap(), &generics)
     };
 
     // If this function has already been degenericed, use the previous one.
@@ -336,6 +377,7 @@ pub async fn degeneric_function(
 
     let mut method_data = FunctionData::clone(&function.data);
     method_data.name.clone_from(&name);
+    method_data.instantiated_at = Some(*span);
     new_function.data = Arc::new(method_data);
     // Degeneric the arguments.
     for argument in &mut new_function.arguments {
@@ -384,8 +426,13 @@ async fn degeneric_code(
 
     let mut variables = SimpleVariableManager::for_function(degenericed_method.deref());
 
-    // Degenerics the code bod
-    degeneric_code_body(&mut code, &*manager, &mut variables, &syntax).await?;
+    // Degenerics the code body, pointing errors back at the call that instantiated it
+    if let Err(error) = degeneric_code_body(&mut code, &*manager, &mut variables, &syntax).await {
+        return Err(match degenericed_method.data.instantiated_at {
+            Some(span) => error.chain(ParsingMessage::WhileInstantiating(original.data.name.clone()), span),
+            None => error,
+        });
+    }
 
     // Combines the degenericed function with the degenericed code to finalize it.
     let output = CodelessFinalizedFunction::clone(degenericed_method.deref()).add_code(code);
@@ -413,29 +460,122 @@ impl Future for FunctionWaiter {
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut locked = self.syntax.lock();
+        locked.async_manager.unblock();
         return if locked.generics.contains_key(&self.data.name) {
             Poll::Ready(())
         } else {
             locked.compiling_wakers.entry(self.data.name.clone()).or_insert(vec![]).push(cx.waker().clone());
+            locked.async_manager.block(|| format!("FunctionWaiter waiting for {} to be checked", self.data.name));
             Poll::Pending
         };
     }
 }
 
+/// A code body on degeneric_code_body's work stack
+struct BodyFrame {
+    /// The code body, taken out of the effect holding it
+    body: FinalizedCodeBody,
+    /// The index of the next expression to degeneric
+    index: usize,
+    /// Code bodies taken out of the last expression that haven't been degenericed yet
+    pending: Vec<FinalizedCodeBody>,
+    /// Code bodies taken out of the last expression that have been degenericed, to put back once all are done
+    finished: Vec<FinalizedCodeBody>,
+}
+
+impl BodyFrame {
+    /// Creates a frame to degeneric the body from its first expression
+    fn new(body: FinalizedCodeBody) -> Self {
+        return Self { body, index: 0, pending: Vec::default(), finished: Vec::default() };
+    }
+}
+
 /// Degenerics every effect inside the body of code.
+/// Code bodies nested in the body, like match arms, are handled with a work stack instead of recursion,
+/// so deeply nested code doesn't grow the async poll stack.
 pub async fn degeneric_code_body(
     code: &mut FinalizedCodeBody,
     process_manager: &dyn ProcessManager,
     variables: &mut SimpleVariableManager,
     syntax: &Arc<Mutex<Syntax>>,
 ) -> Result<(), ParsingError> {
-    for expression in &mut code.expressions {
-        degeneric_effect(&mut expression.effect.types, syntax, process_manager, variables, &expression.effect.span).await?;
+    let mut stack = vec![BodyFrame::new(mem::replace(code, empty_body(&code.label)))];
+    while let Some(mut frame) = stack.pop() {
+        if let Some(inner) = frame.pending.pop() {
+            stack.push(frame);
+            stack.push(BodyFrame::new(inner));
+            continue;
+        }
+        if !frame.finished.is_empty() {
+            let finished = mem::take(&mut frame.finished);
+            put_back_bodies(&mut frame.body.expressions[frame.index - 1].effect.types, finished);
+        }
+
+        if frame.index == frame.body.expressions.len() {
+            // Finished the body, so it goes back to its parent
+            match stack.last_mut() {
+                Some(parent) => parent.finished.push(frame.body),
+                None => *code = frame.body,
+            }
+            continue;
+        }
+
+        let effect = &mut frame.body.expressions[frame.index].effect;
+        // Checked between effects so a cancelled compile doesn't finish degenericing big functions
+        if process_manager.cancelled() {
+            return Err(effect.span.make_error(ParsingMessage::Cancelled));
+        }
+        // The bodies are taken out first so degenericing the rest of the effect doesn't recurse into them
+        let mut bodies = take_bodies(&mut effect.types);
+        degeneric_effect(&mut effect.types, syntax, process_manager, variables, &effect.span).await?;
+        // Popped from the back, so reversed to degeneric them in the order they were found
+        bodies.reverse();
+        frame.pending = bodies;
+        frame.index += 1;
+        stack.push(frame);
     }
 
     return Ok(());
 }
 
+/// Takes every code body out of the effect, including the effect itself if it's one, leaving empty bodies in their place.
+/// Bodies inside the taken bodies are left in them.
+fn take_bodies(effect: &mut FinalizedEffectType) -> Vec<FinalizedCodeBody> {
+    let mut bodies = Vec::default();
+    let mut effects = vec![effect];
+    while let Some(effect) = effects.pop() {
+        if let FinalizedEffectType::CodeBody(body) = effect {
+            let label = body.label.clone();
+            bodies.push(mem::replace(body, empty_body(&label)));
+        } else {
+            effects.extend(effect.effects_mut().into_iter().rev().map(|inner| &mut inner.types));
+        }
+    }
+    return bodies;
+}
+
+/// Puts the degenericed bodies back in place of the empty bodies take_bodies left, matching them by label.
+/// Degenericing can copy part of an effect, like a call's caller into its arguments, so a label can be found twice,
+/// and only one copy gets the body.
+fn put_back_bodies(effect: &mut FinalizedEffectType, finished: Vec<FinalizedCodeBody>) {
+    let mut finished = finished.into_iter().map(|body| (body.label.clone(), body)).collect::<HashMap<_, _>>();
+    let mut effects = vec![effect];
+    while let Some(effect) = effects.pop() {
+        if let FinalizedEffectType::CodeBody(body) = effect {
+            if let Some(found) = finished.remove(&body.label) {
+                *body = found;
+            }
+        } else {
+            effects.extend(effect.effects_mut().into_iter().map(|inner| &mut inner.types));
+        }
+    }
+}
+
+/// Makes an empty code body to hold a code body's place while it's on the work stack
+fn empty_body(label: &String) -> FinalizedCodeBody {
+    return FinalizedCodeBody::new(Vec::default(), label.clone(), false);
+}
+
 /// Degenerics the type by replacing all generics with their solidified value.
 #[async_recursion(Sync)]
 pub async fn degeneric_type(
@@ -462,6 +602,10 @@ pub async fn degeneric_type(
             }
         }
         FinalizedTypes::Reference(inner) => degeneric_type(inner, generics, syntax).await,
+        // Structs without generics, like already degenericed ones, have nothing to replace. Skipping them stops
+        // nested types like Wrap<Wrap<T>>, which have each level in both the base's fields and the bounds, from
+        // taking twice as long for each level.
+        FinalizedTypes::Struct(inner) if inner.generics.is_empty() => {}
         FinalizedTypes::Struct(inner) => {
             let mut temp = FinalizedStruct::clone(inner);
             for field in &mut temp.fields {
@@ -567,8 +711,7 @@ pub async fn degeneric_header(
             _ => panic!("Bad generics in func generics"),
         } {
             if !generic.of_type(bound, syntax.clone()).await {
-                // TODO see if this is needed
-                return Err(span.make_error(ParsingMessage::ShouldntSee("Bounds sanity check!")));
+                return Err(span.make_error(ParsingMessage::MismatchedTypes(generic.clone(), bound.clone())));
             }
         }
         manager.mut_generics().insert(name.clone(), generic.clone());
@@ -606,13 +749,17 @@ pub async fn degeneric_struct(
     generics: &HashMap<String, FinalizedTypes>,
     syntax: &Arc<Mutex<Syntax>>,
 ) -> Arc<FinalizedStruct> {
-    let targets: Vec<_> =
-        generics.iter().filter(|(key, _)| structure.generics.contains_key(*key)).map(|(_, value)| value).collect();
+    // Kept in the struct's order so the same generics always give the same name
+    let targets: Vec<_> = structure.generics.keys().filter_map(|key| generics.get(key).cloned()).collect();
     if targets.is_empty() {
         return Arc::new(structure);
     }
+    let found = syntax.lock().get_generic_struct(&structure.data.name, &targets);
+    if let Some(found) = found {
+        return found;
+    }
     let mut data = StructData::clone(&structure.data);
-    let name = format!("{}${}", data.name, display_parenless(&targets, "_"));
+    let name = mangle_generic_name(&data.name, &targets);
     data.name.clone_from(&name);
 
     // TODO check if this is safe, handle generics calling generics
@@ -622,6 +769,10 @@ pub async fn degeneric_struct(
     }
 
     let mut locked = syntax.lock();
+    // Another task could have degenericed it at the same time
+    if let Some(found) = locked.get_generic_struct(&structure.data.name, &targets) {
+        return found;
+    }
     structure.data = Arc::new(data);
     let output = Arc::new(structure);
 
@@ -12,11 +12,12 @@ use indexmap::IndexMap;
 
 use crate::degeneric::degeneric_type_no_generic_types;
 use syntax::async_util::{NameResolver, UnparsedType};
-use syntax::errors::ParsingError;
-use syntax::program::code::FinalizedEffectType;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
+use syntax::program::code::{FinalizedEffectType, FinalizedEffects};
+use syntax::program::function::CodelessFinalizedFunction;
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
-use syntax::SimpleVariableManager;
+use syntax::{Attribute, SimpleVariableManager};
 
 use crate::output::TypesChecker;
 
@@ -26,12 +27,16 @@ pub mod check_code;
 pub mod check_function;
 /// Checks the impl call effect
 pub mod check_impl_call;
+/// Checks the lambda effect
+pub mod check_lambda;
 /// Checks the method call effect
 pub mod check_method_call;
 /// Checks the operator effect
 pub mod check_operator;
 /// Checks structs
 pub mod check_struct;
+/// Checks lines using the ? operator
+pub mod check_try;
 /// Degenerics types
 pub mod degeneric;
 /// Used to send data to be checked by the checker and then send the result to the compiler
@@ -59,6 +64,16 @@ pub async fn finalize_generics(
     return Ok(output);
 }
 
+/// Checks if an element with the given attributes should be compiled.
+/// Every #[cfg(key = "value")] or #[cfg(flag)] attribute must be one of the enabled conditions,
+/// which are written like target_os=linux or test, so unknown keys never match.
+pub fn is_cfg_enabled(attributes: &Vec<Attribute>, cfg: &Vec<String>) -> bool {
+    return attributes.iter().all(|attribute| match attribute {
+        Attribute::String(name, value) if name == "cfg" => cfg.contains(value),
+        _ => true,
+    });
+}
+
 /// Simple wrapper program for the types used in code verification
 pub struct CodeVerifier<'a> {
     process_manager: &'a TypesChecker,
@@ -69,57 +84,59 @@ pub struct CodeVerifier<'a> {
 
 /// Gets the return type of the effect, requiring a variable manager to get
 /// any variables from, or None if the effect has no return type.
+/// Errors if the effect is malformed, like loading a field from a value without that field.
 #[async_recursion(Sync)]
 pub async fn get_return(
-    types: &FinalizedEffectType,
+    effect: &FinalizedEffects,
     variables: &SimpleVariableManager,
     syntax: &Arc<Mutex<Syntax>>,
-) -> Option<FinalizedTypes> {
-    return match types {
-        FinalizedEffectType::FunctionCall(calling, function, _, returning) => match function.return_type.as_ref().cloned() {
-            Some(mut inner) => {
-                if !returning.is_empty() {
-                    let generics = function
-                        .generics
-                        .iter()
-                        .zip(returning)
-                        //TODO bounds check
-                        .map(|((generic, _bounds), explicit_generic)| (generic.clone(), explicit_generic.0.clone()))
-                        .collect::<HashMap<_, _>>();
-                    degeneric_type_no_generic_types(&mut inner, &generics, syntax).await;
-                } else if let Some(calling) = calling {
-                    let other = get_return(&calling.types, variables, syntax).await;
-                    if let Some(found) = other {
+) -> Result<Option<FinalizedTypes>, ParsingError> {
+    return Ok(match &effect.types {
+        FinalizedEffectType::FunctionCall(calling, function, arguments, returning) => {
+            match function.return_type.as_ref().cloned() {
+                Some(mut inner) => {
+                    if !returning.is_empty() {
+                        let generics = function
+                            .generics
+                            .iter()
+                            .zip(returning)
+                            //TODO bounds check
+                            .map(|((generic, _bounds), explicit_generic)| (generic.clone(), explicit_generic.0.clone()))
+                            .collect::<HashMap<_, _>>();
+                        degeneric_type_no_generic_types(&mut inner, &generics, syntax).await;
+                    } else if let Some(calling) = calling {
+                        if let Some(found) = get_return(calling, variables, syntax).await? {
+                            let mut generics = HashMap::new();
+                            get_parent(function, effect.span)?
+                                .resolve_generic(&found, syntax, &mut generics, effect.span)
+                                .await?;
+                            degeneric_type_no_generic_types(&mut inner, &generics, syntax).await;
+                        }
+                    } else if !function.generics.is_empty() {
+                        // Generics of functions like add<T>(first: T, second: T) are given by the arguments
                         let mut generics = HashMap::new();
-                        function
-                            .parent
-                            .as_ref()
-                            .unwrap()
-                            .resolve_generic(&found, syntax, &mut generics, Span::default())
-                            .await
-                            .unwrap();
+                        for (field, argument) in function.arguments.iter().zip(arguments) {
+                            if let Some(found) = get_return(argument, variables, syntax).await? {
+                                field.field.field_type.resolve_generic(&found, syntax, &mut generics, argument.span).await?;
+                            }
+                        }
                         degeneric_type_no_generic_types(&mut inner, &generics, syntax).await;
                     }
+                    Some(FinalizedTypes::Reference(Box::new(inner)))
                 }
-                Some(FinalizedTypes::Reference(Box::new(inner)))
+                None => None,
             }
-            None => None,
-        },
+        }
         FinalizedEffectType::GenericMethodCall(function, _, args)
         | FinalizedEffectType::VirtualCall(_, function, _, args)
         | FinalizedEffectType::GenericVirtualCall(_, _, function, args) => match function.return_type.as_ref().cloned() {
             Some(mut inner) => {
                 if let Some(calling) = args.get(0) {
-                    let other = get_return(&calling.types, variables, syntax).await;
-                    if let Some(found) = other {
+                    if let Some(found) = get_return(calling, variables, syntax).await? {
                         let mut generics = HashMap::new();
-                        function
-                            .parent
-                            .as_ref()
-                            .unwrap()
-                            .resolve_generic(&found, syntax, &mut generics, Span::default())
-                            .await
-                            .unwrap();
+                        get_parent(function, effect.span)?
+                            .resolve_generic(&found, syntax, &mut generics, effect.span)
+                            .await?;
                         degeneric_type_no_generic_types(&mut inner, &generics, syntax).await;
                     }
                 }
@@ -130,21 +147,37 @@ pub async fn get_return(
         // Stores just return their inner type.
         FinalizedEffectType::HeapStore(inner)
         | FinalizedEffectType::StackStore(inner)
-        | FinalizedEffectType::Set(_, inner) => get_return(&inner.types, variables, syntax).await,
+        | FinalizedEffectType::Set(_, inner) => get_return(inner, variables, syntax).await?,
         // References return their inner type as well.
-        FinalizedEffectType::ReferenceLoad(inner) => match get_return(&inner.types, variables, syntax).await.unwrap() {
-            FinalizedTypes::Reference(inner) => Some(*inner),
-            _ => panic!("Tried to load non-reference!"),
+        FinalizedEffectType::ReferenceLoad(inner) => match get_return(inner, variables, syntax).await? {
+            Some(FinalizedTypes::Reference(inner)) => Some(*inner),
+            Some(_) => return Err(effect.span.make_error(ParsingMessage::ShouldntSee("Loaded a non-reference"))),
+            None => return Err(inner.span.make_error(ParsingMessage::UnexpectedVoid)),
         },
         // Gets the type of the field in the program with that name.
-        FinalizedEffectType::Load(effect, name, _) => get_return(&effect.types, variables, syntax)
-            .await
-            .unwrap()
-            .inner_struct()
-            .fields
-            .iter()
-            .find(|field| &field.field.name == name)
-            .map(|field| field.field.field_type.clone()),
-        _ => types.get_nongeneric_return(variables),
-    };
+        FinalizedEffectType::Load(loading, name, _) => {
+            let Some(loading_type) = get_return(loading, variables, syntax).await? else {
+                return Err(loading.span.make_error(ParsingMessage::UnexpectedVoid));
+            };
+            let field = loading_type
+                .inner_struct_safe()
+                .and_then(|structure| structure.fields.iter().find(|field| &field.field.name == name));
+            let Some(field) = field else {
+                return Err(effect.span.make_error(ParsingMessage::UnknownField(name.clone())));
+            };
+            let mut field_type = field.field.field_type.clone();
+            // Fields of generic types, like current in a Range<u64>, have the type their generic was given
+            if let Some((base, bounds)) = loading_type.inner_generic_type() {
+                let generics = base.inner_struct().generics.keys().cloned().zip(bounds.iter().cloned()).collect();
+                degeneric_type_no_generic_types(&mut field_type, &generics, syntax).await;
+            }
+            Some(field_type)
+        }
+        types => types.get_nongeneric_return(variables),
+    });
+}
+
+/// Gets the struct a method is in, which every method call must have
+fn get_parent(function: &CodelessFinalizedFunction, span: Span) -> Result<&FinalizedTypes, ParsingError> {
+    return function.parent.as_ref().ok_or_else(|| span.make_error(ParsingMessage::ShouldntSee("Method without a parent")));
 }
@@ -8,6 +8,8 @@ use std::sync::Arc;
 use crate::check_function::{verify_function, verify_function_code};
 use crate::check_struct::verify_struct;
 use crate::degeneric::degeneric_function;
+use crate::is_cfg_enabled;
+use data::CancellationToken;
 use syntax::async_util::{HandleWrapper, NameResolver};
 use syntax::program::function::{
     CodeBody, CodelessFinalizedFunction, FinalizedCodeBody, FinalizedFunction, FunctionData, UnfinalizedFunction,
@@ -15,7 +17,7 @@ use syntax::program::function::{
 use syntax::program::r#struct::{FinalizedStruct, StructData, UnfinalizedStruct};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
-use syntax::{ProcessManager, SimpleVariableManager};
+use syntax::{Attribute, ProcessManager, SimpleVariableManager};
 
 /// Wrapper around data used by checkers
 #[derive(Clone)]
@@ -25,13 +27,22 @@ pub struct TypesChecker {
     /// Generics in the current type
     pub generics: HashMap<String, FinalizedTypes>,
     /// Whether to include references
-    include_refs: bool,
+    pub include_refs: bool,
+    /// The conditions #[cfg(...)] attributes can match, like target_os=linux
+    pub cfg: Vec<String>,
+    /// Cancels the compile, kept outside the runtime's lock so checking it doesn't block other tasks
+    cancellation: CancellationToken,
 }
 
 impl TypesChecker {
     /// Makes a new TypesChecker
-    pub fn new(runtime: Arc<Mutex<HandleWrapper>>, include_refs: bool) -> Self {
-        return Self { runtime, generics: HashMap::default(), include_refs };
+    pub fn new(
+        runtime: Arc<Mutex<HandleWrapper>>,
+        include_refs: bool,
+        cfg: Vec<String>,
+        cancellation: CancellationToken,
+    ) -> Self {
+        return Self { runtime, generics: HashMap::default(), include_refs, cfg, cancellation };
     }
 }
 
@@ -84,7 +95,9 @@ impl ProcessManager for TypesChecker {
 
     async fn degeneric_code(&self, function: Arc<CodelessFinalizedFunction>, syntax: &Arc<Mutex<Syntax>>) {
         let variables = SimpleVariableManager::for_function(&function);
-        let _ = match degeneric_function(function, Box::new(self.clone()), &vec![], syntax, &variables, vec![]).await {
+        let span = function.data.span;
+        let _ = match degeneric_function(function, Box::new(self.clone()), &vec![], syntax, &variables, vec![], &span).await
+        {
             Ok(result) => result,
             Err(error) => panic!("{:?}", error),
         };
@@ -120,4 +133,12 @@ impl ProcessManager for TypesChecker {
     fn cloned(&self) -> Box<dyn ProcessManager> {
         return Box::new(self.clone());
     }
+
+    fn cfg_enabled(&self, attributes: &Vec<Attribute>) -> bool {
+        return is_cfg_enabled(attributes, &self.cfg);
+    }
+
+    fn cancelled(&self) -> bool {
+        return self.cancellation.is_cancelled();
+    }
 }
@@ -1,3 +1,6 @@
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -6,18 +9,23 @@ use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::execution_engine::ExecutionEngine;
 use inkwell::module::Module;
+use inkwell::passes::PassBuilderOptions;
+use inkwell::support::load_library_permanently;
+use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple};
 use inkwell::OptimizationLevel;
 use parking_lot::Mutex;
 use tokio::time;
 
 use data::tokens::Span;
-use data::CompilerArguments;
+use data::{CompilerArguments, OptimizationLevel as RavenOptimizationLevel};
 use syntax::async_util::EmptyNameResolver;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::function::{CodelessFinalizedFunction, FinalizedFunction};
 use syntax::program::r#struct::FinalizedStruct;
 use syntax::program::syntax::Syntax;
 
-use crate::function_compiler::{compile_block, instance_function};
+use crate::debug_info::DebugInfo;
+use crate::function_compiler::compile_block;
 use crate::main_future::MainFuture;
 use crate::type_getter::CompilerTypeGetter;
 
@@ -29,16 +37,136 @@ pub struct CompilerImpl<'ctx> {
     pub module: Module<'ctx>,
     /// LLVM builder
     pub builder: Builder<'ctx>,
-    /// LLVM execution engine
-    pub execution_engine: ExecutionEngine<'ctx>,
+    /// LLVM execution engine, or None if the output is written to an object file instead of being run
+    pub execution_engine: Option<ExecutionEngine<'ctx>>,
+    /// The machine being compiled for
+    pub machine: TargetMachine,
+    /// The optimization level to compile with
+    pub opt_level: OptimizationLevel,
+    /// The debug info being generated, or None if compiling without debug info
+    pub debug: Option<DebugInfo<'ctx>>,
 }
 
 impl<'ctx> CompilerImpl<'ctx> {
-    /// Creates a new CompilerImpl from the context
-    pub fn new(context: &'ctx Context) -> Self {
+    /// Creates a new CompilerImpl from the context for the target in the arguments, erroring if the target is invalid.
+    /// The JIT is only created when the output will be run, which requires compiling for the host.
+    pub fn new(context: &'ctx Context, arguments: &CompilerArguments) -> Result<Self, ParsingError> {
+        let opt_level = match arguments.opt_level {
+            RavenOptimizationLevel::None => OptimizationLevel::None,
+            RavenOptimizationLevel::Less => OptimizationLevel::Less,
+            RavenOptimizationLevel::Default => OptimizationLevel::Default,
+            RavenOptimizationLevel::Aggressive => OptimizationLevel::Aggressive,
+        };
+        let machine = Self::create_target_machine(arguments.target_triple.as_deref(), opt_level)
+            .map_err(|error| Span::default().make_error(ParsingMessage::InvalidTarget(error)))?;
+
         let module = context.create_module("main");
-        let execution_engine = module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
-        return Self { module, context, builder: context.create_builder(), execution_engine };
+        module.set_triple(&machine.get_triple());
+        module.set_data_layout(&machine.get_target_data().get_data_layout());
+        let host = TargetMachine::normalize_triple(&TargetMachine::get_default_triple());
+        let execution_engine = if arguments.emit_object || TargetMachine::normalize_triple(&machine.get_triple()) != host {
+            None
+        } else {
+            let engine = module.create_jit_execution_engine(opt_level).map_err(|error| {
+                Span::default().make_error(ParsingMessage::InvalidTarget(format!("Failed to create the JIT: {}", error)))
+            })?;
+            Some(engine)
+        };
+        let debug = if arguments.debug { Some(DebugInfo::new(&module, opt_level)) } else { None };
+        return Ok(Self { module, context, builder: context.create_builder(), execution_engine, machine, opt_level, debug });
+    }
+
+    /// Creates the machine for the target triple, or for the host if there is no triple.
+    /// Errors with every registered target if LLVM doesn't know the triple's architecture.
+    pub fn create_target_machine(triple: Option<&str>, opt_level: OptimizationLevel) -> Result<TargetMachine, String> {
+        let (triple, cpu, features) = match triple {
+            Some(triple) => {
+                Target::initialize_all(&InitializationConfig::default());
+                // Other machines' CPUs are unknown, so only features every CPU of that architecture has are used
+                (TargetTriple::create(triple), "generic".to_string(), String::default())
+            }
+            None => {
+                Target::initialize_native(&InitializationConfig::default())?;
+                (
+                    TargetMachine::get_default_triple(),
+                    TargetMachine::get_host_cpu_name().to_string(),
+                    TargetMachine::get_host_cpu_features().to_string(),
+                )
+            }
+        };
+        let name = triple.as_str().to_string_lossy().to_string();
+
+        let target = match Target::from_triple(&triple) {
+            Ok(target) => target,
+            Err(_) => {
+                let mut registered = Vec::default();
+                let mut next = Target::get_first();
+                while let Some(target) = next {
+                    registered.push(target.get_name().to_string_lossy().to_string());
+                    next = target.get_next();
+                }
+                return Err(format!("Unknown target {}! Registered targets: {}", name, registered.join(", ")));
+            }
+        };
+
+        return target
+            .create_target_machine(&triple, &cpu, &features, opt_level, RelocMode::PIC, CodeModel::Default)
+            .ok_or_else(|| format!("Failed to create a target machine for {}!", name));
+    }
+
+    /// Runs the standard LLVM pass pipeline matching the optimization level over the whole module.
+    /// Unoptimized builds skip this entirely so debug builds stay fast.
+    pub fn optimize(&self) -> Result<(), ParsingError> {
+        let pipeline = match self.opt_level {
+            OptimizationLevel::None => return Ok(()),
+            OptimizationLevel::Less => "default<O1>",
+            OptimizationLevel::Default => "default<O2>",
+            OptimizationLevel::Aggressive => "default<O3>",
+        };
+
+        return self
+            .module
+            .run_passes(pipeline, &self.machine, PassBuilderOptions::create())
+            .map_err(|error| Span::default().make_error(ParsingMessage::FailedToOptimize(error.to_string())));
+    }
+
+    /// Gets every library the program links with, from the arguments and the #[link] attributes.
+    /// Libraries from #[link] attributes have the span of the function the attribute is on.
+    pub fn link_libraries(arguments: &CompilerArguments, syntax: &Arc<Mutex<Syntax>>) -> Vec<(String, Option<Span>)> {
+        let mut libraries = arguments.link_libraries.iter().map(|library| (library.clone(), None)).collect::<Vec<_>>();
+        for (library, span) in &syntax.lock().link_libraries {
+            if !libraries.iter().any(|(found, _)| found == library) {
+                libraries.push((library.clone(), Some(*span)));
+            }
+        }
+        return libraries;
+    }
+
+    /// Loads the libraries into the process so the JIT can find the functions linked from them.
+    /// Libraries that fail to load are warned about, since their functions may already be loaded, like libc's.
+    pub fn load_libraries(arguments: &CompilerArguments, syntax: &Arc<Mutex<Syntax>>) {
+        for (library, span) in Self::link_libraries(arguments, syntax) {
+            // The JIT runs on the host, so the library is named like the host's libraries, like libm.so or m.dll
+            let file = format!("{}{}{}", DLL_PREFIX, library, DLL_SUFFIX);
+            // load_library_permanently returns true if it failed
+            if load_library_permanently(Path::new(&file)) {
+                let warning = match span {
+                    Some(span) => span.make_error(ParsingMessage::LibraryNotLoaded(library)),
+                    None => Span::default().make_error(ParsingMessage::LinkArgumentNotLoaded(library)),
+                };
+                syntax.lock().warnings.push(warning);
+            }
+        }
+    }
+
+    /// Writes the module to an object file in the target machine's format, like COFF for Windows targets
+    pub fn write_object(&self, path: &Path) -> Result<(), ParsingError> {
+        let failed =
+            |error: String| Span::default().make_error(ParsingMessage::FailedToWrite(path.display().to_string(), error));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| failed(error.to_string()))?;
+        }
+        return self.machine.write_to_file(&self.module, FileType::Object, path).map_err(|error| failed(error.to_string()));
     }
 
     /// Finds the main function
@@ -52,7 +180,7 @@ impl<'ctx> CompilerImpl<'ctx> {
             Box::new(EmptyNameResolver {}),
             false,
         )
-            .await
+        .await
         {
             Ok(_) => {}
             Err(_) => return None,
@@ -69,16 +197,19 @@ impl<'ctx> CompilerImpl<'ctx> {
         return Some(Arc::new(function.to_codeless()));
     }
 
-    /// Compiles the main function
+    /// Compiles the main functions and every function they call
     pub fn compile(
-        main: Arc<CodelessFinalizedFunction>,
+        mains: Vec<Arc<CodelessFinalizedFunction>>,
         type_getter: &mut CompilerTypeGetter<'ctx>,
         functions: &Arc<DashMap<String, Arc<FinalizedFunction>>>,
         _structures: &Arc<DashMap<String, Arc<FinalizedStruct>>>,
     ) {
-        instance_function(main, type_getter);
+        for main in &mains {
+            type_getter.get_function(main);
+        }
 
         let start = Instant::now();
+        let mut failed = false;
         while !type_getter.compiling.borrow().is_empty() {
             if start.elapsed().as_secs() > 5 {
                 panic!(
@@ -108,10 +239,25 @@ impl<'ctx> CompilerImpl<'ctx> {
                 continue;
             }
 
-            compile_block(&finalized_function.code, &mut type_getter.for_function(&finalized_function, function_type));
+            let mut function_getter = type_getter.for_function(&finalized_function, function_type);
+            // Keep compiling the other functions so every error is found at once
+            if let Err(error) = compile_block(&finalized_function.code, &mut function_getter) {
+                type_getter.syntax.lock().errors.push(error);
+                failed = true;
+            }
+        }
+
+        // The failed functions are left half-built, which LLVM can't optimize
+        if failed {
+            return;
         }
 
-        //let pass_manager = PassManager::create(&self.compiler.module);
+        if let Some(debug) = &type_getter.compiler.debug {
+            debug.builder.finalize();
+        }
+        if let Err(error) = type_getter.compiler.optimize() {
+            type_getter.syntax.lock().errors.push(error);
+        }
 
         /*unsafe {
             LLVMWriteBitcodeToFile(type_getter.compiler.module.as_mut_ptr(),
@@ -122,3 +268,49 @@ impl<'ctx> CompilerImpl<'ctx> {
         //print_formatted(type_getter.compiler.module.to_string());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use inkwell::context::Context;
+
+    use data::CompilerArguments;
+    use syntax::errors::ParsingMessage;
+
+    use crate::compiler::CompilerImpl;
+
+    /// Checks that an unknown target is an error instead of a panic
+    #[test]
+    pub fn test_invalid_target() {
+        let context = Context::create();
+        let arguments =
+            CompilerArguments { target_triple: Some("nonsense-pc-windows-gnu".to_string()), ..Default::default() };
+        match CompilerImpl::new(&context, &arguments) {
+            Ok(_) => panic!("Unknown target wasn't rejected!"),
+            Err(error) => {
+                assert!(matches!(error.message, ParsingMessage::InvalidTarget(_)), "Wrong error: {}", error.message)
+            }
+        }
+    }
+
+    /// Checks that objects written for Windows are COFF instead of the host's format
+    #[test]
+    pub fn test_windows_object() {
+        let context = Context::create();
+        let arguments = CompilerArguments {
+            target_triple: Some("x86_64-pc-windows-gnu".to_string()),
+            emit_object: true,
+            ..Default::default()
+        };
+        let compiler = CompilerImpl::new(&context, &arguments).unwrap();
+        let function = compiler.module.add_function("main", context.void_type().fn_type(&[], false), None);
+        compiler.builder.position_at_end(context.append_basic_block(function, "0"));
+        compiler.builder.build_return(None).unwrap();
+
+        let path = env::temp_dir().join("raven-windows-object").join("main.obj");
+        compiler.write_object(&path).unwrap();
+        // COFF objects start with the machine type, which is 0x8664 for x86_64, instead of ELF's magic number
+        assert_eq!(fs::read(&path).unwrap()[..2], [0x64, 0x86]);
+    }
+}
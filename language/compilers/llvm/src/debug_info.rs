@@ -0,0 +1,191 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use inkwell::debug_info::{
+    AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DILocation, DIScope, DISubprogram, DIType,
+    DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::{FlagBehavior, Module};
+use inkwell::values::{BasicValueEnum, FunctionValue};
+use inkwell::OptimizationLevel;
+
+use data::tokens::Span;
+use syntax::program::function::CodelessFinalizedFunction;
+use syntax::program::types::FinalizedTypes;
+
+use crate::type_getter::CompilerTypeGetter;
+
+/// The DWARF encoding of addresses
+const DW_ATE_ADDRESS: u32 = 0x01;
+/// The DWARF encoding of booleans
+const DW_ATE_BOOLEAN: u32 = 0x02;
+/// The DWARF encoding of floats
+const DW_ATE_FLOAT: u32 = 0x04;
+/// The DWARF encoding of signed integers
+const DW_ATE_SIGNED: u32 = 0x05;
+/// The DWARF encoding of unsigned integers
+const DW_ATE_UNSIGNED: u32 = 0x08;
+
+/// The debug info builder for a module, and the DWARF files made so far
+pub struct DebugInfo<'ctx> {
+    /// The LLVM debug info builder
+    pub builder: DebugInfoBuilder<'ctx>,
+    /// The compile unit everything is added to
+    pub unit: DICompileUnit<'ctx>,
+    /// Files by their hash, made the first time a span in them is compiled
+    files: RefCell<HashMap<u64, DIFile<'ctx>>>,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    /// Creates the debug info builder and compile unit for the module
+    pub fn new(module: &Module<'ctx>, opt_level: OptimizationLevel) -> Self {
+        // LLVM drops debug info from modules without the version flag
+        let version = module.get_context().i32_type().const_int(3, false);
+        module.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version);
+        let (builder, unit) = module.create_debug_info_builder(
+            true,
+            // DWARF has no language code for Raven, and C is the closest debuggers understand
+            DWARFSourceLanguage::C,
+            "main",
+            ".",
+            "Raven",
+            opt_level != OptimizationLevel::None,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+        return Self { builder, unit, files: RefCell::new(HashMap::default()) };
+    }
+
+    /// Gets the file a span is in, or None if that file wasn't recorded while parsing
+    fn file(&self, type_getter: &CompilerTypeGetter<'ctx>, span: &Span) -> Option<DIFile<'ctx>> {
+        if let Some(file) = self.files.borrow().get(&span.file) {
+            return Some(*file);
+        }
+
+        let locked = type_getter.syntax.lock();
+        let path = Path::new(&locked.source_files.get(&span.file)?.path);
+        let directory = path.parent().map_or(String::default(), |parent| parent.to_string_lossy().to_string());
+        let name = path.file_name().map_or(String::default(), |name| name.to_string_lossy().to_string());
+        let file = self.builder.create_file(&name, &directory);
+        self.files.borrow_mut().insert(span.file, file);
+        return Some(file);
+    }
+
+    /// Gets the line and column a span starts at, or None if its file wasn't recorded while parsing
+    fn position(type_getter: &CompilerTypeGetter<'ctx>, span: &Span) -> Option<(u32, u32)> {
+        return type_getter.syntax.lock().source_files.get(&span.file)?.positions.get(span.start).copied();
+    }
+
+    /// Makes a basic DWARF type for a Raven type. Anything that isn't a number or bool is shown as an address,
+    /// since Raven passes everything else around by pointer.
+    fn make_type(&self, types: &FinalizedTypes) -> DIType<'ctx> {
+        let (size, encoding) = match types.inner_struct().data.name.as_str() {
+            "i64" => (64, DW_ATE_SIGNED),
+            "i32" => (32, DW_ATE_SIGNED),
+            "i16" => (16, DW_ATE_SIGNED),
+            "i8" => (8, DW_ATE_SIGNED),
+            "u64" => (64, DW_ATE_UNSIGNED),
+            "u32" => (32, DW_ATE_UNSIGNED),
+            "u16" => (16, DW_ATE_UNSIGNED),
+            "u8" => (8, DW_ATE_UNSIGNED),
+            "f64" => (64, DW_ATE_FLOAT),
+            "f32" => (32, DW_ATE_FLOAT),
+            "bool" => (8, DW_ATE_BOOLEAN),
+            _ => (64, DW_ATE_ADDRESS),
+        };
+        return self.builder.create_basic_type(&types.to_string(), size, encoding, DIFlags::ZERO).unwrap().as_type();
+    }
+
+    /// Attaches a subprogram to the function so its code can be given locations
+    pub fn add_function(
+        &self,
+        type_getter: &CompilerTypeGetter<'ctx>,
+        function: &CodelessFinalizedFunction,
+        value: FunctionValue<'ctx>,
+    ) {
+        let span = &function.data.span;
+        let (Some(file), Some((line, _))) = (self.file(type_getter, span), Self::position(type_getter, span)) else {
+            return;
+        };
+
+        let arguments =
+            function.arguments.iter().map(|argument| self.make_type(&argument.field.field_type)).collect::<Vec<_>>();
+        let returning = function.return_type.as_ref().map(|returning| self.make_type(returning));
+        let subroutine = self.builder.create_subroutine_type(file, returning, &arguments, DIFlags::ZERO);
+        let subprogram = self.builder.create_function(
+            file.as_debug_info_scope(),
+            &function.data.name,
+            value.get_name().to_str().ok(),
+            file,
+            line,
+            subroutine,
+            false,
+            true,
+            line,
+            DIFlags::ZERO,
+            false,
+        );
+        value.set_subprogram(subprogram);
+    }
+
+    /// Gets the location of a span in the current function, or None if it has no debug info
+    fn location(
+        &self,
+        type_getter: &CompilerTypeGetter<'ctx>,
+        span: &Span,
+    ) -> Option<(DISubprogram<'ctx>, DILocation<'ctx>)> {
+        let subprogram = type_getter.function?.get_subprogram()?;
+        let (line, column) = Self::position(type_getter, span)?;
+        let scope: DIScope<'ctx> = subprogram.as_debug_info_scope();
+        let location = self.builder.create_debug_location(type_getter.compiler.context, line, column, scope, None);
+        return Some((subprogram, location));
+    }
+
+    /// Sets the location of the instructions built after this to the span.
+    /// Functions without debug info have it cleared instead, so they don't keep the last function's location.
+    pub fn set_location(&self, type_getter: &CompilerTypeGetter<'ctx>, span: &Span) {
+        match self.location(type_getter, span) {
+            Some((_, location)) => type_getter.compiler.builder.set_current_debug_location(location),
+            None => type_getter.compiler.builder.unset_current_debug_location(),
+        }
+    }
+
+    /// Declares a variable so debuggers can find it, if the value is stored somewhere
+    pub fn add_variable(
+        &self,
+        type_getter: &CompilerTypeGetter<'ctx>,
+        span: &Span,
+        name: &str,
+        types: &FinalizedTypes,
+        value: BasicValueEnum<'ctx>,
+    ) {
+        let (Some(file), Some((subprogram, location)), Some(block)) =
+            (self.file(type_getter, span), self.location(type_getter, span), type_getter.current_block)
+        else {
+            return;
+        };
+        if !value.is_pointer_value() {
+            return;
+        }
+
+        let variable = self.builder.create_auto_variable(
+            subprogram.as_debug_info_scope(),
+            name,
+            file,
+            location.get_line(),
+            self.make_type(types),
+            true,
+            DIFlags::ZERO,
+            0,
+        );
+        self.builder.insert_declare_at_end(value.into_pointer_value(), Some(variable), None, location, block);
+    }
+}
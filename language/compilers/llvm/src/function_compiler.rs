@@ -8,39 +8,62 @@ use inkwell::types::{BasicType, BasicTypeEnum, StructType};
 use inkwell::values::{BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, PointerValue};
 use inkwell::AddressSpace;
 
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression};
 use syntax::program::function::{CodelessFinalizedFunction, FinalizedCodeBody};
 use syntax::program::types::FinalizedTypes;
 use syntax::{is_modifier, Attribute, Modifier};
 
 use crate::internal::instructions::{compile_internal, malloc_type};
-use crate::internal::intrinsics::compile_llvm_intrinsics;
+use crate::internal::intrinsics::{compile_linked_extern, compile_llvm_intrinsics, link_name};
+use crate::internal::structs::get_internal_struct;
 use crate::type_getter::CompilerTypeGetter;
 use crate::util::create_function_value;
 
+/// Gets the symbol a function is compiled to. That's the #[export_name] if there is one, or the last segment
+/// of the name for #[no_mangle] functions so C can call them, and otherwise the full name.
+pub fn symbol_name(function: &CodelessFinalizedFunction) -> &str {
+    if let Some(name) =
+        Attribute::find_attribute("export_name", &function.data.attributes).and_then(Attribute::as_string_attribute)
+    {
+        return name.trim_matches('"');
+    }
+    if Attribute::has_attribute("no_mangle", &function.data.attributes) {
+        return function.data.name.rsplit("::").next().unwrap();
+    }
+    return &function.data.name;
+}
+
 /// Instances a FunctionValue from its CodelessFinalizedFunction
 pub fn instance_function<'a, 'ctx>(
     function: Arc<CodelessFinalizedFunction>,
     type_getter: &mut CompilerTypeGetter<'ctx>,
 ) -> FunctionValue<'ctx> {
+    // Internal functions are built right away, so the location of the line calling them is put back after
+    let location = type_getter.compiler.builder.get_current_debug_location();
+    type_getter.compiler.builder.unset_current_debug_location();
     let value;
-    if function.data.attributes.iter().any(|attribute| {
-        if let Attribute::Basic(inner) = attribute {
-            inner == "llvm_intrinsic"
-        } else {
-            false
-        }
-    }) {
+    if Attribute::has_attribute("llvm_intrinsic", &function.data.attributes) {
         value = compile_llvm_intrinsics(function.data.name.split("::").last().unwrap(), type_getter);
     } else if is_modifier(function.data.modifiers, Modifier::Internal) {
         value = create_function_value(&function, type_getter, None);
         compile_internal(type_getter, &function, value);
     } else if is_modifier(function.data.modifiers, Modifier::Extern) {
-        value = create_function_value(&function, type_getter, Some(Linkage::External))
+        value = match link_name(&function) {
+            Some(symbol) => compile_linked_extern(symbol, &function, type_getter),
+            None => create_function_value(&function, type_getter, Some(Linkage::External)),
+        };
     } else {
         value = create_function_value(&function, type_getter, None);
+        if let Some(debug) = &type_getter.compiler.debug {
+            debug.add_function(type_getter, &function, value);
+        }
         type_getter.compiling.borrow_mut().push((value, function));
     }
+
+    if let Some(location) = location {
+        type_getter.compiler.builder.set_current_debug_location(location);
+    }
     return value;
 }
 
@@ -49,7 +72,7 @@ pub fn instance_types<'ctx>(types: &FinalizedTypes, type_getter: &mut CompilerTy
     return match types {
         FinalizedTypes::Reference(inner) => type_getter.get_type(inner),
         _ => {
-            if is_modifier(types.inner_struct().data.modifiers, Modifier::Trait) {
+            if types.is_trait() {
                 reference_struct(type_getter).as_basic_type_enum()
             } else {
                 let mut fields = vec![type_getter.compiler.context.i64_type().as_basic_type_enum()];
@@ -67,7 +90,7 @@ pub fn instance_types<'ctx>(types: &FinalizedTypes, type_getter: &mut CompilerTy
 pub fn compile_block<'ctx>(
     code: &FinalizedCodeBody,
     type_getter: &mut CompilerTypeGetter<'ctx>,
-) -> Option<BasicValueEnum<'ctx>> {
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
     // If the block already exists, go to it. If not, create it
     // Helps for weird control flow caused by certain blocks.
     let block = if let Some(block) = type_getter.blocks.get(&code.label) {
@@ -86,7 +109,7 @@ pub fn compile_block<'ctx>(
     // Every block must break in some way, this checks that while compiling each line
     let mut broke = false;
     for line in &code.expressions {
-        compile_line(line, type_getter, &mut broke);
+        compile_line(line, type_getter, &mut broke)?;
     }
 
     // If this code block doesn't return, create an end block to jump to.
@@ -99,7 +122,7 @@ pub fn compile_block<'ctx>(
         panic!("No break in code body!");
     }
 
-    return None;
+    return Ok(None);
 }
 
 /// This is used by control flow altering blocks like for loops for convenience.
@@ -119,25 +142,33 @@ pub fn create_block_end<'ctx>(code: &FinalizedCodeBody, type_getter: &mut Compil
 }
 
 /// Compile a line of code, handling breaking
-pub fn compile_line<'ctx>(line: &FinalizedExpression, type_getter: &mut CompilerTypeGetter<'ctx>, broke: &mut bool) {
+pub fn compile_line<'ctx>(
+    line: &FinalizedExpression,
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    broke: &mut bool,
+) -> Result<(), ParsingError> {
+    if let Some(debug) = &type_getter.compiler.debug {
+        debug.set_location(type_getter, &line.effect.span);
+    }
     match line.expression_type {
         // If there's a return, return None for NOPs, else return the value
         ExpressionType::Return(_) => {
             if matches!(&line.effect.types, FinalizedEffectType::NOP) {
                 type_getter.compiler.builder.build_return(None).unwrap();
             } else {
-                let returned = compile_effect(type_getter, &line.effect).unwrap();
+                let returned = compile_value(type_getter, &line.effect)?;
                 type_getter.compiler.builder.build_return(Some(&returned)).unwrap();
             }
             *broke = true;
         }
-        ExpressionType::Line => compile_nonreturning_line(line, type_getter, broke),
+        ExpressionType::Line => compile_nonreturning_line(line, type_getter, broke)?,
         // TODO implement breaks
         ExpressionType::Break => {
-            compile_effect(type_getter, &line.effect);
+            compile_effect(type_getter, &line.effect)?;
             *broke = true;
         }
     }
+    return Ok(());
 }
 
 /// Compiles a line that isn't a return or break.
@@ -145,11 +176,11 @@ fn compile_nonreturning_line<'ctx>(
     line: &FinalizedExpression,
     type_getter: &mut CompilerTypeGetter<'ctx>,
     broke: &mut bool,
-) {
+) -> Result<(), ParsingError> {
     if *broke {
         // If the function already broke, ignore anything other than code bodies.
         if matches!(&line.effect.types, FinalizedEffectType::CodeBody(_)) {
-            compile_effect(type_getter, &line.effect);
+            compile_effect(type_getter, &line.effect)?;
         }
     } else {
         match &line.effect.types {
@@ -158,17 +189,18 @@ fn compile_nonreturning_line<'ctx>(
                 let destination = get_block_or_create(&body.label, type_getter);
                 type_getter.compiler.builder.build_unconditional_branch(destination).unwrap();
 
-                compile_effect(type_getter, &line.effect);
+                compile_effect(type_getter, &line.effect)?;
             }
             FinalizedEffectType::Jump(_) | FinalizedEffectType::CompareJump(_, _, _) => {
                 *broke = true;
-                compile_effect(type_getter, &line.effect);
+                compile_effect(type_getter, &line.effect)?;
             }
             _ => {
-                compile_effect(type_getter, &line.effect);
+                compile_effect(type_getter, &line.effect)?;
             }
         }
     }
+    return Ok(());
 }
 
 /// Compiles a single effect
@@ -176,14 +208,17 @@ fn compile_nonreturning_line<'ctx>(
 pub fn compile_effect<'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
     effect: &FinalizedEffects,
-) -> Option<BasicValueEnum<'ctx>> {
-    if let Some(inner) = compile_simple_effects(type_getter, effect) {
-        return inner;
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
+    if let Some(inner) = compile_simple_effects(type_getter, effect)? {
+        return Ok(inner);
     }
 
-    return match &effect.types {
+    return Ok(match &effect.types {
         FinalizedEffectType::CreateVariable(name, inner, types) => {
-            let compiled = compile_effect(type_getter, inner).unwrap();
+            let compiled = compile_value(type_getter, inner)?;
+            if let Some(debug) = &type_getter.compiler.debug {
+                debug.add_variable(type_getter, &effect.span, name, types, compiled);
+            }
             type_getter.variables.insert(name.clone(), (types.clone(), compiled.as_basic_value_enum()));
             Some(compiled.as_basic_value_enum())
         }
@@ -193,27 +228,31 @@ pub fn compile_effect<'ctx>(
             None
         }
         FinalizedEffectType::CompareJump(effect, then_body, else_body) => {
-            let effect = compile_effect(type_getter, effect).unwrap();
+            let effect = compile_value(type_getter, effect)?;
             let effect = load_if_pointer(type_getter, type_getter.compiler.context.i64_type(), effect).into_int_value();
             let then = get_block_or_create(then_body, type_getter);
             let else_block = get_block_or_create(else_body, type_getter);
             type_getter.compiler.builder.build_conditional_branch(effect, then, else_block).unwrap();
             None
         }
+        FinalizedEffectType::MatchArm(effect, arms) => {
+            compile_match(type_getter, effect, arms)?;
+            None
+        }
         //Sets pointer to value
         FinalizedEffectType::Set(setting, value) => {
-            let output = compile_effect(type_getter, setting).unwrap();
-            let value = compile_effect(type_getter, value).unwrap();
+            let output = compile_value(type_getter, setting)?;
+            let value = compile_value(type_getter, value)?;
             let storing =
                 load_if_pointer(type_getter, type_getter.compiler.context.ptr_type(AddressSpace::default()), value);
             type_getter.compiler.builder.build_store(output.into_pointer_value(), storing).unwrap();
             Some(output)
         }
         FinalizedEffectType::HeapStore(inner) => {
-            let output = compile_effect(type_getter, inner).unwrap();
+            let output = compile_value(type_getter, inner)?;
 
             let pointer_type = if output.get_type().is_pointer_type() {
-                return Some(output);
+                return Ok(Some(output));
             } else {
                 output.get_type()
             };
@@ -225,7 +264,7 @@ pub fn compile_effect<'ctx>(
             Some(malloc.as_basic_value_enum())
         }
         FinalizedEffectType::StackStore(inner) => {
-            let output = compile_effect(type_getter, inner).unwrap();
+            let output = compile_value(type_getter, inner)?;
             if !output.is_pointer_value() {
                 store_and_load(type_getter, output.get_type(), output)
             } else {
@@ -233,7 +272,7 @@ pub fn compile_effect<'ctx>(
             }
         }
         FinalizedEffectType::ReferenceLoad(inner) => {
-            let inner = compile_effect(type_getter, inner).unwrap();
+            let inner = compile_value(type_getter, inner)?;
             let output = type_getter
                 .compiler
                 .builder
@@ -252,43 +291,138 @@ pub fn compile_effect<'ctx>(
 
             Some(malloc.as_basic_value_enum())
         }
-        _ => unreachable!(),
+        // Handled by compile_simple_effects
+        FinalizedEffectType::NOP
+        | FinalizedEffectType::GenericMethodCall(_, _, _)
+        | FinalizedEffectType::GenericVirtualCall(_, _, _, _)
+        | FinalizedEffectType::CodeBody(_)
+        | FinalizedEffectType::FunctionCall(_, _, _, _)
+        | FinalizedEffectType::VirtualCall(_, _, _, _)
+        | FinalizedEffectType::Downcast(_, _, _)
+        | FinalizedEffectType::Load(_, _, _)
+        | FinalizedEffectType::CreateStruct(_, _, _)
+        | FinalizedEffectType::CreateArray(_, _)
+        | FinalizedEffectType::Float(_)
+        | FinalizedEffectType::UInt(_)
+        | FinalizedEffectType::Number(_, _)
+        | FinalizedEffectType::Bool(_)
+        | FinalizedEffectType::String(_)
+        | FinalizedEffectType::Char(_)
+        | FinalizedEffectType::LoadVariable(_) => unreachable!(),
+    });
+}
+
+/// Compiles a match on a struct's discriminant into a switch to each arm's body, then keeps compiling after
+/// the switch for values that didn't match any arm
+fn compile_match<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    effect: &FinalizedEffects,
+    arms: &[(FinalizedTypes, FinalizedEffects)],
+) -> Result<(), ParsingError> {
+    let i64_type = type_getter.compiler.context.i64_type();
+    let matching = compile_value(type_getter, effect)?;
+    // The discriminant is the first field, so it's at the struct's address
+    let discriminant = if matching.is_pointer_value() {
+        type_getter
+            .compiler
+            .builder
+            .build_load(i64_type, matching.into_pointer_value(), &type_getter.id.to_string())
+            .unwrap()
+    } else {
+        matching
     };
+    type_getter.id += 1;
+
+    let mut cases = Vec::default();
+    for (types, arm) in arms {
+        let FinalizedEffectType::CodeBody(body) = &arm.types else {
+            return Err(internal_error(type_getter, arm, "Match arm isn't a code body"));
+        };
+        cases.push((i64_type.const_int(types.inner_struct().data.id, false), get_block_or_create(&body.label, type_getter)));
+    }
+
+    let fallthrough =
+        type_getter.compiler.context.append_basic_block(type_getter.function.unwrap(), &type_getter.id.to_string());
+    type_getter.id += 1;
+    type_getter.compiler.builder.build_switch(discriminant.into_int_value(), fallthrough, &cases).unwrap();
+
+    for (_, arm) in arms {
+        compile_effect(type_getter, arm)?;
+    }
+    type_getter.current_block = Some(fallthrough);
+    type_getter.compiler.builder.position_at_end(fallthrough);
+    return Ok(());
+}
+
+/// Compiles an effect whose value is used, like an argument or a returned value
+pub fn compile_value<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    effect: &FinalizedEffects,
+) -> Result<BasicValueEnum<'ctx>, ParsingError> {
+    return match compile_effect(type_getter, effect)? {
+        Some(value) => Ok(value),
+        None => Err(internal_error(type_getter, effect, "Expected a value")),
+    };
+}
+
+/// Makes an internal compiler error for an effect the checker should never have let reach the compiler
+pub fn internal_compiler_error(function: &str, effect: &FinalizedEffects, reason: &str) -> ParsingError {
+    return effect
+        .span
+        .make_error(ParsingMessage::InternalCompilerError(function.to_string(), format!("{}: {:?}", reason, effect.types)));
+}
+
+/// Makes an internal compiler error for the effect in the function being compiled, which stops compiling it
+fn internal_error(type_getter: &CompilerTypeGetter, effect: &FinalizedEffects, reason: &str) -> ParsingError {
+    let function =
+        type_getter.function.map_or(String::default(), |function| function.get_name().to_string_lossy().to_string());
+    return internal_compiler_error(&function, effect, reason);
 }
 
 /// Compiles a few effects handled entirely by seperate methods or simple one-liners
 fn compile_simple_effects<'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
     effect: &FinalizedEffects,
-) -> Option<Option<BasicValueEnum<'ctx>>> {
-    return Some(match &effect.types {
-        FinalizedEffectType::NOP => {
-            panic!("Tried to compile a NOP! For {}", type_getter.function.unwrap().get_name().to_str().unwrap())
-        }
-        FinalizedEffectType::GenericMethodCall(func, types, _args) => {
-            panic!("Tried to compile generic method call! {} and {}", func.data.name, types)
+) -> Result<Option<Option<BasicValueEnum<'ctx>>>, ParsingError> {
+    return Ok(Some(match &effect.types {
+        // NOPs have no value, so using one as a value is caught by compile_value
+        FinalizedEffectType::NOP => None,
+        FinalizedEffectType::GenericMethodCall(_, _, _) => {
+            return Err(internal_error(type_getter, effect, "Generic method call wasn't degenericed"));
         }
         FinalizedEffectType::GenericVirtualCall(_, _, _, _) => {
-            panic!("Generic virtual call not degeneric'd!")
+            return Err(internal_error(type_getter, effect, "Generic virtual call wasn't degenericed"));
         }
-        FinalizedEffectType::CodeBody(body) => compile_block(body, type_getter),
+        FinalizedEffectType::CodeBody(body) => compile_block(body, type_getter)?,
         FinalizedEffectType::FunctionCall(_, calling_function, arguments, _) => {
-            compile_function_call(type_getter, calling_function, arguments)
+            compile_function_call(type_getter, calling_function, arguments)?
         }
         FinalizedEffectType::VirtualCall(func_offset, function, _, args) => {
-            compile_virtual_call(type_getter, func_offset, function, args)
+            compile_virtual_call(type_getter, func_offset, function, args)?
         }
-        FinalizedEffectType::Downcast(base, target, functions) => compile_downcast(type_getter, base, target, functions),
+        FinalizedEffectType::Downcast(base, target, functions) => compile_downcast(type_getter, base, target, functions)?,
         //Loads variable/field pointer from program, or self if program is None
-        FinalizedEffectType::Load(loading_from, field, _) => compile_load(type_getter, loading_from, field),
+        FinalizedEffectType::Load(loading_from, field, _) => compile_load(type_getter, loading_from, field)?,
         //Struct to create and a tuple of the index of the argument and the argument
-        FinalizedEffectType::CreateStruct(effect, _, arguments) => compile_create_struct(type_getter, effect, arguments),
+        FinalizedEffectType::CreateStruct(effect, _, arguments) => compile_create_struct(type_getter, effect, arguments)?,
+        FinalizedEffectType::CreateArray(Some(types), values) => compile_create_array(type_getter, types, values)?,
+        FinalizedEffectType::CreateArray(None, _) => {
+            return Err(internal_error(type_getter, effect, "Array type was never inferred"));
+        }
         FinalizedEffectType::Float(float) => {
             Some(type_getter.compiler.context.f64_type().const_float(*float).as_basic_value_enum())
         }
         FinalizedEffectType::UInt(int) => {
             Some(type_getter.compiler.context.i64_type().const_int(*int, false).as_basic_value_enum())
         }
+        FinalizedEffectType::Number(number, types) => {
+            let types = get_internal_struct(type_getter.compiler.context, &types.inner_struct().data.name).unwrap();
+            Some(if types.is_float_type() {
+                types.into_float_type().const_float(*number as f64).as_basic_value_enum()
+            } else {
+                types.into_int_type().const_int(*number, false).as_basic_value_enum()
+            })
+        }
         FinalizedEffectType::Bool(bool) => {
             Some(type_getter.compiler.context.bool_type().const_int(*bool as u64, false).as_basic_value_enum())
         }
@@ -298,23 +432,26 @@ fn compile_simple_effects<'ctx>(
         FinalizedEffectType::Char(char) => {
             Some(type_getter.compiler.context.i8_type().const_int(*char as u64, false).as_basic_value_enum())
         }
-        FinalizedEffectType::LoadVariable(name) => Some(type_getter.variables.get(name).unwrap().1),
-        _ => return None,
-    });
+        FinalizedEffectType::LoadVariable(name) => match type_getter.variables.get(name) {
+            Some((_, value)) => Some(*value),
+            None => return Err(internal_error(type_getter, effect, "Unknown variable")),
+        },
+        _ => return Ok(None),
+    }));
 }
 fn compile_create_struct<'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
     effect: &Option<Box<FinalizedEffects>>,
     arguments: &Vec<(usize, FinalizedEffects)>,
-) -> Option<BasicValueEnum<'ctx>> {
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
     let mut out_arguments = vec![MaybeUninit::uninit(); arguments.len()];
 
     for (index, effect) in arguments {
-        let returned = compile_effect(type_getter, effect).unwrap();
+        let returned = compile_value(type_getter, effect)?;
         *out_arguments.get_mut(*index).unwrap() = MaybeUninit::new(returned);
     }
 
-    let pointer = compile_effect(type_getter, effect.as_ref().unwrap()).unwrap().into_pointer_value();
+    let pointer = compile_value(type_getter, effect.as_ref().unwrap())?.into_pointer_value();
     type_getter.id += 1;
 
     let fields = out_arguments.iter().map(|argument| unsafe { argument.assume_init() }.get_type()).collect::<Vec<_>>();
@@ -331,7 +468,74 @@ fn compile_create_struct<'ctx>(
         offset += 1;
     }
 
-    return Some(pointer.as_basic_value_enum());
+    return Ok(Some(pointer.as_basic_value_enum()));
+}
+
+/// Compiles an array literal into a core Array, whose Pointer points to a buffer with the elements one after another
+fn compile_create_array<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    types: &FinalizedTypes,
+    values: &Vec<FinalizedEffects>,
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
+    let i64_type = type_getter.compiler.context.i64_type();
+    let length = i64_type.const_int(values.len() as u64, false);
+    let values = values.iter().map(|value| compile_value(type_getter, value)).collect::<Result<Vec<_>, _>>()?;
+    // Elements are stored like they're passed around, which is a pointer for anything but primitives
+    let element = values
+        .first()
+        .map_or(type_getter.compiler.context.ptr_type(AddressSpace::default()).as_basic_type_enum(), |value| {
+            value.get_type()
+        });
+    let size =
+        type_getter.compiler.builder.build_int_mul(element.size_of().unwrap(), length, &type_getter.id.to_string()).unwrap();
+    type_getter.id += 1;
+    let buffer = malloc_type(type_getter, size);
+    for (index, value) in values.into_iter().enumerate() {
+        let slot = unsafe {
+            type_getter.compiler.builder.build_in_bounds_gep(
+                element,
+                buffer,
+                &[i64_type.const_int(index as u64, false)],
+                &type_getter.id.to_string(),
+            )
+        }
+        .unwrap();
+        type_getter.id += 1;
+        type_getter.compiler.builder.build_store(slot, value).unwrap();
+    }
+    let address = type_getter.compiler.builder.build_ptr_to_int(buffer, i64_type, &type_getter.id.to_string()).unwrap();
+    type_getter.id += 1;
+
+    // Fields hold pointers to their values, like in compile_create_struct, so the Pointer holds a pointer to the address
+    let address = heap_store(type_getter, address.as_basic_value_enum());
+    let pointer = heap_store(type_getter, address.as_basic_value_enum());
+    let length = heap_store(type_getter, length.as_basic_value_enum());
+
+    // The fields are found by name so their order in the core file doesn't matter
+    let mut structure = types.clone();
+    type_getter.fix_generic_struct(&mut structure);
+    let fields = structure.inner_struct().fields.clone();
+    let pointer_type = type_getter.compiler.context.ptr_type(AddressSpace::default()).as_basic_type_enum();
+    let layout = type_getter.compiler.context.struct_type(&vec![pointer_type; fields.len()], false);
+    let array = malloc_type(type_getter, layout.size_of().unwrap());
+    for (offset, field) in fields.iter().enumerate() {
+        let value = if field.field.name == "size" { length } else { pointer };
+        let field = type_getter
+            .compiler
+            .builder
+            .build_struct_gep(layout, array, offset as u32, &type_getter.id.to_string())
+            .unwrap();
+        type_getter.id += 1;
+        type_getter.compiler.builder.build_store(field, value).unwrap();
+    }
+    return Ok(Some(array.as_basic_value_enum()));
+}
+
+/// Stores the value in a new heap allocation, returning the pointer to it
+fn heap_store<'ctx>(type_getter: &mut CompilerTypeGetter<'ctx>, value: BasicValueEnum<'ctx>) -> PointerValue<'ctx> {
+    let malloc = malloc_type(type_getter, value.get_type().size_of().unwrap());
+    type_getter.compiler.builder.build_store(malloc, value).unwrap();
+    return malloc;
 }
 
 /// Compiles a load effect
@@ -339,8 +543,8 @@ fn compile_load<'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
     loading_from: &FinalizedEffects,
     field: &String,
-) -> Option<BasicValueEnum<'ctx>> {
-    let from = compile_effect(type_getter, loading_from).unwrap();
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
+    let from = compile_value(type_getter, loading_from)?;
     let mut structure = loading_from.types.get_nongeneric_return(type_getter).unwrap();
     type_getter.fix_generic_struct(&mut structure);
     let structure = structure.inner_struct();
@@ -359,7 +563,7 @@ fn compile_load<'ctx>(
         )
         .unwrap();
     type_getter.id += 2;
-    return Some(
+    return Ok(Some(
         type_getter
             .compiler
             .builder
@@ -369,15 +573,15 @@ fn compile_load<'ctx>(
                 &(type_getter.id - 1).to_string(),
             )
             .unwrap(),
-    );
+    ));
 }
 fn compile_virtual_call<'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
     func_offset: &usize,
     function: &Arc<CodelessFinalizedFunction>,
     args: &Vec<FinalizedEffects>,
-) -> Option<BasicValueEnum<'ctx>> {
-    let table = compile_effect(type_getter, &args[0]).unwrap();
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
+    let table = compile_value(type_getter, &args[0])?;
 
     let mut compiled_args = Vec::default();
     let calling = type_getter
@@ -392,7 +596,7 @@ fn compile_virtual_call<'ctx>(
     compiled_args.push(BasicMetadataValueEnum::from(calling));
     type_getter.id += 1;
     for i in 0..args.len() {
-        compiled_args.push(BasicMetadataValueEnum::from(compile_effect(type_getter, &args[i]).unwrap()));
+        compiled_args.push(BasicMetadataValueEnum::from(compile_value(type_getter, &args[i])?));
     }
     let reference_struct = reference_struct(type_getter);
     let table_pointer = type_getter
@@ -413,7 +617,7 @@ fn compile_virtual_call<'ctx>(
     type_getter.id += 1;
     let function_type = type_getter.get_function(function).get_type();
     let function_pointer = get_func_from_vtable(type_getter, vtable.into_pointer_value(), *func_offset);
-    return type_getter
+    return Ok(type_getter
         .compiler
         .builder
         .build_indirect_call(
@@ -424,7 +628,7 @@ fn compile_virtual_call<'ctx>(
         )
         .unwrap()
         .try_as_basic_value()
-        .left();
+        .left());
 }
 
 ///Gets a function from a vtable
@@ -465,15 +669,33 @@ fn compile_function_call<'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
     calling_function: &Arc<CodelessFinalizedFunction>,
     arguments: &Vec<FinalizedEffects>,
-) -> Option<BasicValueEnum<'ctx>> {
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
     let mut final_arguments = Vec::default();
 
     let calling = type_getter.get_function(calling_function);
     type_getter.compiler.builder.position_at_end(type_getter.current_block.unwrap());
+    let linked = link_name(calling_function).is_some();
 
     for i in 0..arguments.len() {
         let argument = arguments.get(i).unwrap();
-        let value = compile_effect(type_getter, argument).unwrap();
+        let mut value = compile_value(type_getter, argument)?;
+
+        // Linked C functions take the value itself
+        if linked {
+            let types = type_getter.get_type(calling_function.arguments[i].field.field_type.dereferenced());
+            final_arguments.push(From::from(load_if_pointer(type_getter, types, value)));
+            continue;
+        }
+
+        // Copy types are passed as a fresh copy instead of a reference to the original
+        if let Some(mut types) = argument.types.get_nongeneric_return(type_getter) {
+            type_getter.fix_generic_struct(&mut types);
+            if value.is_pointer_value() && types.inner_struct_safe().map_or(false, |structure| structure.data.is_copy()) {
+                let copied = type_getter.get_type(&FinalizedTypes::Struct(types.inner_struct().clone()));
+                let loaded = load_if_pointer(type_getter, copied, value);
+                value = store_and_load(type_getter, copied, loaded).unwrap();
+            }
+        }
 
         final_arguments.push(From::from(value));
     }
@@ -486,7 +708,7 @@ fn compile_function_call<'ctx>(
         .try_as_basic_value()
         .left();
     type_getter.id += 1;
-    return match call {
+    return Ok(match call {
         Some(inner) => {
             if inner.is_pointer_value() {
                 Some(inner)
@@ -497,7 +719,7 @@ fn compile_function_call<'ctx>(
             }
         }
         None => None,
-    };
+    });
 }
 
 fn reference_struct<'ctx>(type_getter: &mut CompilerTypeGetter<'ctx>) -> StructType<'ctx> {
@@ -515,17 +737,22 @@ fn compile_downcast<'ctx>(
     base: &Box<FinalizedEffects>,
     target: &FinalizedTypes,
     functions: &Vec<Arc<CodelessFinalizedFunction>>,
-) -> Option<BasicValueEnum<'ctx>> {
+) -> Result<Option<BasicValueEnum<'ctx>>, ParsingError> {
     let base_return_types = base.types.get_nongeneric_return(type_getter).unwrap();
-    if is_modifier(base_return_types.inner_struct().data.modifiers, Modifier::Trait) {
+    if base_return_types.is_trait() {
         if !target.eq(&base_return_types) {
-            panic!("Downcasting to a trait that doesn't match! Not implemented yet!")
+            return Err(internal_error(
+                type_getter,
+                base,
+                &format!("Can't downcast a {} to a different trait {}", base_return_types, target),
+            ));
         } else {
+            // Already downcast to this trait
             return compile_effect(type_getter, base);
         }
     } else {
         let table = type_getter.vtable.clone();
-        let base = compile_effect(type_getter, base).unwrap();
+        let base = compile_value(type_getter, base)?;
         let table = table.borrow_mut().get_vtable(type_getter, target, &base_return_types, functions);
         type_getter.id += 1;
 
@@ -542,7 +769,7 @@ fn compile_downcast<'ctx>(
             type_getter.compiler.builder.build_struct_gep(reference_struct, malloc, 1, &type_getter.id.to_string()).unwrap();
         type_getter.id += 1;
         type_getter.compiler.builder.build_store(offset, table.as_basic_value_enum()).unwrap();
-        return Some(malloc.as_basic_value_enum());
+        return Ok(Some(malloc.as_basic_value_enum()));
     }
 }
 
@@ -586,3 +813,267 @@ fn get_block_or_create<'ctx>(name: &String, type_getter: &mut CompilerTypeGetter
         temp
     };
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use inkwell::context::Context;
+    use inkwell::AddressSpace;
+    use parking_lot::Mutex;
+
+    use data::tokens::Span;
+    use data::{CompilerArguments, OptimizationLevel};
+    use syntax::async_util::{HandleWrapper, NameResolver};
+    use syntax::errors::{ParsingError, ParsingMessage};
+    use syntax::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression};
+    use syntax::program::function::{
+        CodeBody, CodelessFinalizedFunction, FinalizedCodeBody, FinalizedFunction, FunctionData, UnfinalizedFunction,
+    };
+    use syntax::program::r#struct::{FinalizedStruct, StructData, UnfinalizedStruct};
+    use syntax::program::syntax::{SourceFile, Syntax};
+    use syntax::program::types::FinalizedTypes;
+    use syntax::{Attribute, ProcessManager};
+
+    use crate::compiler::CompilerImpl;
+    use crate::function_compiler::{compile_effect, compile_value};
+    use crate::type_getter::CompilerTypeGetter;
+
+    /// The compiler never calls the process manager, so nothing needs implementing
+    struct NoProcessManager;
+
+    #[async_trait]
+    impl ProcessManager for NoProcessManager {
+        fn handle(&self) -> &Arc<Mutex<HandleWrapper>> {
+            unreachable!()
+        }
+
+        async fn verify_func(
+            &self,
+            _function: UnfinalizedFunction,
+            _resolver: &dyn NameResolver,
+            _syntax: &Arc<Mutex<Syntax>>,
+        ) -> (CodelessFinalizedFunction, CodeBody) {
+            unreachable!()
+        }
+
+        async fn verify_code(
+            &self,
+            _function: CodelessFinalizedFunction,
+            _code: CodeBody,
+            _resolver: Box<dyn NameResolver>,
+            _syntax: &Arc<Mutex<Syntax>>,
+        ) -> FinalizedFunction {
+            unreachable!()
+        }
+
+        async fn degeneric_code(&self, _function: Arc<CodelessFinalizedFunction>, _syntax: &Arc<Mutex<Syntax>>) {
+            unreachable!()
+        }
+
+        async fn verify_struct(
+            &self,
+            _structure: UnfinalizedStruct,
+            _resolver: &dyn NameResolver,
+            _syntax: &Arc<Mutex<Syntax>>,
+        ) -> FinalizedStruct {
+            unreachable!()
+        }
+
+        fn generics(&self) -> &HashMap<String, FinalizedTypes> {
+            unreachable!()
+        }
+
+        fn mut_generics(&mut self) -> &mut HashMap<String, FinalizedTypes> {
+            unreachable!()
+        }
+
+        fn cloned(&self) -> Box<dyn ProcessManager> {
+            return Box::new(NoProcessManager);
+        }
+
+        fn cfg_enabled(&self, _attributes: &Vec<Attribute>) -> bool {
+            unreachable!()
+        }
+
+        fn cancelled(&self) -> bool {
+            unreachable!()
+        }
+    }
+
+    /// Compiles the effect as a value inside an empty function named test::broken, returning any error
+    fn compile_broken(effect: &FinalizedEffects) -> Result<(), ParsingError> {
+        let context = Context::create();
+        let compiler = CompilerImpl::new(&context, &CompilerArguments::default()).unwrap();
+        let syntax = Arc::new(Mutex::new(Syntax::new(Box::new(NoProcessManager))));
+        let mut type_getter = CompilerTypeGetter::new(Rc::new(compiler), syntax);
+
+        let function =
+            type_getter.compiler.module.add_function("test::broken", context.void_type().fn_type(&[], false), None);
+        let block = context.append_basic_block(function, "0");
+        type_getter.compiler.builder.position_at_end(block);
+        type_getter.function = Some(function);
+        type_getter.current_block = Some(block);
+
+        return compile_value(&mut type_getter, effect).map(|_| ());
+    }
+
+    /// Checks the error is an internal compiler error in test::broken at the span, with the reason and effect
+    fn assert_internal_error(error: ParsingError, span: &Span, expected: &str) {
+        assert_eq!((error.span.file, error.span.start, error.span.end), (span.file, span.start, span.end));
+        match error.message {
+            ParsingMessage::InternalCompilerError(function, details) => {
+                assert_eq!(function, "test::broken");
+                assert_eq!(details, expected);
+            }
+            message => panic!("Expected an internal compiler error, got {}", message),
+        }
+    }
+
+    /// Checks NOPs compile to nothing, but are an internal compiler error when used as a value
+    #[test]
+    pub fn test_nop_value() {
+        let span = Span::new(1, 4);
+        let effect = FinalizedEffects::new(span.clone(), FinalizedEffectType::NOP);
+        let error = compile_broken(&effect).unwrap_err();
+        assert_internal_error(error, &span, "Expected a value: NOP");
+
+        let context = Context::create();
+        let compiler = CompilerImpl::new(&context, &CompilerArguments::default()).unwrap();
+        let syntax = Arc::new(Mutex::new(Syntax::new(Box::new(NoProcessManager))));
+        assert!(compile_effect(&mut CompilerTypeGetter::new(Rc::new(compiler), syntax), &effect).unwrap().is_none());
+    }
+
+    /// Checks loading a variable that was never created is an internal compiler error instead of a crash
+    #[test]
+    pub fn test_unknown_variable() {
+        let span = Span::new(2, 7);
+        let effect = FinalizedEffects::new(span.clone(), FinalizedEffectType::LoadVariable("missing".to_string()));
+        let error = compile_broken(&effect).unwrap_err();
+        assert_internal_error(error, &span, "Unknown variable: LoadVariable(\"missing\")");
+    }
+
+    /// Checks a match with three arms compiles to a switch on the discriminant that survives optimization
+    #[test]
+    pub fn test_match_switch() {
+        let output = compile_match_switch();
+        assert!(output.contains("switch i64"), "No switch in {}", output);
+        for name in ["Circle", "Square", "Triangle"] {
+            let id = StructData::new(vec![], vec![], 0, Span::default(), format!("test::{}", name)).id;
+            assert!(output.contains(&format!("i64 {}, label", id as i64)), "No case for {} in {}", name, output);
+        }
+    }
+
+    /// Checks the discriminants in the IR are the same every build, so the output can be reproduced
+    #[test]
+    pub fn test_reproducible_ids() {
+        let output = compile_match_switch();
+        assert_eq!(output, compile_match_switch());
+        for id in [2497826969u64, 3024184502, 3839491573] {
+            assert!(output.contains(&format!("i64 {}, label", id)), "No case for {} in {}", id, output);
+        }
+    }
+
+    /// Compiles a match on a test::Shape with a Circle, Square and Triangle arm, returning the optimized IR
+    fn compile_match_switch() -> String {
+        let context = Context::create();
+        let arguments = CompilerArguments { opt_level: OptimizationLevel::Default, ..Default::default() };
+        let compiler = CompilerImpl::new(&context, &arguments).unwrap();
+        let syntax = Arc::new(Mutex::new(Syntax::new(Box::new(NoProcessManager))));
+        let mut type_getter = CompilerTypeGetter::new(Rc::new(compiler), syntax);
+
+        let pointer = context.ptr_type(AddressSpace::default());
+        let function = type_getter.compiler.module.add_function(
+            "test::matching",
+            context.i64_type().fn_type(&[pointer.into()], false),
+            None,
+        );
+        let block = context.append_basic_block(function, "0");
+        type_getter.compiler.builder.position_at_end(block);
+        type_getter.function = Some(function);
+        type_getter.current_block = Some(block);
+
+        let shape = FinalizedTypes::Struct(Arc::new(FinalizedStruct::empty_of(StructData::new(
+            vec![],
+            vec![],
+            0,
+            Span::default(),
+            "test::Shape".to_string(),
+        ))));
+        let parameter = function.get_first_param().unwrap();
+        type_getter.variables.insert("shape".to_string(), (shape, parameter));
+
+        // Each arm returns a different number so they can't be merged
+        let arms = ["Circle", "Square", "Triangle"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let data = StructData::new(vec![], vec![], 0, Span::default(), format!("test::{}", name));
+                let returning = FinalizedExpression::new(
+                    ExpressionType::Return(Span::default()),
+                    FinalizedEffects::new(Span::default(), FinalizedEffectType::UInt(i as u64 + 1)),
+                );
+                let body = FinalizedCodeBody::new(vec![returning], format!("arm{}", i), true);
+                (
+                    FinalizedTypes::Struct(Arc::new(FinalizedStruct::empty_of(data))),
+                    FinalizedEffects::new(Span::default(), FinalizedEffectType::CodeBody(body)),
+                )
+            })
+            .collect::<Vec<_>>();
+        let matching = FinalizedEffects::new(
+            Span::default(),
+            FinalizedEffectType::MatchArm(
+                Box::new(FinalizedEffects::new(Span::default(), FinalizedEffectType::LoadVariable("shape".to_string()))),
+                arms,
+            ),
+        );
+        compile_effect(&mut type_getter, &matching).unwrap();
+        // Nothing matched
+        type_getter.compiler.builder.build_return(Some(&context.i64_type().const_zero())).unwrap();
+
+        type_getter.compiler.optimize().unwrap();
+        return function.print_to_string().to_string();
+    }
+
+    /// Checks debug builds give functions a subprogram in their source file and their instructions its lines
+    #[test]
+    pub fn test_debug_locations() {
+        let context = Context::create();
+        let compiler = CompilerImpl::new(&context, &CompilerArguments { debug: true, ..Default::default() }).unwrap();
+        let mut syntax = Syntax::new(Box::new(NoProcessManager));
+        syntax.source_files.insert(
+            7,
+            SourceFile { path: "src/test/debug.rv".to_string(), positions: vec![(1, 0), (1, 3), (2, 4)], blocks: vec![] },
+        );
+        let mut type_getter = CompilerTypeGetter::new(Rc::new(compiler), Arc::new(Mutex::new(syntax)));
+
+        let function =
+            type_getter.compiler.module.add_function("test::debugged", context.void_type().fn_type(&[], false), None);
+        let codeless = CodelessFinalizedFunction {
+            generics: Default::default(),
+            arguments: vec![],
+            return_type: None,
+            data: Arc::new(FunctionData::new(vec![], 0, "test::debugged".to_string(), Span::new(7, 0))),
+            parent: None,
+        };
+        let debug = type_getter.compiler.debug.as_ref().unwrap();
+        debug.add_function(&type_getter, &codeless, function);
+
+        let block = context.append_basic_block(function, "0");
+        type_getter.compiler.builder.position_at_end(block);
+        type_getter.function = Some(function);
+        type_getter.current_block = Some(block);
+        debug.set_location(&type_getter, &Span::new(7, 2));
+        type_getter.compiler.builder.build_return(None).unwrap();
+        debug.builder.finalize();
+
+        let output = type_getter.compiler.module.print_to_string().to_string();
+        assert!(output.contains("!DIFile(filename: \"debug.rv\", directory: \"src/test\")"), "No file in {}", output);
+        assert!(output.contains("!DISubprogram(name: \"test::debugged\""), "No subprogram in {}", output);
+        assert!(output.contains("ret void, !dbg"), "No location in {}", output);
+        assert!(output.contains("!DILocation(line: 2, column: 4"), "Wrong location in {}", output);
+    }
+}
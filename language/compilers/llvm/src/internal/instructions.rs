@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use crate::compiler::CompilerImpl;
 use crate::internal::intrinsics::compile_llvm_intrinsics;
-use crate::internal::math_internal::math_internal;
+use crate::internal::math_internal::{compile_float_to_int, compile_int_to_float, is_float, math_internal};
 use crate::internal::string_internal::string_internal;
 use crate::type_getter::CompilerTypeGetter;
 use inkwell::types::{BasicType, BasicTypeEnum};
@@ -53,7 +53,14 @@ pub fn compile_internal<'ctx>(
     } else if name.starts_with("types::pointer::Pointer<T>::read_ptr_data$") {
         compiler.builder.build_return(Some(&params[0].into_pointer_value())).unwrap();
     } else if name.starts_with("numbers::Cast") {
-        build_cast(value.get_params().first().unwrap(), value.get_type().get_return_type().unwrap(), &compiler);
+        // Integers and floats have different representations, so casts between them are converted
+        let from = &function.arguments[0].field.field_type.inner_struct().data.name;
+        let to = &function.return_type.as_ref().unwrap().inner_struct().data.name;
+        match (is_float(from), is_float(to)) {
+            (false, true) => compile_int_to_float(type_getter, &compiler, &params),
+            (true, false) => compile_float_to_int(type_getter, &compiler, &params),
+            _ => build_cast(value.get_params().first().unwrap(), value.get_type().get_return_type().unwrap(), &compiler),
+        }
     } else if name.starts_with("math::RightShift") {
         let pointer_type = params.first().unwrap().into_pointer_value();
         let malloc = malloc_type(type_getter, type_getter.compiler.context.i64_type().size_of());
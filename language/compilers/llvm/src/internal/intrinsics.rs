@@ -1,7 +1,10 @@
 use crate::type_getter::CompilerTypeGetter;
-use inkwell::types::BasicMetadataTypeEnum;
+use inkwell::module::Linkage;
+use inkwell::types::{BasicMetadataTypeEnum, BasicType};
 use inkwell::values::FunctionValue;
 use inkwell::AddressSpace;
+use syntax::program::function::CodelessFinalizedFunction;
+use syntax::Attribute;
 
 /// Compiles a method with the attribute #[llvm_intrinsic]
 pub fn compile_llvm_intrinsics<'ctx>(name: &str, type_getter: &CompilerTypeGetter<'ctx>) -> FunctionValue<'ctx> {
@@ -15,6 +18,10 @@ pub fn compile_llvm_intrinsics<'ctx>(name: &str, type_getter: &CompilerTypeGette
                 &[BasicMetadataTypeEnum::from(type_getter.compiler.context.ptr_type(AddressSpace::default()))],
                 true,
             ),
+            "puts" => type_getter.compiler.context.i32_type().fn_type(
+                &[BasicMetadataTypeEnum::from(type_getter.compiler.context.ptr_type(AddressSpace::default()))],
+                false,
+            ),
             "malloc" => type_getter.compiler.context.ptr_type(AddressSpace::default()).fn_type(
                 &[BasicMetadataTypeEnum::from(type_getter.compiler.context.ptr_type(AddressSpace::default()))],
                 false,
@@ -49,3 +56,33 @@ pub fn compile_llvm_intrinsics<'ctx>(name: &str, type_getter: &CompilerTypeGette
         None,
     );
 }
+
+/// Gets the C symbol an extern function is linked to with #[link_name]
+pub fn link_name(function: &CodelessFinalizedFunction) -> Option<&str> {
+    return Attribute::find_attribute("link_name", &function.data.attributes)
+        .and_then(Attribute::as_string_attribute)
+        .map(|symbol| symbol.trim_matches('"'));
+}
+
+/// Declares an extern function with the #[link_name] attribute as the C symbol it names, with no body.
+/// C functions take and return values instead of the references Raven passes around.
+pub fn compile_linked_extern<'ctx>(
+    symbol: &str,
+    function: &CodelessFinalizedFunction,
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+) -> FunctionValue<'ctx> {
+    if let Some(func) = type_getter.compiler.module.get_function(symbol) {
+        return func;
+    }
+
+    let mut params = Vec::default();
+    for argument in &function.arguments {
+        params.push(BasicMetadataTypeEnum::from(type_getter.get_type(argument.field.field_type.dereferenced())));
+    }
+
+    let function_type = match &function.return_type {
+        Some(returning) => type_getter.get_type(returning.dereferenced()).fn_type(params.as_slice(), false),
+        None => type_getter.compiler.context.void_type().fn_type(params.as_slice(), false),
+    };
+    return type_getter.compiler.module.add_function(symbol, function_type, Some(Linkage::External));
+}
@@ -2,8 +2,9 @@ use crate::compiler::CompilerImpl;
 use crate::internal::instructions::malloc_type;
 use crate::type_getter::CompilerTypeGetter;
 use inkwell::builder::{Builder, BuilderError};
-use inkwell::values::{BasicValueEnum, FunctionValue, IntValue};
-use inkwell::IntPredicate;
+use inkwell::types::FloatType;
+use inkwell::values::{BasicValueEnum, FloatValue, FunctionValue, IntValue};
+use inkwell::{FloatPredicate, IntPredicate};
 
 /// Compiles internal math functions
 pub fn math_internal<'ctx>(
@@ -13,6 +14,10 @@ pub fn math_internal<'ctx>(
     value: &FunctionValue<'ctx>,
 ) -> bool {
     let params = value.get_params();
+    // Degenericed functions are suffixed by their solidified type, so floats get their own instructions
+    if is_float(name) && float_internal(type_getter, compiler, name, &params) {
+        return true;
+    }
     if name.starts_with("math::Add") {
         compile_two_arg_func(type_getter, compiler, &params, &Builder::build_int_add);
     } else if name.starts_with("math::Subtract") {
@@ -53,6 +58,10 @@ pub fn math_internal<'ctx>(
         compile_two_arg_func(type_getter, compiler, &params, &Builder::build_or);
     } else if name.starts_with("math::BitAnd") || name.starts_with("math::And") {
         compile_two_arg_func(type_getter, compiler, &params, &Builder::build_and);
+    } else if name.starts_with("math::cast_u64_to_f64") {
+        compile_int_to_float(type_getter, compiler, &params);
+    } else if name.starts_with("math::cast_f64_to_u64") {
+        compile_float_to_int(type_getter, compiler, &params);
     } else {
         return false;
     }
@@ -84,6 +93,65 @@ fn compile_two_arg_func<'ctx>(
     compiler.builder.build_return(Some(&malloc)).unwrap();
 }
 
+/// Compiles the float versions of internal math functions, returning false if there isn't one
+fn float_internal<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    compiler: &CompilerImpl<'ctx>,
+    name: &String,
+    params: &Vec<BasicValueEnum<'ctx>>,
+) -> bool {
+    let float_type = if name.ends_with("f32") { compiler.context.f32_type() } else { compiler.context.f64_type() };
+    if name.starts_with("math::Add") {
+        compile_two_arg_float_func(type_getter, compiler, float_type, params, &Builder::build_float_add);
+    } else if name.starts_with("math::Subtract") {
+        compile_two_arg_float_func(type_getter, compiler, float_type, params, &Builder::build_float_sub);
+    } else if name.starts_with("math::Multiply") {
+        compile_two_arg_float_func(type_getter, compiler, float_type, params, &Builder::build_float_mul);
+    } else if name.starts_with("math::Divide") {
+        compile_two_arg_float_func(type_getter, compiler, float_type, params, &Builder::build_float_div);
+    } else if name.starts_with("math::Remainder") {
+        compile_two_arg_float_func(type_getter, compiler, float_type, params, &Builder::build_float_rem);
+    } else if name.starts_with("math::Equal") {
+        compile_float_relational_op(FloatPredicate::OEQ, compiler, float_type, params, type_getter);
+    } else if name.starts_with("math::GreaterThan") {
+        compile_float_relational_op(FloatPredicate::OGT, compiler, float_type, params, type_getter);
+    } else if name.starts_with("math::LessThan") {
+        compile_float_relational_op(FloatPredicate::OLT, compiler, float_type, params, type_getter);
+    } else {
+        return false;
+    }
+    return true;
+}
+
+/// Creates a two-argument internal function on floats, calling the function on both arguments
+fn compile_two_arg_float_func<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    compiler: &CompilerImpl<'ctx>,
+    float_type: FloatType<'ctx>,
+    params: &Vec<BasicValueEnum<'ctx>>,
+    function: &dyn Fn(&Builder<'ctx>, FloatValue<'ctx>, FloatValue<'ctx>, &str) -> Result<FloatValue<'ctx>, BuilderError>,
+) {
+    let malloc = malloc_type(type_getter, float_type.size_of());
+
+    let returning = function(
+        &compiler.builder,
+        compiler
+            .builder
+            .build_load(float_type, params.first().unwrap().into_pointer_value(), "2")
+            .unwrap()
+            .into_float_value(),
+        compiler
+            .builder
+            .build_load(float_type, params.get(1).unwrap().into_pointer_value(), "3")
+            .unwrap()
+            .into_float_value(),
+        "1",
+    )
+    .unwrap();
+    compiler.builder.build_store(malloc, returning).unwrap();
+    compiler.builder.build_return(Some(&malloc)).unwrap();
+}
+
 /// Creates a one-argument internal function, calling the function on one argument
 fn compile_one_arg_func<'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
@@ -104,6 +172,48 @@ fn compile_one_arg_func<'ctx>(
     compiler.builder.build_return(Some(&malloc)).unwrap();
 }
 
+/// Casts an unsigned integer argument to a float
+pub fn compile_int_to_float<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    compiler: &CompilerImpl<'ctx>,
+    params: &Vec<BasicValueEnum<'ctx>>,
+) {
+    let pointer_type = params.first().unwrap().into_pointer_value();
+    let malloc = malloc_type(type_getter, compiler.context.f64_type().size_of());
+
+    let returning = compiler
+        .builder
+        .build_unsigned_int_to_float(
+            compiler.builder.build_load(compiler.context.i64_type(), pointer_type, "2").unwrap().into_int_value(),
+            compiler.context.f64_type(),
+            "1",
+        )
+        .unwrap();
+    compiler.builder.build_store(malloc, returning).unwrap();
+    compiler.builder.build_return(Some(&malloc)).unwrap();
+}
+
+/// Casts a float argument to an unsigned integer, truncating towards zero
+pub fn compile_float_to_int<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    compiler: &CompilerImpl<'ctx>,
+    params: &Vec<BasicValueEnum<'ctx>>,
+) {
+    let pointer_type = params.first().unwrap().into_pointer_value();
+    let malloc = malloc_type(type_getter, compiler.context.i64_type().size_of());
+
+    let returning = compiler
+        .builder
+        .build_float_to_unsigned_int(
+            compiler.builder.build_load(compiler.context.f64_type(), pointer_type, "2").unwrap().into_float_value(),
+            compiler.context.i64_type(),
+            "1",
+        )
+        .unwrap();
+    compiler.builder.build_store(malloc, returning).unwrap();
+    compiler.builder.build_return(Some(&malloc)).unwrap();
+}
+
 /// Compiles relational operators
 fn compile_relational_op(
     op: IntPredicate,
@@ -134,6 +244,42 @@ fn compile_relational_op(
     compiler.builder.build_return(Some(&malloc)).unwrap();
 }
 
+/// Compiles relational operators on floats
+fn compile_float_relational_op<'ctx>(
+    op: FloatPredicate,
+    compiler: &CompilerImpl<'ctx>,
+    float_type: FloatType<'ctx>,
+    params: &Vec<BasicValueEnum<'ctx>>,
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+) {
+    let malloc = malloc_type(type_getter, type_getter.compiler.context.bool_type().size_of());
+
+    let returning = compiler
+        .builder
+        .build_float_compare(
+            op,
+            compiler
+                .builder
+                .build_load(float_type, params.first().unwrap().into_pointer_value(), "2")
+                .unwrap()
+                .into_float_value(),
+            compiler
+                .builder
+                .build_load(float_type, params.get(1).unwrap().into_pointer_value(), "3")
+                .unwrap()
+                .into_float_value(),
+            "1",
+        )
+        .unwrap();
+    compiler.builder.build_store(malloc, returning).unwrap();
+    compiler.builder.build_return(Some(&malloc)).unwrap();
+}
+
+/// Returns true if a number is a float
+pub fn is_float(name: &String) -> bool {
+    return name.ends_with("f64") || name.ends_with("f32");
+}
+
 /// Returns true if a number is unsigned
 fn is_unsigned(name: &String) -> bool {
     return match name {
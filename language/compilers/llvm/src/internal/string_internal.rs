@@ -3,7 +3,6 @@ use crate::internal::instructions::malloc_type;
 use crate::internal::intrinsics::compile_llvm_intrinsics;
 use crate::type_getter::CompilerTypeGetter;
 use inkwell::values::{BasicMetadataValueEnum, BasicValue, FunctionValue};
-use inkwell::AddressSpace;
 
 /// Compiles internal string methods
 pub fn string_internal<'ctx>(
@@ -14,31 +13,41 @@ pub fn string_internal<'ctx>(
 ) -> bool {
     let params = value.get_params();
     if name.starts_with("string::Cast") {
-        type_getter.compiler.builder.build_return(Some(value.get_params().first().unwrap())).unwrap();
-    } else if name.starts_with("string::Add<char + u64>_char::add") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, type_getter.compiler.context.i64_type().size_of());
-        let pointer_type = compiler
+        // Characters are cast into a single character null-terminated string
+        let malloc = malloc_type(type_getter, compiler.context.i64_type().const_int(2, false));
+        let character = compiler
             .builder
-            .build_bit_cast(pointer_type, compiler.context.ptr_type(AddressSpace::default()), "1")
+            .build_load(compiler.context.i8_type(), params.first().unwrap().into_pointer_value(), "0")
+            .unwrap();
+        compiler.builder.build_store(malloc, character).unwrap();
+        let terminator = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(
+                    compiler.context.i8_type(),
+                    malloc,
+                    &[compiler.context.i64_type().const_int(1, false)],
+                    "1",
+                )
+                .unwrap()
+        };
+        compiler.builder.build_store(terminator, compiler.context.i8_type().const_zero()).unwrap();
+        compiler.builder.build_return(Some(&malloc.as_basic_value_enum())).unwrap();
+    } else if name.starts_with("string::Add<u64 + char>_char::add") {
+        let malloc = malloc_type(type_getter, compiler.context.i8_type().size_of());
+        let character = compiler
+            .builder
+            .build_load(compiler.context.i8_type(), params.first().unwrap().into_pointer_value(), "0")
             .unwrap()
-            .into_pointer_value();
-        let returning = compiler
+            .into_int_value();
+        let offset = compiler
             .builder
-            .build_int_add(
-                compiler
-                    .builder
-                    .build_load(type_getter.compiler.context.i64_type(), pointer_type, "2")
-                    .unwrap()
-                    .into_int_value(),
-                compiler
-                    .builder
-                    .build_load(type_getter.compiler.context.i64_type(), params.get(1).unwrap().into_pointer_value(), "3")
-                    .unwrap()
-                    .into_int_value(),
-                "1",
-            )
-            .unwrap();
+            .build_load(compiler.context.i64_type(), params.get(1).unwrap().into_pointer_value(), "1")
+            .unwrap()
+            .into_int_value();
+        // Characters are a single byte, so the offset wraps around
+        let offset = compiler.builder.build_int_truncate(offset, compiler.context.i8_type(), "2").unwrap();
+        let returning = compiler.builder.build_int_add(character, offset, "3").unwrap();
         compiler.builder.build_store(malloc, returning).unwrap();
         compiler.builder.build_return(Some(&malloc)).unwrap();
     } else if name.starts_with("string::Add<str + str>_str::add") {
@@ -74,7 +83,13 @@ pub fn string_internal<'ctx>(
             .try_as_basic_value()
             .unwrap_left()
             .into_int_value();
-        let total = type_getter.compiler.builder.build_int_add(length, second_length, "4").unwrap();
+        // Strings are null-terminated, so leave room for the terminator strcat copies over
+        let total = type_getter.compiler.builder.build_int_add(length, second_length, "3").unwrap();
+        let total = type_getter
+            .compiler
+            .builder
+            .build_int_add(total, type_getter.compiler.context.i64_type().const_int(1, false), "4")
+            .unwrap();
         let malloc = type_getter
             .compiler
             .builder
@@ -130,7 +145,7 @@ pub fn string_internal<'ctx>(
             .unwrap_left()
             .into_pointer_value();
         type_getter.compiler.builder.build_return(Some(&malloc.as_basic_value_enum())).unwrap();
-    } else if name.starts_with("string::Add<str + char>_str::add") {
+    } else if name.starts_with("string::Add<char + str>_str::add") {
         let length = type_getter
             .compiler
             .builder
@@ -147,10 +162,11 @@ pub fn string_internal<'ctx>(
             .try_as_basic_value()
             .unwrap_left()
             .into_int_value();
+        // One byte for the added character and one for the null terminator
         let total = type_getter
             .compiler
             .builder
-            .build_int_add(length, type_getter.compiler.context.i64_type().const_int(1, false), "4")
+            .build_int_add(length, type_getter.compiler.context.i64_type().const_int(2, false), "4")
             .unwrap();
         let malloc = type_getter
             .compiler
@@ -195,19 +211,14 @@ pub fn string_internal<'ctx>(
                     type_getter
                         .compiler
                         .builder
-                        .build_in_bounds_gep(
-                            type_getter.compiler.context.ptr_type(AddressSpace::default()),
-                            malloc,
-                            &[length],
-                            "7",
-                        )
+                        .build_in_bounds_gep(type_getter.compiler.context.i8_type(), malloc, &[length], "7")
                         .unwrap()
                 },
                 type_getter
                     .compiler
                     .builder
                     .build_load(
-                        type_getter.compiler.context.ptr_type(AddressSpace::default()),
+                        type_getter.compiler.context.i8_type(),
                         value.get_params().get(1).unwrap().into_pointer_value(),
                         "8",
                     )
@@ -227,12 +238,7 @@ pub fn string_internal<'ctx>(
                     type_getter
                         .compiler
                         .builder
-                        .build_in_bounds_gep(
-                            type_getter.compiler.context.ptr_type(AddressSpace::default()),
-                            malloc,
-                            &[plus_one],
-                            "10",
-                        )
+                        .build_in_bounds_gep(type_getter.compiler.context.i8_type(), malloc, &[plus_one], "10")
                         .unwrap()
                 },
                 type_getter.compiler.context.i8_type().const_zero(),
@@ -9,22 +9,29 @@ use inkwell::context::Context;
 use tokio::sync::mpsc::Receiver;
 
 use async_trait::async_trait;
+use data::arena::arena_malloc;
 use data::CompilerArguments;
 use syntax::program::function::FinalizedFunction;
 use syntax::program::r#struct::FinalizedStruct;
-use syntax::program::syntax::{Compiler, Syntax};
+use syntax::program::syntax::{Compiler, Program, Syntax};
 
 use crate::compiler::CompilerImpl;
+use crate::function_compiler::symbol_name;
+use crate::program::LLVMProgram;
 use crate::type_getter::CompilerTypeGetter;
 
 /// The compiler that compiles a syntax
 pub mod compiler;
+/// Generates DWARF debug info
+pub mod debug_info;
 /// Compiles a function to LLVM
 pub mod function_compiler;
 /// Implementations of internal types
 pub mod internal;
 /// A future that waits on main to finish verifying
 pub mod main_future;
+/// A compiled program kept loaded so it can be called
+pub mod program;
 /// Handles translating Raven types into LLVM
 pub mod type_getter;
 /// Utility functions used in other files
@@ -32,20 +39,18 @@ pub mod util;
 /// Handles Virtual Tables
 pub mod vtable_manager;
 
+/// Checks that LLVM can compile for the target triple, erroring with every registered target if it can't
+pub fn check_target(triple: &str) -> Result<(), String> {
+    return CompilerImpl::create_target_machine(Some(triple), inkwell::OptimizationLevel::None).map(|_| ());
+}
+
 /// An LLVM compiler and the data it requires
 pub struct LLVMCompiler {
     compiling: Arc<DashMap<String, Arc<FinalizedFunction>>>,
     struct_compiling: Arc<DashMap<String, Arc<FinalizedStruct>>>,
     arguments: CompilerArguments,
-    context: Context,
 }
 
-/// SAFETY: LLVMCompiler isn't actually multi-threaded, so this is safe
-unsafe impl Sync for LLVMCompiler {}
-
-/// SAFETY: LLVMCompiler isn't actually multi-threaded, so this is safe
-unsafe impl Send for LLVMCompiler {}
-
 impl LLVMCompiler {
     /// Creates a new LLVM compiler
     pub fn new(
@@ -53,7 +58,7 @@ impl LLVMCompiler {
         struct_compiling: Arc<DashMap<String, Arc<FinalizedStruct>>>,
         arguments: CompilerArguments,
     ) -> Self {
-        return Self { compiling, struct_compiling, arguments, context: Context::create() };
+        return Self { compiling, struct_compiling, arguments };
     }
 }
 
@@ -63,9 +68,70 @@ impl<T> Compiler<T> for LLVMCompiler {
     async fn compile(&self, mut receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Option<T> {
         if let Some(main) = CompilerImpl::get_main(&self.arguments, syntax).await {
             if receiver.recv().await.is_some() {
-                let mut binding = CompilerTypeGetter::new(Rc::new(CompilerImpl::new(&self.context)), syntax.clone());
-                CompilerImpl::compile(main, &mut binding, &self.compiling, &self.struct_compiling);
-                return binding.get_target(&self.arguments.target).map(|inner| unsafe { inner.call() });
+                let symbol = symbol_name(&main).to_string();
+                // The context is made after the last await, so the future never holds it while it could move threads
+                let context = Context::create();
+                let compiler = match CompilerImpl::new(&context, &self.arguments) {
+                    Ok(compiler) => compiler,
+                    Err(error) => {
+                        syntax.lock().errors.push(error);
+                        return None;
+                    }
+                };
+                let mut binding = CompilerTypeGetter::new(Rc::new(compiler), syntax.clone());
+                CompilerImpl::compile(vec![main], &mut binding, &self.compiling, &self.struct_compiling);
+                if !syntax.lock().errors.is_empty() {
+                    return None;
+                }
+                // Programs that can't be run here are written to an object file instead
+                if binding.compiler.execution_engine.is_none() {
+                    let name = if self.arguments.target_os == "windows" { "main.obj" } else { "main.o" };
+                    let path = self.arguments.temp_folder.join(name);
+                    match binding.compiler.write_object(&path) {
+                        Ok(()) => syntax.lock().object_file = Some(path),
+                        Err(error) => {
+                            syntax.lock().errors.push(error);
+                            return None;
+                        }
+                    }
+                    // The object file has to be linked with the libraries by the linker the program is built with
+                    let libraries = CompilerImpl::link_libraries(&self.arguments, syntax);
+                    syntax.lock().link_flags = libraries.iter().map(|(library, _)| format!("-l{}", library)).collect();
+                    return None;
+                }
+                CompilerImpl::load_libraries(&self.arguments, syntax);
+                // Everything the program allocates is put in the arena, so what it returns can be freed after translating it
+                if let Some(malloc) = binding.compiler.module.get_function("malloc") {
+                    binding.compiler.execution_engine.as_ref().unwrap().add_global_mapping(&malloc, arena_malloc as usize);
+                }
+                let _arena = self.arguments.arena.enter();
+                return binding.get_target(&symbol).map(|inner| unsafe { inner.call() });
+            }
+        } else {
+            receiver.recv().await;
+        }
+
+        return None;
+    }
+
+    /// Compiles a syntax into LLVM IR, with a receiver that is used to wait for verification before compiling
+    async fn compile_to_string(&self, mut receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Option<String> {
+        if let Some(main) = CompilerImpl::get_main(&self.arguments, syntax).await {
+            if receiver.recv().await.is_some() {
+                let context = Context::create();
+                let compiler = match CompilerImpl::new(&context, &self.arguments) {
+                    Ok(compiler) => compiler,
+                    Err(error) => {
+                        syntax.lock().errors.push(error);
+                        return None;
+                    }
+                };
+                let mut binding = CompilerTypeGetter::new(Rc::new(compiler), syntax.clone());
+                CompilerImpl::compile(vec![main], &mut binding, &self.compiling, &self.struct_compiling);
+                if !syntax.lock().errors.is_empty() {
+                    return None;
+                }
+                return Some(binding.compiler.module.print_to_string().to_string());
             }
         } else {
             receiver.recv().await;
@@ -73,4 +139,37 @@ impl<T> Compiler<T> for LLVMCompiler {
 
         return None;
     }
+
+    /// Compiles every function in the modules into a program that stays loaded
+    async fn compile_program(
+        &self,
+        mut receiver: Receiver<()>,
+        syntax: &Arc<Mutex<Syntax>>,
+        modules: Vec<String>,
+        host_functions: Vec<(String, usize)>,
+    ) -> Option<Box<dyn Program>> {
+        receiver.recv().await?;
+        let functions = self
+            .compiling
+            .iter()
+            .filter(|function| {
+                // Generic functions are compiled when they're called
+                !function.data.name.contains('$')
+                    && modules.iter().any(|module| {
+                        function.data.name.strip_prefix(module.as_str()).map_or(false, |name| name.starts_with("::"))
+                    })
+            })
+            .map(|function| Arc::new(function.to_codeless()))
+            .collect::<Vec<_>>();
+        return LLVMProgram::compile(
+            &self.arguments,
+            syntax,
+            functions,
+            &self.compiling,
+            &self.struct_compiling,
+            &host_functions,
+        )
+        .await
+        .map(|program| Box::new(program) as Box<dyn Program>);
+    }
 }
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use dashmap::DashMap;
+use inkwell::context::Context;
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+use data::arena::arena_malloc;
+use data::CompilerArguments;
+use syntax::program::function::{CodelessFinalizedFunction, FinalizedFunction};
+use syntax::program::r#struct::FinalizedStruct;
+use syntax::program::syntax::{Program, Syntax};
+
+use crate::compiler::CompilerImpl;
+use crate::function_compiler::symbol_name;
+use crate::type_getter::CompilerTypeGetter;
+
+/// A JIT compiled program. LLVM's context can't be used by other threads, so the program is compiled and kept loaded
+/// by a thread of its own, and only the addresses of its functions leave that thread.
+pub struct LLVMProgram {
+    /// The address of every function that can be called, by its Raven name
+    addresses: HashMap<String, usize>,
+    /// Dropping this disconnects the thread keeping the program loaded, which frees it
+    _loaded: mpsc::Sender<()>,
+}
+
+impl LLVMProgram {
+    /// Compiles the functions and every function they call, linking externs named in host_functions to their address.
+    /// Returns None if anything failed to compile, or if the program can't be run on the host.
+    pub async fn compile(
+        arguments: &CompilerArguments,
+        syntax: &Arc<Mutex<Syntax>>,
+        functions: Vec<Arc<CodelessFinalizedFunction>>,
+        compiling: &Arc<DashMap<String, Arc<FinalizedFunction>>>,
+        struct_compiling: &Arc<DashMap<String, Arc<FinalizedStruct>>>,
+        host_functions: &Vec<(String, usize)>,
+    ) -> Option<Self> {
+        let (sender, receiver) = oneshot::channel();
+        let (loaded, unloaded) = mpsc::channel::<()>();
+        let arguments = arguments.clone();
+        let syntax = syntax.clone();
+        let compiling = compiling.clone();
+        let struct_compiling = struct_compiling.clone();
+        let host_functions = host_functions.clone();
+        thread::spawn(move || {
+            let context = Context::create();
+            let Some((_compiler, addresses)) =
+                Self::load(&context, &arguments, syntax, functions, &compiling, &struct_compiling, &host_functions)
+            else {
+                let _ = sender.send(None);
+                return;
+            };
+            let _ = sender.send(Some(addresses));
+            // Keeps the compiler and its context until the program is dropped
+            let _ = unloaded.recv();
+        });
+
+        let addresses = receiver.await.ok()??;
+        return Some(Self { addresses, _loaded: loaded });
+    }
+
+    /// Compiles the functions in the context, returning the compiler holding the JIT and the address of each function
+    fn load<'ctx>(
+        context: &'ctx Context,
+        arguments: &CompilerArguments,
+        syntax: Arc<Mutex<Syntax>>,
+        functions: Vec<Arc<CodelessFinalizedFunction>>,
+        compiling: &Arc<DashMap<String, Arc<FinalizedFunction>>>,
+        struct_compiling: &Arc<DashMap<String, Arc<FinalizedStruct>>>,
+        host_functions: &Vec<(String, usize)>,
+    ) -> Option<(Rc<CompilerImpl<'ctx>>, HashMap<String, usize>)> {
+        let compiler = match CompilerImpl::new(context, arguments) {
+            Ok(compiler) => compiler,
+            Err(error) => {
+                syntax.lock().errors.push(error);
+                return None;
+            }
+        };
+        let mut binding = CompilerTypeGetter::new(Rc::new(compiler), syntax.clone());
+
+        let symbols = functions
+            .iter()
+            .map(|function| (function.data.name.clone(), symbol_name(function).to_string()))
+            .collect::<Vec<_>>();
+        CompilerImpl::compile(functions, &mut binding, compiling, struct_compiling);
+        if !syntax.lock().errors.is_empty() {
+            return None;
+        }
+
+        let engine = binding.compiler.execution_engine.as_ref()?;
+        CompilerImpl::load_libraries(arguments, &syntax);
+        for (symbol, address) in host_functions {
+            // Host functions that are never called aren't declared
+            if let Some(function) = binding.compiler.module.get_function(symbol) {
+                engine.add_global_mapping(&function, *address);
+            }
+        }
+        // Each call allocates in the arena it's made in, so it can be freed once the call is done
+        if let Some(malloc) = binding.compiler.module.get_function("malloc") {
+            engine.add_global_mapping(&malloc, arena_malloc as usize);
+        }
+
+        let addresses = symbols
+            .into_iter()
+            .filter_map(|(name, symbol)| Some((name, engine.get_function_address(&symbol).ok()?)))
+            .collect::<HashMap<_, _>>();
+        return Some((binding.compiler.clone(), addresses));
+    }
+}
+
+impl Program for LLVMProgram {
+    fn get_function(&self, name: &str) -> Option<usize> {
+        return self.addresses.get(name).copied();
+    }
+}
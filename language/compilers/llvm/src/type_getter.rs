@@ -4,10 +4,11 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::rc::Rc;
 use std::sync::Arc;
+use syntax::errors::{ErrorSource, ParsingMessage};
 use syntax::program::r#struct::{FinalizedStruct, StructData};
 
 use crate::compiler::CompilerImpl;
-use crate::function_compiler::{instance_function, instance_types};
+use crate::function_compiler::{instance_function, instance_types, symbol_name};
 use crate::internal::structs::get_internal_struct;
 use crate::vtable_manager::VTableManager;
 use inkwell::basic_block::BasicBlock;
@@ -15,9 +16,9 @@ use inkwell::execution_engine::JitFunction;
 use inkwell::types::{BasicType, BasicTypeEnum};
 use inkwell::values::{BasicValueEnum, FunctionValue};
 use inkwell::AddressSpace;
-use syntax::program::function::{display_parenless, CodelessFinalizedFunction, FinalizedFunction, FunctionData};
+use syntax::program::function::{CodelessFinalizedFunction, FinalizedFunction, FunctionData};
 use syntax::program::syntax::{Main, Syntax};
-use syntax::program::types::FinalizedTypes;
+use syntax::program::types::{mangle_generic_name, FinalizedTypes};
 use syntax::VariableManager;
 
 /// Data used when compiling a function
@@ -30,6 +31,8 @@ pub struct CompilerTypeGetter<'ctx> {
     pub compiler: Rc<CompilerImpl<'ctx>>,
     /// Functions being compiled
     pub compiling: Rc<RefCell<Vec<(FunctionValue<'ctx>, Arc<CodelessFinalizedFunction>)>>>,
+    /// The names of the functions compiled to each symbol, to catch two functions with the same symbol
+    pub symbols: Rc<RefCell<HashMap<String, Vec<String>>>>,
     /// Current function's code blocks
     pub blocks: HashMap<String, BasicBlock<'ctx>>,
     /// The current block
@@ -50,6 +53,7 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
             vtable: Rc::new(RefCell::new(VTableManager::default())),
             compiler,
             compiling: Rc::new(RefCell::new(Vec::default())),
+            symbols: Rc::new(RefCell::new(HashMap::default())),
             blocks: HashMap::default(),
             current_block: None,
             variables: HashMap::default(),
@@ -71,6 +75,7 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
             vtable: self.vtable.clone(),
             compiler: self.compiler.clone(),
             compiling: self.compiling.clone(),
+            symbols: self.symbols.clone(),
             blocks: self.blocks.clone(),
             current_block: self.current_block.clone(),
             variables,
@@ -79,9 +84,22 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
         };
     }
 
-    /// Gets the LLVM version of the function
+    /// Gets the LLVM version of the function, erroring if a different function was already compiled to its symbol
     pub fn get_function(&mut self, function: &Arc<CodelessFinalizedFunction>) -> FunctionValue<'ctx> {
-        match self.compiler.module.get_function(&function.data.name) {
+        let symbol = symbol_name(function);
+        let mut symbols = self.symbols.borrow_mut();
+        let names = symbols.entry(symbol.to_string()).or_default();
+        if !names.contains(&function.data.name) {
+            if let Some(other) = names.first() {
+                let error =
+                    function.data.span.make_error(ParsingMessage::DuplicateSymbol(symbol.to_string(), other.clone()));
+                self.syntax.lock().errors.push(error);
+            }
+            names.push(function.data.name.clone());
+        }
+        drop(symbols);
+
+        match self.compiler.module.get_function(symbol) {
             Some(found) => found,
             None => {
                 return instance_function(function.clone(), self);
@@ -110,7 +128,7 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
     /// Gets the target function that can be called directly from Rust
     pub(crate) fn get_target<T>(&self, target: &str) -> Option<JitFunction<'_, Main<T>>> {
         return unsafe {
-            match self.compiler.execution_engine.get_function(target) {
+            match self.compiler.execution_engine.as_ref()?.get_function(target) {
                 Ok(value) => Some(value),
                 Err(_) => None,
             }
@@ -127,17 +145,11 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
                     // If there are no bounds, we're good.
                     return;
                 }
-                let name = format!("{}<{}>", base.data.name, display_parenless(&bounds, ", "));
-                // If this type has already been flattened with these args, return that.
-                if self.syntax.lock().structures.types.contains_key(&name) {
-                    let base;
-                    {
-                        let locked = self.syntax.lock();
-                        let data = locked.structures.types.get(&name).unwrap().clone();
-                        // skipcq: RS-W1070 Initialization of a value can't use clone_from
-                        base = locked.structures.data.get(&data).unwrap().clone();
-                    }
-                    *types = FinalizedTypes::Struct(base);
+                let name = mangle_generic_name(&base.data.name, bounds);
+                // If this type has already been flattened or degenericed with these args, return that.
+                let found = self.syntax.lock().get_generic_struct(&base.data.name, bounds);
+                if let Some(found) = found {
+                    *types = FinalizedTypes::Struct(found);
                 } else {
                     // Clone the type and add the new type to the structures.
                     let mut other = StructData::clone(&base.data);
@@ -173,6 +185,7 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
                     // Add the flattened type to the syntax
                     {
                         let mut locked = self.syntax.lock();
+                        locked.structures.add_type(arc_other.clone());
                         locked.structures.add_data(arc_other, data.clone());
                     }
                     *types = FinalizedTypes::Struct(data.clone());
@@ -207,4 +220,8 @@ impl VariableManager for CompilerTypeGetter<'_> {
     fn get_variable(&self, name: &String) -> Option<FinalizedTypes> {
         return self.variables.get(name).map(|found| found.0.clone());
     }
+
+    fn all_variables(&self) -> Vec<(&String, &FinalizedTypes)> {
+        return self.variables.iter().map(|(name, (types, _))| (name, types)).collect();
+    }
 }
@@ -1,3 +1,4 @@
+use crate::function_compiler::symbol_name;
 use crate::type_getter::CompilerTypeGetter;
 use inkwell::module::Linkage;
 use inkwell::types::BasicType;
@@ -62,5 +63,5 @@ pub fn create_function_value<'ctx>(
         None => type_getter.compiler.context.void_type().fn_type(params.as_slice(), false),
     };
 
-    return type_getter.compiler.module.add_function(&function.data.name, llvm_function, linkage);
+    return type_getter.compiler.module.add_function(symbol_name(function), llvm_function, linkage);
 }
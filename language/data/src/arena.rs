@@ -0,0 +1,89 @@
+use std::alloc::{self, Layout};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+extern "C" {
+    /// The allocator used when Raven code isn't running in an arena
+    fn malloc(size: usize) -> *mut c_void;
+}
+
+thread_local! {
+    /// The arena Raven code running on this thread allocates in
+    static CURRENT: RefCell<Option<Arc<Arena>>> = RefCell::new(None);
+}
+
+/// The memory a Raven program allocated while it ran. The value the program returns is allocated here too,
+/// so it can be freed once it's translated into Rust instead of Rust taking ownership of Raven's memory.
+#[derive(Debug, Default)]
+pub struct Arena {
+    /// The layout of every allocation that hasn't been freed, by address
+    allocations: Mutex<HashMap<usize, Layout>>,
+}
+
+impl Arena {
+    /// Allocates memory aligned for any Raven type
+    pub fn allocate(&self, size: usize) -> *mut u8 {
+        let layout = Layout::from_size_align(size.max(1), 16).unwrap();
+        let pointer = unsafe { alloc::alloc(layout) };
+        if pointer.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        self.allocations.lock().insert(pointer as usize, layout);
+        return pointer;
+    }
+
+    /// Frees memory allocated by the arena. Anything else, like a string constant, is left alone.
+    pub fn free(&self, pointer: *const u8) {
+        if let Some(layout) = self.allocations.lock().remove(&(pointer as usize)) {
+            unsafe { alloc::dealloc(pointer as *mut u8, layout) };
+        }
+    }
+
+    /// Frees everything the arena allocated
+    pub fn clear(&self) {
+        for (address, layout) in self.allocations.lock().drain() {
+            unsafe { alloc::dealloc(address as *mut u8, layout) };
+        }
+    }
+
+    /// How many allocations haven't been freed yet
+    pub fn allocations(&self) -> usize {
+        return self.allocations.lock().len();
+    }
+
+    /// Makes Raven code running on this thread allocate in the arena until the guard is dropped
+    pub fn enter(self: &Arc<Self>) -> ArenaGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        return ArenaGuard { previous };
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Stops Raven code on this thread allocating in the entered arena once it's dropped
+pub struct ArenaGuard {
+    /// The arena that was entered before, which is entered again
+    previous: Option<Arc<Arena>>,
+}
+
+impl Drop for ArenaGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| *current.borrow_mut() = self.previous.take());
+    }
+}
+
+/// Linked in place of malloc for programs run by the compiler, allocating in the thread's arena if one was entered
+pub extern "C" fn arena_malloc(size: usize) -> *mut u8 {
+    return CURRENT.with(|current| match &*current.borrow() {
+        Some(arena) => arena.allocate(size),
+        None => unsafe { malloc(size) as *mut u8 },
+    });
+}
@@ -2,12 +2,21 @@ use std::ffi::c_char;
 use std::mem::size_of;
 use std::ptr;
 
-use crate::RavenExtern;
+use crate::arena::{arena_malloc, Arena};
+use crate::{RavenExtern, ToRaven};
+
+/// Moves the value into memory allocated the same way Raven allocates, so Raven can keep it.
+/// Inside an entered arena, the value is allocated in that arena.
+pub unsafe fn allocate<T>(value: T) -> *mut T {
+    let pointer = arena_malloc(size_of::<T>()) as *mut T;
+    ptr::write(pointer, value);
+    return pointer;
+}
 
 impl RavenExtern for String {
     type Input = c_char;
 
-    unsafe fn translate(raven_type: *mut c_char) -> Self {
+    unsafe fn translate_borrowed(raven_type: *const c_char) -> Self {
         let mut output = vec![];
         let mut pointer = raven_type;
         loop {
@@ -22,45 +31,114 @@ impl RavenExtern for String {
     }
 }
 
+impl ToRaven for String {
+    /// Raven strings end at the first null byte, so anything after a null in the string is cut off
+    unsafe fn to_raven(&self) -> *mut c_char {
+        let pointer = arena_malloc(self.len() + 1) as *mut c_char;
+        ptr::copy_nonoverlapping(self.as_ptr() as *const c_char, pointer, self.len());
+        ptr::write(pointer.add(self.len()), 0);
+        return pointer;
+    }
+}
+
 impl RavenExtern for bool {
     type Input = bool;
 
-    unsafe fn translate(raven_type: *mut bool) -> Self {
+    unsafe fn translate_borrowed(raven_type: *const bool) -> Self {
+        return ptr::read(raven_type);
+    }
+}
+
+impl ToRaven for bool {
+    unsafe fn to_raven(&self) -> *mut bool {
+        return allocate(*self);
+    }
+}
+
+impl RavenExtern for u64 {
+    type Input = u64;
+
+    unsafe fn translate_borrowed(raven_type: *const u64) -> Self {
         return ptr::read(raven_type);
     }
 }
 
+impl ToRaven for u64 {
+    unsafe fn to_raven(&self) -> *mut u64 {
+        return allocate(*self);
+    }
+}
+
+impl RavenExtern for i64 {
+    type Input = i64;
+
+    unsafe fn translate_borrowed(raven_type: *const i64) -> Self {
+        return ptr::read(raven_type);
+    }
+}
+
+impl ToRaven for i64 {
+    unsafe fn to_raven(&self) -> *mut i64 {
+        return allocate(*self);
+    }
+}
+
+impl RavenExtern for f64 {
+    type Input = f64;
+
+    unsafe fn translate_borrowed(raven_type: *const f64) -> Self {
+        return ptr::read(raven_type);
+    }
+}
+
+impl ToRaven for f64 {
+    unsafe fn to_raven(&self) -> *mut f64 {
+        return allocate(*self);
+    }
+}
+
 impl<T: RavenExtern> RavenExtern for Vec<T> {
     type Input = ();
 
-    unsafe fn translate(raven_type: *mut ()) -> Self {
+    unsafe fn translate_borrowed(raven_type: *const ()) -> Self {
         return load_array(raven_type);
     }
+
+    unsafe fn free(raven_type: *mut (), arena: &Arena) {
+        let length = ptr::read(raven_type as *const u64);
+        let offset = size_of::<T::Input>() as u64;
+        let mut pointer = (raven_type as u64 + offset) as *const u64;
+        for _ in 0..length {
+            T::free(ptr::read(pointer) as *mut T::Input, arena);
+            pointer = (pointer as u64 + offset) as *const u64;
+        }
+        arena.free(raven_type as *const u8);
+    }
 }
 
 /// Loads a raw array into a Vec
-fn load_raw<T: RavenExtern>(length: u64, pointer: *mut T) -> Vec<T> {
+fn load_raw<T: RavenExtern>(length: u64, pointer: *const T) -> Vec<T> {
     let mut output = Vec::new();
     let offset = size_of::<T::Input>() as u64;
-    let mut pointer = pointer as *mut u64;
+    let mut pointer = pointer as *const u64;
     for _ in 0..length {
-        output.push(unsafe { T::translate(ptr::read(pointer) as *mut T::Input) });
-        pointer = (pointer as u64 + offset) as *mut u64;
+        output.push(unsafe { T::translate_borrowed(ptr::read(pointer) as *const T::Input) });
+        pointer = (pointer as u64 + offset) as *const u64;
     }
 
     return output;
 }
 
 /// Loads an array from a pointer into a Vec
-fn load_array<T: RavenExtern>(ptr: *mut ()) -> Vec<T> {
-    let len = unsafe { ptr::read(ptr as *mut u64) };
-    return load_raw(len, (ptr as u64 + (size_of::<T::Input>()) as u64) as *mut T);
+fn load_array<T: RavenExtern>(ptr: *const ()) -> Vec<T> {
+    let len = unsafe { ptr::read(ptr as *const u64) };
+    return load_raw(len, (ptr as u64 + (size_of::<T::Input>()) as u64) as *const T);
 }
 
 impl RavenExtern for () {
     type Input = ();
 
-    unsafe fn translate(_: *mut ()) -> Self {
+    unsafe fn translate_borrowed(_: *const ()) -> Self {
         return ();
     }
 }
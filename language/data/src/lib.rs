@@ -1,20 +1,46 @@
 #![feature(try_trait_v2)]
 
+use std::env;
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Waker;
+use std::time::Duration;
 
+use parking_lot::Mutex;
 use tokio::runtime::{Builder, Runtime};
 
+use crate::arena::Arena;
 use crate::tokens::Token;
 
 /// The type of the main LLVM function called by the program
 pub type Main<T> = unsafe extern "C" fn() -> T;
 
-/// Handles the externals for translating Raven types to Rust types
+/// How deeply generic types can be nested before degenericing gives up, unless the arguments set another limit
+pub const MAX_GENERIC_DEPTH: usize = 32;
+
+/// The memory Raven programs allocate in while they run
+pub mod arena;
+/// Handles the externals for translating between Raven types and Rust types
 pub mod externs;
 /// Tokens
 pub mod tokens;
 
+/// Hashes a struct's full name, like magpie::RavenProject, into the ID the compiler gives the struct.
+/// The hash is 32 bit FNV-1a, so it's the same every build and Rust code can know a struct's ID ahead of time.
+pub const fn type_id(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash: u32 = 0x811c9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x01000193);
+        i += 1;
+    }
+    return hash as u64;
+}
+
 /// Settings used in configuring the runner
 pub struct RunnerSettings {
     /// Sources to pull source raven files from
@@ -24,7 +50,7 @@ pub struct RunnerSettings {
 }
 
 /// Arguments used when configuring the compiler
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct CompilerArguments {
     /// Which compiler to use, defaults to LLVM
     pub compiler: String,
@@ -32,6 +58,76 @@ pub struct CompilerArguments {
     pub target: String,
     /// The temp folder to use while compiling
     pub temp_folder: PathBuf,
+    /// How much the compiler should optimize the output
+    pub opt_level: OptimizationLevel,
+    /// How deeply generic types can be nested before degenericing gives up
+    pub max_generic_depth: usize,
+    /// The operating system being compiled for, checked by #[cfg(target_os = "...")]
+    pub target_os: String,
+    /// If the program is being compiled for tests, checked by #[cfg(test)]
+    pub test: bool,
+    /// The conditions #[cfg(...)] attributes can match, written like target_os=linux or test.
+    /// Set up from the target and test by magpie before compiling
+    pub cfg: Vec<String>,
+    /// If warnings should be reported as errors
+    pub deny_warnings: bool,
+    /// If DWARF debug info should be generated
+    pub debug: bool,
+    /// If each function's code should be printed after parsing, before it's checked
+    pub dump_ast: bool,
+    /// If the files being compiled should be printed before compiling
+    pub verbose: bool,
+    /// The LLVM target triple to compile for, like x86_64-pc-windows-gnu, or None to compile for the host
+    pub target_triple: Option<String>,
+    /// If an object file should be written to the temp folder instead of running the program.
+    /// Programs compiled for another target are always written, since they can't run on the host.
+    pub emit_object: bool,
+    /// How long parsing and checking can take before the tasks still running are stopped and reported as stuck
+    pub timeout: Duration,
+    /// Libraries to link the program with, like m for libm, on top of the ones named by #[link(name = "...")]
+    pub link_libraries: Vec<String>,
+    /// Where the program allocates while it's run. Clones share it, and it's cleared at the start of every run with
+    /// these arguments and by magpie once the value the program returned is translated into Rust, so it only ever
+    /// holds one run's memory. Engines call each function in an arena of their own instead.
+    pub arena: Arc<Arena>,
+}
+
+impl Default for CompilerArguments {
+    fn default() -> Self {
+        return Self {
+            compiler: "llvm".to_string(),
+            target: String::default(),
+            temp_folder: PathBuf::default(),
+            opt_level: OptimizationLevel::default(),
+            max_generic_depth: MAX_GENERIC_DEPTH,
+            target_os: env::consts::OS.to_string(),
+            test: false,
+            cfg: vec![format!("target_os={}", env::consts::OS), format!("os={}", env::consts::OS)],
+            deny_warnings: false,
+            debug: false,
+            dump_ast: false,
+            verbose: false,
+            target_triple: None,
+            emit_object: false,
+            timeout: Duration::from_secs(60),
+            link_libraries: Vec::default(),
+            arena: Arc::default(),
+        };
+    }
+}
+
+/// The optimization level passed to the compiler
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OptimizationLevel {
+    /// No optimizations, used for debug builds
+    #[default]
+    None,
+    /// Only cheap optimizations
+    Less,
+    /// The standard optimizations, used for release builds
+    Default,
+    /// Every optimization, even ones that take a long time to run
+    Aggressive,
 }
 
 /// Arguments for running Raven
@@ -42,6 +138,39 @@ pub struct Arguments {
     pub cpu_runtime: Runtime,
     /// The settings for the runner running Raven
     pub runner_settings: RunnerSettings,
+    /// Called with how many tasks are running while the program is checked, then with 0 once they're all done
+    pub progress: Option<fn(usize)>,
+    /// Cancels the compile when cancelled, like when an IDE's file changes before it finished
+    pub cancellation: CancellationToken,
+}
+
+/// Stops a running compile, cloned to be shared between the host and the compile
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    /// If the compile was cancelled
+    cancelled: Arc<AtomicBool>,
+    /// Woken when the compile is cancelled, so whatever is waiting on the compile can stop
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl CancellationToken {
+    /// Cancels the compile, which stops at the next point it checks the token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Some(waker) = self.waker.lock().take() {
+            waker.wake();
+        }
+    }
+
+    /// Checks if the compile was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        return self.cancelled.load(Ordering::Relaxed);
+    }
+
+    /// Sets the waker to wake when the compile is cancelled
+    pub fn register(&self, waker: &Waker) {
+        *self.waker.lock() = Some(waker.clone());
+    }
 }
 
 impl Arguments {
@@ -61,8 +190,15 @@ impl Arguments {
             },
             cpu_runtime: cpu_runtime.enable_time().thread_name("cpu-runtime").build().expect("Failed to build CPU runtime"),
             runner_settings,
+            progress: None,
+            cancellation: CancellationToken::default(),
         };
     }
+
+    /// The runtime to spawn IO tasks on, which is the CPU runtime if there's no IO runtime
+    pub fn io_or_cpu(&self) -> &Runtime {
+        return self.io_runtime.as_ref().unwrap_or(&self.cpu_runtime);
+    }
 }
 
 impl RunnerSettings {
@@ -77,8 +213,8 @@ impl RunnerSettings {
 
 /// A readable type
 pub trait Readable: Send {
-    /// Reads the readable to a list of tokens
-    fn read(&self) -> Vec<Token>;
+    /// Lazily tokenizes the readable, ending with the EOF token
+    fn read(&self) -> Box<dyn Iterator<Item = Token> + '_>;
 
     /// Gets the file's contents
     fn contents(&self) -> String;
@@ -99,10 +235,41 @@ pub trait SourceSet: Debug + Send + Sync {
 
     /// Clones the source set and boxes it
     fn cloned(&self) -> Box<dyn SourceSet>;
+
+    /// A human-readable label for the source set used in error messages, like its root path or <core>
+    fn name(&self) -> String;
 }
 
-/// A small type for translating external Raven types into Rust types
+/// A small type for translating external Raven types into Rust types.
+/// Translating copies everything out of Raven's memory, so Rust never owns memory allocated by Raven.
 pub trait RavenExtern {
+    /// The Raven type's layout in memory
     type Input;
-    unsafe fn translate(raven_type: *mut Self::Input) -> Self;
+
+    /// Copies the Raven value into a Rust value, leaving the Raven memory untouched
+    unsafe fn translate_borrowed(raven_type: *const Self::Input) -> Self;
+
+    /// Frees the Raven value and everything it points to that was allocated in the arena.
+    /// Values without pointers in them only free themselves.
+    unsafe fn free(raven_type: *mut Self::Input, arena: &Arena) {
+        arena.free(raven_type as *const u8);
+    }
+
+    /// Translates the Raven value, then frees it from the arena
+    unsafe fn translate(raven_type: *mut Self::Input, arena: &Arena) -> Self
+    where
+        Self: Sized,
+    {
+        let output = Self::translate_borrowed(raven_type);
+        Self::free(raven_type, arena);
+        return output;
+    }
+}
+
+/// Translates Rust types into Raven types, the reverse of RavenExtern.
+/// The Raven value is allocated with arena_malloc, the same as Raven's own values, so Raven code can hold onto it
+/// until the arena it was made in is cleared.
+pub trait ToRaven: RavenExtern {
+    /// Copies the Rust value into a new Raven value, returning the pointer Raven functions take it as
+    unsafe fn to_raven(&self) -> *mut Self::Input;
 }
@@ -37,7 +37,8 @@ impl Token {
         while buffer[end] == b' ' || buffer[end] == b'\t' || buffer[end] == b'\r' || buffer[end] == b'\n' && start < end {
             end -= 1;
         }
-        return String::from_utf8_lossy(&buffer[start..=end]).to_string();
+        // Multi-byte whitespace, like non-breaking spaces, is trimmed once it's decoded
+        return String::from_utf8_lossy(&buffer[start..=end]).trim().to_string();
     }
 }
 
@@ -95,6 +96,15 @@ impl Span {
     pub fn extend_span(&mut self, end: usize) {
         self.end = end;
     }
+
+    /// Merges two spans into one span covering both of them.
+    /// Spans from different files can't be merged, so the span with a file is kept.
+    pub fn merge(a: Span, b: Span) -> Span {
+        if a.file != b.file {
+            return if a.file == 0 { b } else { a };
+        }
+        return Span { file: a.file, start: a.start.min(b.start), end: a.end.max(b.end) };
+    }
 }
 
 /// The different types of tokens.
@@ -250,4 +260,10 @@ pub enum TokenTypes {
     Char = 70,
     /// A blank line
     BlankLine = 71,
+    /// The extends keyword, between a struct's name and its parent
+    Extends = 72,
+    /// A loop label, like 'outer
+    Label = 73,
+    /// The continue keyword
+    Continue = 74,
 }
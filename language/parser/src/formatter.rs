@@ -0,0 +1,299 @@
+use std::mem;
+
+use anyhow::Error;
+use data::tokens::{Token, TokenTypes};
+
+use crate::tokens::tokenizer::tokenize;
+
+/// Operators that get a space on each side when they come after a value, like a + b or a == b.
+/// Less than and greater than are left as they're written, since generics like Pointer<T> use them too.
+const BINARY_OPERATORS: [&str; 22] = [
+    "+", "-", "*", "/", "%", "^", "&", "=", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=", "%=", "&=", "|=",
+    "^=",
+];
+
+/// A token's text, split from the whitespace before it
+struct Piece {
+    /// The type of the token
+    token_type: TokenTypes,
+    /// The whitespace between the last piece and this one
+    gap: String,
+    /// The token's text without the whitespace around it
+    body: String,
+    /// If the piece is inside a string, so its text is kept exactly as it's written
+    raw: bool,
+}
+
+/// Formats Raven code from its tokens instead of the parsed code, so comments and literals are kept as they're written.
+/// Code is indented by four spaces per bracket, operators are spaced, argument lists split over multiple lines get
+/// trailing commas, and top elements are separated by a blank line.
+/// Errors instead of formatting if the code has invalid characters, or if the formatted code's tokens are different.
+pub fn format_source(source: &str) -> Result<String, Error> {
+    let mut pieces = split_pieces(source)?;
+    add_trailing_commas(&mut pieces);
+    let formatted = format_pieces(&pieces);
+    if let Some(line) = changed_line(source, &formatted) {
+        return Err(Error::msg(format!("Formatting changed the tokens at line {}, so the file was left alone", line)));
+    }
+    return Ok(formatted);
+}
+
+/// Splits the source into a piece for each token that isn't only whitespace
+fn split_pieces(source: &str) -> Result<Vec<Piece>, Error> {
+    let mut pieces = Vec::default();
+    let mut gap = String::default();
+    let mut in_string = false;
+    for token in tokenize(source.as_bytes()) {
+        if token.token_type == TokenTypes::InvalidCharacters {
+            return Err(Error::msg(format!("Invalid characters at line {}", token.end.0)));
+        }
+        let text = source
+            .get(token.start_offset..token.end_offset)
+            .ok_or_else(|| Error::msg(format!("The token at line {} ends partway through a character", token.end.0)))?;
+
+        // Whitespace in strings is part of the string, so it's kept with the text
+        if in_string {
+            in_string = token.token_type != TokenTypes::StringEnd;
+            let body = mem::take(&mut gap) + text;
+            pieces.push(Piece { token_type: token.token_type, gap: String::default(), body, raw: true });
+            continue;
+        }
+
+        let body = text.trim();
+        if body.is_empty() {
+            gap.push_str(text);
+            continue;
+        }
+        let start = text.len() - text.trim_start().len();
+        gap.push_str(&text[..start]);
+        pieces.push(Piece { token_type: token.token_type, gap: mem::take(&mut gap), body: body.to_string(), raw: false });
+        // Some tokens end with the whitespace after them, like impl in impl Type
+        gap.push_str(&text[start + body.len()..]);
+        in_string = token.token_type == TokenTypes::StringStart;
+    }
+    return Ok(pieces);
+}
+
+/// Adds a trailing comma to every argument list that ends on its own line
+fn add_trailing_commas(pieces: &mut Vec<Piece>) {
+    // Whether each open parenthesis starts an argument list, instead of grouping like (a + b)
+    let mut arguments = Vec::default();
+    let mut index = 0;
+    while index < pieces.len() {
+        let piece = &pieces[index];
+        if piece.raw {
+            index += 1;
+            continue;
+        }
+        match piece.token_type {
+            TokenTypes::ArgumentsStart => arguments.push(true),
+            TokenTypes::ParenOpen => arguments.push(index > 0 && piece.gap.is_empty() && is_callable(&pieces[index - 1])),
+            TokenTypes::ParenClose | TokenTypes::ArgumentsEnd => {
+                if arguments.pop().unwrap_or(false) && piece.gap.contains('\n') {
+                    let last = (0..index).rev().find(|last| pieces[*last].token_type != TokenTypes::Comment).unwrap();
+                    if pieces[last].body != "," && pieces[last].body != "(" {
+                        let comma = Piece {
+                            token_type: TokenTypes::ArgumentEnd,
+                            gap: String::default(),
+                            body: ",".to_string(),
+                            raw: false,
+                        };
+                        pieces.insert(last + 1, comma);
+                        index += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+}
+
+/// Checks if a parenthesis right after the piece is a call, like in method() or none<u64>()
+fn is_callable(piece: &Piece) -> bool {
+    return match piece.token_type {
+        TokenTypes::Variable | TokenTypes::CallingType => true,
+        TokenTypes::Operator => piece.body == ">",
+        _ => false,
+    };
+}
+
+/// Writes the pieces back out with canonical whitespace between them
+fn format_pieces(pieces: &[Piece]) -> String {
+    let operators = binary_operators(pieces);
+    let mut output = String::default();
+    // The indent of the line each open bracket is on, which the line closing it goes back to
+    let mut brackets: Vec<usize> = Vec::default();
+    let mut indent = 0;
+    for (index, piece) in pieces.iter().enumerate() {
+        if index > 0 && !piece.raw {
+            let last = &pieces[index - 1];
+            let newlines = piece.gap.matches('\n').count();
+            let closing = piece.body.starts_with(['}', ')', ']']);
+            let top_element = brackets.is_empty()
+                && match last.token_type {
+                    TokenTypes::CodeEnd | TokenTypes::StructEnd => true,
+                    TokenTypes::ImportEnd => piece.token_type != TokenTypes::ImportStart,
+                    _ => false,
+                }
+                && (newlines > 0 || piece.token_type != TokenTypes::Comment);
+
+            if top_element || newlines > 0 {
+                // Blank lines are kept, but only one, and never right inside brackets
+                let blank = top_element || newlines > 1 && !last.body.ends_with(['{', '(', '[']) && !closing;
+                output.push_str(if blank { "\n\n" } else { "\n" });
+                indent = match brackets.last() {
+                    Some(opened) if closing => *opened,
+                    Some(opened) => opened + 1,
+                    None => 0,
+                };
+                // Chained calls on their own lines are indented past the start of the chain
+                if piece.token_type == TokenTypes::Period
+                    && pieces.get(index + 1).map_or(false, |next| next.token_type == TokenTypes::CallingType)
+                {
+                    indent += 1;
+                }
+                output.push_str(&"    ".repeat(indent));
+            } else if spaced(pieces, &operators, index) {
+                output.push(' ');
+            }
+        }
+        output.push_str(&piece.body);
+
+        if !piece.raw && piece.token_type != TokenTypes::Comment && piece.token_type != TokenTypes::Char {
+            for character in piece.body.chars() {
+                match character {
+                    '{' | '(' | '[' => brackets.push(indent),
+                    // Brackets opened after this on the same line are indented from the line the closed one opened on
+                    '}' | ')' | ']' => indent = brackets.pop().map_or(indent, |opened| opened.min(indent)),
+                    _ => {}
+                }
+            }
+        }
+    }
+    if !pieces.is_empty() {
+        output.push('\n');
+    }
+    return output;
+}
+
+/// Finds the binary operators, returning if each piece starts one and if each piece ends one.
+/// Operators like == are split into a token per character, so the tokens written next to each other are joined.
+fn binary_operators(pieces: &[Piece]) -> Vec<(bool, bool)> {
+    let mut operators = vec![(false, false); pieces.len()];
+    let mut index = 0;
+    while index < pieces.len() {
+        if !is_operator(&pieces[index]) {
+            index += 1;
+            continue;
+        }
+        let mut end = index;
+        while end + 1 < pieces.len() && is_operator(&pieces[end + 1]) && pieces[end + 1].gap.is_empty() {
+            end += 1;
+        }
+        let operator = pieces[index..=end].iter().map(|piece| piece.body.as_str()).collect::<String>();
+        // Operators after anything but a value are prefixes, like -1 or !value
+        if index > 0 && is_value(&pieces[index - 1]) && BINARY_OPERATORS.contains(&operator.as_str()) {
+            operators[index].0 = true;
+            operators[end].1 = true;
+        }
+        index = end + 1;
+    }
+    return operators;
+}
+
+/// Checks if the piece is part of an operator in code
+fn is_operator(piece: &Piece) -> bool {
+    return !piece.raw && (piece.token_type == TokenTypes::Operator || piece.token_type == TokenTypes::Equals);
+}
+
+/// Checks if the piece ends a value, so an operator after it is between two values
+fn is_value(piece: &Piece) -> bool {
+    return matches!(
+        piece.token_type,
+        TokenTypes::Variable
+            | TokenTypes::Integer
+            | TokenTypes::Float
+            | TokenTypes::ParenClose
+            | TokenTypes::CallingType
+            | TokenTypes::True
+            | TokenTypes::False
+            | TokenTypes::StringEnd
+            | TokenTypes::Char
+    );
+}
+
+/// Checks if there's a space between the piece and the one before it, when they're on the same line
+fn spaced(pieces: &[Piece], operators: &[(bool, bool)], index: usize) -> bool {
+    let piece = &pieces[index];
+    let last = &pieces[index - 1];
+    if operators[index].0 || operators[index - 1].1 {
+        return true;
+    }
+    if matches!(piece.body.as_str(), ";" | "," | ")" | "]")
+        || matches!(
+            piece.token_type,
+            TokenTypes::CallingType | TokenTypes::Colon | TokenTypes::ArgumentTypeSeparator | TokenTypes::FieldSeparator
+        )
+    {
+        return false;
+    }
+    // Periods are only attached to values, so the .. in { ..other } keeps its space
+    if piece.token_type == TokenTypes::Period && is_value(last) {
+        return false;
+    }
+    if last.body.ends_with(['(', '[']) || last.token_type == TokenTypes::Period {
+        return false;
+    }
+    // Paths like Pointer<T>::malloc_size have a token per colon, and the name after them stays attached
+    if index > 1
+        && last.token_type == TokenTypes::Colon
+        && last.gap.is_empty()
+        && pieces[index - 2].token_type == TokenTypes::Colon
+    {
+        return false;
+    }
+    if piece.body == "{"
+        || last.body == ","
+        || matches!(last.token_type, TokenTypes::Colon | TokenTypes::ArgumentTypeSeparator | TokenTypes::FieldSeparator)
+    {
+        return true;
+    }
+    return !piece.gap.is_empty();
+}
+
+/// Finds the first line where the formatted code's tokens are different from the source's, ignoring added trailing commas
+fn changed_line(source: &str, formatted: &str) -> Option<u32> {
+    let source_tokens = significant_tokens(source);
+    let formatted_tokens = significant_tokens(formatted);
+    for (index, (line, token_type, text)) in source_tokens.iter().enumerate() {
+        match formatted_tokens.get(index) {
+            Some((_, found_type, found_text)) if found_type == token_type && found_text == text => {}
+            _ => return Some(*line),
+        }
+    }
+    return formatted_tokens.get(source_tokens.len()).map(|(line, _, _)| *line);
+}
+
+/// Gets the line, type and text of every token that isn't only whitespace, except commas right before a )
+fn significant_tokens(source: &str) -> Vec<(u32, TokenTypes, String)> {
+    let tokens = tokenize(source.as_bytes())
+        .map(|token| (token.end.0, token.token_type, token_text(source, &token)))
+        .filter(|(_, _, text)| !text.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut output = Vec::default();
+    for (index, token) in tokens.iter().enumerate() {
+        let next = tokens[index + 1..].iter().find(|(_, token_type, _)| *token_type != TokenTypes::Comment);
+        if token.2 == "," && next.map_or(false, |(_, _, text)| text == ")") {
+            continue;
+        }
+        output.push(token.clone());
+    }
+    return output;
+}
+
+/// Gets the text of a token without the whitespace around it
+fn token_text(source: &str, token: &Token) -> String {
+    return source.get(token.start_offset..token.end_offset).unwrap_or_default().trim().to_string();
+}
@@ -5,22 +5,24 @@ extern crate core;
 use indexmap::IndexMap;
 use parking_lot::Mutex;
 use std::collections::hash_map::DefaultHasher;
+use std::fs;
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::{fs, path};
 
 use anyhow::Error;
 
 use data::tokens::{Token, TokenTypes};
 use data::{Readable, SourceSet};
 use syntax::async_util::{HandleWrapper, NameResolver, UnparsedType};
-use syntax::program::syntax::Syntax;
+use syntax::program::syntax::{SourceFile, Syntax};
 
 use crate::parser::top_parser::parse_top;
 use crate::parser::util::ParserUtils;
-use crate::tokens::tokenizer::Tokenizer;
+use crate::tokens::tokenizer::{tokenize, tokenize_owned};
 
+/// The Raven formatter, which formats code from its tokens
+pub mod formatter;
 /// The Raven parser
 pub mod parser;
 /// The Raven tokenizer
@@ -29,20 +31,48 @@ pub mod tokens;
 /// Parses a file into the syntax
 pub async fn parse(syntax: Arc<Mutex<Syntax>>, handle: Arc<Mutex<HandleWrapper>>, name: String, file: Box<dyn Readable>) {
     let buffer = file.contents();
+    // Spans index into the tokens, so parsing needs all of them
+    let tokens = file.read().collect::<Vec<_>>();
+    {
+        // Recorded even without debug info, since the language server finds files by path
+        let positions = tokens.iter().map(|token| token.start).collect();
+        let source = SourceFile { path: file.path(), positions, blocks: find_blocks(&tokens) };
+        syntax.lock().source_files.insert(file.hash(), source);
+    }
+
     let mut parser_utils = ParserUtils {
         buffer: buffer.as_bytes(),
         index: 0,
-        tokens: file.read(),
+        tokens,
         syntax,
         file: file.hash(),
         file_name: name.clone(),
         imports: ImportNameResolver::new(name.clone()),
         handle,
+        loops: Vec::default(),
     };
 
     parse_top(&mut parser_utils);
 }
 
+/// Finds the first and last token of every code block, in the order they end
+fn find_blocks(tokens: &[Token]) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::default();
+    let mut opened = Vec::default();
+    for (index, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenTypes::CodeStart | TokenTypes::BlockStart => opened.push(index),
+            TokenTypes::CodeEnd | TokenTypes::BlockEnd => {
+                if let Some(start) = opened.pop() {
+                    blocks.push((start, index));
+                }
+            }
+            _ => {}
+        }
+    }
+    return blocks;
+}
+
 /// Basic name resolver implementation
 #[derive(Clone)]
 pub struct ImportNameResolver {
@@ -85,11 +115,29 @@ impl NameResolver for ImportNameResolver {
     }
 }
 
-/// A simple source set of a single file/folder
+/// A simple source set of a single file/folder, or of every file matching a glob
 #[derive(Clone, Debug)]
 pub struct FileSourceSet {
-    /// The path of the file/folder
+    /// The path of the file/folder, or the folder every matched file is in for globs
     pub root: PathBuf,
+    /// The files matched by a glob, or None if every file in the root is included
+    pub files: Option<Vec<PathBuf>>,
+}
+
+impl FileSourceSet {
+    /// Creates a source set of a single file/folder
+    pub fn new(root: PathBuf) -> Self {
+        return Self { root, files: None };
+    }
+
+    /// Creates a source set of every Raven file matching the glob, like src/**/*.rv
+    pub fn new_glob(pattern: &str) -> Result<Self, glob::PatternError> {
+        let files = glob::glob(pattern)?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file() && path.extension().map_or(false, |extension| extension == "rv"))
+            .collect::<Vec<_>>();
+        return Ok(Self { root: common_folder(&files), files: Some(files) });
+    }
 }
 
 /// A wrapper around the PathBuf type, used for implementing traits on it
@@ -105,18 +153,8 @@ impl Hash for FilePath {
 }
 
 impl Readable for FilePath {
-    fn read(&self) -> Vec<Token> {
-        let binding = self.contents();
-        let mut tokenizer = Tokenizer::new(binding.as_bytes());
-        let mut tokens = Vec::default();
-        loop {
-            tokens.push(tokenizer.next());
-            if tokens.last().unwrap().token_type == TokenTypes::EOF {
-                break;
-            }
-        }
-
-        return tokens;
+    fn read(&self) -> Box<dyn Iterator<Item = Token> + '_> {
+        return Box::new(tokenize_owned(self.contents()));
     }
 
     fn contents(&self) -> String {
@@ -137,6 +175,10 @@ impl Readable for FilePath {
 
 impl SourceSet for FileSourceSet {
     fn get_files(&self) -> Vec<Box<dyn Readable>> {
+        if let Some(files) = &self.files {
+            return files.iter().map(|path| Box::new(FilePath { path: path.clone() }) as Box<dyn Readable>).collect();
+        }
+
         let mut output = Vec::default();
         read_recursive(self.root.clone(), &mut output)
             .unwrap_or_else(|_| panic!("Failed to read source files! Make sure {:?} exists", self.root));
@@ -144,19 +186,39 @@ impl SourceSet for FileSourceSet {
     }
 
     fn relative(&self, other: &dyn Readable) -> String {
-        let name =
-            other.path().replace(self.root.to_str().unwrap(), "").replace(path::MAIN_SEPARATOR, "::").replace('/', "::");
-        if name.len() == 0 {
-            let path = other.path();
-            let name: &str = path.split(path::MAIN_SEPARATOR).last().unwrap();
-            return name[0..name.len() - 3].to_string();
+        let path = PathBuf::from(other.path());
+        let mut relative = path.strip_prefix(&self.root).unwrap_or(&path);
+        // If the root is the file itself, just use the file's name
+        if relative.as_os_str().is_empty() {
+            relative = Path::new(path.file_name().unwrap());
         }
-        return name.as_str()[2..name.len() - 3].to_string();
+        return relative
+            .with_extension("")
+            .components()
+            .map(|component| component.as_os_str().to_str().unwrap())
+            .collect::<Vec<_>>()
+            .join("::");
     }
 
     fn cloned(&self) -> Box<dyn SourceSet> {
         return Box::new(self.clone());
     }
+
+    fn name(&self) -> String {
+        return self.root.to_str().unwrap().to_string();
+    }
+}
+
+/// Finds the deepest folder that every path is inside of
+fn common_folder(paths: &Vec<PathBuf>) -> PathBuf {
+    let mut folder = match paths.first() {
+        Some(first) => first.parent().map(Path::to_path_buf).unwrap_or_default(),
+        None => return PathBuf::default(),
+    };
+    for path in paths {
+        while !path.starts_with(&folder) && folder.pop() {}
+    }
+    return folder;
 }
 
 /// Recursively reads a folder/file into the list of files
@@ -171,3 +233,80 @@ fn read_recursive(base: PathBuf, output: &mut Vec<Box<dyn Readable>>) -> Result<
     }
     return Ok(());
 }
+
+/// A source set of files kept in memory, for running Raven code that isn't on disk
+#[derive(Clone, Debug)]
+pub struct StringSourceSet {
+    /// The label of the set, shown as <name> in errors
+    pub name: String,
+    /// Each file's path, like util/math.rv, and its contents
+    pub files: Vec<(String, String)>,
+}
+
+impl StringSourceSet {
+    /// Creates an empty source set
+    pub fn new(name: String) -> Self {
+        return Self { name, files: Vec::default() };
+    }
+
+    /// Adds a file to the set, named like util::math
+    pub fn add(&mut self, name: &str, contents: String) {
+        self.files.push((format!("{}.rv", name.replace("::", "/")), contents));
+    }
+}
+
+impl SourceSet for StringSourceSet {
+    fn get_files(&self) -> Vec<Box<dyn Readable>> {
+        return self
+            .files
+            .iter()
+            .map(|(path, contents)| {
+                Box::new(StringFile { set: self.name.clone(), path: path.clone(), contents: contents.clone() })
+                    as Box<dyn Readable>
+            })
+            .collect();
+    }
+
+    fn relative(&self, other: &dyn Readable) -> String {
+        let path = other.path();
+        return path.strip_suffix(".rv").unwrap_or(&path).replace('/', "::");
+    }
+
+    fn cloned(&self) -> Box<dyn SourceSet> {
+        return Box::new(self.clone());
+    }
+
+    fn name(&self) -> String {
+        return format!("<{}>", self.name);
+    }
+}
+
+/// A file kept in memory
+pub struct StringFile {
+    /// The name of the set the file is in, so files with the same path in different sets don't collide
+    pub set: String,
+    /// The path of the file
+    pub path: String,
+    /// The file's contents
+    pub contents: String,
+}
+
+impl Readable for StringFile {
+    fn read(&self) -> Box<dyn Iterator<Item = Token> + '_> {
+        return Box::new(tokenize(self.contents.as_bytes()));
+    }
+
+    fn contents(&self) -> String {
+        return self.contents.clone();
+    }
+
+    fn path(&self) -> String {
+        return self.path.clone();
+    }
+
+    fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::default();
+        Hash::hash(&(&self.set, &self.path), &mut hasher);
+        return hasher.finish();
+    }
+}
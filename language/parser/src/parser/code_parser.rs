@@ -1,5 +1,5 @@
 use crate::parser::control_parser::{parse_do_while, parse_for, parse_if, parse_while};
-use crate::parser::operator_parser::parse_operator;
+use crate::parser::operator_parser::{parse_operator, parse_range};
 use crate::parser::util::{parse_generics, ParserUtils};
 use data::tokens::{Span, Token, TokenTypes};
 use std::mem;
@@ -73,6 +73,8 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
         parser_utils.index += 1;
         if effect.is_some() {
             match token.token_type {
+                // The end of a struct literal ends the value of its last field
+                TokenTypes::BlockEnd if state == ParseState::New || state == ParseState::InOperator => {}
                 TokenTypes::Float
                 | TokenTypes::Integer
                 | TokenTypes::Char
@@ -107,6 +109,10 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                 ControlFlow::Returning(returning) => return Ok(Some(returning)),
                 ControlFlow::Skipping => continue,
                 ControlFlow::Finish => break,
+                ControlFlow::FinishBefore => {
+                    parser_utils.index = span.start;
+                    break;
+                }
                 ControlFlow::NotFound => {}
             }
         }
@@ -173,13 +179,13 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                 return Ok(Some(Expression::new(expression_type, expression.effect)));
             }
             TokenTypes::For => {
-                return Ok(Some(Expression::new(expression_type, parse_for(parser_utils)?)));
+                return Ok(Some(Expression::new(expression_type, parse_for(parser_utils, None)?)));
             }
             TokenTypes::While => {
-                return Ok(Some(Expression::new(expression_type, parse_while(parser_utils)?)));
+                return Ok(Some(Expression::new(expression_type, parse_while(parser_utils, None)?)));
             }
             TokenTypes::Do => {
-                return Ok(Some(Expression::new(expression_type, parse_do_while(parser_utils)?)));
+                return Ok(Some(Expression::new(expression_type, parse_do_while(parser_utils, None)?)));
             }
             TokenTypes::Equals => {
                 let other = parser_utils.tokens.get(parser_utils.index).unwrap().token_type.clone();
@@ -205,6 +211,10 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                     }
                 }
             }
+            // A | where a value is expected starts a lambda, since it can't be a binary operator
+            TokenTypes::Operator if effect.is_none() && token.to_string(parser_utils.buffer) == "|" => {
+                effect = Some(parse_lambda(parser_utils, &span)?);
+            }
             TokenTypes::Operator => {
                 let last = parser_utils.tokens.get(parser_utils.index - 2).unwrap();
                 // If there is a variable right next to a less than, it's probably a generic method call.
@@ -258,6 +268,8 @@ enum ControlFlow {
     NotFound,
     Skipping,
     Finish,
+    /// Finishes the line before the token, leaving it for the caller to parse
+    FinishBefore,
     Returning(Expression),
 }
 
@@ -271,7 +283,8 @@ fn parse_basic_line(line_info: &mut LineParseInfo) -> Result<ControlFlow, Parsin
     let (span, token, state) = (line_info.span, line_info.token, line_info.state);
 
     return Ok(match line_info.token.token_type {
-        TokenTypes::BlockEnd if *line_info.state == ParseState::New => ControlFlow::Finish,
+        // Left for the struct literal to end on
+        TokenTypes::BlockEnd if *state == ParseState::New || *state == ParseState::InOperator => ControlFlow::FinishBefore,
         TokenTypes::Return => {
             *expression_type = ExpressionType::Return(*span);
             ControlFlow::Skipping
@@ -284,9 +297,11 @@ fn parse_basic_line(line_info: &mut LineParseInfo) -> Result<ControlFlow, Parsin
             ControlFlow::Skipping
         }
         TokenTypes::Integer => {
+            // Literals too big for an i64, like u64::MAX, are kept unsigned
+            let value: u64 = token.to_string(parser_utils.buffer).parse().unwrap();
             *effect = Some(Effects::new(
                 Span::new(parser_utils.file, parser_utils.index),
-                EffectType::Int(token.to_string(parser_utils.buffer).parse().unwrap()),
+                i64::try_from(value).map_or(EffectType::UInt(value), EffectType::Int),
             ));
             ControlFlow::Skipping
         }
@@ -319,9 +334,18 @@ fn parse_basic_line(line_info: &mut LineParseInfo) -> Result<ControlFlow, Parsin
             }
             ControlFlow::Returning(Expression::new(expression_type, expression.effect))
         }
-        TokenTypes::For => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_for(parser_utils)?)),
-        TokenTypes::While => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_while(parser_utils)?)),
-        TokenTypes::Do => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_do_while(parser_utils)?)),
+        TokenTypes::For => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_for(parser_utils, None)?)),
+        TokenTypes::While => {
+            ControlFlow::Returning(Expression::new(expression_type.clone(), parse_while(parser_utils, None)?))
+        }
+        TokenTypes::Do => {
+            ControlFlow::Returning(Expression::new(expression_type.clone(), parse_do_while(parser_utils, None)?))
+        }
+        TokenTypes::Label => {
+            ControlFlow::Returning(Expression::new(expression_type.clone(), parse_labeled_loop(parser_utils, token, span)?))
+        }
+        TokenTypes::Break => ControlFlow::Returning(parse_loop_jump(parser_utils, span, false)?),
+        TokenTypes::Continue => ControlFlow::Returning(parse_loop_jump(parser_utils, span, true)?),
         TokenTypes::LineEnd | TokenTypes::ParenClose | TokenTypes::ArgumentEnd => {
             parser_utils.index -= 1;
             ControlFlow::Finish
@@ -329,16 +353,14 @@ fn parse_basic_line(line_info: &mut LineParseInfo) -> Result<ControlFlow, Parsin
         TokenTypes::Comment => ControlFlow::Skipping,
         TokenTypes::Period => {
             if parser_utils.tokens[parser_utils.index].token_type == TokenTypes::Period {
-                let mut temp = None;
-                mem::swap(&mut temp, effect);
-                let operator = parse_operator(temp, parser_utils, &state)?;
-                // Operators inside operators return immediately so operators can be combined
-                // later on for operators like [].
+                // Ranges bind looser than operators, so 1 + 2..5 ranges from 3.
+                // An operator's right side ends before the range, which is then parsed around the whole operator.
                 if ParseState::InOperator == *state || ParseState::ControlOperator == *state {
-                    return Ok(ControlFlow::Returning(Expression::new(expression_type.clone(), operator)));
-                } else {
-                    *effect = Some(operator);
+                    return Ok(ControlFlow::FinishBefore);
                 }
+                let mut temp = None;
+                mem::swap(&mut temp, effect);
+                *effect = Some(parse_range(temp, parser_utils, &state)?);
             }
             ControlFlow::Skipping
         }
@@ -346,6 +368,48 @@ fn parse_basic_line(line_info: &mut LineParseInfo) -> Result<ControlFlow, Parsin
     });
 }
 
+/// Parses a loop with a label before it, like 'outer: for i in 0..10 {}
+fn parse_labeled_loop(parser_utils: &mut ParserUtils, token: &Token, span: &Span) -> Result<Effects, ParsingError> {
+    let label = Some(token.to_string(parser_utils.buffer)[1..].to_string());
+    if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::Colon {
+        return Err(span.make_error(ParsingMessage::MisplacedLabel));
+    }
+    parser_utils.index += 2;
+    return match parser_utils.tokens[parser_utils.index - 1].token_type {
+        TokenTypes::For => parse_for(parser_utils, label),
+        TokenTypes::While => parse_while(parser_utils, label),
+        TokenTypes::Do => parse_do_while(parser_utils, label),
+        _ => Err(span.make_error(ParsingMessage::MisplacedLabel)),
+    };
+}
+
+/// Parses a break, which jumps to the end of the innermost loop or the loop with the given label,
+/// or a continue, which jumps to where that loop checks if it runs again
+fn parse_loop_jump(parser_utils: &mut ParserUtils, span: &Span, continuing: bool) -> Result<Expression, ParsingError> {
+    let keyword = if continuing { "continue" } else { "break" };
+    let label = if parser_utils.tokens[parser_utils.index].token_type == TokenTypes::Label {
+        parser_utils.index += 1;
+        Some(parser_utils.tokens[parser_utils.index - 1].to_string(parser_utils.buffer)[1..].to_string())
+    } else {
+        None
+    };
+
+    let found = match &label {
+        Some(label) => parser_utils.loops.iter().rev().find(|found| found.name.as_ref() == Some(label)),
+        None => parser_utils.loops.last(),
+    };
+    return match found {
+        Some(found) => {
+            let target = if continuing { found.next.clone() } else { found.end.clone() };
+            Ok(Expression::new(ExpressionType::Line, Effects::new(*span, EffectType::Jump(target))))
+        }
+        None => Err(span.make_error(match label {
+            Some(label) => ParsingMessage::UnknownLabel(label, keyword.to_string()),
+            None => ParsingMessage::OutsideLoop(keyword.to_string()),
+        })),
+    };
+}
+
 /// Parses a parenthesis open
 fn parse_paren_open(parser_utils: &mut ParserUtils, effect: &mut Option<Effects>, span: &Span) -> Result<(), ParsingError> {
     let last = parser_utils.tokens.get(parser_utils.index - 2).unwrap().clone();
@@ -353,6 +417,9 @@ fn parse_paren_open(parser_utils: &mut ParserUtils, effect: &mut Option<Effects>
         TokenTypes::Variable | TokenTypes::CallingType => {
             // Name of the method = the last token
             let name = last.to_string(parser_utils.buffer);
+            // The call's span starts at its name instead of the parenthesis
+            let mut span = *span;
+            span.extend_span_backwards(parser_utils.index - 2);
             let mut temp = None;
             mem::swap(&mut temp, effect);
             // The calling effect must be boxed if it exists.
@@ -363,7 +430,7 @@ fn parse_paren_open(parser_utils: &mut ParserUtils, effect: &mut Option<Effects>
                     get_effects(parser_utils)?,
                     vec![],
                 ),
-                span: *span,
+                span,
             });
         }
         // If it's not a method call, it's a parenthesized effect.
@@ -375,9 +442,8 @@ fn parse_paren_open(parser_utils: &mut ParserUtils, effect: &mut Option<Effects>
                 ));
                 parser_utils.index += 1;
             } else {
-                // TODO figure out if this actually ever triggers
-                //effect = None;
-                panic!("Unknown code path - report this!");
+                // Empty parenthesis, like (), have no value to call methods on or use
+                return Err(span.make_error(ParsingMessage::ExpectedEffect));
             }
         }
     }
@@ -386,6 +452,8 @@ fn parse_paren_open(parser_utils: &mut ParserUtils, effect: &mut Option<Effects>
 
 /// Parses tokens from the Raven code into a string
 fn parse_string(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
+    // The opening quote, which errors for unterminated strings point at
+    let opening = parser_utils.index - 1;
     let mut string = String::default(); //the string from the Raven code
 
     loop {
@@ -411,53 +479,49 @@ fn parse_string(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError>
                 // get the text from the Raven file starting at the last token up to the current escape character
                 let found = token.to_string(parser_utils.buffer);
 
-                // check if it a hex value, because if it is, then it will 4 characters long (\xAA)
-                let is_hex = found.len() >= 3 && &found[found.len() - 3..found.len() - 2] == "x";
-                let string_end = found.len() - (if is_hex { 4 } else { 2 });
+                // the text before the escape can't have a \ in it, so the first one starts the escape
+                let escape_start = found.find('\\').unwrap();
 
                 // add the text to the string, because this text is part of the string in the Raven Code
-                string += &found[0..string_end];
+                string += &found[0..escape_start];
 
-                // match the character after the \ to see what type of escape character it is
-                let index = if is_hex { found.len() - 3 } else { found.len() - 1 };
-                match &found[index..=index] {
-                    "n" => {
-                        string += "\n";
-                    }
-                    "t" => {
-                        string += "\t";
-                    }
-                    "r" => {
-                        string += "\r";
-                    }
-                    "\\" => {
-                        string += "\\";
-                    }
-                    "\'" => {
-                        string += "\'";
-                    }
-                    "\"" => {
-                        string += "\"";
-                    }
-                    "x" => {
-                        // Convert the hex to a character, and append it to the string
-                        string.push(
-                            u8::from_str_radix(&found[found.len() - 2..found.len()], 16).expect("Unexpected hex value")
-                                as char,
-                        );
-                    }
-                    _ => {
-                        // not a supported character
-                        panic!("Unexpected escape character: {}", parser_utils.buffer[token.end_offset - 1] as char)
-                    }
-                }
+                let escape = &found[escape_start + 1..];
+                string.push(parse_escape(escape).ok_or_else(|| {
+                    Span::new(parser_utils.file, parser_utils.index - 1)
+                        .make_error(ParsingMessage::InvalidEscape(escape.to_string()))
+                })?);
             }
             TokenTypes::StringStart => {} //the first token is always a StringStart, so skip this
+            TokenTypes::EOF => {
+                return Err(Span::new(parser_utils.file, opening).make_error(ParsingMessage::UnterminatedString))
+            }
             _ => panic!("How'd you get here? {:?}", token.token_type),
         }
     }
 }
 
+/// Gets the character an escape sequence (without the \) stands for, or None if it isn't a valid escape
+fn parse_escape(escape: &str) -> Option<char> {
+    return match escape {
+        "n" => Some('\n'),
+        "t" => Some('\t'),
+        "r" => Some('\r'),
+        "0" => Some('\0'),
+        "\\" => Some('\\'),
+        "\'" => Some('\''),
+        "\"" => Some('"'),
+        // Hex escapes are a single byte (ex. \xAA)
+        _ if escape.len() == 3 && escape.starts_with('x') => {
+            u8::from_str_radix(&escape[1..], 16).ok().map(|value| value as char)
+        }
+        // Unicode escapes are a codepoint (ex. \u{1F600})
+        _ if escape.starts_with("u{") && escape.ends_with('}') => {
+            u32::from_str_radix(&escape[2..escape.len() - 1], 16).ok().and_then(char::from_u32)
+        }
+        _ => None,
+    };
+}
+
 /// Parses a generic method call
 fn parse_generic_method(effect: Option<Effects>, parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
     let name = parser_utils.tokens[parser_utils.index - 2].to_string(parser_utils.buffer);
@@ -513,12 +577,57 @@ fn get_effects(parser_utils: &mut ParserUtils) -> Result<Vec<Effects>, ParsingEr
                 break;
             }
             parser_utils.index += 1;
+            // Allows a trailing comma before the closing paren
+            if parser_utils.tokens[parser_utils.index].token_type == TokenTypes::ParenClose {
+                break;
+            }
         }
     }
     parser_utils.index += 1;
     return Ok(effects);
 }
 
+/// Parses a lambda like |value: u64| value + 1, starting after the first |
+fn parse_lambda(parser_utils: &mut ParserUtils, span: &Span) -> Result<Effects, ParsingError> {
+    let mut arguments = Vec::default();
+    loop {
+        let token = parser_utils.tokens[parser_utils.index].clone();
+        parser_utils.index += 1;
+        match token.token_type {
+            TokenTypes::Variable => {
+                let name = token.to_string(parser_utils.buffer);
+                let type_token = &parser_utils.tokens[parser_utils.index + 1];
+                if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::Colon
+                    || type_token.token_type != TokenTypes::Variable
+                {
+                    return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedToken));
+                }
+                let types = UnparsedType::Basic(
+                    Span::new(parser_utils.file, parser_utils.index + 1),
+                    type_token.to_string(parser_utils.buffer),
+                );
+                parser_utils.index += 2;
+                arguments.push((name, types));
+            }
+            TokenTypes::ArgumentEnd | TokenTypes::Comment => {}
+            TokenTypes::Operator if token.to_string(parser_utils.buffer) == "|" => break,
+            _ => {
+                return Err(Span::new(parser_utils.file, parser_utils.index - 1).make_error(ParsingMessage::UnexpectedToken))
+            }
+        }
+    }
+
+    // The body ends at the end of the line or argument, which is left for the caller.
+    // Loops outside the lambda can't be broken out of from inside it
+    let loops = mem::take(&mut parser_utils.loops);
+    let body = parse_line(parser_utils, ParseState::None);
+    parser_utils.loops = loops;
+    return match body? {
+        Some(body) => Ok(Effects::new(*span, EffectType::Lambda(arguments, Box::new(body.effect)))),
+        None => Err(span.make_error(ParsingMessage::ExpectedEffect)),
+    };
+}
+
 /// Parses a let statement
 fn parse_let(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
     let name;
@@ -579,12 +688,20 @@ fn parse_new(parser_utils: &mut ParserUtils, span: &Span) -> Result<Effects, Par
         }
     }
 
-    return Ok(Effects::new(Span::new(parser_utils.file, type_token), EffectType::CreateStruct(types.unwrap(), values)));
+    let (fields, base) = values;
+    return Ok(Effects::new(
+        Span::new(parser_utils.file, type_token),
+        EffectType::CreateStruct(types.unwrap(), fields, base),
+    ));
 }
 
-/// Parses the arguments to a new struct effect
-fn parse_new_args(parser_utils: &mut ParserUtils, span: &Span) -> Result<Vec<(String, Effects)>, ParsingError> {
+/// Parses the arguments to a new struct effect, and the struct to copy the rest of the fields from if there is one
+fn parse_new_args(
+    parser_utils: &mut ParserUtils,
+    span: &Span,
+) -> Result<(Vec<(String, Effects)>, Option<Box<Effects>>), ParsingError> {
     let mut values = Vec::default();
+    let mut base = None;
     let mut name = String::default();
     loop {
         let token: &Token = &parser_utils.tokens[parser_utils.index];
@@ -596,6 +713,17 @@ fn parse_new_args(parser_utils: &mut ParserUtils, span: &Span) -> Result<Vec<(St
                 values.push((name, effect));
                 name = String::default();
             }
+            // The struct the other fields are copied from, like ..base
+            TokenTypes::Period if parser_utils.tokens[parser_utils.index].token_type == TokenTypes::Period => {
+                parser_utils.index += 1;
+                base = match parse_line(parser_utils, ParseState::New)? {
+                    Some(inner) => Some(Box::new(inner.effect)),
+                    None => return Err(span.make_error(ParsingMessage::ExpectedEffect)),
+                };
+                if parser_utils.tokens[parser_utils.index].token_type == TokenTypes::ArgumentEnd {
+                    parser_utils.index += 1;
+                }
+            }
             TokenTypes::BlockEnd | TokenTypes::ParenClose => break,
             TokenTypes::InvalidCharacters => {}
             TokenTypes::Comment => {}
@@ -603,7 +731,7 @@ fn parse_new_args(parser_utils: &mut ParserUtils, span: &Span) -> Result<Vec<(St
         }
     }
 
-    return Ok(values);
+    return Ok((values, base));
 }
 
 fn parse_new_arg_statement(parser_utils: &mut ParserUtils, span: &Span, name: &String) -> Result<Effects, ParsingError> {
@@ -6,6 +6,33 @@ use crate::parser::code_parser::{parse_code, parse_line, ParseState};
 use crate::ParserUtils;
 use data::tokens::{Span, TokenTypes};
 
+/// The labels of a loop the parser is inside of, which breaks and continues jump to
+pub struct LoopLabels {
+    /// The loop's label, if it has one, like outer for 'outer: while
+    pub name: Option<String>,
+    /// The label of the block after the loop
+    pub end: String,
+    /// The label of the block checking if the loop runs again
+    pub next: String,
+}
+
+/// Parses the body of a loop, reserving the given number of ids for its blocks first so breaks and continues
+/// inside the body know where the loop ends and where it checks its condition, which is the last reserved id.
+/// Returns the first reserved id and the body.
+fn parse_loop_body(
+    parser_utils: &mut ParserUtils,
+    label: Option<String>,
+    ids: u32,
+) -> Result<(u32, CodeBody), ParsingError> {
+    let id = parser_utils.imports.last_id;
+    parser_utils.imports.last_id += ids;
+
+    parser_utils.loops.push(LoopLabels { name: label, end: id.to_string() + "end", next: (id + ids - 1).to_string() });
+    let body = parse_code(parser_utils);
+    parser_utils.loops.pop();
+    return Ok((id, body?.1));
+}
+
 /// Parses an if statement into a single expression.
 pub fn parse_if(parser_utils: &mut ParserUtils) -> Result<Expression, ParsingError> {
     // Get the effect inside the if. The if token itself is already parsed, so next is whatever
@@ -93,7 +120,8 @@ pub fn parse_if(parser_utils: &mut ParserUtils) -> Result<Expression, ParsingErr
         returning = ExpressionType::Line;
     }
 
-    let adding = 1 + else_ifs.len() as u32 + else_body.is_some() as u32;
+    // One label for the top block and one for each else if check
+    let adding = 1 + else_ifs.len() as u32;
     parser_utils.imports.last_id += adding;
     return Ok(Expression::new(
         returning,
@@ -102,7 +130,7 @@ pub fn parse_if(parser_utils: &mut ParserUtils) -> Result<Expression, ParsingErr
 }
 
 /// Parses a for statement into a single expression
-pub fn parse_for(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
+pub fn parse_for(parser_utils: &mut ParserUtils, label: Option<String>) -> Result<Effects, ParsingError> {
     let name = &parser_utils.tokens[parser_utils.index];
     parser_utils.index += 1;
     // Gets the name of the for loop variable
@@ -133,15 +161,14 @@ pub fn parse_for(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError
     parser_utils.index += 1;
 
     // Parses the body of the for loop
-    let body = parse_code(parser_utils)?.1;
-    parser_utils.imports.last_id += 2;
+    let (id, body) = parse_loop_body(parser_utils, label, 2)?;
 
     // Returns the finished for loop.
-    return create_for(name, effect.unwrap().effect, body, parser_utils.imports.last_id - 2);
+    return create_for(name, effect.unwrap().effect, body, id);
 }
 
 /// Parses a while statement into a single expression
-pub fn parse_while(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
+pub fn parse_while(parser_utils: &mut ParserUtils, label: Option<String>) -> Result<Effects, ParsingError> {
     let effect = parse_line(parser_utils, ParseState::ControlVariable)?;
     if effect.is_none() {
         return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedVoid));
@@ -153,20 +180,19 @@ pub fn parse_while(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingErr
 
     parser_utils.index += 1;
 
-    let (_returning, body) = parse_code(parser_utils)?;
-    parser_utils.imports.last_id += 1;
-    return create_while(effect.unwrap().effect, body, parser_utils.imports.last_id - 1);
+    let (id, body) = parse_loop_body(parser_utils, label, 1)?;
+    return create_while(effect.unwrap().effect, body, id);
 }
 
 /// Parses a do while into a single expression
-pub fn parse_do_while(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
+pub fn parse_do_while(parser_utils: &mut ParserUtils, label: Option<String>) -> Result<Effects, ParsingError> {
     if parser_utils.tokens.get(parser_utils.index).unwrap().token_type != TokenTypes::BlockStart {
         return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedVoid));
     }
 
     parser_utils.index += 1;
 
-    let (_returning, body) = parse_code(parser_utils)?;
+    let (id, body) = parse_loop_body(parser_utils, label, 2)?;
 
     if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::While {
         return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::ExpectedWhile));
@@ -179,24 +205,30 @@ pub fn parse_do_while(parser_utils: &mut ParserUtils) -> Result<Effects, Parsing
         return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedVoid));
     }
 
-    parser_utils.imports.last_id += 1;
-    return create_do_while(effect.unwrap().effect, body, parser_utils.imports.last_id - 1);
+    return create_do_while(effect.unwrap().effect, body, id);
 }
 
-/// Creates a do while effect from the body and the condition
+/// Creates a do while effect from the body and the condition.
+/// The condition is checked in its own block, id + 1, so continues can jump to it.
 fn create_do_while(effect: Effects, mut body: CodeBody, id: u32) -> Result<Effects, ParsingError> {
     let mut top = Vec::default();
 
-    let label = body.label.clone();
-    body.expressions.push(Expression::new(
-        ExpressionType::Line,
-        Effects::new(Span::default(), EffectType::Jump((id - 1).to_string() + "end")),
-    ));
+    let check = CodeBody::new(
+        vec![Expression::new(
+            ExpressionType::Line,
+            Effects::new(
+                Span::default(),
+                EffectType::CompareJump(Box::new(effect), body.label.clone(), id.to_string() + "end"),
+            ),
+        )],
+        (id + 1).to_string(),
+    );
+    body.expressions
+        .push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::Jump(check.label.clone()))));
+    // The body runs once before the condition is checked
+    top.push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::Jump(body.label.clone()))));
     top.push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::CodeBody(body))));
-    top.push(Expression::new(
-        ExpressionType::Line,
-        Effects::new(Span::default(), EffectType::CompareJump(Box::new(effect), label, id.to_string() + "end")),
-    ));
+    top.push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::CodeBody(check))));
 
     return Ok(Effects::new(Span::default(), EffectType::CodeBody(CodeBody::new(top, id.to_string()))));
 }
@@ -216,93 +248,67 @@ fn create_while(effect: Effects, mut body: CodeBody, id: u32) -> Result<Effects,
     return Ok(Effects::new(Span::default(), EffectType::CodeBody(CodeBody::new(top, id.to_string()))));
 }
 
-/// Creates an if statement from the body, the effect, the else ifs, and the else body
+/// Creates an if statement from the body, the effect, the else ifs, and the else body.
+/// Else ifs are flattened into a chain of checks inside the top block instead of nesting each one inside
+/// the previous else, so every branch jumps to the same end label.
+/// ex:
+/// if a {} else if b {} else {}
+/// becomes
+/// id: CompareJump(a, if_body, id + 1), if_body, id + 1: CompareJump(b, else_if_body, else_body), else_if_body, else_body
 fn create_if(
     effect: Effects,
-    body: CodeBody,
-    mut else_ifs: Vec<(Effects, CodeBody)>,
+    mut body: CodeBody,
+    else_ifs: Vec<(Effects, CodeBody)>,
     else_body: Option<CodeBody>,
     id: u32,
 ) -> Result<Effects, ParsingError> {
-    let mut body = body;
+    let end = id.to_string() + "end";
 
-    // Maps the else body, if there is an else_if there needs to be an empty else to put the else if into.
-    let mut else_body = if let Some(mut body) = else_body {
-        body.expressions.push(Expression::new(
-            ExpressionType::Line,
-            Effects::new(Span::default(), EffectType::Jump(id.to_string() + "end")),
-        ));
-        Some(body)
-    } else if !else_ifs.is_empty() {
-        Some(CodeBody::new(Vec::default(), id.to_string()))
-    } else {
-        None
-    };
-
-    let mut temp = id + 1;
-    // Add every else if statement
-    while !else_ifs.is_empty() {
-        let (effect, mut body) = else_ifs.remove(0);
-        body.expressions.push(Expression::new(
-            ExpressionType::Line,
-            Effects::new(Span::default(), EffectType::Jump(id.to_string() + "end")),
-        ));
-        // Creates the body of the else if by adding another if statement to the top of the else.
-        let inner = CodeBody::new(
-            vec![
-                Expression::new(
-                    ExpressionType::Line,
-                    Effects::new(
-                        Span::default(),
-                        EffectType::CompareJump(
-                            Box::new(effect),
-                            body.label.clone(),
-                            else_body.as_ref().unwrap().label.clone(),
-                        ),
-                    ),
-                ),
-                Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::CodeBody(body))),
-                Expression::new(
-                    ExpressionType::Line,
-                    Effects::new(Span::default(), EffectType::CodeBody(else_body.unwrap())),
-                ),
-            ],
-            temp.to_string(),
-        );
-        else_body = Some(inner);
-        temp += 1;
-    }
+    // Where each check jumps if it fails, the last else if jumps to the else body or the end.
+    let mut jumps = (0..else_ifs.len()).map(|i| (id + 1 + i as u32).to_string()).collect::<Vec<_>>();
+    jumps.push(else_body.as_ref().map(|body| body.label.clone()).unwrap_or_else(|| end.clone()));
 
-    // Where we jump if the if fails
-    let if_jumping = if let Some(body) = &mut else_body {
-        body.expressions.push(Expression::new(
-            ExpressionType::Line,
-            Effects::new(Span::default(), EffectType::Jump(body.label.clone())),
-        ));
-        body.label.clone()
-    } else {
-        id.to_string() + "end"
-    };
-
-    body.expressions.push(Expression::new(
-        ExpressionType::Line,
-        Effects::new(Span::default(), EffectType::Jump(id.to_string() + "end")),
-    ));
+    body.expressions
+        .push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::Jump(end.clone()))));
 
     // The CodeBody before the if statement that controls the control flow
     let mut top = CodeBody::new(
         vec![
             Expression::new(
                 ExpressionType::Line,
-                Effects::new(Span::default(), EffectType::CompareJump(Box::new(effect), body.label.clone(), if_jumping)),
+                Effects::new(
+                    Span::default(),
+                    EffectType::CompareJump(Box::new(effect), body.label.clone(), jumps[0].clone()),
+                ),
             ),
             Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::CodeBody(body))),
         ],
         id.to_string(),
     );
 
+    // Add every else if as a check block followed by its body
+    for (i, (effect, mut body)) in else_ifs.into_iter().enumerate() {
+        body.expressions
+            .push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::Jump(end.clone()))));
+        let check = CodeBody::new(
+            vec![Expression::new(
+                ExpressionType::Line,
+                Effects::new(
+                    Span::default(),
+                    EffectType::CompareJump(Box::new(effect), body.label.clone(), jumps[i + 1].clone()),
+                ),
+            )],
+            jumps[i].clone(),
+        );
+        top.expressions
+            .push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::CodeBody(check))));
+        top.expressions
+            .push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::CodeBody(body))));
+    }
+
     // Add the else body.
-    if let Some(body) = else_body {
+    if let Some(mut body) = else_body {
+        body.expressions.push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::Jump(end))));
         top.expressions
             .push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::CodeBody(body))));
     }
@@ -60,6 +60,7 @@ pub fn parse_function(
                         Vec::default(),
                         0,
                         last_arg,
+                        None,
                     )));
                 } else {
                     fields.push(Box::pin(to_field(
@@ -67,6 +68,7 @@ pub fn parse_function(
                         Vec::default(),
                         0,
                         last_arg,
+                        None,
                     )));
                     last_arg_type = String::default();
                 }
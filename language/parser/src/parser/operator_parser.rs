@@ -1,4 +1,4 @@
-use syntax::errors::ParsingError;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::code::{EffectType, Effects};
 
 use crate::parser::code_parser::{parse_line, ParseState};
@@ -50,15 +50,18 @@ pub fn parse_operator(
     };
     first_element_token.extend_span(parser_utils.index);
 
-    // If it's a list effect, get all the elements
+    // If it's a list effect like [a, b], get all the elements
     if right.is_some() {
-        while parser_utils.tokens.get(parser_utils.index).unwrap().token_type == TokenTypes::ArgumentEnd {
+        while operation.ends_with('[')
+            && parser_utils.tokens.get(parser_utils.index).unwrap().token_type == TokenTypes::ArgumentEnd
+        {
             (index, tokens) = (parser_utils.index.clone(), parser_utils.tokens.len());
+            // Skip the comma
+            parser_utils.index += 1;
             let mut next_element_token = Span::new(parser_utils.file, parser_utils.index);
             let next = parse_line(parser_utils, ParseState::InOperator)?.map(|inner| inner.effect);
             next_element_token.extend_span(parser_utils.index);
             if let Some(next_element) = next {
-                parser_utils.index += 1;
                 right = match right.unwrap().types {
                     EffectType::CreateArray(mut inner) => {
                         inner.push(next_element);
@@ -112,3 +115,25 @@ pub fn parse_operator(
         span: Span::new(parser_utils.file, parser_utils.index),
     });
 }
+
+/// Parses a range like start..end, starting at the second period.
+/// The end is the rest of the expression, since ranges bind looser than every operator.
+pub fn parse_range(
+    start: Option<Effects>,
+    parser_utils: &mut ParserUtils,
+    state: &ParseState,
+) -> Result<Effects, ParsingError> {
+    let span = Span::new(parser_utils.file, parser_utils.index - 1);
+    let start = match start {
+        Some(start) => start,
+        None => return Err(span.make_error(ParsingMessage::UnexpectedVoid)),
+    };
+
+    // Skip the second period
+    parser_utils.index += 1;
+    let end = match parse_line(parser_utils, state.clone())? {
+        Some(end) => end.effect,
+        None => return Err(span.make_error(ParsingMessage::UnexpectedVoid)),
+    };
+    return Ok(Effects::new(Span::merge(start.span, end.span), EffectType::Range(Box::new(start), Box::new(end))));
+}
@@ -3,12 +3,13 @@ use std::sync::Arc;
 use data::tokens::{Span, Token, TokenTypes};
 use syntax::async_util::{NameResolver, UnparsedType};
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
-use syntax::program::code::{Field, MemberField};
+use syntax::program::code::{Effects, Field, MemberField};
 use syntax::program::r#struct::{get_internal, StructData, UnfinalizedStruct};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::Types;
 use syntax::{get_modifier, is_modifier, Attribute, Modifier, ParsingFuture, TraitImplementor};
 
+use crate::parser::code_parser::{parse_line, ParseState};
 use crate::parser::function_parser::parse_function;
 use crate::parser::top_parser::{parse_attribute, parse_import, parse_modifier};
 use crate::parser::util::ParserUtils;
@@ -26,6 +27,8 @@ pub fn parse_structure(
 
     let start = Span::new(parser_utils.file, parser_utils.index);
     let mut name = String::default();
+    let mut parent = None;
+    let mut extending = false;
     let mut fields = Vec::default();
     let mut functions = Vec::default();
     while parser_utils.tokens.len() != parser_utils.index {
@@ -33,6 +36,19 @@ pub fn parse_structure(
         let token: Token = token.clone();
         parser_utils.index += 1;
         match token.token_type {
+            // The identifier after extends is the parent, not the struct's name
+            TokenTypes::Identifier if extending => {
+                parent = Some(UnparsedType::Basic(
+                    Span::new(parser_utils.file, parser_utils.index - 1),
+                    token.to_string(parser_utils.buffer),
+                ));
+                extending = false;
+            }
+            TokenTypes::Extends => extending = true,
+            // The struct's own generics come before extends, so generics after it are the parent's, like Base<T>
+            TokenTypes::GenericsStart if parent.is_some() => {
+                parent = Some(UnparsedType::Generic(Box::new(parent.unwrap()), parse_type_generics(parser_utils)?));
+            }
             TokenTypes::Identifier => {
                 name = token.to_string(parser_utils.buffer);
                 parser_utils.imports.parent =
@@ -71,13 +87,20 @@ pub fn parse_structure(
                 } else {
                     parser_utils.file_name = format!("{}::{}", parser_utils.file_name, name);
                 }
+                let enabled = parser_utils.syntax.lock().process_manager.cfg_enabled(&member_attributes);
                 let function = parse_function(
                     parser_utils,
                     is_modifier(modifiers, Modifier::Trait),
                     member_attributes,
                     member_modifiers,
                 );
-                functions.push(ParserUtils::add_function(&parser_utils.syntax, parser_utils.file_name.clone(), function));
+                if enabled {
+                    functions.push(ParserUtils::add_function(
+                        &parser_utils.syntax,
+                        parser_utils.file_name.clone(),
+                        function,
+                    ));
+                }
                 parser_utils.file_name = file;
                 member_attributes = Vec::default();
                 member_modifiers = Vec::default();
@@ -100,6 +123,8 @@ pub fn parse_structure(
 
     let generics = parser_utils.imports.generics.clone();
     parser_utils.imports.generics.clear();
+    // Functions after the struct aren't in it, so they don't get its type as their parent
+    parser_utils.imports.parent = None;
 
     let data = if is_modifier(modifiers, Modifier::Internal) && !is_modifier(modifiers, Modifier::Trait) {
         get_internal(name)
@@ -114,7 +139,7 @@ pub fn parse_structure(
         ))
     };
 
-    return Ok(UnfinalizedStruct { generics, fields, functions, data });
+    return Ok(UnfinalizedStruct { generics, fields, functions, parent, data });
 }
 
 /// Parses an implementor
@@ -125,6 +150,8 @@ pub fn parse_implementor(
 ) -> (Result<TraitImplementor, ParsingError>, String, String) {
     let mut base = None;
     let mut implementor = None;
+    // The generics declared by the impl, since the functions' generics are added after them
+    let mut declared = Vec::default();
 
     let mut member_attributes = Vec::default();
     let mut member_modifiers = Vec::default();
@@ -148,7 +175,9 @@ pub fn parse_implementor(
             }
             TokenTypes::GenericsStart => {
                 if state == 0 {
+                    let before = parser_utils.imports.generics.len();
                     parse_generics(parser_utils);
+                    declared = parser_utils.imports.generics.keys().skip(before).cloned().collect::<Vec<_>>();
                 } else {
                     let type_generics = match parse_type_generics(parser_utils) {
                         Ok(generics) => generics,
@@ -173,6 +202,10 @@ pub fn parse_implementor(
                 }
             }
             TokenTypes::FunctionStart => {
+                // Inherent impls like impl str have no implementor, so the functions belong to the base
+                if implementor.is_none() {
+                    parser_utils.imports.parent.clone_from(&base);
+                }
                 let file = parser_utils.file_name.clone();
                 if parser_utils.file_name.is_empty() {
                     parser_utils.file_name = format!("{}_{}", base.as_ref().unwrap(), implementor.as_ref().unwrap());
@@ -182,11 +215,16 @@ pub fn parse_implementor(
                 } else {
                     parser_utils.file_name = format!("{}::{}", parser_utils.file_name, base.as_ref().unwrap());
                 }
+                let enabled = parser_utils.syntax.lock().process_manager.cfg_enabled(&member_attributes);
                 let function = match parse_function(parser_utils, false, member_attributes, member_modifiers) {
                     Ok(inner) => inner,
                     Err(error) => return (Err(error), "error".to_string(), "error".to_string()),
                 };
-                functions.push(function);
+                if enabled {
+                    functions.push(function);
+                }
+                // The function's generics are its own, so they're removed from the impl's
+                parser_utils.imports.generics.retain(|generic, _| declared.contains(generic));
                 parser_utils.file_name = file;
                 member_attributes = Vec::default();
                 member_modifiers = Vec::default();
@@ -226,10 +264,20 @@ pub fn parse_implementor(
 
     let generics = parser_utils.imports.generics.clone();
     parser_utils.imports.generics.clear();
+    // Functions after the impl aren't in it, so they don't get its type as their parent
+    parser_utils.imports.parent = None;
 
+    let base = base.unwrap();
     return (
-        Ok(TraitImplementor { base: base_future, generics, implementor: implementor_future, functions, attributes }),
-        base.unwrap().to_string(),
+        Ok(TraitImplementor {
+            base: base_future,
+            generics,
+            implementor: implementor_future,
+            functions,
+            attributes,
+            span: base.get_span(),
+        }),
+        base.to_string(),
         implementor.map_or_else(|| "none".to_string(), |inner| inner.to_string()),
     );
 }
@@ -379,6 +427,7 @@ pub fn parse_field(
     modifiers: Vec<Modifier>,
 ) -> ParsingFuture<MemberField> {
     let mut types = None;
+    let mut default_value = None;
     while !parser_utils.tokens.is_empty() {
         let token = &parser_utils.tokens[parser_utils.index];
         parser_utils.index += 1;
@@ -387,13 +436,28 @@ pub fn parse_field(
                 let name = token.to_string(parser_utils.buffer).clone();
                 types = Some(parser_utils.get_struct(&Span::new(parser_utils.file, parser_utils.index - 1), name))
             }
+            TokenTypes::FieldValue => match parse_line(parser_utils, ParseState::None) {
+                Ok(found) => default_value = found.map(|expression| expression.effect),
+                Err(error) => {
+                    // Skip the rest of the value so the rest of the struct can still be parsed
+                    while !matches!(
+                        parser_utils.tokens[parser_utils.index].token_type,
+                        TokenTypes::LineEnd | TokenTypes::EOF
+                    ) {
+                        parser_utils.index += 1;
+                    }
+                    parser_utils.index += 1;
+                    return Box::pin(async move { Err(error) });
+                }
+            },
             TokenTypes::FieldSeparator => {}
-            TokenTypes::FieldEnd => break,
+            // A default value ends with the same token as a line of code
+            TokenTypes::FieldEnd | TokenTypes::LineEnd => break,
             _ => panic!("How'd you get here? {:?}", token.token_type),
         }
     }
 
-    return Box::pin(to_field(types.unwrap(), attributes, get_modifier(modifiers.as_slice()), name));
+    return Box::pin(to_field(types.unwrap(), attributes, get_modifier(modifiers.as_slice()), name, default_value));
 }
 
 /// Waits for the type to finish and converts it to a field
@@ -402,6 +466,7 @@ pub async fn to_field(
     attributes: Vec<Attribute>,
     modifier: u8,
     name: String,
+    default_value: Option<Effects>,
 ) -> Result<MemberField, ParsingError> {
-    return Ok(MemberField::new(modifier, attributes, Field::new(name, types.await?)));
+    return Ok(MemberField::new(modifier, attributes, Field::new(name, types.await?), default_value));
 }
@@ -28,7 +28,17 @@ pub fn parse_top(parser_utils: &mut ParserUtils) {
             TokenTypes::AttributesStart => parse_attribute(parser_utils, &mut attributes),
             TokenTypes::ModifiersStart => parse_modifier(parser_utils, &mut modifiers),
             TokenTypes::FunctionStart => {
+                let enabled = parser_utils.syntax.lock().process_manager.cfg_enabled(&attributes);
+                // The last function's generics are still in the imports, and they aren't this function's
+                parser_utils.imports.generics.clear();
                 let function = parse_function(parser_utils, false, attributes, modifiers);
+                attributes = vec![];
+                modifiers = vec![];
+                // Elements disabled by a #[cfg] attribute are parsed but never added to the syntax
+                if !enabled {
+                    continue;
+                }
+
                 let function = ParserUtils::add_function(&parser_utils.syntax, parser_utils.file_name.clone(), function);
                 let process_manager = parser_utils.syntax.lock().process_manager.cloned();
                 parser_utils.handle.lock().spawn(
@@ -41,25 +51,38 @@ pub fn parse_top(parser_utils: &mut ParserUtils) {
                         process_manager,
                     ),
                 );
-
-                attributes = vec![];
-                modifiers = vec![];
             }
             TokenTypes::StructStart => {
+                let enabled = parser_utils.syntax.lock().process_manager.cfg_enabled(&attributes);
                 let structure = parse_structure(parser_utils, attributes, modifiers);
-                parser_utils.add_struct(structure);
+                if enabled {
+                    parser_utils.add_struct(structure);
+                }
                 attributes = vec![];
                 modifiers = vec![];
             }
             TokenTypes::TraitStart => {
                 modifiers.push(Modifier::Trait);
+                if Attribute::has_attribute("operation", &attributes) {
+                    modifiers.push(Modifier::Operation);
+                }
+                let enabled = parser_utils.syntax.lock().process_manager.cfg_enabled(&attributes);
                 let structure = parse_structure(parser_utils, attributes, modifiers);
-                parser_utils.add_struct(structure);
+                if enabled {
+                    parser_utils.add_struct(structure);
+                }
                 attributes = Vec::default();
                 modifiers = Vec::default();
             }
             TokenTypes::ImplStart => {
+                let enabled = parser_utils.syntax.lock().process_manager.cfg_enabled(&attributes);
                 let (trait_implementor, base, implementor) = parse_implementor(parser_utils, attributes, modifiers);
+                attributes = Vec::default();
+                modifiers = Vec::default();
+                if !enabled {
+                    continue;
+                }
+
                 let process_manager = {
                     let mut locked = parser_utils.syntax.lock();
                     locked.async_manager.parsing_impls += 1;
@@ -78,8 +101,6 @@ pub fn parse_top(parser_utils: &mut ParserUtils) {
                         implementor,
                     ),
                 );
-                attributes = Vec::default();
-                modifiers = Vec::default();
             }
             TokenTypes::Comment => {}
             TokenTypes::EOF => return,
@@ -130,6 +151,12 @@ pub fn parse_attribute(parser_utils: &mut ParserUtils, attributes: &mut Vec<Attr
             }
             let value = split.next().unwrap();
             let value = &value[0..value.len() - 1];
+            // Conditions are stored without whitespace or quotes, so #[cfg(target_os = "linux")] is target_os=linux
+            if name == "cfg" {
+                let value = value.chars().filter(|char| !char.is_whitespace() && *char != '"').collect::<String>();
+                attributes.push(Attribute::String(name, value));
+                continue;
+            }
             match value.parse::<i64>() {
                 Ok(value) => Attribute::Integer(name, value),
                 Err(_) => match value.parse::<bool>() {
@@ -137,6 +164,10 @@ pub fn parse_attribute(parser_utils: &mut ParserUtils, attributes: &mut Vec<Attr
                     Err(_) => Attribute::String(name, value.to_string()),
                 },
             }
+        } else if let Some((name, value)) = string.split_once('=') {
+            // #[name = "value"] is the same as #[name("value")]
+            let name = name.trim().trim_start_matches("#[").to_lowercase();
+            Attribute::String(name, value.trim().to_string())
         } else {
             Attribute::Basic(string)
         });
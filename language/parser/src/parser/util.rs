@@ -5,7 +5,7 @@ use indexmap::IndexMap;
 
 use data::tokens::{Span, Token, TokenTypes};
 use syntax::async_util::{HandleWrapper, NameResolver, UnparsedType};
-use syntax::errors::ParsingError;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::function::{CodeBody, FunctionData, UnfinalizedFunction};
 use syntax::program::r#struct::{StructData, UnfinalizedStruct};
 use syntax::program::syntax::Syntax;
@@ -14,6 +14,7 @@ use syntax::{
     FinishedStructImplementor, FinishedTraitImplementor, ParsingFuture, ProcessManager, TopElement, TraitImplementor,
 };
 
+use crate::parser::control_parser::LoopLabels;
 use crate::ImportNameResolver;
 
 /// A struct containing the data needed for parsing
@@ -34,6 +35,8 @@ pub struct ParserUtils<'a> {
     pub imports: ImportNameResolver,
     /// Handle for spawning async tasks
     pub handle: Arc<Mutex<HandleWrapper>>,
+    /// The loops the parser is currently inside of, innermost last
+    pub loops: Vec<LoopLabels>,
 }
 
 impl<'a> ParserUtils<'a> {
@@ -59,6 +62,7 @@ impl<'a> ParserUtils<'a> {
             generics: IndexMap::default(),
             fields: Vec::default(),
             functions: Vec::default(),
+            parent: None,
             data: Arc::new(StructData::new_poisoned(format!("${}", self.file), error)),
         });
 
@@ -95,14 +99,14 @@ impl<'a> ParserUtils<'a> {
                     Ok(_) => {}
                     Err(error) => {
                         let mut locked = syntax.lock();
-                        locked.async_manager.parsing_impls -= 1;
+                        locked.async_manager.finish_impl();
                         return Err(error);
                     }
                 };
             }
             Err(error) => {
                 let mut locked = syntax.lock();
-                locked.async_manager.parsing_impls -= 1;
+                locked.async_manager.finish_impl();
                 return Err(error);
             }
         }
@@ -143,13 +147,65 @@ impl<'a> ParserUtils<'a> {
         for function in &implementor.functions {
             functions.push(function.data.clone());
         }
+        // Functions reported as duplicates, which aren't registered
+        let mut duplicates = vec![false; functions.len()];
 
-        if let Some(base) = implementor.implementor {
-            let base = base.await?;
-            let base = base.finalize(syntax.clone()).await;
+        let base = match implementor.implementor {
+            Some(base) => Some(base.await?.finalize(syntax.clone()).await),
+            None => None,
+        };
+
+        // A generic that's in neither type can't be found from them when the impl is used
+        if let Some(unused) = generics
+            .keys()
+            .find(|generic| !target.uses_generic(generic) && !base.as_ref().map_or(false, |base| base.uses_generic(generic)))
+        {
+            return Err(implementor.span.make_error(ParsingMessage::UnconstrainedGeneric(unused.clone())));
+        }
+
+        if let Some(base) = base {
+            // Functions the trait doesn't declare would be unreachable through it, so they're added to the struct instead
+            let trait_data = target.inner_struct().data.clone();
+            let (functions, inherent): (Vec<_>, Vec<_>) = functions
+                .into_iter()
+                .partition(|function| trait_data.functions.iter().any(|found| short_name(found) == short_name(function)));
 
             let chalk_type = Arc::new(Syntax::make_impldatum(&generics, &target, &base));
 
+            {
+                let mut locked = syntax.lock();
+                for (i, function) in functions.iter().enumerate() {
+                    if functions[..i].iter().any(|found| short_name(found) == short_name(function)) {
+                        let error = ParsingMessage::DuplicateTraitFunction(
+                            trait_data.name.clone(),
+                            short_name(function).to_string(),
+                        );
+                        locked.errors.push(function.span.make_error(error));
+                    }
+                }
+                // Calls through the trait need every function, so one missing would leave a hole in its vtable
+                for function in &trait_data.functions {
+                    if !functions.iter().any(|found| short_name(found) == short_name(function)) {
+                        let error =
+                            ParsingMessage::MissingTraitFunction(trait_data.name.clone(), short_name(function).to_string());
+                        locked.errors.push(implementor.span.make_error(error));
+                    }
+                }
+
+                if !inherent.is_empty() {
+                    for function in &inherent {
+                        locked.functions.add_type(function.clone());
+                    }
+                    let output = FinishedStructImplementor {
+                        target: base.clone(),
+                        attributes: implementor.attributes.clone(),
+                        functions: inherent,
+                        generics: generics.clone(),
+                    };
+                    Self::add_struct_implementor(&mut locked, output);
+                }
+            }
+
             let output = FinishedTraitImplementor {
                 target,
                 base,
@@ -161,39 +217,48 @@ impl<'a> ParserUtils<'a> {
 
             {
                 let mut locked = syntax.lock();
-                locked.implementations.push(Arc::new(output));
-
-                locked.async_manager.parsing_impls -= 1;
-                for waker in &locked.async_manager.impl_waiters {
-                    waker.wake_by_ref();
-                }
-                locked.async_manager.impl_waiters.clear();
+                locked.add_implementation(Arc::new(output));
+                locked.async_manager.finish_impl();
             }
         } else {
-            let output = FinishedStructImplementor { target, attributes: implementor.attributes, functions, generics };
+            let mut output = FinishedStructImplementor { target, attributes: implementor.attributes, functions, generics };
 
             {
                 let mut locked = syntax.lock();
-                for function in &output.functions {
-                    locked.functions.add_type(function.clone());
-                }
-
-                let mut target = output.target.clone();
-                if let Some((base, _bounds)) = target.inner_generic_type() {
-                    target = FinalizedTypes::clone(base);
+                // Functions are named after the file and type, so one with the same name as another in the type's impls
+                // in this file would replace it
+                let mut names = locked.struct_implementations.get(&Self::implemented_type(&output.target)).map_or(
+                    Vec::default(),
+                    |implementors| {
+                        implementors
+                            .iter()
+                            .flat_map(|found| found.functions.iter().map(|function| function.name.clone()))
+                            .collect()
+                    },
+                );
+                for (index, function) in output.functions.iter().enumerate() {
+                    if names.contains(&function.name) {
+                        let error =
+                            ParsingMessage::DuplicateFunction(output.target.to_string(), short_name(function).to_string());
+                        locked.errors.push(function.span.make_error(error));
+                        duplicates[index] = true;
+                    } else {
+                        names.push(function.name.clone());
+                    }
                 }
+                // Duplicates aren't registered, so they can't replace the function they duplicate
+                let mut found = duplicates.iter();
+                output.functions.retain(|_| !found.next().unwrap());
 
-                locked.struct_implementations.entry(target).or_default().push(Arc::new(output));
-
-                locked.async_manager.parsing_impls -= 1;
-                for waker in &locked.async_manager.impl_waiters {
-                    waker.wake_by_ref();
+                for function in &output.functions {
+                    locked.functions.add_type(function.clone());
                 }
-                locked.async_manager.impl_waiters.clear();
+                Self::add_struct_implementor(&mut locked, output);
+                locked.async_manager.finish_impl();
             }
         }
 
-        for function in implementor.functions {
+        for (function, _) in implementor.functions.into_iter().zip(duplicates).filter(|(_, duplicate)| !duplicate) {
             handle.lock().spawn(
                 function.data.name.clone(),
                 FunctionData::verify(
@@ -209,6 +274,20 @@ impl<'a> ParserUtils<'a> {
         return Ok(());
     }
 
+    /// Adds a struct's own implementation to the implementations of its base type
+    fn add_struct_implementor(syntax: &mut Syntax, implementor: FinishedStructImplementor) {
+        let target = Self::implemented_type(&implementor.target);
+        syntax.struct_implementations.entry(target).or_default().push(Arc::new(implementor));
+    }
+
+    /// The type a struct's own implementation is stored under, which is the base of generic types
+    fn implemented_type(target: &FinalizedTypes) -> FinalizedTypes {
+        return match target.inner_generic_type() {
+            Some((base, _bounds)) => FinalizedTypes::clone(base),
+            None => target.clone(),
+        };
+    }
+
     /// Adds a function to the syntax
     pub fn add_function(
         syntax: &Arc<Mutex<Syntax>>,
@@ -232,27 +311,49 @@ impl<'a> ParserUtils<'a> {
     }
 }
 
-/// Parses generics, returning both its unparsed form
+/// Parses generics after the opening <, up to and including the matching >, returning its unparsed form
 pub fn parse_generics(input: UnparsedType, parser_utils: &mut ParserUtils) -> UnparsedType {
+    let (generics, _) =
+        parse_nested_generics(input, &parser_utils.tokens, parser_utils.buffer, parser_utils.file, &mut parser_utils.index);
+    return generics;
+}
+
+/// Parses generics like parse_generics, also returning how many outer levels of generics were closed with it.
+/// Closers can end up in one token, like the >> in Map<str, List<u64>>, so each > closes one level and
+/// the ones left over are passed back up to the levels they close.
+fn parse_nested_generics(
+    input: UnparsedType,
+    tokens: &[Token],
+    buffer: &[u8],
+    file: u64,
+    index: &mut usize,
+) -> (UnparsedType, usize) {
     let mut unparsed_generics = Vec::default();
     let mut last: Option<UnparsedType> = None;
+    let mut closing = 0;
     loop {
-        let token = parser_utils.tokens.get(parser_utils.index).unwrap();
-        parser_utils.index += 1;
+        let token = tokens.get(*index).unwrap();
+        *index += 1;
         match token.token_type {
             TokenTypes::Variable => {
                 if let Some(unparsed) = last {
                     unparsed_generics.push(unparsed);
                 }
-                last = Some(UnparsedType::Basic(
-                    Span::new(parser_utils.file, parser_utils.index - 1),
-                    token.to_string(parser_utils.buffer),
-                ))
+                last = Some(UnparsedType::Basic(Span::new(file, *index - 1), token.to_string(buffer)))
             }
             TokenTypes::Operator => {
-                if let Some(unparsed) = last {
-                    unparsed_generics.push(parse_generics(unparsed, parser_utils));
-                    last = None;
+                let closers = token.to_string(buffer).chars().take_while(|character| *character == '>').count();
+                if closers > 0 {
+                    closing = closers - 1;
+                    break;
+                }
+                if let Some(unparsed) = last.take() {
+                    let (generic, remaining) = parse_nested_generics(unparsed, tokens, buffer, file, index);
+                    unparsed_generics.push(generic);
+                    if remaining > 0 {
+                        closing = remaining - 1;
+                        break;
+                    }
                 }
             }
             TokenTypes::ArgumentEnd => {
@@ -262,15 +363,63 @@ pub fn parse_generics(input: UnparsedType, parser_utils: &mut ParserUtils) -> Un
                 }
             }
             _ => {
-                parser_utils.index -= 1;
+                *index -= 1;
                 break;
             }
         }
     }
 
-    return if unparsed_generics.is_empty() {
-        input.clone()
-    } else {
-        UnparsedType::Generic(Box::new(input.clone()), unparsed_generics)
-    };
+    if let Some(unparsed) = last {
+        unparsed_generics.push(unparsed);
+    }
+
+    let generics =
+        if unparsed_generics.is_empty() { input } else { UnparsedType::Generic(Box::new(input), unparsed_generics) };
+    return (generics, closing);
+}
+
+/// The name of a function without the path to it
+fn short_name(function: &FunctionData) -> &str {
+    return function.name.split("::").last().unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use data::tokens::{Span, TokenTypes};
+    use syntax::async_util::UnparsedType;
+
+    use crate::parser::util::parse_nested_generics;
+    use crate::tokens::tokenizer::tokenize;
+
+    /// Parses the generics of a struct made with the type, returning them and the token parsing stopped at
+    fn parse(types: &str) -> (String, TokenTypes) {
+        let source = format!("fn test() {{\n    new {} {{}};\n}}", types);
+        let buffer = source.as_bytes();
+        let tokens = tokenize(buffer).collect::<Vec<_>>();
+        // The type's generics start at the first operator, which is the <
+        let opening = tokens.iter().position(|token| token.token_type == TokenTypes::Operator).unwrap();
+
+        let mut index = opening + 1;
+        let base = UnparsedType::Basic(Span::default(), tokens[opening - 1].to_string(buffer));
+        let (generics, closing) = parse_nested_generics(base, &tokens, buffer, 0, &mut index);
+        assert_eq!(closing, 0);
+        return (generics.to_string(), tokens[index].token_type);
+    }
+
+    /// Checks that a generic inside a generic closes both, and that the parser stops after the last >
+    #[test]
+    pub fn test_two_level_generics() {
+        assert_eq!(parse("Map<str, List<u64>>"), ("Map<str + List<u64>>".to_string(), TokenTypes::BlockStart));
+        assert_eq!(parse("Map<List<u64>, str>"), ("Map<List<u64> + str>".to_string(), TokenTypes::BlockStart));
+    }
+
+    /// Checks that three levels of generics each get their own bounds
+    #[test]
+    pub fn test_three_level_generics() {
+        assert_eq!(parse("Map<str, List<Box<u64>>>"), ("Map<str + List<Box<u64>>>".to_string(), TokenTypes::BlockStart));
+        assert_eq!(
+            parse("Map<List<Box<u64>>, List<str>>"),
+            ("Map<List<Box<u64>> + List<str>>".to_string(), TokenTypes::BlockStart)
+        );
+    }
 }
@@ -2,11 +2,24 @@ use crate::tokens::tokenizer::{Tokenizer, TokenizerState};
 use crate::tokens::util::{parse_acceptable, parse_numbers};
 use data::tokens::{Token, TokenTypes};
 
+/// Gets the byte before the last one read, or None at the start of the buffer
+fn byte_before_last(tokenizer: &Tokenizer) -> Option<u8> {
+    return tokenizer.index.checked_sub(2).map(|index| tokenizer.buffer[index]);
+}
+
 /// Gets the next token in a block of code.
 pub fn next_code_token(tokenizer: &mut Tokenizer) -> Token {
-    return if let Some(found) = check_keywords(tokenizer) {
+    return if tokenizer.state == TokenizerState::FIELD_VALUE && tokenizer.bracket_depth == 0 && tokenizer.matches(";") {
+        // The end of a field's default value goes back to the program's body
+        tokenizer.state = TokenizerState::TOP_ELEMENT_TO_STRUCT;
+        tokenizer.make_token(TokenTypes::LineEnd)
+    } else if let Some(found) = check_keywords(tokenizer) {
         found
-    } else if TokenTypes::Period == tokenizer.last.token_type && tokenizer.buffer[tokenizer.index].is_ascii_alphabetic() {
+    } else if TokenTypes::Period == tokenizer.last.token_type
+        && tokenizer.buffer.get(tokenizer.index).map_or(false, u8::is_ascii_alphabetic)
+        // The end of a range like start..end is a variable, not a field
+        && byte_before_last(tokenizer) != Some(b'.')
+    {
         parse_acceptable(tokenizer, TokenTypes::CallingType)
     } else if tokenizer.matches("{") {
         tokenizer.bracket_depth += 1;
@@ -27,7 +40,9 @@ pub fn next_code_token(tokenizer: &mut Tokenizer) -> Token {
         }
     } else if tokenizer.matches(".") {
         // This is only a number if the thing before and after is a digit. "1." and ".1" aren't numbers.
-        if tokenizer.buffer[tokenizer.index].is_ascii_digit() && tokenizer.buffer[tokenizer.index - 2].is_ascii_digit() {
+        if tokenizer.buffer.get(tokenizer.index).map_or(false, u8::is_ascii_digit)
+            && byte_before_last(tokenizer).map_or(false, |before| before.is_ascii_digit())
+        {
             tokenizer.index -= 1;
             parse_numbers(tokenizer)
         } else {
@@ -35,18 +50,24 @@ pub fn next_code_token(tokenizer: &mut Tokenizer) -> Token {
         }
     } else if tokenizer.matches("\"") {
         // Changes the state type based on what the current state already is.
-        tokenizer.state = if tokenizer.state == TokenizerState::CODE {
-            TokenizerState::STRING
-        } else {
-            TokenizerState::STRING_TO_CODE_STRUCT_TOP
+        tokenizer.state = match tokenizer.state {
+            TokenizerState::CODE => TokenizerState::STRING,
+            TokenizerState::FIELD_VALUE => TokenizerState::STRING_TO_FIELD_VALUE,
+            _ => TokenizerState::STRING_TO_CODE_STRUCT_TOP,
         };
         tokenizer.make_token(TokenTypes::StringStart)
     } else if tokenizer.matches("'") {
-        tokenizer.index += 1;
-        if tokenizer.matches("'") {
-            tokenizer.make_token(TokenTypes::Char)
+        // A quote followed by a name that isn't closed right after it is a loop label, like 'outer
+        let next = tokenizer.buffer.get(tokenizer.index).map_or(b' ', |found| *found);
+        if ((next as char).is_alphabetic() || next == b'_') && tokenizer.buffer.get(tokenizer.index + 1) != Some(&b'\'') {
+            parse_acceptable(tokenizer, TokenTypes::Label)
         } else {
-            tokenizer.handle_invalid()
+            tokenizer.index += 1;
+            if tokenizer.matches("'") {
+                tokenizer.make_token(TokenTypes::Char)
+            } else {
+                tokenizer.handle_invalid()
+            }
         }
     } else {
         let found = tokenizer.next_included()?;
@@ -80,6 +101,8 @@ pub fn check_keywords(tokenizer: &mut Tokenizer) -> Option<Token> {
         tokenizer.make_token(TokenTypes::Return)
     } else if tokenizer.matches_word("break") {
         tokenizer.make_token(TokenTypes::Break)
+    } else if tokenizer.matches_word("continue") {
+        tokenizer.make_token(TokenTypes::Continue)
     } else if tokenizer.matches_word("switch") {
         tokenizer.make_token(TokenTypes::Switch)
     } else if tokenizer.matches_word("true") {
@@ -1,3 +1,5 @@
+use std::cell::OnceCell;
+
 use crate::tokens::code_tokenizer::next_code_token;
 use crate::tokens::top_tokenizer::{next_func_token, next_implementation_token, next_struct_token, next_top_token};
 use crate::tokens::util::{next_generic, parse_string};
@@ -24,6 +26,12 @@ pub struct Tokenizer<'a> {
     pub len: usize,
     /// A buffer of all characters in the file
     pub buffer: &'a [u8],
+    /// Every token from tokenize_all, kept so edits can be re-tokenized incrementally
+    pub tokens: Vec<Token>,
+    /// The state before each token in tokens
+    states: Vec<ParserState>,
+    /// The token after the last one, tokenized the first time it's peeked and kept until the tokenizer moves
+    upcoming: OnceCell<Token>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -39,13 +47,88 @@ impl<'a> Tokenizer<'a> {
             last: Token::new(TokenTypes::Start, (1, 0), 0, (1, 0), 0),
             len: buffer.len(),
             buffer,
+            tokens: Vec::default(),
+            states: Vec::default(),
+            upcoming: OnceCell::new(),
         };
     }
 
-    /// Saves the tokenizer to a ParserState to be loaded later
-    pub fn serialize(&mut self) -> ParserState {
+    /// Tokenizes the rest of the buffer into tokens, saving the state before each token
+    pub fn tokenize_all(&mut self) {
+        loop {
+            self.states.push(self.serialize());
+            let token = self.next();
+            self.tokens.push(token);
+            if token.token_type == TokenTypes::EOF {
+                break;
+            }
+        }
+    }
+
+    /// Updates tokens after the bytes from changed_start to changed_end were swapped with replacement,
+    /// giving new_source. Tokens before the change are kept, and tokenizing resumes from the state before the first
+    /// token the change could affect. Once tokenizing past the change reaches the same state as before it,
+    /// the rest of the old tokens are kept too, moved by the bytes and lines the change added.
+    /// Returns how many tokens were tokenized again.
+    pub fn tokenize_incremental(
+        &mut self,
+        new_source: &'a [u8],
+        changed_start: usize,
+        changed_end: usize,
+        replacement: &[u8],
+    ) -> usize {
+        debug_assert_eq!(new_source.len(), self.len - (changed_end - changed_start) + replacement.len());
+        // The token before the first overlapping one is redone too, since the edit can extend it
+        let first = self.tokens.iter().position(|token| token.end_offset >= changed_start).unwrap_or(self.tokens.len());
+        let first = first.saturating_sub(1);
+
+        let growth = replacement.len() as isize - (changed_end - changed_start) as isize;
+        let lines = line_count(replacement) - line_count(&self.buffer[changed_start..changed_end]);
+        let old_tokens = self.tokens.split_off(first);
+        let old_states = self.states.split_off(first);
+        self.buffer = new_source;
+        self.len = new_source.len();
+        match old_states.first() {
+            Some(state) => self.load(state),
+            None => *self = Tokenizer::new(new_source),
+        }
+
+        // Old states from before the end of the change can't be reused, since the tokens after them changed
+        let mut reusable = old_states.iter().position(|state| state.index >= changed_end).unwrap_or(old_states.len());
+        let mut tokenized = 0;
+        loop {
+            let state = self.serialize();
+            while reusable < old_states.len() && shift(old_states[reusable].index, growth) < state.index {
+                reusable += 1;
+            }
+            if let Some(old) = old_states.get(reusable) {
+                if old.shifted(growth, lines).resumes_like(&state) {
+                    self.tokens.extend(old_tokens[reusable..].iter().map(|token| shift_token(token, growth, lines)));
+                    self.states.push(state);
+                    self.states.extend(old_states[reusable + 1..].iter().map(|state| state.shifted(growth, lines)));
+                    let last = self.states.last().unwrap().clone();
+                    self.load(&last);
+                    return tokenized;
+                }
+            }
+
+            self.states.push(state);
+            let token = self.next();
+            self.tokens.push(token);
+            tokenized += 1;
+            if token.token_type == TokenTypes::EOF {
+                return tokenized;
+            }
+        }
+    }
+
+    /// Saves the tokenizer's position to a ParserState, which can be loaded later to resume tokenizing from here.
+    /// Editors can keep these to re-tokenize from the middle of a file instead of the start.
+    pub fn serialize(&self) -> ParserState {
         return ParserState {
             state: self.state,
+            bracket_depth: self.bracket_depth,
+            generic_depth: self.generic_depth,
             index: self.index,
             line_index: self.line_index,
             line: self.line,
@@ -53,23 +136,28 @@ impl<'a> Tokenizer<'a> {
         };
     }
 
-    /// Loads the state from a ParserState
+    /// Loads a ParserState, so the next token is the one that followed it when it was saved.
+    /// The state must come from a tokenizer over the same buffer, at least up to the state's index.
     pub fn load(&mut self, state: &ParserState) {
         self.state = state.state;
+        self.bracket_depth = state.bracket_depth;
+        self.generic_depth = state.generic_depth;
         self.index = state.index;
         self.line_index = state.line_index;
         self.line = state.line;
         self.last.clone_from(&state.last);
+        self.upcoming.take();
     }
 
     /// Gets the next token in the file
     pub fn next(&mut self) -> Token {
+        self.upcoming.take();
         if self.matches("//") {
             self.parse_to_line_end(TokenTypes::Comment);
             self.last = self.make_token(TokenTypes::Comment);
             return self.last.clone();
         } else if self.matches("/*") {
-            while !self.matches("*/") {
+            while self.index < self.len && !self.matches("*/") {
                 self.index += 1;
             }
             self.last = self.make_token(TokenTypes::Comment);
@@ -81,8 +169,10 @@ impl<'a> Tokenizer<'a> {
             TokenizerState::FUNCTION | TokenizerState::FUNCTION_TO_STRUCT_TOP => next_func_token(self),
             TokenizerState::STRUCTURE => next_struct_token(self),
             TokenizerState::IMPLEMENTATION => next_implementation_token(self),
-            TokenizerState::STRING | TokenizerState::STRING_TO_CODE_STRUCT_TOP => parse_string(self),
-            TokenizerState::CODE | TokenizerState::CODE_TO_STRUCT_TOP => next_code_token(self),
+            TokenizerState::STRING | TokenizerState::STRING_TO_CODE_STRUCT_TOP | TokenizerState::STRING_TO_FIELD_VALUE => {
+                parse_string(self)
+            }
+            TokenizerState::CODE | TokenizerState::CODE_TO_STRUCT_TOP | TokenizerState::FIELD_VALUE => next_code_token(self),
             TokenizerState::GENERIC_TO_IMPL
             | TokenizerState::GENERIC_TO_FUNC
             | TokenizerState::GENERIC_TO_STRUCT
@@ -92,6 +182,16 @@ impl<'a> Tokenizer<'a> {
         return self.last.clone();
     }
 
+    /// Gets the next token in the file without advancing the tokenizer.
+    /// The token is found by a copy of the tokenizer, so peeking doesn't change anything.
+    pub fn peek(&self) -> &Token {
+        return self.upcoming.get_or_init(|| {
+            let mut ahead = Tokenizer::new(self.buffer);
+            ahead.load(&self.serialize());
+            return ahead.next();
+        });
+    }
+
     /// The next included character, or the EOF token.
     /// This allows the ? operator to automatically return if the end of the file is reached.
     pub fn next_included(&mut self) -> Result<u8, Token> {
@@ -115,11 +215,27 @@ impl<'a> Tokenizer<'a> {
                 }
                 b'\r' => {}
                 b'\t' => {}
+                // Multi-byte UTF-8 whitespace, like non-breaking spaces, is skipped as a whole codepoint
+                _ if !character.is_ascii() => match self.utf8_whitespace_length(self.index - 1) {
+                    Some(length) => self.index += length - 1,
+                    None => return Ok(character),
+                },
                 _ => return Ok(character),
             }
         }
     }
 
+    /// Decodes the codepoint starting at the byte, returning its length in bytes if it's whitespace
+    pub fn utf8_whitespace_length(&self, start: usize) -> Option<usize> {
+        let end = (start + 4).min(self.len);
+        let decoded = match std::str::from_utf8(&self.buffer[start..end]) {
+            Ok(decoded) => decoded,
+            // The slice can end partway through a later codepoint, so only decode the valid part
+            Err(error) => std::str::from_utf8(&self.buffer[start..start + error.valid_up_to()]).unwrap(),
+        };
+        return decoded.chars().next().filter(|found| found.is_whitespace()).map(char::len_utf8);
+    }
+
     /// Matches a string of characters to the current tokenizer index. Ignores whitespace.
     pub fn matches(&mut self, input: &str) -> bool {
         let state = self.serialize();
@@ -143,7 +259,7 @@ impl<'a> Tokenizer<'a> {
                 return false;
             }
         }
-        return if !self.buffer[self.index].is_ascii_alphabetic() {
+        return if self.index == self.len || !self.buffer[self.index].is_ascii_alphabetic() {
             true
         } else {
             self.load(&state);
@@ -211,10 +327,78 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+/// Lazily tokenizes a buffer, ending after the EOF token.
+pub struct TokenStream<'a> {
+    /// The tokenizer reading the buffer
+    tokenizer: Tokenizer<'a>,
+    /// If the EOF token has been returned
+    ended: bool,
+}
+
+/// Creates a stream of every token in the buffer, including the final EOF token
+pub fn tokenize(buffer: &[u8]) -> TokenStream<'_> {
+    return TokenStream { tokenizer: Tokenizer::new(buffer), ended: false };
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        let token = self.tokenizer.next();
+        if token.token_type == TokenTypes::EOF {
+            self.ended = true;
+        }
+        return Some(token);
+    }
+}
+
+/// Lazily tokenizes a buffer it owns, like a file that was just read, ending after the EOF token.
+/// A tokenizer can't borrow a buffer kept next to it, so its state is saved between tokens instead.
+pub struct OwnedTokenStream {
+    /// The buffer being tokenized
+    buffer: String,
+    /// The state of the tokenizer after the last token
+    state: ParserState,
+    /// If the EOF token has been returned
+    ended: bool,
+}
+
+/// Creates a stream of every token in the buffer, including the final EOF token, which owns the buffer
+pub fn tokenize_owned(buffer: String) -> OwnedTokenStream {
+    let state = Tokenizer::new(buffer.as_bytes()).serialize();
+    return OwnedTokenStream { buffer, state, ended: false };
+}
+
+impl Iterator for OwnedTokenStream {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        let mut tokenizer = Tokenizer::new(self.buffer.as_bytes());
+        tokenizer.load(&self.state);
+        let token = tokenizer.next();
+        self.state = tokenizer.serialize();
+        if token.token_type == TokenTypes::EOF {
+            self.ended = true;
+        }
+        return Some(token);
+    }
+}
+
 /// A serialized parser state, used to save/load the state of parsing mid-file.
+#[derive(Clone, Debug)]
 pub struct ParserState {
-    /// The state number
+    /// The state number, one of the TokenizerState constants
     pub state: u64,
+    /// The depth of brackets
+    pub bracket_depth: u8,
+    /// The depth of generics
+    pub generic_depth: u8,
     /// The index in the file
     pub index: usize,
     /// The index in the line
@@ -225,6 +409,54 @@ pub struct ParserState {
     pub last: Token,
 }
 
+impl ParserState {
+    /// Moves the state by the bytes and lines an edit before it added, which are negative if it removed them
+    fn shifted(&self, growth: isize, lines: isize) -> ParserState {
+        return ParserState {
+            index: shift(self.index, growth),
+            line_index: shift(self.line_index as usize, growth) as u32,
+            line: (self.line as isize + lines) as u32,
+            last: shift_token(&self.last, growth, lines),
+            ..self.clone()
+        };
+    }
+
+    /// Checks if tokenizing from this state gives the same tokens as tokenizing from the other one
+    fn resumes_like(&self, other: &ParserState) -> bool {
+        return self.state == other.state
+            && self.bracket_depth == other.bracket_depth
+            && self.generic_depth == other.generic_depth
+            && self.index == other.index
+            && self.line_index == other.line_index
+            && self.line == other.line
+            && self.last.token_type == other.last.token_type
+            && self.last.end == other.last.end
+            && self.last.end_offset == other.last.end_offset;
+    }
+}
+
+/// Moves an offset by the bytes an edit before it added
+fn shift(offset: usize, growth: isize) -> usize {
+    return (offset as isize + growth) as usize;
+}
+
+/// Moves a token by the bytes and lines an edit before it added. The columns stay the same,
+/// since tokens are only moved once their line starts after the edit
+fn shift_token(token: &Token, growth: isize, lines: isize) -> Token {
+    return Token::new(
+        token.token_type,
+        ((token.start.0 as isize + lines) as u32, token.start.1),
+        shift(token.start_offset, growth),
+        ((token.end.0 as isize + lines) as u32, token.end.1),
+        shift(token.end_offset, growth),
+    );
+}
+
+/// Counts the lines in the bytes
+fn line_count(bytes: &[u8]) -> isize {
+    return bytes.iter().filter(|byte| **byte == b'\n').count() as isize;
+}
+
 /// Struct used to store the tokenizer state constants
 #[non_exhaustive]
 pub struct TokenizerState {}
@@ -261,4 +493,8 @@ impl TokenizerState {
     pub const CODE: u64 = 0xC;
     /// A block of code that returns to a program
     pub const CODE_TO_STRUCT_TOP: u64 = 0xD;
+    /// The default value of a field, which returns to the inside of the program at the ;
+    pub const FIELD_VALUE: u64 = 0xE;
+    /// A string inside the default value of a field
+    pub const STRING_TO_FIELD_VALUE: u64 = 0xF;
 }
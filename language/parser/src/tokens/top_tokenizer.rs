@@ -1,5 +1,7 @@
 use crate::tokens::tokenizer::{Tokenizer, TokenizerState};
-use crate::tokens::util::{parse_attribute_val, parse_modifier, parse_to_character};
+use crate::tokens::util::{
+    parse_acceptable, parse_attribute_val, parse_modifier, parse_to_character, parse_type_to_character,
+};
 use data::tokens::{Token, TokenTypes};
 
 /// Handles when the tokenizer isn't in any other state.
@@ -41,7 +43,7 @@ pub fn next_top_token(tokenizer: &mut Tokenizer) -> Token {
             if tokenizer.matches("=") {
                 // Handles the code for the field's value
                 if tokenizer.state == TokenizerState::TOP_ELEMENT_TO_STRUCT {
-                    tokenizer.state = TokenizerState::CODE_TO_STRUCT_TOP;
+                    tokenizer.state = TokenizerState::FIELD_VALUE;
                 } else {
                     tokenizer.state = TokenizerState::CODE;
                 }
@@ -178,7 +180,7 @@ pub fn next_func_token(tokenizer: &mut Tokenizer) -> Token {
                 }
             }
         }
-        TokenTypes::ArgumentTypeSeparator => parse_to_character(tokenizer, TokenTypes::ArgumentType, &[b',', b')']),
+        TokenTypes::ArgumentTypeSeparator => parse_type_to_character(tokenizer, TokenTypes::ArgumentType, &[b',', b')']),
         TokenTypes::ArgumentType => {
             if tokenizer.matches(",") {
                 tokenizer.make_token(TokenTypes::ArgumentSeparator)
@@ -223,11 +225,19 @@ pub fn get_return_token(tokenizer: &mut Tokenizer) -> Token {
 /// program name, and the start of the code.
 pub fn next_struct_token(tokenizer: &mut Tokenizer) -> Token {
     match tokenizer.last.token_type {
-        TokenTypes::StructStart | TokenTypes::TraitStart | TokenTypes::For => {
+        // Struct names stop at whitespace, since they can be followed by extends
+        TokenTypes::StructStart => {
+            tokenizer.next_included()?;
+            tokenizer.index -= 1;
+            parse_acceptable(tokenizer, TokenTypes::Identifier)
+        }
+        TokenTypes::TraitStart | TokenTypes::For | TokenTypes::Extends => {
             parse_to_character(tokenizer, TokenTypes::Identifier, &[b'{', b'<'])
         }
         TokenTypes::Identifier | TokenTypes::GenericsEnd => {
-            if tokenizer.matches("<") {
+            if tokenizer.matches_word("extends") {
+                tokenizer.make_token(TokenTypes::Extends)
+            } else if tokenizer.matches("<") {
                 tokenizer.state = TokenizerState::GENERIC_TO_STRUCT;
                 tokenizer.make_token(TokenTypes::GenericsStart)
             } else if tokenizer.matches("{") {
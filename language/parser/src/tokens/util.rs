@@ -1,6 +1,6 @@
 use crate::tokens::tokenizer::{Tokenizer, TokenizerState};
 use data::tokens::{Token, TokenTypes};
-use syntax::MODIFIERS;
+use syntax::{Modifier, MODIFIERS};
 
 /// Parses to one of the provided end characters
 pub fn parse_to_character(tokenizer: &mut Tokenizer, token_type: TokenTypes, end: &[u8]) -> Token {
@@ -9,6 +9,22 @@ pub fn parse_to_character(tokenizer: &mut Tokenizer, token_type: TokenTypes, end
     return tokenizer.make_token(token_type);
 }
 
+/// Parses a type to one of the provided end characters, skipping the ones in its generics like the comma in Fn<u64, u64>
+pub fn parse_type_to_character(tokenizer: &mut Tokenizer, token_type: TokenTypes, end: &[u8]) -> Token {
+    let mut depth = 0;
+    loop {
+        let next = tokenizer.next_included()?;
+        match next {
+            b'<' => depth += 1,
+            b'>' => depth -= 1,
+            _ if depth == 0 && end.contains(&next) => break,
+            _ => {}
+        }
+    }
+    tokenizer.index -= 1;
+    return tokenizer.make_token(token_type);
+}
+
 /// Parses the value of an attribute
 pub fn parse_attribute_val(tokenizer: &mut Tokenizer, token_type: TokenTypes) -> Token {
     let mut depth = 1;
@@ -35,7 +51,9 @@ pub fn parse_acceptable(tokenizer: &mut Tokenizer, token_type: TokenTypes) -> To
             return tokenizer.make_token(TokenTypes::EOF);
         }
         let character = tokenizer.buffer[tokenizer.index] as char;
-        if !character.is_alphanumeric() && character != ':' && character != '_' {
+        // Multi-byte whitespace, like a non-breaking space, ends the name even though its bytes look alphanumeric
+        let whitespace = !character.is_ascii() && tokenizer.utf8_whitespace_length(tokenizer.index).is_some();
+        if whitespace || !character.is_alphanumeric() && character != ':' && character != '_' {
             if tokenizer.buffer[tokenizer.index - 1] == b':' {
                 tokenizer.index -= 1;
             }
@@ -87,7 +105,8 @@ pub fn parse_numbers(tokenizer: &mut Tokenizer) -> Token {
 
 /// Parses any modifiers.
 pub fn parse_modifier(tokenizer: &mut Tokenizer) -> Option<Token> {
-    for modifier in MODIFIERS {
+    // Operation comes from the operation attribute, so it can't be written as a modifier
+    for modifier in MODIFIERS.iter().filter(|modifier| **modifier != Modifier::Operation) {
         if tokenizer.matches(format!("{}", modifier).as_str()) {
             return Some(tokenizer.make_token(TokenTypes::Modifier));
         }
@@ -112,10 +131,10 @@ pub fn parse_string(tokenizer: &mut Tokenizer) -> Token {
                 return if
                 /*tokenizer.last.token_type != TokenTypes::StringEscape*/
                 tokenizer.buffer[tokenizer.index - 1] != b'\\' {
-                    tokenizer.state = if tokenizer.state == TokenizerState::STRING_TO_CODE_STRUCT_TOP {
-                        TokenizerState::CODE_TO_STRUCT_TOP
-                    } else {
-                        TokenizerState::CODE
+                    tokenizer.state = match tokenizer.state {
+                        TokenizerState::STRING_TO_CODE_STRUCT_TOP => TokenizerState::CODE_TO_STRUCT_TOP,
+                        TokenizerState::STRING_TO_FIELD_VALUE => TokenizerState::FIELD_VALUE,
+                        _ => TokenizerState::CODE,
                     };
                     tokenizer.make_token(TokenTypes::StringEnd)
                 } else {
@@ -123,16 +142,28 @@ pub fn parse_string(tokenizer: &mut Tokenizer) -> Token {
                 };
             }
             b'\\' => {
-                // if it is a hex value, then increment the tokenizer by an extra 2 because
-                // the escape character is 4 characters long instead of 2 (ex. \xAA)
-                if tokenizer.buffer[tokenizer.index] == b'x' {
-                    tokenizer.index += 2;
+                match tokenizer.buffer.get(tokenizer.index) {
+                    // if it is a hex value, then increment the tokenizer by an extra 2 because
+                    // the escape character is 4 characters long instead of 2 (ex. \xAA)
+                    Some(b'x') => tokenizer.index += 2,
+                    // unicode escapes run until the closing brace (ex. \u{1F600}), without eating the string's end
+                    Some(b'u') if tokenizer.buffer.get(tokenizer.index + 1) == Some(&b'{') => {
+                        while tokenizer.index < tokenizer.len
+                            && !matches!(tokenizer.buffer[tokenizer.index], b'}' | b'"' | b'\n')
+                        {
+                            tokenizer.index += 1;
+                        }
+                        if tokenizer.buffer.get(tokenizer.index) != Some(&b'}') {
+                            return tokenizer.make_token(TokenTypes::StringEscape);
+                        }
+                    }
+                    _ => {}
                 }
 
                 // increment the tokenizer so that it includes the \
                 // if you didn't do this, then the character being escaped (ex. n or t or r)
                 //   would be included in the string
-                tokenizer.index += 1;
+                tokenizer.index = (tokenizer.index + 1).min(tokenizer.len);
 
                 return tokenizer.make_token(TokenTypes::StringEscape);
             }
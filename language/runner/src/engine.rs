@@ -0,0 +1,165 @@
+use std::mem;
+use std::sync::Arc;
+
+use data::arena::Arena;
+use data::{Arguments, CompilerArguments, RavenExtern, RunnerSettings, SourceSet, ToRaven};
+use parser::StringSourceSet;
+use syntax::errors::ParsingError;
+use syntax::program::syntax::Program;
+
+use crate::runner::{compile_program, create_syntax};
+
+/// Builds an Engine from scripts kept in memory, the host functions they can call, and any other sources
+/// they need like the core library
+pub struct EngineBuilder {
+    /// Other sources used by the scripts
+    sources: Vec<Box<dyn SourceSet>>,
+    /// The scripts
+    scripts: StringSourceSet,
+    /// The module of every script, whose functions can be called from Rust
+    modules: Vec<String>,
+    /// The extern declarations of the host functions, compiled as the host module
+    host: String,
+    /// The symbol and address of each host function
+    host_functions: Vec<(String, usize)>,
+}
+
+impl EngineBuilder {
+    /// Adds a source set the scripts can import from, like the core library
+    pub fn source_set(mut self, source: Box<dyn SourceSet>) -> Self {
+        self.sources.push(source);
+        return self;
+    }
+
+    /// Adds a script as the module, like my_script or util::math
+    pub fn script(mut self, module: &str, contents: &str) -> Self {
+        self.scripts.add(module, contents.to_string());
+        self.modules.push(module.to_string());
+        return self;
+    }
+
+    /// Adds a Rust function the scripts can call from the host module, like host::log.
+    /// The signature is written in Raven, like (message: str) -> u64, and the function must be an extern "C" fn
+    /// taking and returning the values themselves, the same as a C function linked with #[link_name].
+    ///
+    /// # Safety
+    /// The function must be an extern "C" fn with the signature, since scripts call it without any checks
+    pub unsafe fn host_function(mut self, name: &str, signature: &str, function: *const ()) -> Self {
+        let symbol = format!("raven_host_{}", name);
+        self.host.push_str(&format!("#[link_name(\"{}\")]\npub extern fn {}{} {{\n}}\n\n", symbol, name, signature));
+        self.host_functions.push((symbol, function as usize));
+        return self;
+    }
+
+    /// Compiles the scripts, returning every error found if any of them failed to compile
+    pub fn build(mut self) -> Result<Engine, Vec<ParsingError>> {
+        if !self.host.is_empty() {
+            self.scripts.add("host", self.host);
+        }
+        self.sources.push(Box::new(self.scripts));
+
+        let arguments = Arguments::build_args(
+            false,
+            RunnerSettings {
+                sources: self.sources,
+                compiler_arguments: CompilerArguments { compiler: "llvm".to_string(), ..Default::default() },
+            },
+        );
+        let syntax = create_syntax(&arguments);
+        let program =
+            arguments.cpu_runtime.block_on(compile_program(syntax, &arguments, self.modules, self.host_functions))?;
+        return Ok(Engine { program });
+    }
+}
+
+/// Raven embedded in a Rust program, with compiled scripts that can be called by name
+pub struct Engine {
+    /// The compiled scripts
+    program: Box<dyn Program>,
+}
+
+impl Engine {
+    /// Creates a builder for an engine with no sources
+    pub fn builder() -> EngineBuilder {
+        return EngineBuilder {
+            sources: Vec::default(),
+            scripts: StringSourceSet::new("scripts".to_string()),
+            modules: Vec::default(),
+            host: String::default(),
+            host_functions: Vec::default(),
+        };
+    }
+
+    /// Calls a script's function by its full name, like my_script::main, returning None if there's no such function.
+    /// Each call gets an arena of its own, which everything the call allocated is freed with once it returns.
+    ///
+    /// # Safety
+    /// The arguments and return type must match the Raven function's, or Raven will read them as the wrong types
+    pub unsafe fn call<A: RavenArguments, R: RavenExtern>(&self, name: &str, arguments: A) -> Option<R> {
+        let address = self.program.get_function(name)?;
+        return Some(call_function(address, &arguments, &Arc::default()));
+    }
+}
+
+/// Calls the compiled Raven function at the address inside the arena, translating the value it returns.
+/// The arguments and the returned value are freed from the arena after, and anything else the call allocated
+/// stays in it until the arena is cleared.
+///
+/// # Safety
+/// The address must be a compiled Raven function taking the arguments and returning the type
+pub unsafe fn call_function<A: RavenArguments, R: RavenExtern>(address: usize, arguments: &A, arena: &Arc<Arena>) -> R {
+    let _entered = arena.enter();
+    return arguments.call(address, arena);
+}
+
+/// Arguments a Raven function can be called with.
+/// Raven takes every argument by reference, so each one is copied into Raven's memory and passed as a pointer to it.
+pub trait RavenArguments {
+    /// Calls the function at the address with the arguments, translating the value it returns.
+    /// The arguments and the returned value are freed from the arena once the call is done.
+    ///
+    /// # Safety
+    /// The address must be a compiled Raven function taking these arguments
+    unsafe fn call<R: RavenExtern>(&self, address: usize, arena: &Arena) -> R;
+}
+
+impl RavenArguments for () {
+    unsafe fn call<R: RavenExtern>(&self, address: usize, arena: &Arena) -> R {
+        let function: unsafe extern "C" fn() -> *mut R::Input = mem::transmute(address);
+        return R::translate(function(), arena);
+    }
+}
+
+impl<A: ToRaven> RavenArguments for (A,) {
+    unsafe fn call<R: RavenExtern>(&self, address: usize, arena: &Arena) -> R {
+        let function: unsafe extern "C" fn(*mut A::Input) -> *mut R::Input = mem::transmute(address);
+        let first = self.0.to_raven();
+        let output = R::translate(function(first), arena);
+        A::free(first, arena);
+        return output;
+    }
+}
+
+impl<A: ToRaven, B: ToRaven> RavenArguments for (A, B) {
+    unsafe fn call<R: RavenExtern>(&self, address: usize, arena: &Arena) -> R {
+        let function: unsafe extern "C" fn(*mut A::Input, *mut B::Input) -> *mut R::Input = mem::transmute(address);
+        let (first, second) = (self.0.to_raven(), self.1.to_raven());
+        let output = R::translate(function(first, second), arena);
+        A::free(first, arena);
+        B::free(second, arena);
+        return output;
+    }
+}
+
+impl<A: ToRaven, B: ToRaven, C: ToRaven> RavenArguments for (A, B, C) {
+    unsafe fn call<R: RavenExtern>(&self, address: usize, arena: &Arena) -> R {
+        let function: unsafe extern "C" fn(*mut A::Input, *mut B::Input, *mut C::Input) -> *mut R::Input =
+            mem::transmute(address);
+        let (first, second, third) = (self.0.to_raven(), self.1.to_raven(), self.2.to_raven());
+        let output = R::translate(function(first, second, third), arena);
+        A::free(first, arena);
+        B::free(second, arena);
+        C::free(third, arena);
+        return output;
+    }
+}
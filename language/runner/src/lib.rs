@@ -1,17 +1,23 @@
-use compiler_llvm::LLVMCompiler;
+use compiler_llvm::{check_target, LLVMCompiler};
 use dashmap::DashMap;
+use data::tokens::Span;
 use data::CompilerArguments;
 use parking_lot::Mutex;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use syntax::async_util::HandleWrapper;
-use syntax::errors::ParsingError;
+use syntax::errors::{ParsingError, ParsingMessage};
 use syntax::program::function::FinalizedFunction;
 use syntax::program::r#struct::FinalizedStruct;
-use syntax::program::syntax::Compiler;
+use syntax::program::syntax::{Compiler, Syntax};
+use tokio::time;
+use tokio::time::{Interval, MissedTickBehavior, Sleep};
 
+/// An API for embedding Raven in Rust programs
+pub mod engine;
 /// The main Raven runner
 pub mod runner;
 
@@ -27,17 +33,74 @@ pub fn get_compiler<T>(
     });
 }
 
-/// A future used to wait for the handle to finish
+/// Checks that the compiler in the arguments can compile for their target triple, if there is one
+pub fn check_compiler_target(arguments: &CompilerArguments) -> Result<(), String> {
+    let Some(triple) = &arguments.target_triple else {
+        return Ok(());
+    };
+    return match arguments.compiler.to_lowercase().as_str() {
+        "llvm" => check_target(triple),
+        _ => panic!("Unknown compilers {}", arguments.compiler),
+    };
+}
+
+/// A future used to wait for the handle to finish, which gives up on the tasks if they take too long
 pub struct JoinWaiter {
     /// The handle to wait on
     handle: Arc<Mutex<HandleWrapper>>,
+    /// The program, used to find what stuck tasks are waiting for
+    syntax: Arc<Mutex<Syntax>>,
+    /// How long to wait for
+    timeout: Duration,
+    /// Sleeps until the timeout passes
+    deadline: Pin<Box<Sleep>>,
+}
+
+impl JoinWaiter {
+    /// Creates a waiter for the handle's tasks, which errors if they aren't finished after the timeout
+    pub fn new(handle: Arc<Mutex<HandleWrapper>>, syntax: Arc<Mutex<Syntax>>, timeout: Duration) -> Self {
+        return Self { handle, syntax, timeout, deadline: Box::pin(time::sleep(timeout)) };
+    }
+
+    /// Aborts every task still running, returning an error with how long each ran and what it's waiting for
+    fn timed_out(&self) -> ParsingError {
+        let running = {
+            let mut locked = self.handle.lock();
+            let running = locked.started.iter().map(|(name, started)| (name.clone(), started.elapsed())).collect::<Vec<_>>();
+            locked.abort_all();
+            running
+        };
+
+        let locked = self.syntax.lock();
+        let mut tasks = running
+            .into_iter()
+            .map(|(name, running)| {
+                let waiting = locked.async_manager.blocked.get(&name).map_or("Not waiting on anything", String::as_str);
+                format!("{} (running for {}s): {}", name, running.as_secs(), waiting)
+            })
+            .collect::<Vec<_>>();
+        // Sorted so the same tasks are always reported in the same order
+        tasks.sort();
+        return ParsingError::new(Span::default(), ParsingMessage::Timeout(self.timeout.as_secs(), tasks));
+    }
 }
 
 impl Future for JoinWaiter {
     type Output = Result<(), ParsingError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.deadline.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Err(self.timed_out()));
+        }
+
         let mut locked = self.handle.lock();
+        // Cancelled compiles stop every task instead of waiting for them.
+        // The waker is registered first so a cancel right after the check still wakes this.
+        locked.cancellation.register(cx.waker());
+        if locked.cancellation.is_cancelled() {
+            locked.abort_all();
+            return Poll::Ready(Err(ParsingError::new(Span::default(), ParsingMessage::Cancelled)));
+        }
 
         let mut removing = Vec::default();
 
@@ -72,3 +135,45 @@ impl Future for JoinWaiter {
         };
     }
 }
+
+/// How often a ProgressWaiter reports the task count, which is slow enough to not fight the tasks for the handle's lock
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A future that reports how many tasks the handle is running while waiting on another future
+pub struct ProgressWaiter<F: Future> {
+    /// The handle running the tasks
+    handle: Arc<Mutex<HandleWrapper>>,
+    /// The future being waited on
+    future: Pin<Box<F>>,
+    /// Ticks whenever the count should be reported
+    interval: Interval,
+    /// Called with the count, then with 0 once the future finishes
+    report: fn(usize),
+}
+
+impl<F: Future> ProgressWaiter<F> {
+    /// Creates a waiter for the future, which reports the handle's task count every PROGRESS_INTERVAL
+    pub fn new(handle: Arc<Mutex<HandleWrapper>>, future: F, report: fn(usize)) -> Self {
+        let mut interval = time::interval(PROGRESS_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        return Self { handle, future: Box::pin(future), interval, report };
+    }
+}
+
+impl<F: Future> Future for ProgressWaiter<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            (self.report)(0);
+            return Poll::Ready(output);
+        }
+
+        // Polled until pending so the interval wakes this again on the next tick
+        while self.interval.poll_tick(cx).is_ready() {
+            let running = self.handle.lock().active_task_count();
+            (self.report)(running);
+        }
+        return Poll::Pending;
+    }
+}
@@ -1,5 +1,4 @@
 use std::sync::Arc;
-use std::time::Duration;
 
 use anyhow::Error;
 use parking_lot::Mutex;
@@ -8,18 +7,28 @@ use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time;
 
 use checker::output::TypesChecker;
-use data::{Arguments, CompilerArguments};
+use data::{Arguments, CompilerArguments, RavenExtern};
 use parser::parse;
 use syntax::async_util::HandleWrapper;
 use syntax::errors::ParsingError;
-use syntax::program::syntax::Syntax;
+use syntax::program::syntax::{Program, Syntax};
 
-use crate::{get_compiler, JoinWaiter};
+use crate::engine::{call_function, RavenArguments};
+use crate::{get_compiler, JoinWaiter, ProgressWaiter};
 
 pub fn create_syntax(settings: &Arguments) -> Arc<Mutex<Syntax>> {
-    let handle = Arc::new(Mutex::new(HandleWrapper::new(settings.cpu_runtime.handle().clone())));
-    let mut syntax = Syntax::new(Box::new(TypesChecker::new(handle.clone(), settings.runner_settings.include_references())));
+    let mut wrapper = HandleWrapper::new(settings.cpu_runtime.handle().clone());
+    wrapper.cancellation = settings.cancellation.clone();
+    let handle = Arc::new(Mutex::new(wrapper));
+    let cfg = settings.runner_settings.compiler_arguments.cfg.clone();
+    let checker =
+        TypesChecker::new(handle.clone(), settings.runner_settings.include_references(), cfg, settings.cancellation.clone());
+    let mut syntax = Syntax::new(Box::new(checker));
     syntax.async_manager.target.clone_from(&settings.runner_settings.compiler_arguments.target);
+    syntax.async_manager.max_generic_depth = settings.runner_settings.compiler_arguments.max_generic_depth;
+    syntax.async_manager.deny_warnings = settings.runner_settings.compiler_arguments.deny_warnings;
+    syntax.async_manager.debug = settings.runner_settings.compiler_arguments.debug;
+    syntax.async_manager.dump_ast = settings.runner_settings.compiler_arguments.dump_ast;
     return Arc::new(Mutex::new(syntax));
 }
 
@@ -34,21 +43,20 @@ pub async fn build(syntax: Arc<Mutex<Syntax>>, settings: &Arguments) -> Result<(
                 continue;
             }
 
-            handles.push(
-                settings
-                    .io_runtime
-                    .as_ref()
-                    .map(|inner| inner.handle().clone())
-                    .unwrap_or_else(|| settings.cpu_runtime.handle().clone())
-                    .spawn(parse(syntax.clone(), handle.clone(), source_set.relative(&*file).clone(), file)),
-            );
+            handles.push(settings.io_or_cpu().spawn(parse(
+                syntax.clone(),
+                handle.clone(),
+                source_set.relative(&*file).clone(),
+                file,
+            )));
         }
     }
 
+    let timeout = settings.runner_settings.compiler_arguments.timeout;
     let mut errors = Vec::default();
     //Join any parsing errors
     for handle in handles {
-        match time::timeout(Duration::from_secs(60), handle).await {
+        match time::timeout(timeout, handle).await {
             Err(error) => errors.push(Error::new(error)),
             Ok(_) => {}
         }
@@ -64,21 +72,13 @@ pub async fn build(syntax: Arc<Mutex<Syntax>>, settings: &Arguments) -> Result<(
     syntax.lock().finish();
 
     let mut errors = vec![];
-    let waiter = JoinWaiter { handle: handle.clone() };
-    match time::timeout(Duration::from_secs(60), waiter).await {
-        Ok(error) => {
-            if let Err(error) = error {
-                errors.push(error);
-            }
-        }
-        Err(_) => {
-            eprintln!("Detected infinite loops:");
-            for (name, _) in &handle.lock().names {
-                eprintln!("Infinite loop for {}", name);
-            }
-            let length = handle.lock().joining.len();
-            panic!("Failed to parse with {} ({}) infinite loops", length, handle.lock().names.len());
-        }
+    let waiter = JoinWaiter::new(handle.clone(), syntax.clone(), timeout);
+    let joined = match settings.progress {
+        Some(report) => ProgressWaiter::new(handle, waiter, report).await,
+        None => waiter.await,
+    };
+    if let Err(error) = joined {
+        errors.push(error);
     }
 
     errors.append(&mut syntax.lock().errors);
@@ -90,6 +90,8 @@ pub async fn run<T: Send + 'static>(
     syntax: Arc<Mutex<Syntax>>,
     settings: &Arguments,
 ) -> Result<Option<T>, Vec<ParsingError>> {
+    // The last run's memory is freed, so running with the same arguments over and over doesn't grow the arena
+    settings.runner_settings.compiler_arguments.arena.clear();
     let (sender, mut receiver) = mpsc::channel(1);
     let (go_sender, go_receiver) = mpsc::channel(1);
 
@@ -104,12 +106,98 @@ pub async fn run<T: Send + 'static>(
     build(syntax.clone(), settings).await?;
 
     go_sender.send(()).await.unwrap();
-    return match receiver.recv().await {
+    let received = receiver.recv().await;
+    compile_errors(&syntax)?;
+    return match received {
         Some(value) => Ok(value),
         None => Err(vec![]),
     };
 }
 
+/// Runs Raven with the given arguments, calling the target function with the values.
+/// The target's module is compiled as a program, since the target can't be called like a main function with no arguments.
+/// The target is called the same way as with Engine::call, in an arena of its own.
+///
+/// # Safety
+/// The values and return type must match the target's arguments and return type, like with Engine::call
+pub async unsafe fn run_with<A: RavenArguments, R: RavenExtern>(
+    syntax: Arc<Mutex<Syntax>>,
+    settings: &Arguments,
+    values: A,
+) -> Result<Option<R>, Vec<ParsingError>> {
+    let target = &settings.runner_settings.compiler_arguments.target;
+    let module = target.rsplit_once("::").map_or(target.as_str(), |(module, _)| module);
+    let program = compile_program(syntax, settings, vec![module.to_string()], vec![]).await?;
+    return Ok(program.get_function(target).map(|address| call_function(address, &values, &Arc::default())));
+}
+
+/// Compiles Raven with the given arguments into the compiler's textual output without running it
+pub async fn compile_syntax(syntax: Arc<Mutex<Syntax>>, settings: &Arguments) -> Result<String, Vec<ParsingError>> {
+    let (sender, mut receiver) = mpsc::channel(1);
+    let (go_sender, go_receiver) = mpsc::channel(1);
+
+    // Starts the compiler in anticipation of parsing
+    settings.cpu_runtime.spawn(start_to_string(
+        settings.runner_settings.compiler_arguments.clone(),
+        sender,
+        go_receiver,
+        syntax.clone(),
+    ));
+
+    build(syntax.clone(), settings).await?;
+
+    go_sender.send(()).await.unwrap();
+    let received = receiver.recv().await;
+    compile_errors(&syntax)?;
+    return match received {
+        Some(Some(value)) => Ok(value),
+        _ => Err(vec![]),
+    };
+}
+
+/// Compiles every function in the modules into a program that stays loaded, linking the host functions into it
+pub async fn compile_program(
+    syntax: Arc<Mutex<Syntax>>,
+    settings: &Arguments,
+    modules: Vec<String>,
+    host_functions: Vec<(String, usize)>,
+) -> Result<Box<dyn Program>, Vec<ParsingError>> {
+    {
+        // Programs have no main function, every function in the modules is compiled instead
+        let mut locked = syntax.lock();
+        locked.async_manager.target.clear();
+        locked.async_manager.modules.clone_from(&modules);
+    }
+    let (sender, mut receiver) = mpsc::channel(1);
+    let (go_sender, go_receiver) = mpsc::channel(1);
+
+    // Starts the compiler in anticipation of parsing
+    settings.cpu_runtime.spawn(start_program(
+        settings.runner_settings.compiler_arguments.clone(),
+        sender,
+        go_receiver,
+        syntax.clone(),
+        modules,
+        host_functions,
+    ));
+
+    build(syntax.clone(), settings).await?;
+
+    go_sender.send(()).await.unwrap();
+    let received = receiver.recv().await;
+    compile_errors(&syntax)?;
+    return match received {
+        Some(Some(program)) => Ok(program),
+        _ => Err(vec![]),
+    };
+}
+
+/// Gets any errors found while compiling, like internal compiler errors
+fn compile_errors(syntax: &Arc<Mutex<Syntax>>) -> Result<(), Vec<ParsingError>> {
+    let errors = std::mem::take(&mut syntax.lock().errors);
+    return if errors.is_empty() { Ok(()) } else { Err(errors) };
+}
+
 /// Runs the compiler, waiting for the receiver before running the main function then sending the result on the sender.
 pub async fn start<T>(
     compiler_arguments: CompilerArguments,
@@ -125,3 +213,37 @@ pub async fn start<T>(
 
     let _ = sender.send(code_compiler.compile(receiver, &syntax).await).await;
 }
+
+/// Runs the compiler, waiting for the receiver before compiling then sending the textual output on the sender.
+pub async fn start_to_string(
+    compiler_arguments: CompilerArguments,
+    sender: Sender<Option<String>>,
+    receiver: Receiver<()>,
+    syntax: Arc<Mutex<Syntax>>,
+) {
+    let code_compiler;
+    {
+        let locked = syntax.lock();
+        code_compiler = get_compiler::<()>(locked.compiling.clone(), locked.strut_compiling.clone(), compiler_arguments);
+    }
+
+    let _ = sender.send(code_compiler.compile_to_string(receiver, &syntax).await).await;
+}
+
+/// Runs the compiler, waiting for the receiver before compiling the modules then sending the program on the sender.
+pub async fn start_program(
+    compiler_arguments: CompilerArguments,
+    sender: Sender<Option<Box<dyn Program>>>,
+    receiver: Receiver<()>,
+    syntax: Arc<Mutex<Syntax>>,
+    modules: Vec<String>,
+    host_functions: Vec<(String, usize)>,
+) {
+    let code_compiler;
+    {
+        let locked = syntax.lock();
+        code_compiler = get_compiler::<()>(locked.compiling.clone(), locked.strut_compiling.clone(), compiler_arguments);
+    }
+
+    let _ = sender.send(code_compiler.compile_program(receiver, &syntax, modules, host_functions).await).await;
+}
@@ -8,11 +8,13 @@ use std::ops::DerefMut;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
+use std::time::Instant;
 
 use tokio::runtime::Handle;
 use tokio::task::{AbortHandle, JoinHandle};
 
 use data::tokens::Span;
+use data::CancellationToken;
 
 use crate::errors::{ErrorSource, ParsingMessage};
 use crate::program::function::display_parenless;
@@ -20,6 +22,11 @@ use crate::program::syntax::Syntax;
 use crate::program::types::FinalizedTypes;
 use crate::{FinishedStructImplementor, ParsingError, TopElement};
 
+tokio::task_local! {
+    /// The name of the task being polled, for tasks spawned by a HandleWrapper
+    pub static TASK_NAME: String;
+}
+
 /// A future that asynchronously gets a type from its respective AsyncGetter.
 /// Will never deadlock because types are added to the AsyncGetter before being finalized.
 pub struct AsyncTypesGetter<T: TopElement> {
@@ -141,6 +148,7 @@ impl<T: TopElement> Future for AsyncTypesGetter<T> {
         let not_trait = self.not_trait;
         let locked = self.syntax.clone();
         let mut locked = locked.lock();
+        locked.async_manager.unblock();
 
         // Check if an element directly referenced with that name exists.
         if let Some(output) = self.get_types(&mut locked, String::default(), cx.waker().clone(), not_trait) {
@@ -162,6 +170,7 @@ impl<T: TopElement> Future for AsyncTypesGetter<T> {
         }
 
         // Parsing isn't finished, so this sleeps.
+        locked.async_manager.block(|| format!("AsyncTypesGetter looking for {}", self.getting));
         return Poll::Pending;
     }
 }
@@ -183,6 +192,7 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let locked = self.syntax.clone();
         let mut locked = locked.lock();
+        locked.async_manager.unblock();
 
         let manager = T::get_manager(locked.deref_mut());
 
@@ -193,6 +203,7 @@ where
 
         // The finalized element doesn't exist, sleep.
         manager.wakers.entry(self.getting.name().clone()).or_insert(vec![]).push(cx.waker().clone());
+        locked.async_manager.block(|| format!("AsyncDataGetter waiting for {} to be finalized", self.getting.name()));
 
         // This never panics because as long as the data exists, every element will be finalized.
         return Poll::Pending;
@@ -219,8 +230,10 @@ impl Future for AsyncStructImplGetter {
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut locked = self.syntax.lock();
+        locked.async_manager.unblock();
         if !locked.finished_impls() {
             locked.async_manager.impl_waiters.push(cx.waker().clone());
+            locked.async_manager.block(|| format!("AsyncStructImplGetter waiting for the impls of {}", self.getting));
             return Poll::Pending;
         }
 
@@ -326,28 +339,56 @@ pub struct HandleWrapper {
     pub joining: Vec<JoinHandle<Result<(), ParsingError>>>,
     /// The names of running tasks and a handle to abort them
     pub names: HashMap<String, AbortHandle>,
+    /// When each running task was spawned, by name
+    pub started: HashMap<String, Instant>,
     /// A waker to wake when finished with a task
     pub waker: Option<Waker>,
+    /// Cancels the tasks, checked by long-running tasks like degenericing
+    pub cancellation: CancellationToken,
 }
 
 impl HandleWrapper {
     /// Creates a new handle wrapper
     pub fn new(handle: Handle) -> HandleWrapper {
-        return HandleWrapper { handle, joining: vec![], names: HashMap::default(), waker: None };
+        return HandleWrapper {
+            handle,
+            joining: vec![],
+            names: HashMap::default(),
+            started: HashMap::default(),
+            waker: None,
+            cancellation: CancellationToken::default(),
+        };
     }
-    /// Spawns a task and adds it to the joining vec
+    /// Spawns a task and adds it to the joining vec. The task can find its name with TASK_NAME.
     pub fn spawn<F: Future<Output = Result<(), ParsingError>> + Send + 'static>(&mut self, name: String, future: F) {
-        let handle = self.handle.spawn(future);
-        self.names.insert(name, handle.abort_handle());
+        let handle = self.handle.spawn(TASK_NAME.scope(name.clone(), future));
+        self.names.insert(name.clone(), handle.abort_handle());
+        self.started.insert(name, Instant::now());
 
         self.joining.push(handle);
     }
 
+    /// How many tasks haven't been joined yet
+    pub fn active_task_count(&self) -> usize {
+        return self.joining.len();
+    }
+
     /// Tells the wrapper that a task finished, the waker will remove the handle from the handles vec
     pub fn finish_task(&mut self, name: &String) {
         self.names.remove(name);
+        self.started.remove(name);
         if let Some(found) = &self.waker {
             found.wake_by_ref();
         }
     }
+
+    /// Aborts every running task, used to stop tasks that will never finish.
+    /// Aborted tasks are never joined, so they're removed from the joining tasks too.
+    pub fn abort_all(&mut self) {
+        for task in self.joining.drain(..) {
+            task.abort();
+        }
+        self.names.clear();
+        self.started.clear();
+    }
 }
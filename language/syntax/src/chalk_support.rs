@@ -29,7 +29,7 @@ impl RustIrDatabase<ChalkIr> for Syntax {
 
     /// Gets the trait given the ID.
     fn trait_datum(&self, trait_id: chalk_ir::TraitId<ChalkIr>) -> Arc<TraitDatum<ChalkIr>> {
-        let found = self.structures.sorted.get(trait_id.0 as usize).unwrap();
+        let found = self.structures.ids.get(&(trait_id.0 as u64)).unwrap();
         if found.name.is_empty() {
             panic!("Got an empty structure!");
         }
@@ -41,7 +41,7 @@ impl RustIrDatabase<ChalkIr> for Syntax {
 
     /// Gets the program given the ID.
     fn adt_datum(&self, adt_id: AdtId<ChalkIr>) -> Arc<AdtDatum<ChalkIr>> {
-        let found = self.structures.sorted.get(adt_id.0 as usize).unwrap();
+        let found = self.structures.ids.get(&(adt_id.0 as u64)).unwrap();
         if found.name.is_empty() {
             panic!("Got an empty structure!");
         }
@@ -1,6 +1,6 @@
 use crate::program::types::FinalizedTypes;
 use data::tokens::Span;
-use data::SourceSet;
+use data::{Readable, SourceSet};
 use std::fmt::{Display, Formatter};
 
 use colored::Colorize;
@@ -30,17 +30,58 @@ pub enum ParsingMessage {
     FailedToFind(String),
     UnexpectedCharacters,
     DuplicateStructure,
-    DuplicateFunction,
+    DuplicateFunction(String, String),
     UnknownField(String),
     IncorrectBoundsLength,
     MismatchedTypes(FinalizedTypes, FinalizedTypes),
     UnknownOperation(String),
     UnknownFunction,
-    MissingArgument(u64, u64),
+    WrongArgumentCount(u64, u64),
     AmbiguousMethod(String),
     NoMethod(String, FinalizedTypes),
     NoImpl(FinalizedTypes, String),
     NoTraitImpl(FinalizedTypes, FinalizedTypes),
+    UninferableArray,
+    GenericDepthExceeded(FinalizedTypes, usize),
+    WhileInstantiating(String),
+    UnknownOperator(String, FinalizedTypes, Option<FinalizedTypes>),
+    UnknownAttribute(String, Option<String>),
+    MismatchedAttribute(String, &'static str, &'static str),
+    AmbiguousImpl(Vec<(String, Span)>),
+    ImplCandidate(String),
+    LambdaArguments(usize),
+    MutatedCapture(String),
+    Deprecated(String, Option<String>),
+    IdCollision(String, String),
+    InvalidEscape(String),
+    UnterminatedString,
+    MissingTraitFunction(String, String),
+    LiteralOutOfRange(i128, FinalizedTypes),
+    MismatchedOperands(String, FinalizedTypes, FinalizedTypes),
+    CyclicInheritance(String),
+    InternalCompilerError(String, String),
+    DuplicateTraitFunction(String, String),
+    Timeout(u64, Vec<String>),
+    IntegerOverflow(i128, String, i128),
+    DuplicateField(String),
+    UnusedBase(FinalizedTypes),
+    TryOutsideOption,
+    TryOnNonOption(FinalizedTypes),
+    MisplacedTry,
+    MisplacedLabel,
+    UnknownLabel(String, String),
+    OutsideLoop(String),
+    InvalidLinkAttribute,
+    LibraryNotLoaded(String),
+    LinkArgumentNotLoaded(String),
+    InvalidTarget(String),
+    FailedToWrite(String, String),
+    FailedToOptimize(String),
+    Cancelled,
+    AmbiguousOverload(String),
+    UnconstrainedGeneric(String),
+    UnsatisfiedImplBound(FinalizedTypes, FinalizedTypes, FinalizedTypes),
+    DuplicateSymbol(String, String),
 }
 
 impl Display for ParsingMessage {
@@ -71,15 +112,23 @@ impl Display for ParsingMessage {
             ParsingMessage::FailedToFind(name) => write!(f, "Failed to find type {}, did you import it correctly?", name),
             ParsingMessage::UnexpectedCharacters => write!(f, "Unexpected characters!"),
             ParsingMessage::DuplicateStructure => write!(f, "Duplicate structure!"),
-            ParsingMessage::DuplicateFunction => write!(f, "Duplicate function!"),
+            ParsingMessage::DuplicateFunction(types, function) => {
+                write!(f, "{} has the function {} more than once in this file", types, function)
+            }
             ParsingMessage::UnknownField(field) => write!(f, "Unknown field {}!", field),
             ParsingMessage::IncorrectBoundsLength => write!(f, "Incorrect bounds length!"),
             ParsingMessage::MismatchedTypes(found, bound) => {
                 write!(f, "{} isn't of type {}", fix_type(found), fix_type(bound))
             }
             ParsingMessage::UnknownOperation(operation) => write!(f, "Unknown operation '{}'", operation),
+            ParsingMessage::UnknownOperator(operator, first, second) => match second {
+                Some(second) => {
+                    write!(f, "No operator {} for {} and {}", operator, fix_type(first), fix_type(second))
+                }
+                None => write!(f, "No operator {} for {}", operator, fix_type(first)),
+            },
             ParsingMessage::UnknownFunction => write!(f, "Unknown function!"),
-            ParsingMessage::MissingArgument(expected, found) => {
+            ParsingMessage::WrongArgumentCount(expected, found) => {
                 write!(f, "Expected {} arguments but found {}!", expected, found)
             }
             ParsingMessage::AmbiguousMethod(name) => write!(f, "Ambiguous method {}!", name),
@@ -90,6 +139,100 @@ impl Display for ParsingMessage {
             ParsingMessage::NoTraitImpl(base, traits) => {
                 write!(f, "No implementation of {} for {}", fix_type(traits), fix_type(base))
             }
+            ParsingMessage::UninferableArray => write!(f, "Cannot infer array type!"),
+            ParsingMessage::WhileInstantiating(name) => write!(f, "While instantiating {}", name),
+            ParsingMessage::GenericDepthExceeded(types, depth) => {
+                write!(f, "{} nests generics deeper than the limit of {}", fix_type(types), depth)
+            }
+            ParsingMessage::UnknownAttribute(name, suggestion) => match suggestion {
+                Some(suggestion) => write!(f, "Unknown attribute {}, did you mean {}?", name, suggestion),
+                None => write!(f, "Unknown attribute {}!", name),
+            },
+            ParsingMessage::MismatchedAttribute(name, expected, found) => {
+                write!(f, "Attribute {} expects a {} value but found a {}", name, expected, found)
+            }
+            ParsingMessage::AmbiguousImpl(candidates) => write!(
+                f,
+                "Ambiguous implementation, candidates are: {}",
+                candidates.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", ")
+            ),
+            ParsingMessage::ImplCandidate(name) => write!(f, "Candidate implementation {}", name),
+            ParsingMessage::LambdaArguments(found) => write!(f, "Lambdas take exactly one argument, found {}", found),
+            ParsingMessage::IdCollision(first, second) => {
+                write!(f, "{} and {} have the same type ID, rename one of them", first, second)
+            }
+            ParsingMessage::Deprecated(name, message) => match message {
+                Some(message) => write!(f, "{} is deprecated: {}", name, message),
+                None => write!(f, "{} is deprecated!", name),
+            },
+            ParsingMessage::MutatedCapture(name) => {
+                write!(f, "Can't set {}, lambdas capture variables by value", name)
+            }
+            ParsingMessage::InvalidEscape(escape) => write!(f, "Invalid escape sequence \\{}", escape),
+            ParsingMessage::UnterminatedString => write!(f, "String is never closed!"),
+            ParsingMessage::MissingTraitFunction(trait_name, function) => {
+                write!(f, "Implementation of {} is missing the function {}", trait_name, function)
+            }
+            ParsingMessage::LiteralOutOfRange(value, types) => write!(f, "{} doesn't fit in a {}", value, fix_type(types)),
+            ParsingMessage::MismatchedOperands(operator, first, second) => write!(
+                f,
+                "Can't use {} on a {} and a {}, cast one of them first",
+                operator,
+                fix_type(first),
+                fix_type(second)
+            ),
+            ParsingMessage::CyclicInheritance(name) => write!(f, "{} extends itself through its parents", name),
+            ParsingMessage::InternalCompilerError(function, details) => {
+                write!(f, "Internal compiler error in {}, please report this! {}", function, details)
+            }
+            ParsingMessage::DuplicateTraitFunction(trait_name, function) => {
+                write!(f, "Implementation of {} has the function {} more than once", trait_name, function)
+            }
+            ParsingMessage::Timeout(seconds, tasks) => {
+                write!(f, "Checking didn't finish after {}s, these tasks are stuck:\n{}", seconds, tasks.join("\n"))
+            }
+            ParsingMessage::IntegerOverflow(first, operator, second) => {
+                write!(f, "{} {} {} overflows, so it will wrap at runtime", first, operator, second)
+            }
+            ParsingMessage::DuplicateField(name) => write!(f, "The field {} is set more than once", name),
+            ParsingMessage::UnusedBase(types) => {
+                write!(f, "Every field of {} is set, so the base after .. is never used", fix_type(types))
+            }
+            ParsingMessage::TryOutsideOption => write!(f, "? can only be used in functions that return an option"),
+            ParsingMessage::TryOnNonOption(types) => write!(f, "? can only be used on an option, not a {}", fix_type(types)),
+            ParsingMessage::MisplacedTry => write!(f, "? can only be used on its own line or as the value of a let"),
+            ParsingMessage::MisplacedLabel => write!(f, "Labels can only be put before a loop, like 'outer: while"),
+            ParsingMessage::UnknownLabel(label, keyword) => {
+                write!(f, "There's no loop labeled '{}' around this {}", label, keyword)
+            }
+            ParsingMessage::OutsideLoop(keyword) => write!(f, "{} can only be used in a loop", keyword),
+            ParsingMessage::InvalidLinkAttribute => {
+                write!(f, "The link attribute should name a library, like #[link(name = \"m\")]")
+            }
+            ParsingMessage::LibraryNotLoaded(name) => write!(f, "Couldn't load the library {} to link with", name),
+            ParsingMessage::LinkArgumentNotLoaded(name) => {
+                write!(f, "Couldn't load the library {} passed with --link", name)
+            }
+            ParsingMessage::InvalidTarget(message) => write!(f, "{}", message),
+            ParsingMessage::FailedToWrite(path, error) => write!(f, "Failed to write {}: {}", path, error),
+            ParsingMessage::FailedToOptimize(error) => write!(f, "Failed to optimize the program: {}", error),
+            ParsingMessage::Cancelled => write!(f, "The compile was cancelled"),
+            ParsingMessage::AmbiguousOverload(name) => {
+                write!(f, "More than one {} method matches the arguments equally well", name)
+            }
+            ParsingMessage::UnconstrainedGeneric(name) => {
+                write!(f, "The generic {} isn't used by the implemented types, so it can never be known", name)
+            }
+            ParsingMessage::UnsatisfiedImplBound(types, bound, traits) => write!(
+                f,
+                "{} doesn't implement {}, which the implementation of {} requires",
+                fix_type(types),
+                fix_type(bound),
+                fix_type(traits)
+            ),
+            ParsingMessage::DuplicateSymbol(symbol, other) => {
+                write!(f, "This function is compiled to the symbol {}, which {} already uses", symbol, other)
+            }
         };
     }
 }
@@ -102,6 +245,13 @@ fn fix_type(types: &FinalizedTypes) -> String {
     return string;
 }
 
+/// Labels a file with its path, prefixed by the source set's name if the path doesn't already start with it
+fn label(source: &dyn SourceSet, file: &dyn Readable) -> String {
+    let name = source.name();
+    let path = file.path();
+    return if path.starts_with(&name) { path } else { format!("{}/{}", name, path) };
+}
+
 /// An error somewhere in a source file, with exact location.
 #[derive(Clone, Debug)]
 pub struct ParsingError {
@@ -109,6 +259,8 @@ pub struct ParsingError {
     pub span: Span,
     /// The error message
     pub message: ParsingMessage,
+    /// The error that caused this one, if this error adds context to another
+    pub cause: Option<Box<ParsingError>>,
 }
 
 pub trait ErrorSource {
@@ -124,33 +276,56 @@ impl ErrorSource for Span {
 impl ParsingError {
     /// Creates a new error
     pub fn new(span: Span, message: ParsingMessage) -> Self {
-        return Self { span, message };
+        return Self { span, message, cause: None };
     }
 
-    /// Prints the error to console
-    pub fn print(&self, sources: &Vec<Box<dyn SourceSet>>) {
-        let mut file = None;
-        'outer: for source in sources {
+    /// Wraps this error as the cause of a new error with more context, like the call that caused it
+    pub fn chain(self, context: ParsingMessage, span: Span) -> ParsingError {
+        return Self { span, message: context, cause: Some(Box::new(self)) };
+    }
+
+    /// Iterates over this error and every error that caused it, from the outermost to the root cause
+    pub fn chained(&self) -> impl Iterator<Item = &ParsingError> {
+        return std::iter::successors(Some(self), |error| error.cause.as_deref());
+    }
+
+    /// Finds the file the error is in, and the source set it's from
+    fn find_file<'a>(&self, sources: &'a Vec<Box<dyn SourceSet>>) -> Option<(&'a dyn SourceSet, Box<dyn Readable>)> {
+        for source in sources {
             for readable in source.get_files() {
                 if self.span.file == readable.hash() {
-                    file = Some(readable);
-                    break 'outer;
+                    return Some((source.as_ref(), readable));
                 }
             }
         }
+        return None;
+    }
 
-        if file.is_none() {
+    /// Gets the label of the file the error is in. Embedded files like the core library's only have a path inside
+    /// their directory, so they're labeled with the source set's name, like <core>/iter.rv
+    pub fn file_label(&self, sources: &Vec<Box<dyn SourceSet>>) -> Option<String> {
+        return self.find_file(sources).map(|(source, file)| label(source, &*file));
+    }
+
+    /// Prints the error to console
+    pub fn print(&self, sources: &Vec<Box<dyn SourceSet>>) {
+        let Some((source, file)) = self.find_file(sources) else {
             eprintln!("Missing file: {}", self.message);
             return;
-        }
-        let file = file.unwrap();
+        };
+        let label = label(source, &*file);
         let contents = file.contents();
-        let tokens = file.read();
-        let mut token = tokens[self.span.start].clone();
-        if self.span.start != self.span.end {
-            let end = &tokens[self.span.end];
-            token.end = end.end;
-            token.end_offset = end.end_offset;
+        // Only the tokens up to the end of the span are tokenized
+        let mut tokens = file.read().skip(self.span.start);
+        let Some(mut token) = tokens.next() else {
+            eprintln!("Missing token in {}: {}", label, self.message);
+            return;
+        };
+        if self.span.end > self.span.start {
+            if let Some(end) = tokens.nth(self.span.end - self.span.start - 1) {
+                token.end = end.end;
+                token.end_offset = end.end_offset;
+            }
         }
 
         // Multi-line tokens aren't supported, set the end to the start
@@ -165,7 +340,7 @@ impl ParsingError {
 
         let line = contents.lines().nth((token.start.0 as usize).max(1) - 1).unwrap_or("???");
         eprintln!("{}", self.message.to_string().bright_red());
-        eprintln!("{}", format!("in file {}:{}:{}", file.path(), token.start.0, token.start.1).bright_red());
+        eprintln!("{}", format!("in file {}:{}:{}", label, token.start.0, token.start.1).bright_red());
         eprintln!("{} {}", " ".repeat(token.start.0.to_string().len()), "|".bright_cyan());
         eprintln!("{} {} {}", token.start.0.to_string().bright_cyan(), "|".bright_cyan(), line.bright_red());
         eprintln!(
@@ -175,5 +350,30 @@ impl ParsingError {
             " ".repeat(token.start.1 as usize),
             "^".repeat(token.end_offset - token.start_offset).bright_red()
         );
+
+        match &self.message {
+            // Point to where each candidate is defined
+            ParsingMessage::AmbiguousImpl(candidates) => {
+                for (name, span) in candidates {
+                    span.make_error(ParsingMessage::ImplCandidate(name.clone())).print(sources);
+                }
+            }
+            _ => {}
+        }
+
+        // Point to the error that caused this one
+        if let Some(cause) = &self.cause {
+            cause.print(sources);
+        }
+    }
+}
+
+impl Display for ParsingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        for cause in self.chained().skip(1) {
+            write!(f, "\ncaused by: {}", cause.message)?;
+        }
+        return Ok(());
     }
 }
@@ -50,7 +50,7 @@ pub mod top_element_manager;
 
 //Re-export ParsingError
 use crate::chalk_interner::ChalkIr;
-use crate::errors::ParsingError;
+use crate::errors::{ErrorSource, ParsingError, ParsingMessage};
 use data::tokens::Span;
 
 /// An alias for parsing types, which must be pinned and boxed because Rust generates different impl Futures
@@ -58,7 +58,8 @@ use data::tokens::Span;
 pub type ParsingFuture<T> = Pin<Box<dyn Future<Output = Result<T, ParsingError>> + Send>>;
 
 /// All the modifiers, used for modifier parsing and debug output.
-pub static MODIFIERS: [Modifier; 4] = [Modifier::Public, Modifier::Protected, Modifier::Extern, Modifier::Internal];
+pub static MODIFIERS: [Modifier; 5] =
+    [Modifier::Public, Modifier::Protected, Modifier::Extern, Modifier::Internal, Modifier::Operation];
 
 /// All the modifiers structures/functions/fields can have
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -73,6 +74,8 @@ pub enum Modifier {
     Internal = 0b1000,
     /// Hidden from the user, only used internally
     Trait = 0b1_0000,
+    /// An operator trait, set by the parser from the operation attribute instead of being written out
+    Operation = 0b10_0000,
 }
 
 impl Display for Modifier {
@@ -83,6 +86,7 @@ impl Display for Modifier {
             Modifier::Extern => write!(f, "extern"),
             Modifier::Internal => write!(f, "internal"),
             Modifier::Trait => panic!("Shouldn't display trait modifier!"),
+            Modifier::Operation => write!(f, "operation"),
         };
     }
 }
@@ -115,6 +119,23 @@ pub fn to_modifiers(from: u8) -> Vec<Modifier> {
     return modifiers;
 }
 
+/// Every attribute the compiler reads, with the kind of value it expects. Attributes that take more than one kind
+/// list them separated by " or ".
+const KNOWN_ATTRIBUTES: [(&str, &str); 12] = [
+    ("operation", "string"),
+    ("priority", "integer"),
+    ("parse_left", "bool"),
+    ("llvm_intrinsic", "flag"),
+    ("copy", "flag"),
+    ("cfg", "string"),
+    ("allow_attributes", "string"),
+    ("deprecated", "flag or string"),
+    ("link_name", "string"),
+    ("no_mangle", "flag"),
+    ("export_name", "string"),
+    ("link", "string"),
+];
+
 /// A simple attribute over structures or functions, potentially used later in the process
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Attribute {
@@ -145,6 +166,11 @@ impl Attribute {
         return None;
     }
 
+    /// Checks if there's an attribute with the given name, whatever its value is
+    pub fn has_attribute(name: &str, attributes: &[Attribute]) -> bool {
+        return attributes.iter().any(|attribute| attribute.name() == name);
+    }
+
     /// Converts the attribute to a string attribute or returns None if it's a different type
     pub fn as_string_attribute(&self) -> Option<&String> {
         match self {
@@ -153,6 +179,12 @@ impl Attribute {
         }
     }
 
+    /// Gets the operator of an #[operation(...)] attribute without its operand placeholders, like + for {}+{},
+    /// or returns None if it isn't a string attribute
+    pub fn as_operator(&self) -> Option<String> {
+        return self.as_string_attribute().map(|operation| operation.replace("{}", "").replace("{+}", ""));
+    }
+
     /// Converts the attribute to an int attribute or returns None if it's a different type
     pub fn as_int_attribute(&self) -> Option<i64> {
         match self {
@@ -168,6 +200,75 @@ impl Attribute {
             _ => None,
         }
     }
+
+    /// The name of the attribute
+    pub fn name(&self) -> &String {
+        return match self {
+            Attribute::Basic(name) | Attribute::Integer(name, _) | Attribute::Bool(name, _) | Attribute::String(name, _) => {
+                name
+            }
+        };
+    }
+
+    /// The kind of value the attribute has, matching the kinds in KNOWN_ATTRIBUTES
+    pub fn kind(&self) -> &'static str {
+        return match self {
+            Attribute::Basic(_) => "flag",
+            Attribute::Integer(_, _) => "integer",
+            Attribute::Bool(_, _) => "bool",
+            Attribute::String(_, _) => "string",
+        };
+    }
+
+    /// Checks the attributes against the known attributes.
+    /// A known attribute with the wrong kind of value is an error, an unknown attribute is returned as a warning
+    /// unless it's listed in an #[allow_attributes(...)] on the same element.
+    pub fn validate(attributes: &Vec<Attribute>, span: &Span) -> Result<Vec<ParsingError>, ParsingError> {
+        let allowed = attributes
+            .iter()
+            .filter(|attribute| attribute.name() == "allow_attributes")
+            .filter_map(|attribute| attribute.as_string_attribute())
+            .flat_map(|allowed| allowed.split(',').map(|name| name.trim()))
+            .collect::<Vec<_>>();
+
+        let mut warnings = Vec::default();
+        for attribute in attributes {
+            let name = attribute.name();
+            match KNOWN_ATTRIBUTES.iter().find(|(known, _)| known == name) {
+                Some((_, kind)) if !kind.split(" or ").any(|kind| kind == attribute.kind()) => {
+                    return Err(span.make_error(ParsingMessage::MismatchedAttribute(name.clone(), kind, attribute.kind())));
+                }
+                Some(_) => {}
+                None if allowed.contains(&name.as_str()) => {}
+                None => {
+                    // Suggest the closest known attribute, as long as it's close enough to be a typo
+                    let suggestion = KNOWN_ATTRIBUTES
+                        .iter()
+                        .map(|(known, _)| (edit_distance(name, known), *known))
+                        .min()
+                        .filter(|(distance, _)| *distance <= 2)
+                        .map(|(_, known)| known.to_string());
+                    warnings.push(span.make_error(ParsingMessage::UnknownAttribute(name.clone(), suggestion)));
+                }
+            }
+        }
+        return Ok(warnings);
+    }
+}
+
+/// The number of single character insertions, deletions, or substitutions to turn one string into another
+fn edit_distance(first: &str, second: &str) -> usize {
+    let second = second.chars().collect::<Vec<_>>();
+    let mut last = (0..=second.len()).collect::<Vec<_>>();
+    for (i, first_char) in first.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, second_char) in second.iter().enumerate() {
+            let substitution = last[j] + if first_char == *second_char { 0 } else { 1 };
+            current.push(substitution.min(last[j + 1] + 1).min(current[j] + 1));
+        }
+        last = current;
+    }
+    return last[second.len()];
 }
 
 /// The ProcessManager is used to send data to later steps of compilation
@@ -212,6 +313,12 @@ pub trait ProcessManager: Send + Sync {
 
     /// Clones the process manager, generally pretty fast because most data is Arc'd
     fn cloned(&self) -> Box<dyn ProcessManager>;
+
+    /// Checks if an element with the given attributes should be compiled, from its #[cfg] attributes
+    fn cfg_enabled(&self, attributes: &Vec<Attribute>) -> bool;
+
+    /// Checks if the compile was cancelled. This is called between every effect, so it must not lock anything.
+    fn cancelled(&self) -> bool;
 }
 
 /// A simple manager for variables in a function
@@ -249,11 +356,18 @@ impl VariableManager for SimpleVariableManager {
     fn get_variable(&self, name: &String) -> Option<FinalizedTypes> {
         return self.variables.get(name).cloned();
     }
+
+    fn all_variables(&self) -> Vec<(&String, &FinalizedTypes)> {
+        return self.variables.iter().collect();
+    }
 }
 
 /// A variable manager used for getting return types from effects
 pub trait VariableManager: Debug {
     fn get_variable(&self, name: &String) -> Option<FinalizedTypes>;
+
+    /// Every variable in scope and its type, in no particular order
+    fn all_variables(&self) -> Vec<(&String, &FinalizedTypes)>;
 }
 
 /// Something that has an inner immutable data type (either FunctionData or StructData)
@@ -282,9 +396,6 @@ where
     /// Whether the top element is a trait or trait member
     fn is_trait(&self) -> bool;
 
-    /// Returns a default self type
-    fn default(&self, id: u64) -> Arc<Self>;
-
     /// Returns the id
     fn id(&self) -> Option<u64>;
 
@@ -322,6 +433,8 @@ pub struct TraitImplementor {
     pub attributes: Vec<Attribute>,
     /// The implementor's functions
     pub functions: Vec<UnfinalizedFunction>,
+    /// The span of the implemented type, which errors about the whole implementor point at
+    pub span: Span,
 }
 
 /// Finished impl block for a type.
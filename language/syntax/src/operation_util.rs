@@ -23,6 +23,7 @@ impl Future for OperationGetter {
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let locked = self.syntax.clone();
         let mut locked = locked.lock();
+        locked.async_manager.unblock();
 
         for operation in &self.operation {
             if let Some(output) = locked.operations.get(operation) {
@@ -44,6 +45,7 @@ impl Future for OperationGetter {
             }
         }
 
+        locked.async_manager.block(|| format!("OperationGetter looking for {}", self.operation.join(" or ")));
         return Poll::Pending;
     }
 }
@@ -1,9 +1,11 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
 use std::sync::Arc;
 
 use data::tokens::Span;
 
 use crate::async_util::UnparsedType;
-use crate::program::function::{CodeBody, CodelessFinalizedFunction, FinalizedCodeBody, FunctionData};
+use crate::program::function::{display_parenless, CodeBody, CodelessFinalizedFunction, FinalizedCodeBody, FunctionData};
 use crate::program::r#struct::{BOOL, CHAR, F64, STR, U64};
 use crate::program::types::{FinalizedTypes, Types};
 use crate::{Attribute, VariableManager};
@@ -64,6 +66,8 @@ pub struct MemberField {
     pub attributes: Vec<Attribute>,
     /// The field itself
     pub field: Field,
+    /// The value used when the field isn't set while creating the struct
+    pub default_value: Option<Effects>,
 }
 
 /// A finalized member field.
@@ -75,6 +79,8 @@ pub struct FinalizedMemberField {
     pub attributes: Vec<Attribute>,
     /// The field itself
     pub field: FinalizedField,
+    /// The checked value used when the field isn't set while creating the struct
+    pub default_value: Option<FinalizedEffects>,
 }
 
 impl PartialEq for ExpressionType {
@@ -90,8 +96,8 @@ impl PartialEq for ExpressionType {
 
 impl MemberField {
     /// Creates a new field
-    pub fn new(modifiers: u8, attributes: Vec<Attribute>, field: Field) -> Self {
-        return Self { modifiers, attributes, field };
+    pub fn new(modifiers: u8, attributes: Vec<Attribute>, field: Field, default_value: Option<Effects>) -> Self {
+        return Self { modifiers, attributes, field, default_value };
     }
 }
 
@@ -133,6 +139,114 @@ impl Effects {
     }
 }
 
+impl EffectType {
+    /// Writes the effect as a tree for debugging the parser, with the effect's kind first
+    /// and each effect inside it on its own line indented two spaces past the indent
+    pub fn display_indented(&self, indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            Self::NOP => write!(f, "NOP"),
+            Self::Paren(inner) => {
+                write!(f, "Paren")?;
+                display_child(inner, indent, f)
+            }
+            Self::CreateVariable(name, value) => {
+                write!(f, "CreateVariable {}", name)?;
+                display_child(value, indent, f)
+            }
+            Self::Jump(label) => write!(f, "Jump {}", label),
+            Self::CompareJump(condition, then, otherwise) => {
+                write!(f, "CompareJump {} {}", then, otherwise)?;
+                display_child(condition, indent, f)
+            }
+            Self::CodeBody(body) => {
+                write!(f, "CodeBody ")?;
+                body.display_indented(indent, f)
+            }
+            Self::ImplementationCall(calling, traits, method, arguments) => {
+                write!(f, "ImplementationCall {} {}", traits, method)?;
+                display_child(calling, indent, f)?;
+                display_children(arguments, indent, f)
+            }
+            Self::MethodCall(calling, method, arguments, generics) => {
+                write!(f, "MethodCall {}", method)?;
+                if !generics.is_empty() {
+                    write!(f, "<{}>", display_parenless(generics, ", "))?;
+                }
+                if let Some(calling) = calling {
+                    display_child(calling, indent, f)?;
+                }
+                display_children(arguments, indent, f)
+            }
+            Self::Set(setting, value) => {
+                write!(f, "Set")?;
+                display_child(setting, indent, f)?;
+                display_child(value, indent, f)
+            }
+            Self::LoadVariable(name) => write!(f, "LoadVariable {}", name),
+            Self::Load(loading, field) => {
+                write!(f, "Load {}", field)?;
+                display_child(loading, indent, f)
+            }
+            Self::Operation(operation, values) => {
+                write!(f, "Operation {}", operation)?;
+                display_children(values, indent, f)
+            }
+            Self::CreateStruct(types, fields, base) => {
+                write!(f, "CreateStruct {}", types)?;
+                for (name, value) in fields {
+                    write!(f, "\n{:indent$}Field {}", "", name, indent = indent + 2)?;
+                    display_child(value, indent + 2, f)?;
+                }
+                if let Some(base) = base {
+                    write!(f, "\n{:indent$}Base", "", indent = indent + 2)?;
+                    display_child(base, indent + 2, f)?;
+                }
+                Ok(())
+            }
+            Self::CreateArray(values) => {
+                write!(f, "CreateArray")?;
+                display_children(values, indent, f)
+            }
+            Self::Float(float) => write!(f, "Float {}", float),
+            Self::Int(int) => write!(f, "Int {}", int),
+            Self::UInt(int) => write!(f, "UInt {}", int),
+            Self::Bool(bool) => write!(f, "Bool {}", bool),
+            Self::Char(char) => write!(f, "Char {:?}", char),
+            Self::String(string) => write!(f, "String {:?}", string),
+            Self::Lambda(arguments, body) => {
+                let arguments = arguments.iter().map(|(name, types)| format!("{}: {}", name, types)).collect::<Vec<_>>();
+                write!(f, "Lambda ({})", arguments.join(", "))?;
+                display_child(body, indent, f)
+            }
+            Self::Range(start, end) => {
+                write!(f, "Range")?;
+                display_child(start, indent, f)?;
+                display_child(end, indent, f)
+            }
+        };
+    }
+}
+
+impl Display for EffectType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return self.display_indented(0, f);
+    }
+}
+
+/// Writes an effect inside another effect on its own line, indented two spaces past the indent
+fn display_child(child: &Effects, indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "\n{:indent$}", "", indent = indent + 2)?;
+    return child.types.display_indented(indent + 2, f);
+}
+
+/// Writes each effect inside another effect on its own line, indented two spaces past the indent
+fn display_children(children: &[Effects], indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+    for child in children {
+        display_child(child, indent, f)?;
+    }
+    return Ok(());
+}
+
 /// The type of the effect, storing all per-effect data
 #[derive(Clone, Debug)]
 pub enum EffectType {
@@ -162,8 +276,9 @@ pub enum EffectType {
     Load(Box<Effects>, String),
     /// An unresolved operation, sent to the checker to resolve, with the given arguments.
     Operation(String, Vec<Effects>),
-    /// Struct to create and a tuple of the name of the field and the argument.
-    CreateStruct(UnparsedType, Vec<(String, Effects)>),
+    /// Struct to create, a tuple of the name of the field and the argument,
+    /// and the struct any fields left out are copied from, like ..base.
+    CreateStruct(UnparsedType, Vec<(String, Effects)>, Option<Box<Effects>>),
     /// Creates an array of the given effects.
     CreateArray(Vec<Effects>),
     /// A float
@@ -178,6 +293,12 @@ pub enum EffectType {
     Char(char),
     /// A string
     String(String),
+    /// A lambda with its arguments' names and types, and the body it returns.
+    /// The checker turns this into a struct holding the captured variables that implements Fn.
+    Lambda(Vec<(String, UnparsedType)>, Box<Effects>),
+    /// A range from the start up to, but not including, the end, made by start..end.
+    /// The checker turns this into a core Range struct.
+    Range(Box<Effects>, Box<Effects>),
 }
 
 /// Effects that have been finalized and are ready for compilation
@@ -209,6 +330,10 @@ pub enum FinalizedEffectType {
     CompareJump(Box<FinalizedEffects>, String, String),
     /// Nested code body.
     CodeBody(FinalizedCodeBody),
+    /// Matches an enum-like struct on its discriminant, its first field, which holds the ID of the type it is.
+    /// Jumps to the arm for that type, or falls through to the next line if no arm matches.
+    /// Each arm is a code body that has to return or jump away itself.
+    MatchArm(Box<FinalizedEffects>, Vec<(FinalizedTypes, FinalizedEffects)>),
     /// Calls the function on the given value (if any) with the given arguments and the given return type (if generic). The first arg is the output location
     FunctionCall(
         Option<Box<FinalizedEffects>>,
@@ -226,10 +351,15 @@ pub enum FinalizedEffectType {
     Load(Box<FinalizedEffects>, String, FinalizedTypes),
     /// Creates a struct at the given reference, of the given type with a tuple of the index of the argument and the argument.
     CreateStruct(Option<Box<FinalizedEffects>>, FinalizedTypes, Vec<(usize, FinalizedEffects)>),
+    /// Creates a core Array of the given type holding the elements. The type is None for an empty array
+    /// until it's passed as an argument, which it takes the type of.
+    CreateArray(Option<FinalizedTypes>, Vec<FinalizedEffects>),
     /// Creates a float
     Float(f64),
     /// Creates an unsigned int
     UInt(u64),
+    /// Creates an integer literal as the given number type, which it took from the value it's used with
+    Number(u64, FinalizedTypes),
     /// Creates a boolean
     Bool(bool),
     /// Creates a string
@@ -259,7 +389,7 @@ impl FinalizedEffectType {
     /// This can only be called on degenericed types and as such can be sync
     pub fn get_nongeneric_return(&self, variables: &dyn VariableManager) -> Option<FinalizedTypes> {
         return match self {
-            Self::NOP | Self::Jump(_) | Self::CompareJump(_, _, _) | Self::CodeBody(_) => None,
+            Self::NOP | Self::Jump(_) | Self::CompareJump(_, _, _) | Self::MatchArm(_, _) | Self::CodeBody(_) => None,
             // Downcasts simply return the downcasting target.
             Self::CreateVariable(_, _, types) | Self::Downcast(_, types, _) => Some(types.clone()),
             Self::FunctionCall(_, function, _, _)
@@ -285,6 +415,8 @@ impl FinalizedEffectType {
                 .map(|field| field.field.field_type.clone()),
             // Returns the program type.
             Self::CreateStruct(_, types, _) => Some(FinalizedTypes::Reference(Box::new(types.clone()))),
+            Self::CreateArray(types, _) => types.clone().map(|types| FinalizedTypes::Reference(Box::new(types))),
+            Self::Number(_, types) => Some(types.clone()),
             // Returns the internal constant type.
             Self::Float(_) => Some(FinalizedTypes::Struct(F64.clone())),
             Self::UInt(_) => Some(FinalizedTypes::Struct(U64.clone())),
@@ -304,4 +436,160 @@ impl FinalizedEffectType {
             Self::HeapAllocate(_) => panic!("Tried to get a type from a heap alloc!"),
         };
     }
+
+    /// The effects directly inside this one. Code bodies count as having none, since their lines aren't effects.
+    pub fn effects_mut(&mut self) -> Vec<&mut FinalizedEffects> {
+        return match self {
+            Self::CreateVariable(_, inner, _)
+            | Self::CompareJump(inner, _, _)
+            | Self::Load(inner, _, _)
+            | Self::Downcast(inner, _, _)
+            | Self::HeapStore(inner)
+            | Self::ReferenceLoad(inner)
+            | Self::StackStore(inner) => vec![&mut **inner],
+            Self::Set(setting, value) => vec![&mut **setting, &mut **value],
+            Self::MatchArm(matching, arms) => {
+                let mut effects = vec![&mut **matching];
+                effects.extend(arms.iter_mut().map(|(_, arm)| arm));
+                effects
+            }
+            Self::FunctionCall(calling, _, arguments, _) => {
+                let mut effects = calling.iter_mut().map(|calling| &mut **calling).collect::<Vec<_>>();
+                effects.extend(arguments.iter_mut());
+                effects
+            }
+            Self::VirtualCall(_, _, calling, arguments) => {
+                let mut effects = vec![&mut **calling];
+                effects.extend(arguments.iter_mut());
+                effects
+            }
+            Self::CreateStruct(storing, _, fields) => {
+                let mut effects = storing.iter_mut().map(|storing| &mut **storing).collect::<Vec<_>>();
+                effects.extend(fields.iter_mut().map(|(_, field)| field));
+                effects
+            }
+            Self::GenericMethodCall(_, _, arguments)
+            | Self::GenericVirtualCall(_, _, _, arguments)
+            | Self::CreateArray(_, arguments) => arguments.iter_mut().collect(),
+            Self::NOP
+            | Self::Jump(_)
+            | Self::CodeBody(_)
+            | Self::LoadVariable(_)
+            | Self::Float(_)
+            | Self::UInt(_)
+            | Self::Number(_, _)
+            | Self::Bool(_)
+            | Self::String(_)
+            | Self::Char(_)
+            | Self::HeapAllocate(_) => vec![],
+        };
+    }
+
+    /// Writes the effect as readable IR for debugging, indenting any code bodies in it past the indent
+    pub fn display_indented(&self, indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            Self::NOP => write!(f, "nop"),
+            Self::CreateVariable(name, value, types) => {
+                write!(f, "let {}: {} = ", name, types)?;
+                value.types.display_indented(indent, f)
+            }
+            Self::Jump(label) => write!(f, "goto {}", label),
+            Self::CompareJump(condition, then, otherwise) => {
+                write!(f, "if ")?;
+                condition.types.display_indented(indent, f)?;
+                write!(f, " goto {} else {}", then, otherwise)
+            }
+            Self::CodeBody(body) => body.display_indented(indent, f),
+            Self::MatchArm(matching, arms) => {
+                write!(f, "match ")?;
+                matching.types.display_indented(indent, f)?;
+                write!(f, " {{")?;
+                for (types, arm) in arms {
+                    write!(f, "\n{:indent$}{} => ", "", types, indent = indent + 2)?;
+                    arm.types.display_indented(indent + 2, f)?;
+                }
+                write!(f, "\n{:indent$}}}", "", indent = indent)
+            }
+            // Method calls are shown on the value they're called on
+            Self::FunctionCall(Some(calling), function, arguments, _)
+            | Self::VirtualCall(_, function, calling, arguments) => {
+                calling.types.display_indented(indent, f)?;
+                write!(f, ".{}", function.data.name.split("::").last().unwrap())?;
+                display_arguments(arguments, indent, f)
+            }
+            Self::FunctionCall(None, function, arguments, _)
+            | Self::GenericMethodCall(function, _, arguments)
+            | Self::GenericVirtualCall(_, _, function, arguments) => {
+                write!(f, "{}", function.data.name)?;
+                display_arguments(arguments, indent, f)
+            }
+            Self::Set(setting, value) => {
+                setting.types.display_indented(indent, f)?;
+                write!(f, " = ")?;
+                value.types.display_indented(indent, f)
+            }
+            Self::LoadVariable(name) => write!(f, "{}", name),
+            Self::Load(loading, field, _) => {
+                loading.types.display_indented(indent, f)?;
+                write!(f, ".{}", field)
+            }
+            Self::CreateStruct(_, types, fields) => {
+                write!(f, "new {} {{", types)?;
+                for (i, (index, field)) in fields.iter().enumerate() {
+                    write!(f, "{} {}: ", if i == 0 { "" } else { "," }, index)?;
+                    field.types.display_indented(indent, f)?;
+                }
+                write!(f, " }}")
+            }
+            Self::CreateArray(_, values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    value.types.display_indented(indent, f)?;
+                }
+                write!(f, "]")
+            }
+            Self::Float(float) => write!(f, "{}", float),
+            Self::UInt(int) => write!(f, "{}", int),
+            Self::Number(number, types) => write!(f, "{}{}", number, types),
+            Self::Bool(bool) => write!(f, "{}", bool),
+            Self::String(string) => write!(f, "{:?}", string),
+            Self::Char(char) => write!(f, "{:?}", char),
+            Self::Downcast(base, target, _) => {
+                base.types.display_indented(indent, f)?;
+                write!(f, " as {}", target)
+            }
+            Self::HeapStore(inner) => display_wrapped("heap_store", inner, indent, f),
+            Self::HeapAllocate(types) => write!(f, "heap_allocate({})", types),
+            Self::ReferenceLoad(inner) => display_wrapped("load", inner, indent, f),
+            Self::StackStore(inner) => display_wrapped("stack_store", inner, indent, f),
+        };
+    }
+}
+
+impl Display for FinalizedEffectType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return self.display_indented(0, f);
+    }
+}
+
+/// Writes the arguments of a call in parenthesis, separated by commas
+fn display_arguments(arguments: &[FinalizedEffects], indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "(")?;
+    for (i, argument) in arguments.iter().enumerate() {
+        if i != 0 {
+            write!(f, ", ")?;
+        }
+        argument.types.display_indented(indent, f)?;
+    }
+    return write!(f, ")");
+}
+
+/// Writes an effect wrapped in a named operation, like heap_store(value)
+fn display_wrapped(name: &str, inner: &FinalizedEffects, indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    inner.types.display_indented(indent, f)?;
+    return write!(f, ")");
 }
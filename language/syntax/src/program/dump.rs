@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use data::tokens::{Span, Token};
+use data::SourceSet;
+
+use crate::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects};
+use crate::program::function::{FinalizedCodeBody, FinalizedFunction};
+use crate::program::syntax::Syntax;
+use crate::program::types::FinalizedTypes;
+
+impl Syntax {
+    /// Pretty-prints a finalized function, its arguments, return type, and code as an indented tree of effects.
+    /// Functions are looked up before and after degenericing, and every instantiation of a generic function
+    /// (named with a $) is printed after it. Returns None if no function has that name.
+    pub fn dump_function(&self, name: &str, sources: &Vec<Box<dyn SourceSet>>) -> Option<String> {
+        let mut found = Vec::default();
+        if let Some(function) = self.compiling.get(name).or_else(|| self.generics.get(name)) {
+            found.push(function.clone());
+        }
+
+        let mut instantiations = self
+            .compiling
+            .iter()
+            .filter(|function| function.key().split_once('$').map_or(false, |(base, _)| base == name))
+            .map(|function| function.value().clone())
+            .collect::<Vec<_>>();
+        // Sorted so the output is stable between runs
+        instantiations.sort_by(|first, second| first.data.name.cmp(&second.data.name));
+        found.extend(instantiations);
+
+        if found.is_empty() {
+            return None;
+        }
+
+        let mut dumper = Dumper { sources, tokens: HashMap::default(), output: String::default() };
+        for function in found {
+            dumper.dump_function(&function);
+        }
+        return Some(dumper.output);
+    }
+}
+
+/// Writes functions into a string, resolving spans to line:col with the source files
+struct Dumper<'a> {
+    /// The sources to find files in
+    sources: &'a Vec<Box<dyn SourceSet>>,
+    /// The tokens of every file read so far, by file hash
+    tokens: HashMap<u64, Vec<Token>>,
+    /// The output
+    output: String,
+}
+
+impl Dumper<'_> {
+    /// Writes the function header followed by its code
+    fn dump_function(&mut self, function: &FinalizedFunction) {
+        let arguments = function
+            .fields
+            .iter()
+            .map(|field| format!("{}: {}", field.field.name, dump_type(&field.field.field_type)))
+            .collect::<Vec<_>>();
+        let _ = write!(self.output, "fn {}({})", function.data.name, arguments.join(", "));
+        if let Some(returning) = &function.return_type {
+            let _ = write!(self.output, " -> {}", dump_type(returning));
+        }
+        let location = self.location(&function.data.span);
+        let _ = writeln!(self.output, " @ {}", location);
+        self.dump_body(&function.code, 1);
+    }
+
+    /// Writes a code body and every expression in it
+    fn dump_body(&mut self, code: &FinalizedCodeBody, depth: usize) {
+        let _ = writeln!(self.output, "{}{}:", "  ".repeat(depth), code.label);
+        for expression in &code.expressions {
+            let prefix = match expression.expression_type {
+                ExpressionType::Break => "break",
+                ExpressionType::Return(_) => "return",
+                ExpressionType::Line => "",
+            };
+            self.dump_effect(prefix, &expression.effect, depth + 1);
+        }
+    }
+
+    /// Writes an effect on its own line, then every effect inside it one level deeper
+    fn dump_effect(&mut self, prefix: &str, effect: &FinalizedEffects, depth: usize) {
+        let (description, inner): (String, Vec<&FinalizedEffects>) = match &effect.types {
+            FinalizedEffectType::NOP => ("NOP".to_string(), vec![]),
+            FinalizedEffectType::CreateVariable(name, value, types) => {
+                (format!("CreateVariable {}: {}", name, dump_type(types)), vec![&**value])
+            }
+            FinalizedEffectType::Jump(label) => (format!("Jump {}", label), vec![]),
+            FinalizedEffectType::CompareJump(value, first, second) => {
+                (format!("CompareJump {} else {}", first, second), vec![&**value])
+            }
+            FinalizedEffectType::MatchArm(value, arms) => (
+                format!("MatchArm [{}]", arms.iter().map(|(types, _)| dump_type(types)).collect::<Vec<_>>().join(", ")),
+                std::iter::once(&**value).chain(arms.iter().map(|(_, arm)| arm)).collect(),
+            ),
+            FinalizedEffectType::CodeBody(body) => {
+                let location = self.location(&effect.span);
+                let _ = writeln!(self.output, "{}{}CodeBody @ {}", "  ".repeat(depth), with_prefix(prefix), location);
+                self.dump_body(body, depth + 1);
+                return;
+            }
+            FinalizedEffectType::FunctionCall(calling, function, arguments, _) => (
+                format!("FunctionCall {}", function.data.name),
+                calling.iter().map(|calling| &**calling).chain(arguments).collect(),
+            ),
+            FinalizedEffectType::GenericMethodCall(function, types, arguments) => {
+                (format!("GenericMethodCall {} on {}", function.data.name, dump_type(types)), arguments.iter().collect())
+            }
+            FinalizedEffectType::Set(target, value) => ("Set".to_string(), vec![&**target, &**value]),
+            FinalizedEffectType::LoadVariable(name) => (format!("LoadVariable {}", name), vec![]),
+            FinalizedEffectType::Load(value, field, types) => {
+                (format!("Load {} from {}", field, dump_type(types)), vec![&**value])
+            }
+            FinalizedEffectType::CreateStruct(target, types, fields) => (
+                format!("CreateStruct {}", dump_type(types)),
+                target.iter().map(|target| &**target).chain(fields.iter().map(|(_, field)| field)).collect(),
+            ),
+            FinalizedEffectType::CreateArray(types, values) => {
+                (format!("CreateArray {}", types.as_ref().map_or("?".to_string(), dump_type)), values.iter().collect())
+            }
+            FinalizedEffectType::Float(value) => (format!("Float {}", value), vec![]),
+            FinalizedEffectType::UInt(value) => (format!("UInt {}", value), vec![]),
+            FinalizedEffectType::Number(value, types) => (format!("Number {}: {}", value, dump_type(types)), vec![]),
+            FinalizedEffectType::Bool(value) => (format!("Bool {}", value), vec![]),
+            FinalizedEffectType::String(value) => (format!("String {:?}", value), vec![]),
+            FinalizedEffectType::Char(value) => (format!("Char {:?}", value), vec![]),
+            FinalizedEffectType::VirtualCall(index, function, calling, arguments) => (
+                format!("VirtualCall {} #{}", function.data.name, index),
+                std::iter::once(&**calling).chain(arguments).collect(),
+            ),
+            FinalizedEffectType::GenericVirtualCall(index, target, function, arguments) => (
+                format!("GenericVirtualCall {} #{} for {}", function.data.name, index, target.name),
+                arguments.iter().collect(),
+            ),
+            FinalizedEffectType::Downcast(value, types, functions) => (
+                format!(
+                    "Downcast to {} [{}]",
+                    dump_type(types),
+                    functions.iter().map(|function| function.data.name.as_str()).collect::<Vec<_>>().join(", ")
+                ),
+                vec![&**value],
+            ),
+            FinalizedEffectType::HeapStore(value) => ("HeapStore".to_string(), vec![&**value]),
+            FinalizedEffectType::HeapAllocate(types) => (format!("HeapAllocate {}", dump_type(types)), vec![]),
+            FinalizedEffectType::ReferenceLoad(value) => ("ReferenceLoad".to_string(), vec![&**value]),
+            FinalizedEffectType::StackStore(value) => ("StackStore".to_string(), vec![&**value]),
+        };
+
+        let location = self.location(&effect.span);
+        let _ = writeln!(self.output, "{}{}{} @ {}", "  ".repeat(depth), with_prefix(prefix), description, location);
+        for effect in inner {
+            self.dump_effect("", effect, depth + 1);
+        }
+    }
+
+    /// Resolves a span to the line:col of its first token, or ? if its file can't be found
+    fn location(&mut self, span: &Span) -> String {
+        if !self.tokens.contains_key(&span.file) {
+            let file =
+                self.sources.iter().flat_map(|source| source.get_files()).find(|readable| readable.hash() == span.file);
+            match file {
+                Some(file) => self.tokens.insert(span.file, file.read().collect()),
+                None => return "?".to_string(),
+            };
+        }
+
+        return match self.tokens[&span.file].get(span.start) {
+            Some(token) => format!("{}:{}", token.start.0, token.start.1),
+            None => "?".to_string(),
+        };
+    }
+}
+
+/// Adds a separator after the prefix if there is one
+fn with_prefix(prefix: &str) -> String {
+    return if prefix.is_empty() { String::default() } else { format!("{} ", prefix) };
+}
+
+/// Prints a type, marking references with a &
+fn dump_type(types: &FinalizedTypes) -> String {
+    return match types {
+        FinalizedTypes::Reference(inner) => format!("&{}", dump_type(inner)),
+        types => types.to_string(),
+    };
+}
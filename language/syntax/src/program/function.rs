@@ -1,5 +1,6 @@
 use parking_lot::Mutex;
-use std::fmt::{Debug, Display};
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::sync::Arc;
@@ -10,7 +11,9 @@ use async_trait::async_trait;
 use data::tokens::Span;
 
 use crate::async_util::{HandleWrapper, NameResolver, UnparsedType};
-use crate::program::code::{Expression, FinalizedExpression, FinalizedMemberField, MemberField};
+use crate::program::code::{
+    EffectType, Expression, ExpressionType, FinalizedEffectType, FinalizedExpression, FinalizedMemberField, MemberField,
+};
 use crate::program::types::FinalizedTypes;
 use crate::{
     is_modifier, Attribute, DataType, Modifier, ParsingError, ParsingFuture, ProcessManager, Syntax, TopElement,
@@ -29,6 +32,8 @@ pub struct FunctionData {
     pub name: String,
     /// The function's span
     pub span: Span,
+    /// The call that instantiated this function, if it's a degenericed copy of a generic function
+    pub instantiated_at: Option<Span>,
     /// The function's errors if it has been poison'd
     pub poisoned: Vec<ParsingError>,
 }
@@ -36,12 +41,19 @@ pub struct FunctionData {
 impl FunctionData {
     /// Creates a new function
     pub fn new(attributes: Vec<Attribute>, modifiers: u8, name: String, span: Span) -> Self {
-        return Self { attributes, modifiers, name, span, poisoned: Vec::default() };
+        return Self { attributes, modifiers, name, span, instantiated_at: None, poisoned: Vec::default() };
     }
 
     /// Creates an empty function data that errored while parsing.
     pub fn poisoned(name: String, error: ParsingError) -> Self {
-        return Self { attributes: Vec::default(), modifiers: 0, name, span: error.span.clone(), poisoned: vec![error] };
+        return Self {
+            attributes: Vec::default(),
+            modifiers: 0,
+            name,
+            span: error.span.clone(),
+            instantiated_at: None,
+            poisoned: vec![error],
+        };
     }
 }
 
@@ -63,10 +75,6 @@ impl TopElement for FunctionData {
         return is_modifier(self.modifiers, Modifier::Trait);
     }
 
-    fn default(&self, _id: u64) -> Arc<Self> {
-        panic!("Functions have no default value!")
-    }
-
     fn id(&self) -> Option<u64> {
         None
     }
@@ -92,6 +100,14 @@ impl TopElement for FunctionData {
         process_manager: Box<dyn ProcessManager>,
     ) -> Result<(), ParsingError> {
         let name = current.data.name.clone();
+        {
+            let mut locked = syntax.lock();
+            locked.check_attributes(&current.data.attributes, &current.data.span);
+            if locked.async_manager.dump_ast {
+                let dump = format!("fn {}\n{}", name, current.code);
+                locked.ast_dumps.push(dump);
+            }
+        }
         // Get the codeless finalized function and the code from the function.
         let (codeless_function, code) = process_manager.verify_func(current, resolver.deref(), &syntax).await;
         // Finalize the code and combine it with the codeless finalized function.
@@ -218,6 +234,34 @@ impl CodeBody {
     pub fn new(expressions: Vec<Expression>, label: String) -> Self {
         return Self { label, expressions };
     }
+
+    /// Writes the code body as a tree for debugging the parser, with each line indented two spaces past the indent
+    pub fn display_indented(&self, indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.label)?;
+        for expression in &self.expressions {
+            write!(f, "\n{:indent$}", "", indent = indent + 2)?;
+            let keyword = match expression.expression_type {
+                ExpressionType::Break => Some("Break"),
+                ExpressionType::Return(_) => Some("Return"),
+                ExpressionType::Line => None,
+            };
+            match (keyword, &expression.effect.types) {
+                (Some(keyword), EffectType::NOP) => write!(f, "{}", keyword)?,
+                (Some(keyword), effect) => {
+                    write!(f, "{}\n{:indent$}", keyword, "", indent = indent + 4)?;
+                    effect.display_indented(indent + 4, f)?;
+                }
+                (None, effect) => effect.display_indented(indent + 2, f)?,
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Display for CodeBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return self.display_indented(0, f);
+    }
 }
 
 impl FinalizedCodeBody {
@@ -225,6 +269,34 @@ impl FinalizedCodeBody {
     pub fn new(expressions: Vec<FinalizedExpression>, label: String, returns: bool) -> Self {
         return Self { label, expressions, returns };
     }
+
+    /// Writes the code body as readable IR for debugging, with each line indented two spaces past the indent
+    pub fn display_indented(&self, indent: usize, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.label)?;
+        for expression in &self.expressions {
+            write!(f, "\n{:indent$}", "", indent = indent + 2)?;
+            let keyword = match expression.expression_type {
+                ExpressionType::Break => Some("break"),
+                ExpressionType::Return(_) => Some("return"),
+                ExpressionType::Line => None,
+            };
+            match (keyword, &expression.effect.types) {
+                (Some(keyword), FinalizedEffectType::NOP) => write!(f, "{}", keyword)?,
+                (Some(keyword), effect) => {
+                    write!(f, "{} ", keyword)?;
+                    effect.display_indented(indent + 2, f)?;
+                }
+                (None, effect) => effect.display_indented(indent + 2, f)?,
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Display for FinalizedCodeBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return self.display_indented(0, f);
+    }
 }
 
 /// Helper functions to display types.
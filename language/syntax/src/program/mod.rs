@@ -1,5 +1,7 @@
 /// Types used to represent code
 pub mod code;
+/// Pretty-prints finalized functions for debugging
+pub mod dump;
 /// Types used to represent functions
 pub mod function;
 /// Types used to represent structs
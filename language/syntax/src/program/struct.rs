@@ -2,7 +2,6 @@ use parking_lot::Mutex;
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
-use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 
 use chalk_ir::{AdtId, Binders, GenericArg, Substitution, TraitId, Ty, TyKind};
@@ -24,35 +23,48 @@ use crate::{Attribute, ParsingError};
 
 lazy_static! {
     /// 64-bit integer type
-    pub static ref I64: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("i64".to_string())));
+    pub static ref I64: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("i64".to_string())));
     /// 32-bit integer type
-    pub static ref I32: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("i32".to_string())));
+    pub static ref I32: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("i32".to_string())));
     /// 16-bit integer type
-    pub static ref I16: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("i16".to_string())));
+    pub static ref I16: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("i16".to_string())));
     /// 8-bit integer type
-    pub static ref I8: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("i8".to_string())));
+    pub static ref I8: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("i8".to_string())));
     /// 64-bit float type
-    pub static ref F64: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("f64".to_string())));
+    pub static ref F64: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("f64".to_string())));
     /// 32-bit float type
-    pub static ref F32: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("f32".to_string())));
+    pub static ref F32: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("f32".to_string())));
     /// 64-bit unsigned integer type
-    pub static ref U64: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("u64".to_string())));
+    pub static ref U64: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("u64".to_string())));
     /// 32-bit unsigned integer type
-    pub static ref U32: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("u32".to_string())));
+    pub static ref U32: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("u32".to_string())));
     /// 16-bit unsigned integer type
-    pub static ref U16: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("u16".to_string())));
+    pub static ref U16: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("u16".to_string())));
     /// 8-bit unsigned integer type
-    pub static ref U8: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("u8".to_string())));
+    pub static ref U8: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("u8".to_string())));
     /// Boolean type
-    pub static ref BOOL: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("bool".to_string())));
+    pub static ref BOOL: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("bool".to_string())));
     /// String type
     pub static ref STR: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("str".to_string())));
     /// Character type
-    pub static ref CHAR: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("char".to_string())));
+    pub static ref CHAR: Arc<FinalizedStruct> =
+        Arc::new(FinalizedStruct::empty_of(StructData::empty_copy("char".to_string())));
     /// Void type
     pub static ref VOID: Arc<FinalizedStruct> = Arc::new(FinalizedStruct::empty_of(StructData::empty("()".to_string())));
 }
 
+/// The core struct array literals and array types, like [T], are made into
+pub const ARRAY: &str = "types::array::Array";
+
 /// Gets the internal struct from its name
 pub fn get_internal(name: String) -> Arc<StructData> {
     return match name.as_str() {
@@ -107,7 +119,7 @@ pub struct StructData {
     pub modifiers: u8,
     /// The program's chalk data
     pub chalk_data: ChalkData,
-    /// The program's numerical ID, each struct has a unique ID that starts at 0 and increments by 1
+    /// The program's numerical ID, hashed from its name (see stable_id)
     pub id: u64,
     /// The program's name
     pub name: String,
@@ -129,6 +141,8 @@ pub struct UnfinalizedStruct {
     pub fields: Vec<ParsingFuture<MemberField>>,
     /// The program's functions
     pub functions: Vec<UnfinalizedFunction>,
+    /// The struct this one extends, whose fields come before this struct's own
+    pub parent: Option<UnparsedType>,
     /// The program's data
     pub data: Arc<StructData>,
 }
@@ -175,12 +189,20 @@ impl PartialEq for FinalizedStruct {
     }
 }
 
-static mut ID: AtomicU64 = AtomicU64::new(0);
-
 impl StructData {
+    /// Gets the ID for a struct name. IDs are hashed from the name instead of counted up
+    /// so they're the same every build, no matter what order the structs are parsed in.
+    /// The hash is data::type_id's 32 bit FNV-1a, since Chalk IDs are only 32 bits and std's hashers can change between
+    /// Rust versions. It's shared with the derive macros, so values made in Rust get the same IDs.
+    /// Every ID fits in a u32, so casting it to a Chalk ID never cuts it off. Different names can still hash to the
+    /// same ID, which is reported as an IdCollision when the second one is added to the syntax.
+    pub fn stable_id(name: &String) -> u64 {
+        return data::type_id(name);
+    }
+
     /// creates an empty struct data, usually for internal structs
     pub fn empty(name: String) -> Self {
-        let id = unsafe { ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst) };
+        let id = Self::stable_id(&name);
         return Self {
             attributes: Vec::default(),
             chalk_data: Self::get_chalk_data(id, 0),
@@ -193,6 +215,18 @@ impl StructData {
         };
     }
 
+    /// Creates an empty internal struct data marked as copy, used for primitives
+    pub fn empty_copy(name: String) -> Self {
+        let mut output = Self::empty(name);
+        output.attributes.push(Attribute::Basic("copy".to_string()));
+        return output;
+    }
+
+    /// Checks if the struct is copied by value instead of passed by reference
+    pub fn is_copy(&self) -> bool {
+        return Attribute::has_attribute("copy", &self.attributes);
+    }
+
     /// Creates a new struct data with the given args
     pub fn new(
         attributes: Vec<Attribute>,
@@ -201,7 +235,7 @@ impl StructData {
         span: Span,
         name: String,
     ) -> Self {
-        let id = unsafe { ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst) };
+        let id = Self::stable_id(&name);
 
         return Self {
             attributes,
@@ -276,26 +310,13 @@ impl TopElement for StructData {
     }
 
     fn is_operator(&self) -> bool {
-        return self.is_trait() && Attribute::find_attribute("operation", &self.attributes).is_some();
+        return is_modifier(self.modifiers, Modifier::Operation);
     }
 
     fn is_trait(&self) -> bool {
         return is_modifier(self.modifiers, Modifier::Trait);
     }
 
-    fn default(&self, id: u64) -> Arc<Self> {
-        return Arc::new(StructData {
-            modifiers: 0,
-            chalk_data: Self::get_chalk_data(id, 0),
-            id,
-            name: "empty".to_string(),
-            span: Span::default(),
-            attributes: vec![],
-            functions: vec![],
-            poisoned: vec![],
-        });
-    }
-
     fn id(&self) -> Option<u64> {
         return Some(self.id);
     }
@@ -320,6 +341,7 @@ impl TopElement for StructData {
         process_manager: Box<dyn ProcessManager>,
     ) -> Result<(), ParsingError> {
         let data = current.data.clone();
+        syntax.lock().check_attributes(&data.attributes, &data.span);
         let functions = current.functions;
         current.functions = Vec::default();
         let structure = Arc::new(process_manager.verify_struct(current, resolver.deref(), &syntax).await);
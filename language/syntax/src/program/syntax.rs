@@ -1,5 +1,6 @@
 use parking_lot::Mutex;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::task::Waker;
 
@@ -21,13 +22,15 @@ use async_trait::async_trait;
 use data::tokens::Span;
 pub use data::Main;
 
-use crate::async_util::{AsyncStructImplGetter, AsyncTypesGetter, NameResolver, UnparsedType};
+use crate::async_util::{AsyncDataGetter, AsyncStructImplGetter, AsyncTypesGetter, NameResolver, UnparsedType};
 use crate::chalk_interner::ChalkIr;
 use crate::errors::{ErrorSource, ParsingMessage};
-use crate::program::function::{FinalizedFunction, FunctionData};
-use crate::program::r#struct::{FinalizedStruct, StructData, BOOL, F32, F64, I16, I32, I64, I8, STR, U16, U32, U64, U8};
-use crate::program::types::FinalizedTypes;
-use crate::top_element_manager::{GetterManager, TopElementManager};
+use crate::program::function::{CodelessFinalizedFunction, FinalizedFunction, FunctionData};
+use crate::program::r#struct::{
+    FinalizedStruct, StructData, ARRAY, BOOL, F32, F64, I16, I32, I64, I8, STR, U16, U32, U64, U8,
+};
+use crate::program::types::{mangle_generic_name, FinalizedTypes};
+use crate::top_element_manager::{impl_key, GetterManager, TopElementManager};
 use crate::{
     is_modifier, Attribute, FinishedStructImplementor, FinishedTraitImplementor, Modifier, ParsingError, ProcessManager,
     TopElement, Types,
@@ -48,6 +51,8 @@ pub struct Syntax {
     pub strut_compiling: Arc<DashMap<String, Arc<FinalizedStruct>>>,
     /// All parsing errors on the entire program
     pub errors: Vec<ParsingError>,
+    /// All warnings on the entire program, which don't stop compilation
+    pub warnings: Vec<ParsingError>,
     /// All structures in the program
     pub structures: TopElementManager<StructData>,
     /// All functions in the program
@@ -65,6 +70,29 @@ pub struct Syntax {
     pub operation_wakers: HashMap<String, Vec<Waker>>,
     /// Manages the next steps of compilation after parsing
     pub process_manager: Box<dyn ProcessManager>,
+    /// Every parsed file by hash
+    pub source_files: HashMap<u64, SourceFile>,
+    /// The name of the struct each struct extends, used to find inheritance cycles
+    pub struct_parents: HashMap<String, String>,
+    /// Every library named by a #[link(name = "...")] attribute, which the program is linked with,
+    /// and the function the attribute is on
+    pub link_libraries: Vec<(String, Span)>,
+    /// Each function's parsed code, only recorded when dumping the AST, which magpie prints after building
+    pub ast_dumps: Vec<String>,
+    /// The object file written when building instead of running, which magpie reports after building
+    pub object_file: Option<PathBuf>,
+    /// The flags the object file has to be linked with, like -lm, which magpie reports after building
+    pub link_flags: Vec<String>,
+}
+
+/// A parsed file, used to turn spans back into file locations for debug info and the language server
+pub struct SourceFile {
+    /// The path to the file
+    pub path: String,
+    /// The line and column each token in the file starts at, by token index
+    pub positions: Vec<(u32, u32)>,
+    /// The first and last token index of every code block, used to find which variables are in scope
+    pub blocks: Vec<(usize, usize)>,
 }
 
 impl Syntax {
@@ -76,8 +104,9 @@ impl Syntax {
             compiling_wakers: HashMap::default(),
             strut_compiling: Arc::new(DashMap::default()),
             errors: Vec::default(),
+            warnings: Vec::default(),
             functions: TopElementManager::default(),
-            structures: TopElementManager::with_sorted(vec![
+            structures: TopElementManager::with_ids(vec![
                 I64.data.clone(),
                 I32.data.clone(),
                 I16.data.clone(),
@@ -97,6 +126,12 @@ impl Syntax {
             operations: HashMap::default(),
             operation_wakers: HashMap::default(),
             process_manager,
+            source_files: HashMap::default(),
+            struct_parents: HashMap::default(),
+            link_libraries: Vec::default(),
+            ast_dumps: Vec::default(),
+            object_file: None,
+            link_flags: Vec::default(),
         };
     }
 
@@ -112,7 +147,15 @@ impl Syntax {
             let mut locked = syntax.lock();
             locked.compiling_wakers.remove(&function.data.name).into_iter().flatten().for_each(Waker::wake);
 
-            if function.data.name != locked.async_manager.target {
+            // Functions of a program's modules can all be called, so the non-generic ones are degenericed like the target
+            let in_modules = generic
+                && function.generics.is_empty()
+                && !is_modifier(function.data.modifiers, Modifier::Trait)
+                && locked.async_manager.modules.iter().any(|module| {
+                    function.data.name.strip_prefix(module.as_str()).map_or(false, |name| name.starts_with("::"))
+                });
+
+            if function.data.name != locked.async_manager.target && !in_modules {
                 // Prevent duplicates from empty trait methods
                 if function.code.expressions.len() == 0
                     && ((locked.compiling.contains_key(&function.data.name) && !generic)
@@ -135,7 +178,7 @@ impl Syntax {
                 return;
             }
 
-            if !function.generics.is_empty() || !function.fields.is_empty() {
+            if !in_modules && (!function.generics.is_empty() || !function.fields.is_empty()) {
                 panic!("Invalid main function! Make sure your main function is the top function in your file");
             }
 
@@ -144,11 +187,16 @@ impl Syntax {
             } else {
                 locked.compiling.insert(function.data.name.clone(), function.clone());
             }
-            waker.clone_from(&locked.async_manager.target_waker);
+            if !in_modules {
+                waker.clone_from(&locked.async_manager.target_waker);
+            }
         }
 
         if generic {
-            process_manager.degeneric_code(Arc::new(function.to_codeless()), syntax).await;
+            // The finalized function has no parent, so methods are degenericed from the verified function instead
+            let codeless = syntax.lock().functions.data.get(&function.data).cloned();
+            let codeless = codeless.unwrap_or_else(|| Arc::new(function.to_codeless()));
+            process_manager.degeneric_code(codeless, syntax).await;
         }
         if let Some(found) = waker {
             found.wake();
@@ -157,7 +205,32 @@ impl Syntax {
 
     /// Checks if the implementations are finished parsing.
     pub fn finished_impls(&self) -> bool {
-        return self.async_manager.finished && self.async_manager.parsing_impls == 0;
+        return self.async_manager.finished_impls();
+    }
+
+    /// Adds a finished trait implementation, waking the impl waiters it could affect
+    pub fn add_implementation(&mut self, implementor: Arc<FinishedTraitImplementor>) {
+        let indirect = implementor.base.is_trait() || !matches!(implementor.base.dereferenced(), FinalizedTypes::Struct(_));
+        self.async_manager.wake_impls_of(impl_key(&implementor.target), indirect);
+        self.implementations.push(implementor);
+    }
+
+    /// Groups every function by the hash of the file it's declared in, used for per-file diagnostics.
+    pub fn iter_functions_by_file(&self) -> HashMap<u64, Vec<Arc<FunctionData>>> {
+        let mut output: HashMap<u64, Vec<Arc<FunctionData>>> = HashMap::default();
+        for function in self.functions.types.values() {
+            output.entry(function.span.file).or_default().push(function.clone());
+        }
+        return output;
+    }
+
+    /// Groups every structure by the hash of the file it's declared in, used for per-file diagnostics.
+    pub fn iter_structures_by_file(&self) -> HashMap<u64, Vec<Arc<StructData>>> {
+        let mut output: HashMap<u64, Vec<Arc<StructData>>> = HashMap::default();
+        for structure in self.structures.types.values() {
+            output.entry(structure.span.file).or_default().push(structure.clone());
+        }
+        return output;
     }
 
     /// Sets the syntax to be finished, calling all wakers so non-existent functions can be detected.
@@ -176,8 +249,7 @@ impl Syntax {
         self.operation_wakers.values().flatten().for_each(Waker::wake_by_ref);
         self.operation_wakers.clear();
 
-        self.async_manager.impl_waiters.iter().for_each(Waker::wake_by_ref);
-        self.async_manager.impl_waiters.clear();
+        self.async_manager.wake_impls();
     }
 
     /// Converts an implementation into a Chalk ImplDatum. This allows implementations to be used
@@ -233,6 +305,36 @@ impl Syntax {
         return if output.is_empty() { None } else { Some(output) };
     }
 
+    /// Picks the most specific implementation out of the matching implementations returned by ImplWaiter.
+    /// Implementations for a struct beat implementations for a generic type, which beat blanket implementations.
+    /// Errors with every candidate if multiple implementations are equally specific.
+    pub fn select_implementation(
+        mut implementations: Vec<(Arc<FinishedTraitImplementor>, Vec<Arc<FunctionData>>)>,
+        span: &Span,
+    ) -> Result<(Arc<FinishedTraitImplementor>, Vec<Arc<FunctionData>>), ParsingError> {
+        let best = implementations.iter().map(|(implementor, _)| implementor.base.specificity()).min().unwrap_or(0);
+        implementations.retain(|(implementor, _)| implementor.base.specificity() == best);
+
+        if implementations.len() > 1 {
+            return Err(span.make_error(ParsingMessage::AmbiguousImpl(
+                implementations
+                    .iter()
+                    .map(|(implementor, functions)| {
+                        (
+                            format!("{} for {}", implementor.target, implementor.base),
+                            functions.first().map(|function| function.span.clone()).unwrap_or_default(),
+                        )
+                    })
+                    .collect(),
+            )));
+        }
+
+        return match implementations.pop() {
+            Some(found) => Ok(found),
+            None => Err(span.make_error(ParsingMessage::ShouldntSee("Select implementation"))),
+        };
+    }
+
     /// Recursively solves if a type is a generic type by checking if the target type matches all the bounds.
     fn solve_nonstruct_types(&self, target_type: &FinalizedTypes, checking: &FinalizedTypes) -> Option<bool> {
         return match target_type {
@@ -268,8 +370,7 @@ impl Syntax {
             return inner;
         }
 
-        let second_ty = &second.inner_struct().data;
-        if !is_modifier(second_ty.modifiers, Modifier::Trait) {
+        if !second.is_trait() {
             return false;
         }
         let first_ty = first.inner_struct().data.chalk_data.get_ty().clone();
@@ -279,7 +380,7 @@ impl Syntax {
         let goal = Goal::new(
             ChalkIr,
             GoalData::DomainGoal(DomainGoal::Holds(WhereClause::Implemented(TraitRef {
-                trait_id: TraitId(second_ty.id as u32),
+                trait_id: TraitId(second.inner_struct().data.id as u32),
                 substitution: Substitution::from_iter(ChalkIr, elements.into_iter()),
             }))),
         );
@@ -333,6 +434,8 @@ impl Syntax {
             locked.errors.push(adding.get_span().make_error("Duplicate type!"));
         }*/
 
+        self.check_id(adding);
+
         let manager = T::get_manager(self);
         manager.add_type(adding.clone());
 
@@ -349,15 +452,61 @@ impl Syntax {
         }
     }
 
+    /// Errors if a different element already has the ID of the one being added.
+    /// IDs are hashed from names, so two different names could end up with the same ID
+    fn check_id<T: TopElement + 'static>(&mut self, adding: &Arc<T>) {
+        let colliding = adding
+            .id()
+            .and_then(|id| T::get_manager(self).ids.get(&id))
+            .filter(|existing| existing.name() != adding.name())
+            .map(|existing| existing.name().clone());
+        if let Some(existing) = colliding {
+            let error = ParsingMessage::IdCollision(existing, adding.name().clone());
+            self.errors.push(adding.get_span().make_error(error));
+        }
+    }
+
+    /// Validates an element's attributes, adding any problems to the errors or warnings
+    pub fn check_attributes(&mut self, attributes: &Vec<Attribute>, span: &Span) {
+        match Attribute::validate(attributes, span) {
+            Ok(warnings) => warnings.into_iter().for_each(|warning| self.add_warning(warning)),
+            Err(error) => self.errors.push(error),
+        }
+    }
+
+    /// Warns about a use of a deprecated function or struct, with the message from its deprecated attribute if it has one
+    pub fn check_deprecated(&mut self, attributes: &Vec<Attribute>, name: &str, span: &Span) {
+        let message = match Attribute::find_attribute("deprecated", attributes) {
+            Some(Attribute::String(_, message)) => Some(message.trim_matches('"').to_string()),
+            Some(_) => None,
+            None => return,
+        };
+        self.add_warning(span.make_error(ParsingMessage::Deprecated(name.to_string(), message)));
+    }
+
+    /// Adds a warning, which is added to the errors instead if warnings are denied
+    pub fn add_warning(&mut self, warning: ParsingError) {
+        if self.async_manager.deny_warnings {
+            self.errors.push(warning);
+        } else {
+            self.warnings.push(warning);
+        }
+    }
+
     /// Adds a poisoned type, which means it errored and shouldn't be checked for completeness.
-    pub fn add_poison<T: TopElement>(&mut self, element: Arc<T>) {
+    pub fn add_poison<T: TopElement + 'static>(&mut self, element: Arc<T>) {
         for poison in element.errors() {
             self.errors.push(poison.clone());
         }
 
+        if !T::get_manager(self).types.contains_key(element.name()) {
+            self.check_id(&element);
+        }
         let getter = T::get_manager(self);
         if getter.types.get_mut(element.name()).is_none() {
-            getter.sorted.push(element.clone());
+            if let Some(id) = element.id() {
+                getter.ids.insert(id, element.clone());
+            }
             getter.types.insert(element.name().clone(), element.clone());
         }
 
@@ -378,6 +527,22 @@ impl Syntax {
         return AsyncTypesGetter::new(syntax, getting, name_resolver, not_trait).await;
     }
 
+    /// Gets the generic struct already registered with the generics filled in, if there is one.
+    /// Instances used to also be named like Map<str, u64> or Map<str_u64>, which are checked until nothing uses them.
+    pub fn get_generic_struct(&self, base: &str, generics: &[FinalizedTypes]) -> Option<Arc<FinalizedStruct>> {
+        let generic_names = generics.iter().map(FinalizedTypes::to_string).collect::<Vec<_>>();
+        let names = [
+            mangle_generic_name(base, generics),
+            format!("{}<{}>", base, generic_names.join(", ")),
+            format!("{}<{}>", base, generic_names.join("_")),
+        ];
+        return names
+            .iter()
+            .filter_map(|name| self.structures.types.get(name))
+            .find_map(|data| self.structures.data.get(data))
+            .cloned();
+    }
+
     /// Gets the implementation of a structure
     pub async fn get_struct_impl(
         syntax: Arc<Mutex<Syntax>>,
@@ -386,6 +551,77 @@ impl Syntax {
         return AsyncStructImplGetter::new(syntax.clone(), getting).await;
     }
 
+    /// Finds the best method with the name in the implementations of the first argument's type,
+    /// since methods with the same name in different impls of a type overload each other.
+    /// Arguments matching their parameter exactly are preferred over ones implementing the parameter's trait,
+    /// which are preferred over ones filling a generic. Returns None if no method can take the arguments.
+    pub async fn resolve_overloaded_function(
+        name: &str,
+        arg_types: &[FinalizedTypes],
+        span: &Span,
+        syntax: &Arc<Mutex<Syntax>>,
+    ) -> Result<Option<Arc<FunctionData>>, ParsingError> {
+        let Some(calling) = arg_types.first() else {
+            return Ok(None);
+        };
+
+        let mut best: Option<((usize, usize), Arc<FunctionData>)> = None;
+        let mut tied = false;
+        for implementor in Syntax::get_struct_impl(syntax.clone(), calling.clone()).await {
+            for function in &implementor.functions {
+                if function.name.split("::").last().unwrap() != name {
+                    continue;
+                }
+                let finalized = AsyncDataGetter::new(syntax.clone(), function.clone()).await;
+                let Some(distance) = Self::overload_distance(&finalized, arg_types, syntax).await else {
+                    continue;
+                };
+                match &best {
+                    Some((best_distance, _)) if *best_distance < distance => {}
+                    Some((best_distance, _)) if *best_distance == distance => tied = true,
+                    _ => {
+                        best = Some((distance, function.clone()));
+                        tied = false;
+                    }
+                }
+            }
+        }
+
+        if tied {
+            return Err(span.make_error(ParsingMessage::AmbiguousOverload(name.to_string())));
+        }
+        return Ok(best.map(|(_, function)| function));
+    }
+
+    /// How far the arguments are from the function's parameters as the number of generic parameters and then
+    /// trait parameters they needed, or None if the function can't take the arguments
+    async fn overload_distance(
+        function: &CodelessFinalizedFunction,
+        arg_types: &[FinalizedTypes],
+        syntax: &Arc<Mutex<Syntax>>,
+    ) -> Option<(usize, usize)> {
+        if function.arguments.len() != arg_types.len() {
+            return None;
+        }
+
+        let (mut generics, mut traits) = (0, 0);
+        for (argument, found) in function.arguments.iter().zip(arg_types) {
+            let parameter = &argument.field.field_type;
+            if found.dereferenced() == parameter.dereferenced() {
+                continue;
+            }
+            if !found.of_type(parameter, syntax.clone()).await {
+                return None;
+            }
+            if parameter.is_generic() {
+                generics += 1;
+            } else {
+                traits += 1;
+            }
+        }
+        return Some((generics, traits));
+    }
+
     /// Asynchronously gets a struct, or returns the error if that struct isn't found.
     #[async_recursion]
     pub async fn get_struct(
@@ -411,6 +647,14 @@ impl Syntax {
             return Ok(Types::Generic(name, bounds));
         }
 
+        // Array types are the core array of their elements
+        if let Some(element) = name.strip_prefix('[').and_then(|inner| inner.strip_suffix(']')) {
+            let array = (ARRAY.to_string(), span.clone());
+            let array = Self::get_struct(syntax.clone(), array, name_resolver.boxed_clone(), vec![]).await?;
+            let element = Self::get_struct(syntax, (element.to_string(), span), name_resolver, resolved_generics).await?;
+            return Ok(Types::GenericType(Box::new(array), vec![element]));
+        }
+
         if name.contains('<') {
             return Ok(Self::parse_bounds(name.as_bytes(), &syntax, &span, &*name_resolver).await?.remove(0));
         }
@@ -513,4 +757,25 @@ pub trait Compiler<T> {
     /// Compiles the target function and returns the main runner.
     /// Waits for the receiver before calling any of the code
     async fn compile(&self, receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Option<T>;
+
+    /// Compiles the target function into the compiler's textual output, like LLVM IR, without running anything.
+    /// Waits for the receiver before compiling
+    async fn compile_to_string(&self, receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Option<String>;
+
+    /// Compiles every function in the modules, keeping them loaded so they can be called by name.
+    /// Extern functions with a symbol in host_functions are linked to its address.
+    /// Waits for the receiver before compiling
+    async fn compile_program(
+        &self,
+        receiver: Receiver<()>,
+        syntax: &Arc<Mutex<Syntax>>,
+        modules: Vec<String>,
+        host_functions: Vec<(String, usize)>,
+    ) -> Option<Box<dyn Program>>;
+}
+
+/// A compiled program kept loaded, so its functions can be called from Rust
+pub trait Program: Send {
+    /// Gets the address of a compiled function by its name, or None if it wasn't compiled
+    fn get_function(&self, name: &str) -> Option<usize>;
 }
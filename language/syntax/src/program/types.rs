@@ -20,7 +20,7 @@ use crate::program::function::{display, display_parenless, FunctionData};
 use crate::program::r#struct::{ChalkData, FinalizedStruct};
 use crate::program::syntax::Syntax;
 use crate::top_element_manager::{ImplWaiter, TypeImplementsTypeWaiter};
-use crate::{is_modifier, Modifier, ParsingError, StructData};
+use crate::{ParsingError, StructData, TopElement};
 
 /// A type is assigned to every value at compilation-time in Raven because it's statically typed.
 /// For example, "test" is a Struct called str, which is an internal type.
@@ -61,6 +61,16 @@ impl Types {
         };
     }
 
+    /// Checks if the type is a trait, looking through references and generic types. Generics are never traits.
+    pub fn is_trait(&self) -> bool {
+        return match self {
+            Types::Struct(structure) => structure.is_trait(),
+            Types::Reference(inner) => inner.is_trait(),
+            Types::GenericType(base, _) => base.is_trait(),
+            Types::Generic(_, _) => false,
+        };
+    }
+
     /// Finalized the type by waiting for the FinalizedStruct to be avalible.
     #[async_recursion(Sync)]
     pub async fn finalize(&self, syntax: Arc<Mutex<Syntax>>) -> FinalizedTypes {
@@ -112,7 +122,7 @@ impl FinalizedTypes {
                 .data
                 .functions
                 .iter()
-                .find(|inner| inner.name.ends_with(name))
+                .find(|inner| inner.name.split("::").last().unwrap() == name)
                 .map(|inner| vec![(self.clone(), inner.clone())]),
             FinalizedTypes::Reference(inner) => inner.find_method(name),
             FinalizedTypes::GenericType(base, _) => base.find_method(name),
@@ -211,6 +221,36 @@ impl FinalizedTypes {
         };
     }
 
+    /// How specific the type is when picking between implementations, lower is more specific.
+    /// Structs are the most specific, followed by generic types like List<T>, then blanket generics like T.
+    pub fn specificity(&self) -> u8 {
+        return match self {
+            FinalizedTypes::Struct(_) => 0,
+            FinalizedTypes::GenericType(_, _) => 1,
+            FinalizedTypes::Generic(_, _) => 2,
+            FinalizedTypes::Reference(inner) => inner.specificity(),
+        };
+    }
+
+    /// Removes every reference layer around the type
+    pub fn dereferenced(&self) -> &FinalizedTypes {
+        return match self {
+            FinalizedTypes::Reference(inner) => inner.dereferenced(),
+            _ => self,
+        };
+    }
+
+    /// How deeply generic types are nested in this type, used to stop unbounded generic instantiation.
+    pub fn depth(&self) -> usize {
+        return match self {
+            FinalizedTypes::Struct(_) => 0,
+            FinalizedTypes::GenericType(_, bounds) | FinalizedTypes::Generic(_, bounds) => {
+                1 + bounds.iter().map(FinalizedTypes::depth).max().unwrap_or(0)
+            }
+            FinalizedTypes::Reference(inner) => inner.depth(),
+        };
+    }
+
     /// Checks if a type is generic
     pub fn is_generic(&self) -> bool {
         return match self {
@@ -221,6 +261,28 @@ impl FinalizedTypes {
         };
     }
 
+    /// Checks if the generic with the given name is in the type, like T in List<T>
+    pub fn uses_generic(&self, generic: &str) -> bool {
+        return match self {
+            FinalizedTypes::Reference(inner) => inner.uses_generic(generic),
+            FinalizedTypes::Generic(name, _) => name == generic,
+            FinalizedTypes::Struct(_) => false,
+            FinalizedTypes::GenericType(base, bounds) => {
+                base.uses_generic(generic) || bounds.iter().any(|found| found.uses_generic(generic))
+            }
+        };
+    }
+
+    /// Checks if the type is a trait, looking through references and generic types. Generics are never traits.
+    pub fn is_trait(&self) -> bool {
+        return match self {
+            FinalizedTypes::Struct(structure) => structure.data.is_trait(),
+            FinalizedTypes::Reference(inner) => inner.is_trait(),
+            FinalizedTypes::GenericType(base, _) => base.is_trait(),
+            FinalizedTypes::Generic(_, _) => false,
+        };
+    }
+
     /// Checks if the type is of the other type, following Raven's type rules.
     /// May block until all implementations are finished parsing, must not be called from
     /// implementation parsing to prevent deadlocking.
@@ -248,7 +310,7 @@ impl FinalizedTypes {
                 FinalizedTypes::Struct(other_struct) => {
                     if found == other_struct {
                         (true, None)
-                    } else if is_modifier(other.inner_struct().data.modifiers, Modifier::Trait) {
+                    } else if other.is_trait() {
                         if syntax.is_none() {
                             return (false, None);
                         }
@@ -385,12 +447,12 @@ impl FinalizedTypes {
     }
 
     pub async fn get_has_impl(syntax: Option<Arc<Mutex<Syntax>>>, base: FinalizedTypes, trait_type: FinalizedTypes) -> bool {
-        return ImplWaiter {
-            syntax: syntax.unwrap(),
-            base_type: base,
+        return ImplWaiter::new(
+            syntax.unwrap(),
+            base,
             trait_type,
-            error: Span::default().make_error(ParsingMessage::ShouldntSee("get_has_impl")),
-        }
+            Span::default().make_error(ParsingMessage::ShouldntSee("get_has_impl")),
+        )
         .await
         .is_ok();
     }
@@ -417,19 +479,17 @@ impl FinalizedTypes {
     ) -> Result<(), ParsingError> {
         if !self.of_type_sync(other, None).0 && self.inner_struct_safe().is_some() {
             loop {
-                let waiter = ImplWaiter {
-                    syntax: syntax.clone(),
-                    base_type: other.clone(),
-                    trait_type: self.clone(),
-                    error: bounds_error.make_error(ParsingMessage::ShouldntSee("Resolve generic")),
-                };
+                let waiter = ImplWaiter::new(
+                    syntax.clone(),
+                    other.clone(),
+                    self.clone(),
+                    bounds_error.make_error(ParsingMessage::ShouldntSee("Resolve generic")),
+                );
                 match waiter.await {
                     Ok(implementors) => {
-                        if implementors.len() > 1 {
-                            panic!("Ambiguous impl! Raven can't handle this yet!");
-                        }
-                        self.resolve_generic(&implementors[0].0.target, syntax, generics, bounds_error.clone()).await?;
-                        implementors[0].0.base.resolve_generic(&other, syntax, generics, bounds_error.clone()).await?;
+                        let (implementor, _) = Syntax::select_implementation(implementors, &bounds_error)?;
+                        self.resolve_generic(&implementor.target, syntax, generics, bounds_error.clone()).await?;
+                        implementor.base.resolve_generic(&other, syntax, generics, bounds_error.clone()).await?;
                         return Ok(());
                     }
                     Err(_) => break,
@@ -521,7 +581,10 @@ impl Display for FinalizedTypes {
                 write!(f, "{}: {}", name, display(bounds, " + "))
             }
             FinalizedTypes::GenericType(types, generics) => {
-                write!(f, "{}<{}>", types, display_parenless(generics, "_"))
+                // A degenericed base already has the generics in its name, so only the declared name is written,
+                // otherwise each level of nesting doubles the name's length
+                let base = types.to_string();
+                write!(f, "{}<{}>", base.split('$').next().unwrap(), display_parenless(generics, "_"))
             }
         }
     }
@@ -569,3 +632,9 @@ fn recursive_eq(first: &FinalizedTypes, second: &FinalizedTypes) -> bool {
         _ => unreachable!(),
     };
 }
+
+/// The name of a generic struct or function with its generics filled in, like Map$str_u64.
+/// Every instance is named by this so it's only registered once, and the base name is everything before the $.
+pub fn mangle_generic_name(base: &str, generics: &[FinalizedTypes]) -> String {
+    return format!("{}${}", base, generics.iter().map(FinalizedTypes::to_string).collect::<Vec<_>>().join("_"));
+}
@@ -1,15 +1,17 @@
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
+use std::mem;
 use std::ops::AsyncFnMut;
 use std::pin::{pin, Pin};
 use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 
 use data::tokens::Span;
+use data::MAX_GENERIC_DEPTH;
 
-use crate::async_util::NameResolver;
-use crate::errors::ParsingError;
+use crate::async_util::{NameResolver, TASK_NAME};
+use crate::errors::{ErrorSource, ParsingError, ParsingMessage};
 use crate::program::code::FinalizedEffects;
 use crate::program::function::FunctionData;
 use crate::program::syntax::Syntax;
@@ -17,7 +19,6 @@ use crate::program::types::FinalizedTypes;
 use crate::{FinishedTraitImplementor, TopElement};
 
 /// The async manager, just stores basic information about the current parsing state.
-#[derive(Default)]
 pub struct GetterManager {
     /// If parsing non-impls is finished
     pub finished: bool,
@@ -25,10 +26,112 @@ pub struct GetterManager {
     pub parsing_impls: u32,
     /// Impl waiters, which are woken whenever an impl finishes parsing.
     pub impl_waiters: Vec<Waker>,
+    /// Impl waiters only affected by implementations of one trait, by the trait's name without generics
+    pub trait_waiters: HashMap<String, Vec<Waker>>,
+    /// Traits implemented for traits or generics, which any other implementation could make apply to more types
+    pub indirect_traits: HashSet<String>,
     /// The target method to compile
     pub target: String,
     /// Waker to wake when the target method is found
     pub target_waker: Option<Waker>,
+    /// Modules compiled as a program instead of from a target, whose functions are all degenericed like the target
+    pub modules: Vec<String>,
+    /// How deeply generic types can be nested before degenericing gives up
+    pub max_generic_depth: usize,
+    /// If warnings should be reported as errors
+    pub deny_warnings: bool,
+    /// If debug info should be generated, which requires keeping every file's token positions
+    pub debug: bool,
+    /// If each function's code should be printed after parsing, before it's checked
+    pub dump_ast: bool,
+    /// What each task last went to sleep waiting for, by task name, used to report tasks that never finish
+    pub blocked: HashMap<String, String>,
+}
+
+impl Default for GetterManager {
+    fn default() -> Self {
+        return Self {
+            finished: false,
+            parsing_impls: 0,
+            impl_waiters: Vec::default(),
+            trait_waiters: HashMap::default(),
+            indirect_traits: HashSet::default(),
+            target: String::default(),
+            target_waker: None,
+            modules: Vec::default(),
+            max_generic_depth: MAX_GENERIC_DEPTH,
+            deny_warnings: false,
+            debug: false,
+            dump_ast: false,
+            blocked: HashMap::default(),
+        };
+    }
+}
+
+impl GetterManager {
+    /// Records what the task being polled is waiting for, if it was spawned by a HandleWrapper
+    pub fn block(&mut self, description: impl FnOnce() -> String) {
+        let _ = TASK_NAME.try_with(|name| self.blocked.insert(name.clone(), description()));
+    }
+
+    /// Forgets what the task being polled was waiting for, called when a waiter is woken so finished waits aren't reported
+    pub fn unblock(&mut self) {
+        let _ = TASK_NAME.try_with(|name| self.blocked.remove(name));
+    }
+
+    /// If every implementation is finished parsing
+    pub fn finished_impls(&self) -> bool {
+        return self.finished && self.parsing_impls == 0;
+    }
+
+    /// Waits for an implementation of the trait, or any implementation if the trait isn't known
+    pub fn wait_for_impl(&mut self, trait_name: Option<String>, waker: Waker) {
+        match trait_name {
+            Some(trait_name) => self.trait_waiters.entry(trait_name).or_default().push(waker),
+            None => self.impl_waiters.push(waker),
+        }
+    }
+
+    /// Wakes the waiters an implementation of the trait could affect.
+    /// Implementations for traits or generics let other implementations affect that trait, so they're remembered
+    /// and their trait's waiters are woken by every implementation after them.
+    pub fn wake_impls_of(&mut self, trait_name: Option<String>, indirect: bool) {
+        let Some(trait_name) = trait_name else {
+            self.wake_impls();
+            return;
+        };
+        if indirect {
+            self.indirect_traits.insert(trait_name.clone());
+        }
+
+        let mut waking = mem::take(&mut self.impl_waiters);
+        waking.extend(self.trait_waiters.remove(&trait_name).unwrap_or_default());
+        for indirect in &self.indirect_traits {
+            waking.extend(self.trait_waiters.remove(indirect).unwrap_or_default());
+        }
+        waking.into_iter().for_each(Waker::wake);
+    }
+
+    /// Wakes every impl waiter, used once every implementation is finished so waiters can stop waiting
+    pub fn wake_impls(&mut self) {
+        let mut waking = mem::take(&mut self.impl_waiters);
+        waking.extend(self.trait_waiters.drain().flat_map(|(_, wakers)| wakers));
+        waking.into_iter().for_each(Waker::wake);
+    }
+
+    /// Marks an implementation as done parsing, waking every waiter if it was the last one
+    pub fn finish_impl(&mut self) {
+        self.parsing_impls -= 1;
+        if self.finished_impls() {
+            self.wake_impls();
+        }
+    }
+}
+
+/// The name impl waiters for the trait are grouped by, which ignores generics so every variant of a trait is grouped.
+/// Generics have no name, so waiters for them are woken by every implementation.
+pub fn impl_key(trait_type: &FinalizedTypes) -> Option<String> {
+    return trait_type.inner_struct_safe().map(|structure| structure.data.name.split('$').next().unwrap().to_string());
 }
 
 /// Waits for an implementation of the type
@@ -41,37 +144,130 @@ pub struct ImplWaiter {
     pub trait_type: FinalizedTypes,
     /// Error if the type isn't found
     pub error: ParsingError,
+    /// How many of the program's implementations have already been checked, so they aren't checked again when woken
+    checked: usize,
+    /// The matching implementations found so far
+    found: Vec<(Arc<FinishedTraitImplementor>, Vec<Arc<FunctionData>>)>,
+    /// Looks for an implementation that only failed because of its bounds, once none are found
+    bounds: Option<Pin<Box<dyn Future<Output = Option<ParsingError>> + Send + Sync>>>,
+}
+
+impl ImplWaiter {
+    /// Creates a new impl waiter
+    pub fn new(
+        syntax: Arc<Mutex<Syntax>>,
+        base_type: FinalizedTypes,
+        trait_type: FinalizedTypes,
+        error: ParsingError,
+    ) -> Self {
+        return Self { syntax, base_type, trait_type, error, checked: 0, found: Vec::default(), bounds: None };
+    }
 }
 
 impl Future for ImplWaiter {
     type Output = Result<Vec<(Arc<FinishedTraitImplementor>, Vec<Arc<FunctionData>>)>, ParsingError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let finished = self.syntax.lock().finished_impls();
-        let output = match pin!(Syntax::get_implementation_methods(&self.syntax, &self.base_type, &self.trait_type)).poll(cx)
-        {
-            Poll::Ready(inner) => inner,
-            Poll::Pending => return Poll::Pending,
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (finished, unchecked) = {
+            let mut locked = self.syntax.lock();
+            locked.async_manager.unblock();
+            (locked.finished_impls(), locked.implementations[self.checked..].to_vec())
         };
-        return match output {
-            Some(implementation_methods) => Poll::Ready(Ok(implementation_methods)),
-            None => {
-                if finished {
-                    Poll::Ready(Err(self.error.clone()))
-                } else {
-                    let mut locked = self.syntax.lock();
-                    // Locking rules means a lock can't be held from start to finish, so immediately wake if it finished in that time.
-                    if locked.finished_impls() {
-                        cx.waker().wake_by_ref();
-                    }
-                    locked.async_manager.impl_waiters.push(cx.waker().clone());
-                    Poll::Pending
+
+        // Once an implementation is checked its result can't change, so only new implementations are checked.
+        for implementation in unchecked {
+            if self.trait_type.of_type_sync(&implementation.target, None).0 {
+                let matches = match pin!(self.base_type.of_type(&implementation.base, self.syntax.clone())).poll(cx) {
+                    Poll::Ready(matches) => matches,
+                    Poll::Pending => return Poll::Pending,
+                };
+                if matches {
+                    self.found.push((implementation.clone(), implementation.functions.clone()));
                 }
             }
-        };
+            self.checked += 1;
+        }
+
+        if !self.found.is_empty() {
+            return Poll::Ready(Ok(mem::take(&mut self.found)));
+        } else if finished {
+            if self.bounds.is_none() {
+                let (syntax, base_type, trait_type) = (self.syntax.clone(), self.base_type.clone(), self.trait_type.clone());
+                self.bounds = Some(Box::pin(unsatisfied_bound(syntax, base_type, trait_type, self.error.span.clone())));
+            }
+            return match self.bounds.as_mut().unwrap().as_mut().poll(cx) {
+                Poll::Ready(error) => Poll::Ready(Err(error.unwrap_or_else(|| self.error.clone()))),
+                Poll::Pending => Poll::Pending,
+            };
+        }
+
+        let mut locked = self.syntax.lock();
+        // Locking rules means a lock can't be held from start to finish, so immediately wake if anything changed in that time.
+        if locked.finished_impls() || locked.implementations.len() > self.checked {
+            cx.waker().wake_by_ref();
+        }
+        locked.async_manager.wait_for_impl(impl_key(&self.trait_type), cx.waker().clone());
+        locked.async_manager.block(|| format!("ImplWaiter waiting for {} to implement {}", self.base_type, self.trait_type));
+        return Poll::Pending;
     }
 }
 
+/// Finds an implementation of the trait that matches the type except for its generics' bounds,
+/// like impl<T: Number> Printer for Wrapper<T> for a Wrapper<str>, returning an error naming the failed bound.
+/// Blanket implementations like impl<T: Number> Add<T, T> for T are skipped, since they'd match any type.
+pub async fn unsatisfied_bound(
+    syntax: Arc<Mutex<Syntax>>,
+    base_type: FinalizedTypes,
+    trait_type: FinalizedTypes,
+    span: Span,
+) -> Option<ParsingError> {
+    let implementations = syntax.lock().implementations.clone();
+    for implementation in implementations {
+        if !matches!(implementation.base.dereferenced(), FinalizedTypes::GenericType(_, _))
+            || !trait_type.of_type_sync(&implementation.target, None).0
+        {
+            continue;
+        }
+        let mut generics = HashMap::default();
+        if !bind_generics(&implementation.base, &base_type, &mut generics) {
+            continue;
+        }
+
+        for (name, generic) in &implementation.generics {
+            let (Some(found), FinalizedTypes::Generic(_, bounds)) = (generics.get(name), generic) else {
+                continue;
+            };
+            // Bounds using other generics, like T: Add<E, T>, depend on what those generics are
+            for bound in bounds.iter().filter(|bound| !bound.is_generic()) {
+                if !found.of_type(bound, syntax.clone()).await {
+                    let traits = FinalizedTypes::Struct(implementation.target.inner_struct().clone());
+                    let error = ParsingMessage::UnsatisfiedImplBound(found.clone(), bound.clone(), traits);
+                    return Some(span.make_error(error));
+                }
+            }
+        }
+    }
+    return None;
+}
+
+/// Matches a type with generics against another type ignoring the generics' bounds,
+/// adding what each generic is in the other type to the map. Returns false if the types don't match.
+fn bind_generics(pattern: &FinalizedTypes, other: &FinalizedTypes, generics: &mut HashMap<String, FinalizedTypes>) -> bool {
+    return match (pattern.dereferenced(), other.dereferenced()) {
+        (FinalizedTypes::Generic(name, _), other) => {
+            generics.insert(name.clone(), other.clone());
+            true
+        }
+        (FinalizedTypes::GenericType(base, bounds), FinalizedTypes::GenericType(other_base, other_bounds)) => {
+            bounds.len() == other_bounds.len()
+                && bind_generics(base, other_base, generics)
+                && bounds.iter().zip(other_bounds).all(|(bound, other_bound)| bind_generics(bound, other_bound, generics))
+        }
+        (FinalizedTypes::Struct(structure), FinalizedTypes::Struct(other_structure)) => structure == other_structure,
+        _ => false,
+    };
+}
+
 /// Waits for an implementation of the trait matching the constraints
 pub struct TraitImplWaiter<F> {
     /// The program
@@ -94,6 +290,7 @@ impl<F: AsyncFnMut(Arc<FinishedTraitImplementor>, Arc<FunctionData>) -> Result<F
     type Output = Result<FinalizedEffects, ParsingError>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.syntax.lock().async_manager.unblock();
         let value = pin!(find_trait_implementation(&self.syntax, &*self.resolver, &self.function, &self.return_type))
             .poll(cx)
             .clone();
@@ -121,6 +318,7 @@ impl<F: AsyncFnMut(Arc<FinishedTraitImplementor>, Arc<FunctionData>) -> Result<F
                         Poll::Ready(Err(self.error.clone()))
                     } else {
                         syntax.async_manager.impl_waiters.push(cx.waker().clone());
+                        syntax.async_manager.block(|| self.description());
                         Poll::Pending
                     }
                 }
@@ -129,12 +327,20 @@ impl<F: AsyncFnMut(Arc<FinishedTraitImplementor>, Arc<FunctionData>) -> Result<F
             Poll::Pending => {
                 let mut locked = self.syntax.lock();
                 locked.async_manager.impl_waiters.push(cx.waker().clone());
+                locked.async_manager.block(|| self.description());
                 Poll::Pending
             }
         };
     }
 }
 
+impl<F> TraitImplWaiter<F> {
+    /// Describes what the waiter is waiting for
+    fn description(&self) -> String {
+        return format!("TraitImplWaiter looking for {} on {}", self.function, self.return_type);
+    }
+}
+
 impl<T> Unpin for TraitImplWaiter<T> {}
 
 /// Finds all the implementations of the type
@@ -174,6 +380,8 @@ pub async fn find_trait_implementation(
     if output.is_empty() {
         return Ok(None);
     } else {
+        // Try the most specific implementations first
+        output.sort_by_key(|(implementor, _)| implementor.base.specificity());
         return Ok(Some(output));
     }
 }
@@ -193,6 +401,7 @@ impl Future for TypeImplementsTypeWaiter {
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let mut locked = self.syntax.lock();
+        locked.async_manager.unblock();
         // Only check for implementations if being compared against a trait.
         // Wait for the implementation to finish.
         if locked.solve(&self.current, &self.other) {
@@ -200,7 +409,10 @@ impl Future for TypeImplementsTypeWaiter {
         }
 
         if !locked.finished_impls() {
-            locked.async_manager.impl_waiters.push(cx.waker().clone());
+            locked.async_manager.wait_for_impl(impl_key(&self.other), cx.waker().clone());
+            locked
+                .async_manager
+                .block(|| format!("TypeImplementsTypeWaiter waiting to check if {} is {}", self.current, self.other));
             return Poll::Pending;
         }
 
@@ -217,8 +429,8 @@ where
 {
     /// Types and their data, added immediately after parsing
     pub types: HashMap<String, Arc<T>>,
-    /// A list of data sorted by the data's ID. Guaranteed to be in ID order.
-    pub sorted: Vec<Arc<T>>,
+    /// Data by its ID, which is how chalk refers to types
+    pub ids: HashMap<u64, Arc<T>>,
     /// Data sorted by its finalized type, which contains the finalized code. Added after finalization.
     pub data: HashMap<Arc<T>, Arc<T::Finalized>>,
     /// Wakers waiting on a type to be added to the types hashmap, waked after the type is added to types
@@ -239,11 +451,7 @@ impl<T: TopElement> TopElementManager<T> {
     pub fn add_type(&mut self, data: Arc<T>) {
         self.wake(data.name());
         if let Some(id) = data.id() {
-            while self.sorted.len() <= id as usize {
-                self.sorted.push(data.default(self.sorted.len() as u64));
-            }
-            self.sorted.remove(id as usize);
-            self.sorted.insert(id as usize, data.clone());
+            self.ids.insert(id, data.clone());
         }
         self.types.insert(data.name().clone(), data);
     }
@@ -260,7 +468,7 @@ impl<T: TopElement> Default for TopElementManager<T> {
     fn default() -> Self {
         return Self {
             types: HashMap::default(),
-            sorted: Vec::default(),
+            ids: HashMap::default(),
             data: HashMap::default(),
             wakers: HashMap::default(),
         };
@@ -271,8 +479,9 @@ impl<T> TopElementManager<T>
 where
     T: TopElement,
 {
-    /// Creates the getter with a list of sorted types already, used for internal types declared in the compiler
-    pub fn with_sorted(sorted: Vec<Arc<T>>) -> Self {
-        return Self { types: HashMap::default(), sorted, data: HashMap::default(), wakers: HashMap::default() };
+    /// Creates the getter with types already added by ID, used for internal types declared in the compiler
+    pub fn with_ids(internal: Vec<Arc<T>>) -> Self {
+        let ids = internal.into_iter().map(|data| (data.id().unwrap(), data)).collect();
+        return Self { types: HashMap::default(), ids, data: HashMap::default(), wakers: HashMap::default() };
     }
 }
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use lsp_types::{CompletionItem, CompletionItemKind, Position};
+
+use syntax::program::code::FinalizedEffectType;
+use syntax::program::function::FinalizedCodeBody;
+use syntax::program::syntax::Syntax;
+use syntax::program::types::FinalizedTypes;
+use syntax::{SimpleVariableManager, VariableManager};
+
+/// Gets every variable in scope at the position in the file, with its type as the detail.
+/// Variables are found from the arguments of the function the position is in and the variables made before it.
+pub fn complete(syntax: &Syntax, file: &Path, position: Position) -> Vec<CompletionItem> {
+    let path = file.to_string_lossy();
+    let Some((hash, source)) = syntax.source_files.iter().find(|(_, source)| source.path == path) else {
+        return vec![];
+    };
+
+    // Tokens are one-indexed by line, the IDE's positions are zero-indexed
+    let cursor = (position.line + 1, position.character);
+    let index = source.positions.iter().take_while(|start| **start <= cursor).count();
+
+    let Some(function) = syntax
+        .compiling
+        .iter()
+        .filter(|function| function.data.span.file == *hash && function.data.span.start <= index)
+        .max_by_key(|function| function.data.span.start)
+        .map(|function| function.value().clone())
+    else {
+        return vec![];
+    };
+
+    let mut variables = SimpleVariableManager::for_final_function(&function);
+    let mut found = Vec::default();
+    add_variables(&function.code, &mut found);
+    for (position, name, types) in found {
+        // Variables are in scope after they're made, until the block they're made in ends
+        let block = source.blocks.iter().filter(|(start, end)| *start <= position && position <= *end).max();
+        if position <= index && block.map_or(true, |(start, end)| *start <= index && index <= *end) {
+            variables.variables.insert(name, types);
+        }
+    }
+
+    let mut items = variables
+        .all_variables()
+        .into_iter()
+        // Variables made by the compiler, like a for loop's iterator, start with a $
+        .filter(|(name, _)| !name.starts_with('$'))
+        .map(|(name, types)| CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(types.to_string()),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    // Sorted so the IDE gets the same order every time
+    items.sort_by(|first, second| first.label.cmp(&second.label));
+    return items;
+}
+
+/// Finds every variable made in the code, including in code bodies inside it, with the token index it's made at.
+/// Variables made by control flow don't have a span, so they're given the position of the first line in their body.
+fn add_variables(code: &FinalizedCodeBody, found: &mut Vec<(usize, String, FinalizedTypes)>) {
+    let first_line =
+        code.expressions.iter().map(|expression| expression.effect.span).find(|span| span.file != 0).map(|span| span.start);
+    for expression in &code.expressions {
+        match &expression.effect.types {
+            FinalizedEffectType::CreateVariable(name, _, types) => {
+                let span = expression.effect.span;
+                let position = if span.file != 0 {
+                    span.start
+                } else if let Some(first_line) = first_line {
+                    first_line
+                } else {
+                    continue;
+                };
+                found.push((position, name.clone(), types.clone()));
+            }
+            FinalizedEffectType::CodeBody(body) => add_variables(body, found),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use lsp_types::Position;
+
+    use data::{Arguments, CompilerArguments, RunnerSettings};
+    use magpie_lib::build_project;
+    use parser::FileSourceSet;
+
+    use crate::completion::complete;
+
+    /// Checks that completing finds the variables made before the cursor, but not ones in blocks that already ended
+    #[test]
+    pub fn test_complete() {
+        let source = "fn main() -> u64 {\n    let first = 1;\n    if first == 1 {\n        let hidden = 2;\n    }\n    \
+            let second = 3;\n    \n    let third = 4;\n    return second;\n}\n";
+        let path = env::temp_dir().join("completion.rv");
+        fs::write(&path, source).unwrap();
+        let mut arguments = Arguments::build_args(
+            false,
+            RunnerSettings {
+                sources: vec![],
+                compiler_arguments: CompilerArguments { compiler: "llvm".to_string(), ..Default::default() },
+            },
+        );
+        let (syntax, _) =
+            build_project::<()>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path.clone()))], false).unwrap();
+
+        let labels =
+            complete(&syntax.lock(), &path, Position::new(6, 4)).into_iter().map(|item| item.label).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["first", "second"]);
+        let labels =
+            complete(&syntax.lock(), &path, Position::new(3, 23)).into_iter().map(|item| item.label).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["first", "hidden"]);
+    }
+}
@@ -2,20 +2,25 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 
-use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId};
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
 use lsp_types::notification::{DidChangeTextDocument, DidOpenTextDocument};
-use lsp_types::request::{GotoDeclaration, SemanticTokensFullRequest};
+use lsp_types::request::{Completion, GotoDeclaration, SemanticTokensFullRequest};
 use lsp_types::{
-    DeclarationCapability, InitializeParams, SemanticTokenModifier, SemanticTokenType, SemanticTokensFullOptions,
-    SemanticTokensLegend, SemanticTokensOptions, SemanticTokensServerCapabilities, ServerCapabilities,
-    TextDocumentSyncCapability, TextDocumentSyncKind, Url, WorkDoneProgressOptions,
+    CompletionOptions, CompletionResponse, DeclarationCapability, InitializeParams, SemanticTokenModifier,
+    SemanticTokenType, SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
+    SemanticTokensServerCapabilities, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+    WorkDoneProgressOptions,
 };
 use tokio::runtime::Builder;
 
-use crate::semantic_tokens::{parse_semantic_tokens, TokenIterator};
+use parser::tokens::tokenizer::tokenize;
+
+use crate::completion::complete;
+use crate::semantic_tokens::parse_semantic_tokens;
 use crate::syntax_manager::SyntaxManager;
 
 /// This file is templated from Rust's LSP example.
+mod completion;
 mod semantic_tokens;
 mod syntax_manager;
 
@@ -28,6 +33,8 @@ pub fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     // Run the server and wait for the two threads to end (typically by trigger LSP Exit event).
     let server_capabilities = serde_json::to_value(&ServerCapabilities {
         declaration_provider: Some(DeclarationCapability::Simple(true)),
+        // Completion provider suggests the variables in scope
+        completion_provider: Some(CompletionOptions::default()),
         // Semantic tokens provider gives the coloring of tokens
         semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
             work_done_progress_options: WorkDoneProgressOptions::default(),
@@ -116,12 +123,23 @@ fn main_loop(connection: Connection, params: serde_json::Value) -> Result<(), Bo
                     Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
                     Err(ExtractError::MethodMismatch(req)) => req,
                 };
+                let req = match cast::<Completion>(req) {
+                    Ok((id, params)) => {
+                        let file = params.text_document_position.text_document.uri.to_file_path().unwrap();
+                        let items =
+                            complete(&syntax.get_syntax(file.clone()).lock(), &file, params.text_document_position.position);
+                        connection.sender.send(Message::Response(Response::new_ok(id, CompletionResponse::Array(items))))?;
+                        continue;
+                    }
+                    Err(err @ ExtractError::JsonError { .. }) => panic!("{:?}", err),
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                };
                 match cast::<GotoDeclaration>(req) {
                     Ok((_id, params)) => {
                         syntax.get_syntax(params.text_document_position_params.text_document.uri.to_file_path().unwrap());
                         let mut position = params.text_document_position_params.position;
                         position.line += 1;
-                        let token = TokenIterator::new(
+                        let token = tokenize(
                             fs::read(params.text_document_position_params.text_document.uri.to_file_path().unwrap())
                                 .unwrap()
                                 .as_slice(),
@@ -168,6 +186,10 @@ fn main_loop(connection: Connection, params: serde_json::Value) -> Result<(), Bo
                         if params.content_changes[0].text.contains("ending") {
                             panic!("Ending");
                         }
+                        // The file changed, so anything compiled from the old version is out of date
+                        if let Ok(file) = params.text_document.uri.to_file_path() {
+                            syntax.invalidate(&file);
+                        }
                         // Assume it's only one thing being changed across the whole document
                         documents.insert(params.text_document.uri, params.content_changes[0].text.clone());
                         continue;
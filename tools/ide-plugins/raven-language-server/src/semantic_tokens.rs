@@ -3,38 +3,11 @@ use lsp_server::{Message, RequestId, Response};
 use lsp_types::{SemanticToken, SemanticTokens, SemanticTokensResult};
 
 use data::tokens::{Token, TokenTypes};
-use parser::tokens::tokenizer::Tokenizer;
+use parser::tokens::tokenizer::tokenize;
 
 /// Parses the semantic tokens from the file and sends it to the IDE
 pub async fn parse_semantic_tokens(id: RequestId, file: String, sender: Sender<Message>) {
-    // Converts the tokens into semantic tokens
-    let mut last: Option<Token> = None;
-    let data = TokenIterator::new(file.as_bytes())
-        .map(|mut token| {
-            // Multi-line tokens aren't supported, set the end to the start
-            if token.start.0 != token.end.0 {
-                token.start_offset = token.end_offset - token.end.1 as usize;
-                token.start = (token.end.0, 0);
-            }
-
-            // Calculate the semantic token data as the LSP protocol requires
-            let delta_line = (token.start.0 - 1) - last.clone().map_or(0, |inner| inner.start.0 - 1);
-            let temp = SemanticToken {
-                delta_line,
-                delta_start: if delta_line == 0 {
-                    token.start_offset - last.clone().map_or(0, |inner| inner.start_offset)
-                } else {
-                    0
-                } as u32,
-                length: (token.end_offset - token.start_offset) as u32,
-                token_type: get_token(last.as_ref().map_or(&TokenTypes::EOF, |inner| &inner.token_type), &token.token_type),
-                token_modifiers_bitset: 0,
-            };
-
-            last = Some(token);
-            temp
-        })
-        .collect::<Vec<_>>();
+    let data = encode(&classify(&file));
 
     // Send the response back to the IDE
     let result = Some(SemanticTokensResult::Tokens(SemanticTokens { result_id: None, data }));
@@ -43,30 +16,177 @@ pub async fn parse_semantic_tokens(id: RequestId, file: String, sender: Sender<M
     sender.send(Message::Response(resp)).unwrap();
 }
 
-pub struct TokenIterator<'a> {
-    tokenizer: Tokenizer<'a>,
-    ended: bool,
+/// A classified token at an absolute position, which never spans more than one line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedToken {
+    /// The SemanticTokenTypes of the token
+    pub token_type: u32,
+    /// The line the token is on, starting at 0
+    pub line: u32,
+    /// The byte in the line the token starts at
+    pub column: u32,
+    /// The length of the token in bytes
+    pub length: u32,
 }
 
-impl<'a> TokenIterator<'a> {
-    pub fn new(file: &'a [u8]) -> Self {
-        return TokenIterator { tokenizer: Tokenizer::new(file), ended: false };
+/// Classifies every token in the source. Tokens are trimmed of the whitespace before them,
+/// and tokens that span multiple lines (like strings and block comments) are split into one token per line.
+pub fn classify(source: &str) -> Vec<PositionedToken> {
+    let buffer = source.as_bytes();
+    let tokens = tokenize(buffer).collect::<Vec<_>>();
+    let mut context = TokenContext::default();
+    let line_starts = std::iter::once(0)
+        .chain(buffer.iter().enumerate().filter(|(_, character)| **character == b'\n').map(|(offset, _)| offset + 1))
+        .collect::<Vec<_>>();
+    let mut output = Vec::default();
+    for index in 0..tokens.len() {
+        let token_type = context.classify(&tokens, index, buffer);
+        if token_type != SemanticTokenTypes::None as u32 {
+            split_lines(buffer, &line_starts, &tokens[index], token_type, &mut output);
+        }
     }
+    return output;
 }
 
-impl Iterator for TokenIterator<'_> {
-    type Item = Token;
+/// Delta encodes the tokens as the LSP protocol requires, with each position relative to the last token
+pub fn encode(tokens: &[PositionedToken]) -> Vec<SemanticToken> {
+    let mut last = (0, 0);
+    return tokens
+        .iter()
+        .map(|token| {
+            let delta_line = token.line - last.0;
+            let delta_start = if delta_line == 0 { token.column - last.1 } else { token.column };
+            last = (token.line, token.column);
+            SemanticToken {
+                delta_line,
+                delta_start,
+                length: token.length,
+                token_type: token.token_type,
+                token_modifiers_bitset: 0,
+            }
+        })
+        .collect();
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.ended {
-            return None;
+/// Adds the text of the token to the output as one token per line, without the whitespace around it.
+/// Positions are found from the offsets, because the tokenizer doesn't count the lines inside strings.
+fn split_lines(buffer: &[u8], line_starts: &[usize], token: &Token, token_type: u32, output: &mut Vec<PositionedToken>) {
+    let end = token.end_offset.min(buffer.len());
+    let mut offset = token.start_offset;
+    let mut line = line_starts.partition_point(|start| *start <= offset) - 1;
+    while offset < end {
+        let line_end = line_starts.get(line + 1).map_or(end, |next| (*next - 1).min(end));
+        let text = &buffer[offset..line_end];
+        let leading = text.iter().take_while(|character| character.is_ascii_whitespace()).count();
+        let length = text[leading..].trim_ascii_end().len();
+        if length > 0 {
+            let column = offset + leading - line_starts[line];
+            output.push(PositionedToken { token_type, line: line as u32, column: column as u32, length: length as u32 });
         }
-        let token = self.tokenizer.next();
-        if token.token_type == TokenTypes::EOF {
-            self.ended = true;
+        offset = line_end + 1;
+        line += 1;
+    }
+}
+
+/// Tracks the generics in scope, so uses of a generic are shown as type parameters
+#[derive(Default)]
+struct TokenContext {
+    /// Generics declared by the enclosing struct, trait, or impl
+    outer_generics: Vec<String>,
+    /// Generics declared by the current function
+    function_generics: Vec<String>,
+    /// How deep into a generic declaration the tokens are, or 0 if they aren't declaring generics.
+    /// Only generics at depth 1 are declared, deeper ones are the arguments of a bound.
+    depth: u32,
+    /// If the generics being declared belong to a function instead of the enclosing type
+    declaring_function: bool,
+}
+
+impl TokenContext {
+    /// Gets the type of the token, updating the generics in scope
+    fn classify(&mut self, tokens: &[Token], index: usize, buffer: &[u8]) -> u32 {
+        let token = &tokens[index];
+        match token.token_type {
+            TokenTypes::StructStart | TokenTypes::TraitStart | TokenTypes::ImplStart | TokenTypes::StructEnd => {
+                self.outer_generics.clear();
+                self.function_generics.clear();
+            }
+            TokenTypes::FunctionStart => self.function_generics.clear(),
+            TokenTypes::GenericsStart => {
+                if self.depth > 0 {
+                    self.depth += 1;
+                } else if let Some(function) = declared_by(tokens, index) {
+                    self.depth = 1;
+                    self.declaring_function = function;
+                }
+            }
+            TokenTypes::GenericBoundEnd if self.depth > 1 => self.depth -= 1,
+            TokenTypes::GenericsEnd => self.depth = 0,
+            TokenTypes::Generic => {
+                let name = token.to_string(buffer);
+                if self.depth == 1 {
+                    if self.declaring_function {
+                        self.function_generics.push(name);
+                    } else {
+                        self.outer_generics.push(name);
+                    }
+                    return SemanticTokenTypes::TypeParameter as u32;
+                }
+                return self.type_of(&name);
+            }
+            TokenTypes::ArgumentType | TokenTypes::ReturnType | TokenTypes::FieldType => {
+                return self.type_of(&token.to_string(buffer));
+            }
+            TokenTypes::AttributeStart | TokenTypes::Attribute | TokenTypes::AttributeEnd => {
+                return SemanticTokenTypes::Decorator as u32;
+            }
+            // Calls after a period have a receiver, and anything else after a period is a field
+            TokenTypes::CallingType => {
+                return if next_is(tokens, index, TokenTypes::ParenOpen) {
+                    SemanticTokenTypes::Method
+                } else {
+                    SemanticTokenTypes::Property
+                } as u32;
+            }
+            TokenTypes::Variable if next_is(tokens, index, TokenTypes::ParenOpen) => {
+                return SemanticTokenTypes::Function as u32;
+            }
+            TokenTypes::Variable | TokenTypes::Identifier if self.is_generic(&token.to_string(buffer)) => {
+                return SemanticTokenTypes::TypeParameter as u32;
+            }
+            _ => {}
         }
-        return Some(token);
+
+        let last = index.checked_sub(1).map_or(TokenTypes::Start, |last| tokens[last].token_type);
+        return get_token(&last, &token.token_type);
+    }
+
+    /// Checks if the name is a generic in scope
+    fn is_generic(&self, name: &str) -> bool {
+        return self.function_generics.iter().chain(&self.outer_generics).any(|generic| generic == name);
     }
+
+    /// Gets the type of a type name, which is a type parameter if it's a generic in scope
+    fn type_of(&self, name: &str) -> u32 {
+        return if self.is_generic(name) { SemanticTokenTypes::TypeParameter } else { SemanticTokenTypes::Type } as u32;
+    }
+}
+
+/// Checks which declaration the generics starting at the index belong to.
+/// Returns Some(true) for a function, Some(false) for a struct, trait, or impl, and None if they're the generics of a type.
+fn declared_by(tokens: &[Token], index: usize) -> Option<bool> {
+    let before = |offset: usize| index.checked_sub(offset).map(|found| tokens[found].token_type);
+    return match (before(2), before(1)) {
+        (_, Some(TokenTypes::ImplStart)) => Some(false),
+        (Some(TokenTypes::StructStart | TokenTypes::TraitStart), Some(TokenTypes::Identifier)) => Some(false),
+        (Some(TokenTypes::FunctionStart), Some(TokenTypes::Identifier)) => Some(true),
+        _ => None,
+    };
+}
+
+/// Checks if the token after the index is of the given type
+fn next_is(tokens: &[Token], index: usize, token_type: TokenTypes) -> bool {
+    return tokens.get(index + 1).map_or(false, |next| next.token_type == token_type);
 }
 
 /// Gets the token's type from the last token and the current token
@@ -93,13 +213,15 @@ fn get_token(last: &TokenTypes, token_type: &TokenTypes) -> u32 {
         | TokenTypes::GenericBound
         | TokenTypes::GenericBoundEnd
         | TokenTypes::For
+        | TokenTypes::Extends
         | TokenTypes::If
         | TokenTypes::True
         | TokenTypes::False
         | TokenTypes::Else
         | TokenTypes::Do
         | TokenTypes::While
-        | TokenTypes::In => SemanticTokenTypes::Keyword,
+        | TokenTypes::In
+        | TokenTypes::StructStart => SemanticTokenTypes::Keyword,
         TokenTypes::Comment => SemanticTokenTypes::Comment,
         TokenTypes::ImportStart | TokenTypes::Return | TokenTypes::New | TokenTypes::FunctionStart | TokenTypes::Let => {
             SemanticTokenTypes::Keyword
@@ -164,3 +286,66 @@ pub enum SemanticTokenTypes {
     /// No color
     None = 100,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::semantic_tokens::{classify, encode};
+
+    /// Checks generics, attributes, calls, and multi-line strings are classified at the right positions
+    #[test]
+    pub fn test_classify() {
+        let source = "#[priority(5)]\npub struct Holder<T> {\n    value: T;\n}\n\nimpl<T> Holder<T> {\n    fn get(self) -> T {\n        \
+            return self.value;\n    }\n}\n\nfn main() {\n    let text = \"a\nb\";\n    print(text.length());\n}\n";
+        let found = classify(source)
+            .iter()
+            .map(|token| (token.token_type, token.line, token.column, token.length))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (22, 0, 0, 2),
+                (22, 0, 2, 11),
+                (22, 0, 13, 1),
+                (15, 1, 0, 3),
+                (15, 1, 4, 6),
+                (1, 1, 11, 6),
+                (15, 1, 17, 1),
+                (6, 1, 18, 1),
+                (15, 1, 19, 1),
+                (6, 2, 11, 1),
+                (9, 5, 0, 4),
+                (15, 5, 4, 1),
+                (6, 5, 5, 1),
+                (15, 5, 6, 1),
+                (1, 5, 8, 6),
+                (15, 5, 14, 1),
+                (6, 5, 15, 1),
+                (15, 5, 16, 1),
+                (15, 6, 4, 2),
+                (12, 6, 7, 3),
+                (1, 6, 11, 4),
+                (1, 6, 17, 2),
+                (6, 6, 20, 1),
+                (15, 7, 8, 6),
+                (9, 7, 15, 4),
+                (9, 7, 20, 5),
+                (15, 11, 0, 2),
+                (12, 11, 3, 4),
+                (15, 12, 4, 3),
+                (9, 12, 8, 4),
+                (18, 12, 15, 1),
+                (18, 12, 16, 1),
+                (18, 13, 0, 2),
+                (12, 14, 4, 5),
+                (9, 14, 10, 4),
+                (13, 14, 15, 6),
+            ]
+        );
+
+        let encoded = encode(&classify(source));
+        // The string's second line starts a new line, so its start is absolute
+        assert_eq!((encoded[32].delta_line, encoded[32].delta_start), (1, 0));
+        // The method call is on the same line as the variable before it, so its start is relative
+        assert_eq!((encoded[35].delta_line, encoded[35].delta_start), (0, 5));
+    }
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 
 use parking_lot::Mutex;
 
-use data::{Arguments, CompilerArguments, RunnerSettings, SourceSet};
+use data::{Arguments, CancellationToken, CompilerArguments, RunnerSettings, SourceSet};
 use magpie_lib::{build_project, build_project_file};
 use parser::FileSourceSet;
 use syntax::program::syntax::Syntax;
@@ -12,6 +12,8 @@ use syntax::program::syntax::Syntax;
 #[derive(Default)]
 pub struct SyntaxManager {
     pub parents: HashMap<PathBuf, Arc<Mutex<Syntax>>>,
+    /// Cancels the latest compile of each file, so an edit stops a compile that's out of date
+    pub compiling: HashMap<PathBuf, CancellationToken>,
 }
 
 impl SyntaxManager {
@@ -32,14 +34,36 @@ impl SyntaxManager {
             },
         );
 
+        // Stop the last compile of the file, it's out of date now
+        if let Some(previous) = self.compiling.insert(file.clone(), arguments.cancellation.clone()) {
+            previous.cancel();
+        }
+
         let mut directory = Self::get_project(&mut arguments, &file);
 
-        self.parents.insert(file.clone(), build_project::<()>(&mut arguments, &mut directory, false).unwrap().0);
+        let syntax = match build_project::<()>(&mut arguments, &mut directory, false) {
+            Ok((syntax, _)) => syntax,
+            // A cancelled compile has nothing to show, so the last finished syntax is kept
+            Err(error) => match self.parents.get(&file) {
+                // skipcq: RS-W1110 Incorrectly assumes this is copy-able
+                Some(found) => return found.clone(),
+                None => panic!("{}", error),
+            },
+        };
+        self.parents.insert(file.clone(), syntax);
 
         // skipcq: RS-W1110 Incorrectly assumes this is copy-able
         return self.parents.get(&file).unwrap().clone();
     }
 
+    /// Cancels any compile of the file and forgets its syntax, so the next request compiles the edited file
+    pub fn invalidate(&mut self, file: &PathBuf) {
+        if let Some(compiling) = self.compiling.remove(file) {
+            compiling.cancel();
+        }
+        self.parents.remove(file);
+    }
+
     fn get_project(arguments: &mut Arguments, file: &PathBuf) -> Vec<Box<dyn SourceSet>> {
         let mut directory = file.parent();
         while let Some(dir) = directory {
@@ -50,9 +74,9 @@ impl SyntaxManager {
         }
         return if let Some(directory) = directory {
             let _unused = build_project_file(arguments, directory.to_path_buf());
-            vec![Box::new(FileSourceSet { root: file.clone() })]
+            vec![Box::new(FileSourceSet::new(file.clone()))]
         } else {
-            vec![Box::new(FileSourceSet { root: file.clone() })]
+            vec![Box::new(FileSourceSet::new(file.clone()))]
         };
     }
 }
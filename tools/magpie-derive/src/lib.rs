@@ -3,10 +3,14 @@ extern crate proc_macro;
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, quote_spanned};
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, Generics};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, GenericParam, Generics, LitStr, Type, Variant,
+};
 
-/// Implements the derive macro for RavenExtern
-#[proc_macro_derive(RavenExtern)]
+/// Implements the derive macro for RavenExtern.
+/// Each variant of an enum needs the full name of the Raven struct it's translated from, like
+/// #[raven(name = "script::Value")], since the struct's type ID is hashed from it.
+#[proc_macro_derive(RavenExtern, attributes(raven))]
 pub fn raven_extern_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // Parse the input tokens into a syntax tree
     let input = parse_macro_input!(input as DeriveInput);
@@ -19,25 +23,36 @@ pub fn raven_extern_macro(input: proc_macro::TokenStream) -> proc_macro::TokenSt
     let generics = add_trait_bounds(input.generics);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let translated = translate(&input.data);
+    let translated = translate(&input.data, &name);
     let translated_extern = translate_extern(&input.data);
+    let freed = free(&input.data);
 
     let expanded = quote! {
         #[repr(C, align(8))]
         #[derive(Debug)]
+        #[allow(non_snake_case)]
         pub struct #raven_name {
-            type_id: core::ffi::c_int,
+            type_id: u64,
             #translated_extern
         }
 
         // The generated impl.
         impl #impl_generics data::RavenExtern for #name #ty_generics #where_clause {
             type Input = #raven_name;
-            unsafe fn translate(raven_type: *mut #raven_name) -> Self {
-                let raven_type = std::ptr::read(raven_type);
-                return Self {
-                    #translated
+            unsafe fn translate_borrowed(raven_type: *const #raven_name) -> Self {
+                // Only borrow the Raven value, every field is copied out of it
+                let raven_type = &*raven_type;
+                return #translated;
+            }
+
+            unsafe fn free(raven_type: *mut #raven_name, arena: &data::arena::Arena) {
+                {
+                    // Unused if every field is stored in the struct itself
+                    #[allow(unused_variables)]
+                    let raven_type = &*raven_type;
+                    #freed
                 }
+                arena.free(raven_type as *const u8);
             }
         }
     };
@@ -46,6 +61,44 @@ pub fn raven_extern_macro(input: proc_macro::TokenStream) -> proc_macro::TokenSt
     proc_macro::TokenStream::from(expanded)
 }
 
+/// Implements the derive macro for ToRaven, which needs RavenExtern to also be derived for the Raven type's layout.
+/// The struct needs the full name of the Raven struct, like #[raven(name = "script::Value")], to give it the same
+/// type ID the compiler does.
+#[proc_macro_derive(ToRaven, attributes(raven))]
+pub fn to_raven_macro(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    // Enums would need the type_id of the variant, which isn't supported yet
+    if let Data::Enum(_) = input.data {
+        return proc_macro::TokenStream::from(quote! {
+            compile_error!("ToRaven can only be derived for structs");
+        });
+    }
+    let Some(full_name) = find_raven_name(&input.attrs) else {
+        return proc_macro::TokenStream::from(quote! {
+            compile_error!("ToRaven needs the Raven struct's full name, like #[raven(name = \"script::Value\")]");
+        });
+    };
+
+    let name = input.ident;
+    let raven_name = Ident::new(&*(name.to_string() + "_RavenType"), Span::call_site());
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = to_raven_fields(&input.data);
+
+    let expanded = quote! {
+        impl #impl_generics data::ToRaven for #name #ty_generics #where_clause {
+            unsafe fn to_raven(&self) -> *mut #raven_name {
+                return data::externs::allocate(#raven_name {
+                    type_id: data::type_id(#full_name),
+                    #fields
+                });
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
 // Add a bound `T: RavenExtern` to every type parameter T.
 fn add_trait_bounds(mut generics: Generics) -> Generics {
     for param in &mut generics.params {
@@ -56,24 +109,112 @@ fn add_trait_bounds(mut generics: Generics) -> Generics {
     generics
 }
 
-// Generate an expression to translate field.
-fn translate(data: &Data) -> TokenStream {
+// Generate an expression to translate the Raven type into Self.
+fn translate(data: &Data, name: &Ident) -> TokenStream {
     match *data {
         Data::Struct(ref data) => match data.fields {
             Fields::Named(ref fields) => {
                 let recurse = fields.named.iter().map(|f| {
                     let name = &f.ident;
+                    match primitive(&f.ty) {
+                        // Raven chars are a single byte
+                        Some("char") => quote_spanned! {f.span()=>
+                            #name: raven_type.#name as char
+                        },
+                        Some(_) => quote_spanned! {f.span()=>
+                            #name: raven_type.#name
+                        },
+                        None => quote_spanned! {f.span()=>
+                            #name: data::RavenExtern::translate_borrowed(std::mem::transmute(raven_type.#name.load(std::sync::atomic::Ordering::Relaxed)))
+                        },
+                    }
+                });
+                quote! {
+                    Self {
+                        #(#recurse),*
+                    }
+                }
+            }
+            _ => unimplemented!(),
+        },
+        // Enums pick the variant whose Raven struct has the type_id, and the variant's payload is behind its pointer
+        Data::Enum(ref data) => {
+            let recurse = data.variants.iter().map(|variant| {
+                let variant_name = &variant.ident;
+                let id = variant_id(variant);
+                match variant.fields {
+                    Fields::Unit => quote_spanned! {variant.span()=>
+                        id if id == #id => Self::#variant_name
+                    },
+                    Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => quote_spanned! {variant.span()=>
+                        id if id == #id => Self::#variant_name(data::RavenExtern::translate_borrowed(std::mem::transmute(
+                            raven_type.#variant_name.load(std::sync::atomic::Ordering::Relaxed))))
+                    },
+                    _ => quote_spanned! {variant.span()=>
+                        _ => compile_error!("RavenExtern enum variants must be a unit or have a single unnamed field")
+                    },
+                }
+            });
+            let enum_name = name.to_string();
+            quote! {
+                match raven_type.type_id {
+                    #(#recurse,)*
+                    id => panic!("Unknown variant {} of {}", id, #enum_name)
+                }
+            }
+        }
+        Data::Union(_) => unimplemented!(),
+    }
+}
+
+// Generate the code freeing everything the Raven type points to, before the Raven type itself is freed.
+fn free(data: &Data) -> TokenStream {
+    match *data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let recurse = fields.named.iter().filter(|f| primitive(&f.ty).is_none()).map(|f| {
+                    let name = &f.ident;
+                    let types = &f.ty;
                     quote_spanned! {f.span()=>
-                        #name: data::RavenExtern::translate(std::mem::transmute(raven_type.#name.load(std::sync::atomic::Ordering::Relaxed)))
+                        <#types as data::RavenExtern>::free(
+                            raven_type.#name.load(std::sync::atomic::Ordering::Relaxed) as *mut <#types as data::RavenExtern>::Input,
+                            arena,
+                        );
                     }
                 });
                 quote! {
-                    #(#recurse),*
+                    #(#recurse)*
                 }
             }
             _ => unimplemented!(),
         },
-        Data::Enum(_) | Data::Union(_) => unimplemented!(),
+        // Only the payload of the variant the value is gets freed
+        Data::Enum(ref data) => {
+            let recurse = data.variants.iter().filter_map(|variant| {
+                let variant_name = &variant.ident;
+                let id = variant_id(variant);
+                match variant.fields {
+                    Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                        let types = &fields.unnamed[0].ty;
+                        Some(quote_spanned! {variant.span()=>
+                            id if id == #id => <#types as data::RavenExtern>::free(
+                                raven_type.#variant_name.load(std::sync::atomic::Ordering::Relaxed)
+                                    as *mut <#types as data::RavenExtern>::Input,
+                                arena,
+                            )
+                        })
+                    }
+                    _ => None,
+                }
+            });
+            quote! {
+                match raven_type.type_id {
+                    #(#recurse,)*
+                    _ => {}
+                }
+            }
+        }
+        Data::Union(_) => unimplemented!(),
     }
 }
 
@@ -83,8 +224,58 @@ fn translate_extern(data: &Data) -> TokenStream {
             Fields::Named(ref fields) => {
                 let recurse = fields.named.iter().map(|f| {
                     let name = &f.ident;
-                    quote_spanned! {f.span()=>
-                        #name: std::sync::atomic::AtomicPtr<()>
+                    let types = &f.ty;
+                    match primitive(types) {
+                        Some("char") => quote_spanned! {f.span()=>
+                            #name: u8
+                        },
+                        Some(_) => quote_spanned! {f.span()=>
+                            #name: #types
+                        },
+                        None => quote_spanned! {f.span()=>
+                            #name: std::sync::atomic::AtomicPtr<()>
+                        },
+                    }
+                });
+                quote! {
+                    #(#recurse),*
+                }
+            }
+            _ => unimplemented!(),
+        },
+        // Every variant with a payload gets a pointer to it
+        Data::Enum(ref data) => {
+            let recurse = data.variants.iter().filter(|variant| !matches!(variant.fields, Fields::Unit)).map(|variant| {
+                let name = &variant.ident;
+                quote_spanned! {variant.span()=>
+                    #name: std::sync::atomic::AtomicPtr<()>
+                }
+            });
+            quote! {
+                #(#recurse),*
+            }
+        }
+        Data::Union(_) => unimplemented!(),
+    }
+}
+
+// Generate the fields of the Raven type from the Rust value.
+fn to_raven_fields(data: &Data) -> TokenStream {
+    match *data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let recurse = fields.named.iter().map(|f| {
+                    let name = &f.ident;
+                    match primitive(&f.ty) {
+                        Some("char") => quote_spanned! {f.span()=>
+                            #name: self.#name as u8
+                        },
+                        Some(_) => quote_spanned! {f.span()=>
+                            #name: self.#name
+                        },
+                        None => quote_spanned! {f.span()=>
+                            #name: std::sync::atomic::AtomicPtr::new(data::ToRaven::to_raven(&self.#name) as *mut ())
+                        },
                     }
                 });
                 quote! {
@@ -96,3 +287,36 @@ fn translate_extern(data: &Data) -> TokenStream {
         Data::Enum(_) | Data::Union(_) => unimplemented!(),
     }
 }
+
+// Gets the full name of the Raven struct set by #[raven(name = "...")], like script::Value.
+fn find_raven_name(attributes: &[Attribute]) -> Option<LitStr> {
+    let mut found = None;
+    for attribute in attributes.iter().filter(|attribute| attribute.path().is_ident("raven")) {
+        let _ = attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                found = Some(meta.value()?.parse::<LitStr>()?);
+            }
+            return Ok(());
+        });
+    }
+    return found;
+}
+
+// Generate the type ID of the Raven struct an enum variant is translated from, which is hashed from its name.
+fn variant_id(variant: &Variant) -> TokenStream {
+    return match find_raven_name(&variant.attrs) {
+        Some(name) => quote! { data::type_id(#name) },
+        None => quote_spanned! {variant.span()=>
+            compile_error!("Enum variants need the Raven struct's full name, like #[raven(name = \"script::Value\")]")
+        },
+    };
+}
+
+// Gets the name of a primitive type, which Raven stores in the struct itself instead of behind a pointer.
+fn primitive(types: &Type) -> Option<&'static str> {
+    let Type::Path(path) = types else {
+        return None;
+    };
+    let ident = path.path.get_ident()?;
+    return ["u64", "i64", "f64", "bool", "char"].into_iter().find(|primitive| ident == primitive);
+}
@@ -1,15 +1,26 @@
+use std::env;
+
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
-fn fibonacci(n: u64) -> u64 {
-    match n {
-        0 => 1,
-        1 => 1,
-        n => fibonacci(n - 1) + fibonacci(n - 2),
-    }
-}
+use magpie_lib::std_sources;
+use parser::tokens::tokenizer::{tokenize, tokenize_owned};
 
+/// Benchmarks tokenizing the core and standard libraries, comparing streaming the tokens with collecting them
 fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("fib 20", |b| b.iter(|| fibonacci(black_box(20))));
+    let files = std_sources(env::consts::OS).iter().flat_map(|source| source.get_files()).collect::<Vec<_>>();
+    let contents = files.iter().map(|file| file.contents()).collect::<Vec<_>>();
+
+    c.bench_function("stream std tokens", |b| b.iter(|| files.iter().map(|file| file.read().count()).sum::<usize>()));
+    c.bench_function("collect std tokens", |b| {
+        b.iter(|| files.iter().map(|file| black_box(file.read().collect::<Vec<_>>()).len()).sum::<usize>())
+    });
+    // Files read from disk own their contents, so their tokenizer saves its state between tokens
+    c.bench_function("stream owned std tokens", |b| {
+        b.iter(|| contents.iter().map(|contents| tokenize_owned(contents.clone()).count()).sum::<usize>())
+    });
+    c.bench_function("stream borrowed std tokens", |b| {
+        b.iter(|| contents.iter().map(|contents| tokenize(contents.as_bytes()).count()).sum::<usize>())
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,18 +1,20 @@
 use anyhow::Error;
+use std::env;
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicPtr, Ordering};
 use std::sync::Arc;
-use std::{env, path};
 
-use ::runner::runner::{build, create_syntax, run};
+use ::runner::check_compiler_target;
+use ::runner::runner::{build, compile_syntax, create_syntax, run};
 use include_dir::{include_dir, Dir, DirEntry, File};
 use parking_lot::Mutex;
 
 use crate::project::RavenProject;
-use data::tokens::{Token, TokenTypes};
+use data::tokens::Token;
 use data::{Arguments, RavenExtern, Readable, SourceSet};
-use parser::tokens::tokenizer::Tokenizer;
+use parser::tokens::tokenizer::tokenize;
 use parser::FileSourceSet;
 use syntax::errors::ParsingError;
 use syntax::program::syntax::Syntax;
@@ -34,29 +36,94 @@ static STD_MACOS: Dir = include_dir!("lib/std/macos/src");
 /// The Magpie classes
 pub static MAGPIE: Dir = include_dir!("tools/magpie/lib/src");
 
-/// Sets up the arguments with the std
-pub fn setup_arguments(arguments: &mut Arguments, source: &mut Vec<Box<dyn SourceSet>>) {
-    let platform_std = match env::consts::OS {
-        "windows" => &STD_WINDOWS,
-        "linux" => &STD_LINUX,
-        "macos" => &STD_MACOS,
-        _ => panic!("Unsupported platform {}!", env::consts::OS),
-    };
+/// Sets up the arguments with the std of the target's operating system, erroring if the target triple is invalid
+pub fn setup_arguments(arguments: &mut Arguments, source: &mut Vec<Box<dyn SourceSet>>) -> Result<(), Error> {
+    let compiler_arguments = &mut arguments.runner_settings.compiler_arguments;
+    if let Some(triple) = &compiler_arguments.target_triple {
+        check_compiler_target(compiler_arguments).map_err(Error::msg)?;
+        compiler_arguments.target_os = match triple_os(triple) {
+            Some(target_os) => target_os.to_string(),
+            None => return Err(Error::msg(format!("Unsupported platform in target {}!", triple))),
+        };
+    }
 
-    source.push(Box::new(InnerSourceSet { set: &STD_UNIVERSAL }));
-    source.push(Box::new(InnerSourceSet { set: platform_std }));
-    source.push(Box::new(InnerSourceSet { set: &CORE }));
+    // The conditions #[cfg(...)] attributes are checked against
+    let target_os = &compiler_arguments.target_os;
+    compiler_arguments.cfg = vec![format!("target_os={}", target_os), format!("os={}", target_os)];
+    if compiler_arguments.test {
+        compiler_arguments.cfg.push("test".to_string());
+    }
+
+    let std = std_sets(&compiler_arguments.target_os);
+    if compiler_arguments.verbose {
+        for set in source.iter() {
+            print_files(&set.name(), set.get_files().iter().map(|file| file.path()).collect());
+        }
+        for set in &std {
+            print_files(&set.name(), set.list_files());
+        }
+    }
+    source.extend(std.into_iter().map(|set| Box::new(set) as Box<dyn SourceSet>));
 
     arguments.runner_settings.sources = source.iter().map(|inner| inner.cloned()).collect::<Vec<_>>();
+    return Ok(());
+}
+
+/// Prints the files of a source set being compiled, for verbose mode
+fn print_files(name: &str, files: Vec<String>) {
+    println!("Compiling {}:", name);
+    for file in files {
+        println!("  {}", file);
+    }
+}
+
+/// Gets the standard library for the operating system, and the core library it's built on
+pub fn std_sources(target_os: &str) -> Vec<Box<dyn SourceSet>> {
+    return std_sets(target_os).into_iter().map(|set| Box::new(set) as Box<dyn SourceSet>).collect();
+}
+
+/// Gets the standard library and core library's source sets, without boxing them
+fn std_sets(target_os: &str) -> Vec<InnerSourceSet> {
+    let (name, platform_std) = match target_os {
+        "windows" => ("std-windows", &STD_WINDOWS),
+        "linux" => ("std-linux", &STD_LINUX),
+        "macos" => ("std-macos", &STD_MACOS),
+        _ => panic!("Unsupported platform {}!", target_os),
+    };
+
+    return vec![
+        InnerSourceSet { name: "std-universal", set: &STD_UNIVERSAL },
+        InnerSourceSet { name, set: platform_std },
+        InnerSourceSet { name: "core", set: &CORE },
+    ];
+}
+
+/// Gets the operating system of a target triple like x86_64-pc-windows-gnu, named like env::consts::OS
+fn triple_os(triple: &str) -> Option<&'static str> {
+    return triple.split('-').find_map(|part| match part {
+        "windows" => Some("windows"),
+        "linux" => Some("linux"),
+        "darwin" | "macos" => Some("macos"),
+        _ => None,
+    });
 }
 
 pub fn build_project_file(arguments: &mut Arguments, file: PathBuf) -> Result<RavenProject, Error> {
-    arguments.runner_settings.compiler_arguments.target = "build::project".to_string();
-    return match build_project::<RavenProject>(
+    // The build file is always run on the host, even if the project is compiled for another target
+    let project_arguments = arguments.runner_settings.compiler_arguments.clone();
+    let compiler_arguments = &mut arguments.runner_settings.compiler_arguments;
+    compiler_arguments.target = "build::project".to_string();
+    compiler_arguments.target_triple = None;
+    compiler_arguments.emit_object = false;
+    compiler_arguments.target_os = env::consts::OS.to_string();
+
+    let result = build_project::<RavenProject>(
         arguments,
-        &mut vec![Box::new(FileSourceSet { root: file }), Box::new(InnerSourceSet { set: &MAGPIE })],
+        &mut vec![Box::new(FileSourceSet::new(file)), Box::new(InnerSourceSet { name: "magpie", set: &MAGPIE })],
         true,
-    ) {
+    );
+    arguments.runner_settings.compiler_arguments = project_arguments;
+    return match result {
         Ok((_, found)) => match found {
             Some(found) => Ok(RavenProject::from(found)),
             None => {
@@ -69,21 +136,87 @@ pub fn build_project_file(arguments: &mut Arguments, file: PathBuf) -> Result<Ra
     };
 }
 
+/// Adds the source folder of every dependency of the project, and every dependency of those, to the sources.
+/// Each dependency's files are prefixed with the dependency's name, so lib/src/util.rv is imported as lib::util.
+pub fn add_dependencies(
+    arguments: &mut Arguments,
+    project: &RavenProject,
+    root: &PathBuf,
+    source: &mut Vec<Box<dyn SourceSet>>,
+) -> Result<(), Error> {
+    return add_dependencies_recursive(arguments, project, root, &mut vec![project.name.clone()], &mut vec![], source);
+}
+
+/// Recursively adds dependencies, keeping track of the current chain of parents to detect cycles
+fn add_dependencies_recursive(
+    arguments: &mut Arguments,
+    project: &RavenProject,
+    root: &PathBuf,
+    parents: &mut Vec<String>,
+    added: &mut Vec<String>,
+    source: &mut Vec<Box<dyn SourceSet>>,
+) -> Result<(), Error> {
+    for dependency in &project.dependencies {
+        if let Some(start) = parents.iter().position(|parent| *parent == dependency.name) {
+            let mut cycle = parents[start..].to_vec();
+            cycle.push(dependency.name.clone());
+            return Err(Error::msg(format!("Dependency cycle detected: {}", cycle.join(" -> "))));
+        }
+
+        // Dependencies shared by multiple projects are only added once
+        if added.contains(&dependency.name) {
+            continue;
+        }
+
+        let path = root.join(&dependency.path);
+        let build_file = path.join("build.rv");
+        if !build_file.exists() {
+            return Err(Error::msg(format!("Dependency {} has no build file at {:?}", dependency.name, build_file)));
+        }
+
+        let dependency_project = build_project_file(arguments, build_file)?;
+        parents.push(dependency.name.clone());
+        add_dependencies_recursive(arguments, &dependency_project, &path, parents, added, source)?;
+        parents.pop();
+
+        added.push(dependency.name.clone());
+        source.push(Box::new(DependencySourceSet {
+            name: dependency.name.clone(),
+            set: FileSourceSet::new(path.join("src")),
+        }));
+    }
+    return Ok(());
+}
+
 /// Builds a Raven project, adding the needed dependencies
 pub fn build_project<T: RavenExtern + 'static>(
     arguments: &mut Arguments,
     source: &mut Vec<Box<dyn SourceSet>>,
     compile: bool,
 ) -> Result<(Arc<Mutex<Syntax>>, Option<T>), Error> {
-    setup_arguments(arguments, source);
+    setup_arguments(arguments, source)?;
+    let syntax = create_syntax(arguments);
     let value = if compile {
-        build_run::<T>(&arguments)
+        build_run::<T>(syntax.clone(), &arguments)
     } else {
-        let syntax = create_syntax(arguments);
-        arguments.cpu_runtime.block_on(build(syntax.clone(), arguments)).map(|_| (syntax, None))
+        arguments.cpu_runtime.block_on(build(syntax.clone(), arguments)).map(|_| None)
     };
+
+    // The parsed code is printed even if it failed to compile, since it can explain the errors
+    for dump in syntax.lock().ast_dumps.drain(..) {
+        println!("{}", dump);
+    }
     return match value {
-        Ok(inner) => Ok(inner),
+        Ok(inner) => {
+            let warnings = syntax.lock().warnings.clone();
+            if !warnings.is_empty() {
+                eprintln!("Warnings:");
+                for warning in warnings {
+                    warning.print(&source);
+                }
+            }
+            Ok((syntax, inner))
+        }
         Err(errors) => {
             eprintln!("Errors:");
             for error in errors {
@@ -94,24 +227,85 @@ pub fn build_project<T: RavenExtern + 'static>(
     };
 }
 
-/// Runs Raven and blocks until a result is gotten
-fn build_run<T: RavenExtern + 'static>(arguments: &Arguments) -> Result<(Arc<Mutex<Syntax>>, Option<T>), Vec<ParsingError>> {
+/// Compiles a Raven project, adding the needed dependencies, and returns the compiler's output without running it
+pub fn compile_project(arguments: &mut Arguments, source: &mut Vec<Box<dyn SourceSet>>) -> Result<String, Error> {
+    setup_arguments(arguments, source)?;
     let syntax = create_syntax(arguments);
-    let result = arguments.cpu_runtime.block_on(run::<AtomicPtr<T::Input>>(syntax.clone(), arguments))?;
-    return Ok((syntax, result.map(|inner| unsafe { RavenExtern::translate(inner.load(Ordering::Relaxed)) })));
+    return match arguments.cpu_runtime.block_on(compile_syntax(syntax, arguments)) {
+        Ok(output) => Ok(output),
+        Err(errors) => {
+            eprintln!("Errors:");
+            for error in errors {
+                error.print(&source);
+            }
+            Err(Error::msg("Failed to compile"))
+        }
+    };
+}
+
+/// Runs Raven and blocks until a result is gotten
+fn build_run<T: RavenExtern + 'static>(
+    syntax: Arc<Mutex<Syntax>>,
+    arguments: &Arguments,
+) -> Result<Option<T>, Vec<ParsingError>> {
+    let result = arguments.cpu_runtime.block_on(run::<AtomicPtr<T::Input>>(syntax, arguments));
+    let result =
+        result.map(|value| value.map(|inner| unsafe { RavenExtern::translate_borrowed(inner.load(Ordering::Relaxed)) }));
+    // The result was copied out, so everything the program allocated can be freed, even if it failed
+    arguments.runner_settings.compiler_arguments.arena.clear();
+    return result;
 }
 
 /// A source set for an internal directory with the include_dir macro
 #[derive(Clone, Debug)]
 pub struct InnerSourceSet {
+    /// The label of the directory, like core or std-linux
+    pub name: &'static str,
+    /// The directory
     pub set: &'static Dir<'static>,
 }
 
+/// A source set for a dependency's source folder, which prefixes every file with the dependency's name
+#[derive(Clone, Debug)]
+pub struct DependencySourceSet {
+    /// The name of the dependency
+    pub name: String,
+    /// The dependency's source folder
+    pub set: FileSourceSet,
+}
+
+impl SourceSet for DependencySourceSet {
+    fn get_files(&self) -> Vec<Box<dyn Readable>> {
+        return self.set.get_files();
+    }
+
+    fn relative(&self, other: &dyn Readable) -> String {
+        return format!("{}::{}", self.name, self.set.relative(other));
+    }
+
+    fn cloned(&self) -> Box<dyn SourceSet> {
+        return Box::new(self.clone());
+    }
+
+    fn name(&self) -> String {
+        return self.set.name();
+    }
+}
+
 /// Forced to make a wrapper to implement Readable due to orphan rule
 pub struct FileWrapper {
     file: &'static File<'static>,
 }
 
+impl InnerSourceSet {
+    /// Gets the path of every file in the directory, relative to it, like util/math.rv
+    pub fn list_files(&self) -> Vec<String> {
+        let mut files = Vec::new();
+        read_recursive(&self.set, &mut files);
+        return files.iter().map(|file| file.path()).collect();
+    }
+}
+
 impl SourceSet for InnerSourceSet {
     fn get_files(&self) -> Vec<Box<dyn Readable>> {
         let mut output = Vec::new();
@@ -127,6 +321,10 @@ impl SourceSet for InnerSourceSet {
     fn cloned(&self) -> Box<dyn SourceSet> {
         return Box::new(self.clone());
     }
+
+    fn name(&self) -> String {
+        return format!("<{}>", self.name);
+    }
 }
 
 /// Recursively reads an include_dir directory to the output
@@ -142,18 +340,8 @@ fn read_recursive(base: &Dir<'static>, output: &mut Vec<Box<dyn Readable>>) {
 }
 
 impl Readable for FileWrapper {
-    fn read(&self) -> Vec<Token> {
-        let binding = self.contents();
-        let mut tokenizer = Tokenizer::new(binding.as_bytes());
-        let mut tokens = Vec::default();
-        loop {
-            tokens.push(tokenizer.next());
-            if tokens.last().unwrap().token_type == TokenTypes::EOF {
-                break;
-            }
-        }
-
-        return tokens;
+    fn read(&self) -> Box<dyn Iterator<Item = Token> + '_> {
+        return Box::new(tokenize(self.file.contents()));
     }
 
     fn contents(&self) -> String {
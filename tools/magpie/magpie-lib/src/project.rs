@@ -14,6 +14,8 @@ pub struct RavenProject {
 /// A Raven project dependency
 #[derive(Debug, RavenExtern)]
 pub struct Dependency {
-    /// Dependency name
+    /// Dependency name, which is also the module prefix of its source files
     pub name: String,
+    /// Path to the dependency's folder, relative to the project's folder
+    pub path: String,
 }
@@ -7,8 +7,8 @@ use data::{Arguments, RavenExtern, SourceSet};
 use parser::FileSourceSet;
 use syntax::program::syntax::Syntax;
 
-use crate::build_project;
 use crate::project::RavenProject;
+use crate::{add_dependencies, build_project};
 
 pub fn _build_file<T: RavenExtern + 'static>(
     arguments: &mut Arguments,
@@ -22,12 +22,13 @@ pub fn _build_file<T: RavenExtern + 'static>(
 
     let build_sources = arguments.runner_settings.compiler_arguments.temp_folder.parent().unwrap().join("build.rv");
     arguments.runner_settings.compiler_arguments.target = "build::project".to_string();
-    let _project = build_project::<RavenProject>(arguments, &mut vec![Box::new(FileSourceSet { root: build_sources })], true)
-        .unwrap()
-        .1
-        .unwrap();
+    let project =
+        build_project::<RavenProject>(arguments, &mut vec![Box::new(FileSourceSet::new(build_sources.clone()))], true)
+            .unwrap()
+            .1
+            .unwrap();
 
-    // TODO use project for dependencies
+    add_dependencies(arguments, &project, &build_sources.parent().unwrap().to_path_buf(), sources)?;
 
     arguments.runner_settings.compiler_arguments.target = "main::main".to_string();
     return build_project(arguments, sources, compile);
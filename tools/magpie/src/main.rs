@@ -1,18 +1,57 @@
-use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, fs, process};
 
-use data::{Arguments, CompilerArguments, RunnerSettings};
-use magpie_lib::{build_project, build_project_file};
+use data::{Arguments, CompilerArguments, OptimizationLevel, RunnerSettings, SourceSet};
+use magpie_lib::{add_dependencies, build_project, build_project_file};
+use parser::formatter::format_source;
 use parser::FileSourceSet;
 
 mod test;
 
 /// Finds the Raven project/file and runs it
 fn main() {
-    let args = env::args().collect::<Vec<_>>();
+    let mut opt_level = OptimizationLevel::None;
+    let mut deny_warnings = false;
+    let mut debug = false;
+    let mut dump_ast = false;
+    let mut verbose = false;
+    let mut target_triple = None;
+    let mut emit_object = false;
+    let mut link_libraries = Vec::default();
+    let mut globs = Vec::default();
+    let mut inspecting = None;
+    let mut formatting = false;
+    let mut check = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--release" | "-O" => opt_level = OptimizationLevel::Default,
+            "--deny-warnings" => deny_warnings = true,
+            "--debug" | "-g" => debug = true,
+            // Prints each function's parsed code before it's checked
+            "--dump-ast" => dump_ast = true,
+            "--verbose" | "-v" => verbose = true,
+            "--target" => target_triple = Some(args.next().expect("Expected a target triple!")),
+            "--link" | "-l" => link_libraries.push(args.next().expect("Expected a library to link!")),
+            // Writes an object file to the target folder instead of running the project
+            "build" => emit_object = true,
+            // Prints the given function after checking instead of running the project
+            "inspect" => inspecting = Some(args.next().expect("Expected a function to inspect!")),
+            // Formats the sources instead of running the project
+            "fmt" => formatting = true,
+            // With fmt, fails if any source isn't formatted instead of formatting it
+            "--check" => check = true,
+            // Anything else is a comma-separated list of globs to use as the sources instead of the src folder
+            _ if !arg.starts_with('-') => globs.extend(arg.split(',').map(str::to_string)),
+            _ => panic!("Unknown argument {}!", arg),
+        }
+    }
 
-    if args.len() == 2 {
-    } else if args.len() > 2 {
-        panic!("Unknown extra arguments! {:?}", args);
+    if formatting {
+        if !format_sources(&find_sources(&globs), check) {
+            process::exit(1);
+        }
+        return;
     }
 
     let build_path = env::current_dir().unwrap().join("build.rv");
@@ -30,6 +69,15 @@ fn main() {
                 target: String::default(),
                 compiler: "llvm".to_string(),
                 temp_folder: env::current_dir().unwrap().join("target"),
+                opt_level,
+                deny_warnings,
+                debug,
+                dump_ast,
+                verbose,
+                target_triple,
+                emit_object,
+                link_libraries,
+                ..Default::default()
             },
         },
     );
@@ -42,16 +90,99 @@ fn main() {
             return;
         }
     };
+
+    let mut sources = find_sources(&globs);
+    if let Err(error) = add_dependencies(&mut arguments, &project, &env::current_dir().unwrap(), &mut sources) {
+        println!("{}", error);
+        return;
+    }
     arguments.runner_settings.compiler_arguments.target = "main::main".to_string();
+    arguments.progress = Some(print_progress);
+
+    if let Some(function) = inspecting {
+        println!("Building {}...", project.name);
+        if let Ok((syntax, _)) = build_project::<()>(&mut arguments, &mut sources, false) {
+            match syntax.lock().dump_function(&function, &arguments.runner_settings.sources) {
+                Some(dumped) => print!("{}", dumped),
+                None => println!("No function named {}!", function),
+            }
+        }
+        return;
+    }
+
+    if arguments.runner_settings.compiler_arguments.emit_object {
+        println!("Building {}...", project.name);
+    } else {
+        println!("Building and running {}...", project.name);
+    }
+    if let Ok((syntax, _)) = build_project::<()>(&mut arguments, &mut sources, true) {
+        let locked = syntax.lock();
+        if let Some(object) = &locked.object_file {
+            println!("Wrote {}", object.display());
+        }
+        if !locked.link_flags.is_empty() {
+            println!("Link with {}", locked.link_flags.join(" "));
+        }
+    }
+}
+
+/// Gets the sources matching the globs, or the src folder if there aren't any globs
+fn find_sources(globs: &Vec<String>) -> Vec<Box<dyn SourceSet>> {
+    let mut sources: Vec<Box<dyn SourceSet>> = Vec::default();
+    for glob in globs {
+        match FileSourceSet::new_glob(glob) {
+            Ok(found) => sources.push(Box::new(found)),
+            Err(error) => panic!("Invalid source glob {}: {}", glob, error),
+        }
+    }
+
+    if sources.is_empty() {
+        let source = env::current_dir().unwrap().join("src");
 
-    let source = env::current_dir().unwrap().join("src");
+        if !source.exists() {
+            panic!("Source folder (src) not found!");
+        }
+        sources.push(Box::new(FileSourceSet::new(source)));
+    }
+    return sources;
+}
 
-    if !source.exists() {
-        panic!("Source folder (src) not found!");
+/// Formats every file in the sources, or when checking, prints every file that isn't formatted.
+/// Returns false if a file couldn't be formatted, or wasn't formatted when checking.
+fn format_sources(sources: &Vec<Box<dyn SourceSet>>, check: bool) -> bool {
+    let mut passed = true;
+    for file in sources.iter().flat_map(|source| source.get_files()) {
+        let contents = file.contents();
+        match format_source(&contents) {
+            Ok(formatted) if formatted == contents => {}
+            Ok(_) if check => {
+                println!("{} isn't formatted", file.path());
+                passed = false;
+            }
+            Ok(formatted) => {
+                if let Err(error) = fs::write(file.path(), formatted) {
+                    println!("Failed to write {}: {}", file.path(), error);
+                    passed = false;
+                }
+            }
+            Err(error) => {
+                println!("Failed to format {}: {}", file.path(), error);
+                passed = false;
+            }
+        }
     }
+    return passed;
+}
+
+/// Prints a spinner with how many tasks are still running, clearing it once they're all done
+fn print_progress(running: usize) {
+    /// The spinner's frame, which advances every time it's printed
+    static FRAME: AtomicUsize = AtomicUsize::new(0);
 
-    println!("Building and running {}...", project.name);
-    match build_project::<()>(&mut arguments, &mut vec![Box::new(FileSourceSet { root: source })], true) {
-        _ => {}
+    if running == 0 {
+        eprint!("\r\x1b[K");
+        return;
     }
+    let frame = ['|', '/', '-', '\\'][FRAME.fetch_add(1, Ordering::Relaxed) % 4];
+    eprint!("\r{} {} tasks running", frame, running);
 }
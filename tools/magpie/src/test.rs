@@ -1,10 +1,63 @@
 #[cfg(test)]
 mod test {
-    use data::{Arguments, CompilerArguments, RunnerSettings};
-    use magpie_lib::build_project;
-    use parser::FileSourceSet;
+    use checker::check_code::store;
+    use checker::degeneric::{degeneric_code_body, degeneric_struct, degeneric_type};
+    use checker::get_return;
+    use data::arena::Arena;
+    use data::tokens::{Span, TokenTypes};
+    use data::{Arguments, CancellationToken, CompilerArguments, RavenExtern, RunnerSettings, SourceSet, ToRaven};
+    use magpie_derive::{RavenExtern, ToRaven};
+    use magpie_lib::{
+        add_dependencies, build_project, build_project_file, compile_project, setup_arguments, std_sources, InnerSourceSet,
+        MAGPIE,
+    };
+    use parser::formatter::format_source;
+    use parser::parser::code_parser::{parse_code, parse_line, ParseState};
+    use parser::parser::util::ParserUtils;
+    use parser::tokens::tokenizer::{tokenize, Tokenizer};
+    use parser::{FileSourceSet, ImportNameResolver, StringSourceSet};
+    use runner::engine::{call_function, Engine};
+    use runner::runner::{build, create_syntax, run, run_with};
+    use runner::JoinWaiter;
+    use std::collections::HashMap;
+    use std::ffi::{c_char, CStr, CString};
+    use std::ops::Deref;
     use std::path::PathBuf;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::task::{Wake, Waker};
+    use std::thread;
+    use std::time::{Duration, Instant};
     use std::{env, fs, path};
+    use syntax::async_util::AsyncDataGetter;
+    use syntax::errors::{ParsingError, ParsingMessage};
+    use syntax::program::code::{EffectType, ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression};
+    use syntax::program::function::FinalizedCodeBody;
+    use syntax::program::r#struct::{FinalizedStruct, StructData, BOOL, U64};
+    use syntax::program::types::{FinalizedTypes, Types};
+    use syntax::top_element_manager::GetterManager;
+    use syntax::{get_modifier, to_modifiers, Attribute, Modifier, SimpleVariableManager, VariableManager, MODIFIERS};
+    use tokio::sync::oneshot;
+
+    /// The settings for compiling a test target with LLVM, with its output in the target folder
+    fn test_settings(target: &str) -> RunnerSettings {
+        return RunnerSettings {
+            sources: vec![],
+            compiler_arguments: CompilerArguments {
+                compiler: "llvm".to_string(),
+                target: target.to_string(),
+                temp_folder: env::current_dir().unwrap().join("target"),
+                test: true,
+                ..Default::default()
+            },
+        };
+    }
+
+    /// The arguments for compiling a test target, see test_settings
+    fn test_arguments(target: &str) -> Arguments {
+        return Arguments::build_args(false, test_settings(target));
+    }
 
     /// Main test
     #[test]
@@ -13,6 +66,1777 @@ mod test {
         test_recursive(test_folder);
     }
 
+    /// Checks that a glob finds the test files and names them relative to the folder they're in
+    #[test]
+    pub fn test_glob_sources() {
+        let pattern: PathBuf = ["..", "..", "lib", "test", "test", "**", "*.rv"].iter().collect();
+        let sources = FileSourceSet::new_glob(pattern.to_str().unwrap()).unwrap();
+        let files = sources.get_files();
+        assert!(!files.is_empty(), "Glob found no test files!");
+        for file in &files {
+            let path = PathBuf::from(file.path());
+            assert_eq!(sources.relative(file.deref()), path.file_stem().unwrap().to_str().unwrap());
+        }
+    }
+
+    /// Checks that compiling to a string gives the LLVM IR with the target defined in it
+    #[test]
+    pub fn test_compile_to_string() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "variables.rv"].iter().collect();
+        let mut arguments = test_arguments("variables::test");
+        let output = compile_project(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))]).unwrap();
+        assert!(output.contains("define"), "No functions defined in:\n{}", output);
+        assert!(output.contains("variables::test"), "Target not defined in:\n{}", output);
+    }
+
+    /// Checks that copy types are stored on the stack and passed to functions as a copy instead of the original
+    #[test]
+    pub fn test_copy_ir() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "copy-types.rv"].iter().collect();
+        let mut arguments = test_arguments("copy-types::test");
+        let output = compile_project(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))]).unwrap();
+        let start = output.find("@\"copy-types::test\"").unwrap_or_else(|| panic!("No test in:\n{}", output));
+        let function = &output[start..start + output[start..].find("\n}").unwrap()];
+        assert!(!function.contains("@malloc"), "Copy types were heap allocated in:\n{}", function);
+
+        // The variable first is stored on the stack, then a copy of it is passed to add_two
+        let first = function
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("store i64 5, ptr "))
+            .unwrap_or_else(|| panic!("first isn't stored on the stack in:\n{}", function));
+        let call = function.lines().find(|line| line.contains("add_two")).unwrap();
+        assert!(!call.contains(&format!("ptr {})", first)), "first was passed by reference in:\n{}", function);
+    }
+
+    /// Checks that a debug build of a project has debug info for its source files, on top of the compiler's own test
+    #[test]
+    pub fn test_debug_build() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "variables.rv"].iter().collect();
+        let mut arguments = test_arguments("variables::test");
+        arguments.runner_settings.compiler_arguments.debug = true;
+        let output = compile_project(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))]).unwrap();
+        assert!(output.contains("!DICompileUnit"), "No compile unit in:\n{}", output);
+        assert!(output.contains("filename: \"variables.rv\""), "No file for variables.rv in:\n{}", output);
+        assert!(output.contains("!DISubprogram(name: \"variables::test\""), "No subprogram for the test in:\n{}", output);
+    }
+
+    /// How many times the embedded script logged "Doubling"
+    static LOGGED: AtomicU64 = AtomicU64::new(0);
+
+    /// The log function exposed to the embedded script
+    extern "C" fn host_log(message: *const c_char) {
+        if unsafe { CStr::from_ptr(message) }.to_str() == Ok("Doubling") {
+            LOGGED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Checks that an embedded script can call a host function, and can be called with an argument
+    #[test]
+    pub fn test_engine() {
+        let script = "import host;\n\
+            \n\
+            pub fn double(value: u64) -> u64 {\n    \
+                log(\"Doubling\");\n    \
+                return value * 2;\n\
+            }\n\
+            \n\
+            pub fn quadruple(value: u64) -> u64 {\n    \
+                return double(double(value));\n\
+            }\n";
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        // SAFETY: host_log is an extern "C" fn taking the string's pointer, which is how Raven passes a str
+        let builder =
+            unsafe { builder.script("script", script).host_function("log", "(message: str)", host_log as *const ()) };
+        let engine = builder.build().unwrap_or_else(|errors| panic!("Failed to compile the script: {:?}", errors));
+
+        let doubled: u64 = unsafe { engine.call("script::double", (21u64,)) }.unwrap();
+        assert_eq!(doubled, 42);
+        let quadrupled: u64 = unsafe { engine.call("script::quadruple", (5u64,)) }.unwrap();
+        assert_eq!(quadrupled, 20);
+        assert_eq!(LOGGED.load(Ordering::Relaxed), 3);
+        assert!(unsafe { engine.call::<(u64,), u64>("script::missing", (1,)) }.is_none());
+    }
+
+    /// Stands in for a compiled Raven function, returning a new string made from its arguments like Raven would
+    extern "C" fn join(count: *mut u64, name: *mut c_char) -> *mut c_char {
+        let joined = unsafe { format!("{}{}", String::translate_borrowed(name), *count) };
+        return unsafe { joined.to_raven() };
+    }
+
+    /// Checks that calls allocate their arguments and the value they return in the arena, freeing all of them after
+    #[test]
+    pub fn test_call_arena() {
+        let arena = Arc::new(Arena::default());
+        let joined: String = unsafe { call_function(join as *const () as usize, &(3u64, "Raven".to_string()), &arena) };
+        assert_eq!(joined, "Raven3");
+        assert_eq!(arena.allocations(), 0, "The call leaked");
+
+        // Outside of a call, values go back to being allocated with malloc
+        let pointer = unsafe { 5u64.to_raven() };
+        assert_eq!(arena.allocations(), 0);
+        assert_eq!(unsafe { u64::translate_borrowed(pointer) }, 5);
+    }
+
+    /// Compiles the script with the standard library, which has to fail with the expected error, and returns that error
+    fn expect_error(script: &str, expected: &str) -> ParsingError {
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        let Err(errors) = builder.script("script", script).build() else {
+            panic!("Compiled without the error {:?}!", expected);
+        };
+        let error = errors.iter().find(|error| error.message.to_string() == expected);
+        return error.unwrap_or_else(|| panic!("Expected {:?}, found: {:?}", expected, errors)).clone();
+    }
+
+    /// Compiles the script with the standard library, panicking if there are any errors starting with the message
+    fn expect_no_error(script: &str, message: &str) {
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        if let Err(errors) = builder.script("script", script).build() {
+            let found = errors.iter().find(|error| error.message.to_string().starts_with(message));
+            assert!(found.is_none(), "Unexpected error: {:?}", found);
+        }
+    }
+
+    /// Checks that implementing the same trait function twice in one impl is an error
+    #[test]
+    pub fn test_duplicate_trait_function() {
+        let script = "trait Named {\n    \
+                fn name(self) -> u64;\n\
+            }\n\
+            \n\
+            impl Named for u64 {\n    \
+                pub fn name(self) -> u64 {\n        \
+                    return 1;\n    \
+                }\n\
+                \n    \
+                pub fn name(self) -> u64 {\n        \
+                    return 2;\n    \
+                }\n\
+            }\n";
+        expect_error(script, "Implementation of script::Named has the function name more than once");
+    }
+
+    /// Checks that two functions with the same name in a type's impls in one file is an error, even in separate impls
+    #[test]
+    pub fn test_duplicate_function() {
+        let function = "    pub fn name(self) -> u64 {\n        return 1;\n    }\n";
+        let value = "pub struct Value {\n    id: u64;\n}\n\n";
+        let expected = "script::Value has the function name more than once in this file";
+        expect_error(&format!("{}impl Value {{\n{}\n{}}}\n", value, function, function), expected);
+        expect_error(&format!("{}impl Value {{\n{}}}\n\nimpl Value {{\n{}}}\n", value, function, function), expected);
+    }
+
+    /// Checks that two structs whose names hash to the same type ID is an error instead of mixing up their types
+    #[test]
+    pub fn test_id_collision() {
+        let script = "pub struct tiu {}\n\npub struct SiAA {}\n";
+        // The structs are added in parallel, so either one can be the second
+        let first = "script::tiu and script::SiAA have the same type ID, rename one of them";
+        let second = "script::SiAA and script::tiu have the same type ID, rename one of them";
+        let Err(errors) = Engine::builder().script("script", script).build() else {
+            panic!("Compiled without an ID collision!");
+        };
+        assert!(errors.iter().any(|error| [first, second].contains(&error.message.to_string().as_str())), "{:?}", errors);
+    }
+
+    /// Checks that loading a field from a value without it, like a number literal, is an error instead of a panic
+    #[test]
+    pub fn test_literal_field() {
+        expect_error("fn main() -> u64 {\n    return 5.value;\n}\n", "Unknown field value!");
+    }
+
+    /// Checks that setting a field twice in a new expression is an error, even with a base to copy from
+    #[test]
+    pub fn test_duplicate_field() {
+        let script = "struct Point {\n    x: u64;\n    y: u64;\n}\n\n\
+            fn main() -> u64 {\n    let first = new Point { x: 1, y: 2 };\n    \
+            let second = new Point { x: 3, x: 4, ..first };\n    return second.x;\n}\n";
+        expect_error(script, "The field x is set more than once");
+    }
+
+    /// Checks that a base no field is copied from is an error, since it would never be evaluated
+    #[test]
+    pub fn test_unused_base() {
+        let script = "struct Point {\n    x: u64;\n    y: u64;\n}\n\n\
+            fn main() -> u64 {\n    let first = new Point { x: 1, y: 2 };\n    \
+            let second = new Point { x: 3, y: 4, ..first };\n    return second.x;\n}\n";
+        expect_error(script, "Every field of script::Point is set, so the base after .. is never used");
+    }
+
+    /// Checks that breaking out of a label no loop has is an error
+    #[test]
+    pub fn test_unknown_label() {
+        let source =
+            "fn main() {\n    'outer: while true {\n        while true {\n            break 'inner;\n        }\n    }\n}\n";
+        expect_error(source, "There's no loop labeled 'inner' around this break");
+    }
+
+    /// Checks that continuing a label no loop has is an error
+    #[test]
+    pub fn test_unknown_continue_label() {
+        let source = "fn main() {\n    while true {\n        continue 'outer;\n    }\n}\n";
+        expect_error(source, "There's no loop labeled 'outer' around this continue");
+    }
+
+    /// Checks that continuing outside of a loop is an error
+    #[test]
+    pub fn test_continue_outside_loop() {
+        expect_error("fn main() {\n    continue;\n}\n", "continue can only be used in a loop");
+    }
+
+    /// Checks that a file ending inside a string is an error at the opening quote instead of a panic
+    #[test]
+    pub fn test_unterminated_string() {
+        let source = "fn main() {\n    let value = \"oops";
+        let error = expect_error(source, "String is never closed!");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].token_type, TokenTypes::StringStart);
+    }
+
+    /// Checks that unknown escapes, and hex or unicode escapes with bad values, are errors at the escape
+    #[test]
+    pub fn test_invalid_escape() {
+        for (escape, expected) in [
+            ("\\q", "Invalid escape sequence \\q"),
+            ("\\xZZ", "Invalid escape sequence \\xZZ"),
+            ("\\u{110000}", "Invalid escape sequence \\u{110000}"),
+        ] {
+            let source = format!("fn main() {{\n    let value = \"a{}b\";\n}}\n", escape);
+            let error = expect_error(&source, expected);
+            let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+            assert_eq!(tokens[error.span.start].token_type, TokenTypes::StringEscape);
+            assert_eq!(tokens[error.span.start].start.0, 2);
+        }
+    }
+
+    /// Checks that generics nested one deeper than the default limit are an error, even without arguments setting the limit
+    #[test]
+    pub fn test_generic_depth_limit() {
+        assert_eq!(GetterManager::default().max_generic_depth, 32);
+
+        let script = "struct Wrap<T> {\n    pub value: T;\n}\n\n\
+            fn nest<T>(value: T, count: u64) -> u64 {\n    \
+                if count == 0 {\n        \
+                    return 0;\n    \
+                }\n    \
+                return nest(new Wrap<T> { value: value }, count - 1);\n\
+            }\n\n\
+            fn main() -> u64 {\n    \
+                return nest(1, 40);\n\
+            }\n";
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        let Err(errors) = builder.script("script", script).build() else {
+            panic!("Nested generics past the limit!");
+        };
+        // The error is inside the instantiation of nest, so it's the cause of the error at the call
+        let error = errors.iter().map(|error| error.to_string()).find(|error| error.contains("limit of 32"));
+        let error = error.unwrap_or_else(|| panic!("No generic depth error in {:?}", errors));
+        // The first type past the limit is 33 wrappers deep
+        assert_eq!(error.matches("Wrap<").count(), 33, "{}", error);
+    }
+
+    /// Checks that structs extending themselves, directly or through another struct, are errors instead of hanging
+    #[test]
+    pub fn test_cyclic_inheritance() {
+        let main = "fn main() -> u64 {\n    return 1;\n}\n";
+        let source = format!("struct Loop extends Loop {{\n    value: u64;\n}}\n\n{}", main);
+        let error = expect_error(&source, "script::Loop extends itself through its parents");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].to_string(source.as_bytes()), "Loop");
+        assert_eq!(tokens[error.span.start].start.0, 1);
+
+        let source = format!(
+            "struct First extends Second {{\n    a: u64;\n}}\n\nstruct Second extends First {{\n    b: u64;\n}}\n\n{}",
+            main
+        );
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        let Err(errors) = builder.script("script", &source).build() else {
+            panic!("Compiled structs that extend each other!");
+        };
+        // Whichever struct is checked second finds the cycle
+        let messages = errors.iter().map(|error| error.message.to_string()).collect::<Vec<_>>();
+        assert!(
+            messages.iter().any(|message| message == "script::First extends itself through its parents"
+                || message == "script::Second extends itself through its parents"),
+            "{:?}",
+            messages
+        );
+    }
+
+    /// A script calling a generic function with a type that breaks a bound of a generic function it calls in turn,
+    /// which can only be found once the first function is degenericed
+    const BROKEN_BOUND: &str = "trait Named {\n    fn name(self) -> u64;\n}\n\n\
+        fn inner<T: Named>(value: T) -> u64 {\n    return value.name();\n}\n\n\
+        fn outer<T>(value: T) -> u64 {\n    return inner(value);\n}\n\n\
+        fn main() -> u64 {\n    return outer(5);\n}\n";
+
+    /// Checks that an error found while degenericing a function points at the call that instantiated it
+    #[test]
+    pub fn test_degeneric_error_span() {
+        let error = expect_error(BROKEN_BOUND, "While instantiating script::outer");
+        let tokens = tokenize(BROKEN_BOUND.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].to_string(BROKEN_BOUND.as_bytes()), "outer");
+        assert_eq!(tokens[error.span.start].start.0, 14);
+
+        // The bounds error inside outer points at its call to inner
+        let cause = error.cause.as_ref().unwrap();
+        assert_eq!(cause.message.to_string(), "u64 isn't of type script::Named");
+        assert_eq!(tokens[cause.span.start].to_string(BROKEN_BOUND.as_bytes()), "inner");
+        assert_eq!(tokens[cause.span.start].start.0, 10);
+    }
+
+    /// Checks that chained errors keep every cause, and display from the outermost error to the root cause
+    #[test]
+    pub fn test_error_chain() {
+        let root = ParsingError::new(Span::new(1, 2), ParsingMessage::UnknownFunction);
+        let error = root
+            .chain(ParsingMessage::WhileInstantiating("test::inner".to_string()), Span::new(1, 5))
+            .chain(ParsingMessage::WhileInstantiating("test::outer".to_string()), Span::new(1, 9));
+        assert_eq!(error.chained().count(), 3);
+        assert_eq!(error.chained().map(|error| error.span.start).collect::<Vec<_>>(), vec![9, 5, 2]);
+        assert_eq!(
+            error.to_string(),
+            "While instantiating test::outer\ncaused by: While instantiating test::inner\ncaused by: Unknown function!"
+        );
+
+        // An error inside a degenericed function is chained to the call that instantiated it
+        let error = expect_error(BROKEN_BOUND, "While instantiating script::outer");
+        assert_eq!(error.chained().count(), 2);
+    }
+
+    /// Checks that an impl leaving out one of the trait's functions is an error, even if it's never downcast
+    #[test]
+    pub fn test_partial_impl() {
+        let source = "trait Named {\n    fn name(self) -> u64;\n\n    fn id(self) -> u64;\n}\n\n\
+            impl Named for u64 {\n    \
+                pub fn name(self) -> u64 {\n        \
+                    return 1;\n    \
+                }\n\
+            }\n\n\
+            fn main() -> u64 {\n    \
+                return 5.name();\n\
+            }\n";
+        let error = expect_error(source, "Implementation of script::Named is missing the function id");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].to_string(source.as_bytes()), "Named");
+        assert_eq!(tokens[error.span.start].start.0, 7);
+    }
+
+    /// Checks that two equally specific implementations are an ambiguity error naming both of them
+    #[test]
+    pub fn test_ambiguous_impl() {
+        let script = "trait First {}\n\ntrait Second {}\n\nimpl First for u64 {}\n\nimpl Second for u64 {}\n\n\
+            trait Describe {\n    fn describe(self) -> u64;\n}\n\n\
+            impl<T: First> Describe for T {\n    pub fn describe(self) -> u64 {\n        return 1;\n    }\n}\n\n\
+            impl<T: Second> Describe for T {\n    pub fn describe(self) -> u64 {\n        return 2;\n    }\n}\n\n\
+            fn call<T: Describe>(value: T) -> u64 {\n    return value.describe();\n}\n\n\
+            fn main() -> u64 {\n    return call(5);\n}\n";
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        let Err(errors) = builder.script("script", script).build() else {
+            panic!("Compiled with ambiguous implementations!");
+        };
+        // The ambiguity is found while instantiating call, so it's the cause of the error at the call
+        let message = errors
+            .iter()
+            .flat_map(|error| error.chained())
+            .map(|error| error.message.to_string())
+            .find(|message| message.starts_with("Ambiguous implementation"))
+            .unwrap_or_else(|| panic!("No ambiguity error in: {:?}", errors));
+        assert!(message.contains("T: (script::First)"), "First candidate missing: {}", message);
+        assert!(message.contains("T: (script::Second)"), "Second candidate missing: {}", message);
+    }
+
+    /// Checks that an operator with no impl for its operands names the operator without its placeholders
+    #[test]
+    pub fn test_unknown_operator() {
+        let operation = Attribute::String("operation".to_string(), "{}<<{}".to_string());
+        assert_eq!(operation.as_operator(), Some("<<".to_string()));
+        assert_eq!(Attribute::Basic("operation".to_string()).as_operator(), None);
+
+        let source = "fn main() -> u64 {\n    let text = \"a\";\n    return text << 2;\n}\n";
+        let error = expect_error(source, "No operator << for str and u64");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].start.0, 3);
+    }
+
+    /// Checks that a partial impl is an error even if nothing calls its functions, and that finishing it fixes it
+    #[test]
+    pub fn test_unused_partial_impl() {
+        let name = "    pub fn name(self) -> u64 {\n        return 1;\n    }\n";
+        let id = "\n    pub fn id(self) -> u64 {\n        return 2;\n    }\n";
+        let source = |functions: &str| {
+            format!(
+                "trait Named {{\n    fn name(self) -> u64;\n\n    fn id(self) -> u64;\n}}\n\n\
+                impl Named for u64 {{\n{}}}\n\nfn main() -> u64 {{\n    return 0;\n}}\n",
+                functions
+            )
+        };
+        expect_error(&source(name), "Implementation of script::Named is missing the function id");
+        expect_no_error(&source(&format!("{}{}", name, id)), "Implementation of script::Named is missing");
+    }
+
+    /// Checks that operators on two different number types ask for a cast, pointing at the right operand
+    #[test]
+    pub fn test_mismatched_operands() {
+        let source = "fn main() -> u64 {\n    let count = 1;\n    return count +\n        1.5;\n}\n";
+        let error = expect_error(source, "Can't use + on a u64 and a f64, cast one of them first");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].start.0, 4);
+    }
+
+    /// Checks that integer literals, including negative folded constants, have to fit in the number they're used with
+    #[test]
+    pub fn test_literal_out_of_range() {
+        let small = "pub struct Small {\n    value: u8;\n}\n\n";
+        expect_error(
+            &format!(
+                "{}fn main() -> u8 {{\n    let small = new Small {{ value: 300 }};\n    return small.value;\n}}\n",
+                small
+            ),
+            "300 doesn't fit in a u8",
+        );
+        expect_error(
+            &format!(
+                "{}fn main() -> u8 {{\n    let small = new Small {{ value: 1 }};\n    return small.value + (2 - 5);\n}}\n",
+                small
+            ),
+            "-3 doesn't fit in a u8",
+        );
+    }
+
+    /// Checks that a string left open on a line before more code is an error at its opening quote
+    #[test]
+    pub fn test_unterminated_string_lines() {
+        let source = "fn main() {\n    let value = \"oops;\n    return;\n}\n";
+        let error = expect_error(source, "String is never closed!");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].token_type, TokenTypes::StringStart);
+        assert_eq!(tokens[error.span.start].start.0, 2);
+    }
+
+    /// Checks that calling a function with too few arguments is an error
+    #[test]
+    pub fn test_too_few_arguments() {
+        let source = "fn pick(first: u64, second: u64) -> u64 {\n    return first;\n}\n\n\
+            fn main() -> u64 {\n    return pick(1);\n}\n";
+        expect_error(source, "Expected 2 arguments but found 1!");
+    }
+
+    /// Checks that a call with a mismatched argument underlines the whole call instead of only the function's name
+    #[test]
+    pub fn test_call_error_span() {
+        let source = "fn pick(first: u64, second: u64) -> u64 {\n    return first;\n}\n\n\
+            fn main() -> u64 {\n    return pick(1, \"x\");\n}\n";
+        let error = expect_error(source, "str isn't of type u64");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        let underlined = tokens[error.span.start..=error.span.end]
+            .iter()
+            .map(|token| token.to_string(source.as_bytes()))
+            .collect::<String>();
+        assert!(underlined.contains("pick"), "The name isn't underlined: {:?}", underlined);
+        assert_eq!(tokens[error.span.end].token_type, TokenTypes::StringEnd);
+        assert_eq!(tokens[error.span.start].start.0, 6);
+    }
+
+    /// Checks that calling a generic function with too many arguments is an error before it's degenericed
+    #[test]
+    pub fn test_too_many_arguments() {
+        let source = "fn pick<T>(first: T, second: T) -> T {\n    return first;\n}\n\n\
+            fn main() -> u64 {\n    return pick(1, 2, 3);\n}\n";
+        expect_error(source, "Expected 2 arguments but found 3!");
+    }
+
+    /// Checks that an impl generic used by neither the trait nor the type is an error at the impl
+    #[test]
+    pub fn test_unconstrained_impl_generic() {
+        let source = "pub trait Named {\n    fn name(self) -> u64;\n}\n\n\
+            pub struct Value {\n    id: u64;\n}\n\n\
+            impl<T> Named for Value {\n    fn name(self) -> u64 {\n        return 1;\n    }\n}\n\n\
+            fn main() -> u64 {\n    return 0;\n}\n";
+        let error = expect_error(source, "The generic T isn't used by the implemented types, so it can never be known");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].to_string(source.as_bytes()), "Named");
+    }
+
+    /// Checks that a function's own generics aren't mistaken for the impl's, which would make them unconstrained
+    #[test]
+    pub fn test_impl_function_generic() {
+        let source = "pub trait Named<T> {\n    fn name(self, other: T) -> u64;\n}\n\n\
+            pub struct Value {\n    id: u64;\n}\n\n\
+            impl<T> Named<T> for Value {\n    \
+            fn name(self, other: T) -> u64 {\n        return 1;\n    }\n\n    \
+            fn other<U>(self, first: U) -> u64 {\n        return 2;\n    }\n}\n\n\
+            fn main() -> u64 {\n    return 0;\n}\n";
+        expect_no_error(source, "The generic");
+    }
+
+    /// Checks that using an impl with a type its generic's bounds don't allow is an error at the call naming the bound
+    #[test]
+    pub fn test_unsatisfied_impl_bound() {
+        let source = "import math::Add;\nimport numbers::Number;\n\n\
+            pub struct Wrapper<T> {\n    value: T;\n}\n\n\
+            impl<T: Number> Add<Wrapper<T>, u64> for Wrapper<T> {\n    \
+            fn add(self, other: Wrapper<T>) -> u64 {\n        return 1;\n    }\n}\n\n\
+            fn main() -> u64 {\n    let first = new Wrapper<str> { value: \"a\" };\n    return first + first;\n}\n";
+        let expected = "str doesn't implement numbers::Number, which the implementation of math::Add requires";
+        let error = expect_error(source, expected);
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].start.0, 16, "Error isn't on the call's line");
+    }
+
+    /// Compiles scripts each implementing a pick method on the same struct, then calls main, which returns
+    /// the number of the pick method that was called
+    fn call_overload(first: &str, second: &str) -> u64 {
+        let values = "pub struct Value {\n    id: u64;\n}\n\n\
+            pub trait Named {\n    fn name(self) -> u64;\n}\n\n\
+            impl Named for u64 {\n    fn name(self) -> u64 {\n        return self;\n    }\n}\n";
+        let main = "import values::Value;\n\n\
+            fn main() -> u64 {\n    let value = new Value { id: 0 };\n    return value.pick(5);\n}\n";
+        let imports = "import values::Value;\nimport values::Named;\n\n";
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        let engine = builder
+            .script("values", values)
+            .script("first", &format!("{}impl Value {{\n{}\n}}\n", imports, first))
+            .script("second", &format!("{}impl Value {{\n{}\n}}\n", imports, second))
+            .script("main", main)
+            .build()
+            .unwrap_or_else(|errors| panic!("Failed to compile the overloads: {:?}", errors));
+        return unsafe { engine.call("main::main", ()) }.unwrap();
+    }
+
+    /// Checks that an overload taking the argument's exact type is picked over one taking a trait it implements
+    #[test]
+    pub fn test_overload_exact() {
+        let exact = "    pub fn pick(self, other: u64) -> u64 {\n        return 1;\n    }";
+        let named = "    pub fn pick(self, other: Named) -> u64 {\n        return 2;\n    }";
+        assert_eq!(call_overload(exact, named), 1);
+        assert_eq!(call_overload(named, exact), 1);
+    }
+
+    /// Checks that an overload taking a trait the argument implements is picked over a generic one
+    #[test]
+    pub fn test_overload_subtype() {
+        let named = "    pub fn pick(self, other: Named) -> u64 {\n        return 2;\n    }";
+        let generic = "    pub fn pick<T>(self, other: T) -> u64 {\n        return 3;\n    }";
+        assert_eq!(call_overload(named, generic), 2);
+        assert_eq!(call_overload(generic, named), 2);
+    }
+
+    /// Checks that getting the type of a malformed load, like a field of a number, is an error instead of a panic
+    #[test]
+    pub fn test_malformed_load() {
+        let arguments = Arguments::build_args(
+            false,
+            RunnerSettings { sources: vec![], compiler_arguments: CompilerArguments::default() },
+        );
+        let syntax = create_syntax(&arguments);
+        let variables = SimpleVariableManager { variables: HashMap::default() };
+        let number = FinalizedEffects::new(Span::default(), store(FinalizedEffectType::UInt(5)));
+        let load = FinalizedEffects::new(
+            Span::default(),
+            FinalizedEffectType::Load(Box::new(number), "value".to_string(), FinalizedTypes::Struct(U64.clone())),
+        );
+        let Err(error) = arguments.cpu_runtime.block_on(get_return(&load, &variables, &syntax)) else {
+            panic!("Loaded a field from a number!");
+        };
+        assert_eq!(error.message.to_string(), "Unknown field value!");
+    }
+
+    /// Checks that assigning a value of another type to a variable is an error naming both types
+    #[test]
+    pub fn test_assignment_mismatch() {
+        let script = "fn main() -> u64 {\n    let x = 1;\n    x = \"s\";\n    return x;\n}\n";
+        expect_error(script, "str isn't of type u64");
+    }
+
+    /// Checks that an array literal with an element of another type is an error at that element
+    #[test]
+    pub fn test_array_mismatch() {
+        let source = "fn main() -> u64 {\n    let values = [\n        1,\n        \"a\",\n    ];\n    return 1;\n}\n";
+        let error = expect_error(source, "str isn't of type u64");
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[error.span.start].start.0, 4, "Error isn't on the string's line");
+    }
+
+    /// Checks that an empty array literal that isn't passed as an argument can't be given a type
+    #[test]
+    pub fn test_uninferable_array() {
+        expect_error("fn main() -> u64 {\n    let values = [];\n    return 1;\n}\n", "Cannot infer array type!");
+    }
+
+    /// Checks that a task waiting for something that never happens is stopped and reported instead of hanging
+    #[test]
+    pub fn test_stuck_task_timeout() {
+        let arguments = Arguments::build_args(
+            false,
+            RunnerSettings {
+                sources: vec![],
+                compiler_arguments: CompilerArguments {
+                    compiler: "llvm".to_string(),
+                    timeout: Duration::from_secs(1),
+                    ..Default::default()
+                },
+            },
+        );
+        let syntax = create_syntax(&arguments);
+        let handle = syntax.lock().process_manager.handle().clone();
+
+        // This struct is never verified, so it's never finalized
+        let never = Arc::new(StructData::new(vec![], vec![], 0, Span::default(), "stuck::Never".to_string()));
+        let waiting = syntax.clone();
+        handle.lock().spawn("stuck".to_string(), async move {
+            AsyncDataGetter::new(waiting, never).await;
+            return Ok(());
+        });
+
+        let Err(errors) = arguments.cpu_runtime.block_on(build(syntax, &arguments)) else {
+            panic!("Stuck task finished!");
+        };
+        let message = errors[0].message.to_string();
+        assert!(message.contains("stuck (running for "), "Stuck task not listed: {}", message);
+        assert!(message.contains("AsyncDataGetter waiting for stuck::Never to be finalized"), "Wrong waiter: {}", message);
+        // The aborted task isn't counted as running anymore
+        assert_eq!(handle.lock().active_task_count(), 0);
+    }
+
+    /// Checks that the active task count goes down as tasks finish and are joined, down to 0
+    #[test]
+    pub fn test_active_task_count() {
+        let arguments = Arguments::build_args(
+            false,
+            RunnerSettings { sources: vec![], compiler_arguments: CompilerArguments::default() },
+        );
+        let syntax = create_syntax(&arguments);
+        let handle = syntax.lock().process_manager.handle().clone();
+
+        // Each task runs until it's told to finish
+        let mut finishing = vec![];
+        for i in 0..3 {
+            let (sender, receiver) = oneshot::channel::<()>();
+            handle.lock().spawn(format!("task{}", i), async move {
+                receiver.await.unwrap();
+                return Ok(());
+            });
+            finishing.push(sender);
+        }
+        assert_eq!(handle.lock().active_task_count(), 3);
+
+        // Finished tasks are only removed when they're joined, and the waiter has to be made in the runtime
+        let (waiting, waiting_syntax) = (handle.clone(), syntax.clone());
+        let joining = arguments
+            .cpu_runtime
+            .spawn(async move { JoinWaiter::new(waiting, waiting_syntax, Duration::from_secs(10)).await });
+        for (i, sender) in finishing.into_iter().enumerate() {
+            sender.send(()).unwrap();
+            let expected = 2 - i;
+            let start = Instant::now();
+            while handle.lock().active_task_count() != expected {
+                assert!(start.elapsed() < Duration::from_secs(5), "Task count never went down to {}", expected);
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        arguments.cpu_runtime.block_on(joining).unwrap().unwrap();
+    }
+
+    /// Parses the code in a function in the source with the parse function, starting after the {
+    fn parse_function<T>(source: &str, parse: impl FnOnce(&mut ParserUtils) -> T) -> T {
+        let arguments = Arguments::build_args(
+            true,
+            RunnerSettings {
+                sources: vec![],
+                compiler_arguments: CompilerArguments { compiler: "llvm".to_string(), ..Default::default() },
+            },
+        );
+        let syntax = create_syntax(&arguments);
+        let handle = syntax.lock().process_manager.handle().clone();
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        let start = tokens.iter().position(|token| token.token_type == TokenTypes::CodeStart).unwrap() + 1;
+
+        let mut parser_utils = ParserUtils {
+            buffer: source.as_bytes(),
+            index: start,
+            tokens,
+            syntax,
+            file: 0,
+            file_name: "test".to_string(),
+            imports: ImportNameResolver::new("test".to_string()),
+            handle,
+            loops: Vec::default(),
+        };
+        return parse(&mut parser_utils);
+    }
+
+    /// Parses a line of code in a function, returning the tree of its effect without its own span
+    fn parse_tree(line: &str) -> String {
+        let expression = parse_function(&format!("fn test() {{\n    {};\n}}", line), |parser_utils| {
+            parse_line(parser_utils, ParseState::None).unwrap().expect("No effect parsed!")
+        });
+        return format!("{:?}", expression.effect.types);
+    }
+
+    /// Checks that 0..10 is parsed as a range from 0 to 10, and that a range binds looser than the operators in it
+    #[test]
+    pub fn test_range_parsing() {
+        let parse_range = |line: &str| {
+            let expression = parse_function(&format!("fn test() {{\n    {};\n}}", line), |parser_utils| {
+                parse_line(parser_utils, ParseState::None).unwrap().expect("No effect parsed!")
+            });
+            let EffectType::Range(start, end) = expression.effect.types else {
+                panic!("{} wasn't parsed as a range: {:?}", line, expression.effect.types);
+            };
+            return (format!("{:?}", start.types), format!("{:?}", end.types));
+        };
+        assert_eq!(parse_range("0..10"), ("Int(0)".to_string(), "Int(10)".to_string()));
+        let (start, end) = parse_range("1 + 2..n + 1");
+        assert!(start.starts_with("Operation(\"{}+{}\""), "Start isn't the sum: {}", start);
+        assert!(end.starts_with("Operation(\"{}+{}\""), "End isn't the sum: {}", end);
+    }
+
+    /// Checks that code ending right after a period is tokenized without reading past the end of the file
+    #[test]
+    pub fn test_period_at_end() {
+        for source in ["fn test() {\n    value.", "fn test() {\n    1."] {
+            let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+            assert_eq!(tokens.last().unwrap().token_type, TokenTypes::EOF);
+        }
+    }
+
+    /// Checks that files ending in whitespace, including non-breaking spaces, are tokenized without reading past the end
+    #[test]
+    pub fn test_trailing_spaces() {
+        for source in ["fn test() {\n}   ", "fn test() {\n}\n\t  \n  ", "fn test() {\n}\u{a0}", "fn test() {\n}\n\u{a0}  "] {
+            let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+            assert_eq!(tokens.last().unwrap().token_type, TokenTypes::EOF, "{:?} didn't end in EOF", source);
+            assert!(
+                tokens.iter().all(|token| token.token_type != TokenTypes::InvalidCharacters),
+                "Invalid characters in {:?}",
+                source
+            );
+        }
+    }
+
+    /// Checks that an embedded source set lists its files relative to the directory
+    #[test]
+    pub fn test_list_files() {
+        let set = InnerSourceSet { name: "magpie", set: &MAGPIE };
+        assert_eq!(set.list_files(), vec!["magpie.rv".to_string()]);
+    }
+
+    /// Checks that dumping a function's parsed code shows each effect's kind, with the effects inside it under it
+    #[test]
+    pub fn test_dump_ast() {
+        let source = "fn test() -> u64 {\n    let value = add(1, 2);\n    return value + 1;\n}";
+        let (_, code) = parse_function(source, |parser_utils| parse_code(parser_utils).unwrap());
+        let dumped = code.to_string();
+        for expected in [
+            "\n  CreateVariable value\n    MethodCall add\n      Int 1\n      Int 2",
+            "\n  Return\n    Operation {}+{}\n      LoadVariable value\n      Int 1",
+        ] {
+            assert!(dumped.contains(expected), "Missing {:?} in:\n{}", expected, dumped);
+        }
+
+        // Building with dump_ast records each function's dump for magpie to print
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "variables.rv"].iter().collect();
+        let mut arguments = test_arguments("variables::test");
+        arguments.runner_settings.compiler_arguments.dump_ast = true;
+        setup_arguments(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))]).unwrap();
+        let syntax = create_syntax(&arguments);
+        arguments.cpu_runtime.block_on(build(syntax.clone(), &arguments)).unwrap();
+        let dumps = syntax.lock().ast_dumps.clone();
+        assert!(dumps.iter().any(|dump| dump.starts_with("fn variables::test\n")), "No dump of the test in: {:?}", dumps);
+    }
+
+    /// Checks that a trailing comma in a call's arguments or a struct literal doesn't change what's parsed
+    #[test]
+    pub fn test_trailing_commas() {
+        assert_eq!(parse_tree("add(1, 2,)"), parse_tree("add(1, 2)"));
+        assert_eq!(parse_tree("new S { a: 1, b: 2, }"), parse_tree("new S { a: 1, b: 2 }"));
+        assert_eq!(parse_tree("new S { a: 1 + 2, }"), parse_tree("new S { a: 1 + 2 }"));
+    }
+
+    /// Checks that an if/else if/else chain is parsed into one flat block of checks instead of nesting each else if
+    #[test]
+    pub fn test_else_if_parsing() {
+        let source = "fn test() {\n    if a {\n        one();\n    } else if b {\n        two();\n    } \
+            else {\n        three();\n    }\n}";
+        let expression = parse_function(source, |parser_utils| {
+            parse_line(parser_utils, ParseState::None).unwrap().expect("No effect parsed!")
+        });
+        let EffectType::CodeBody(top) = expression.effect.types else {
+            panic!("The if wasn't parsed as a block: {:?}", expression.effect.types);
+        };
+        let blocks = top.expressions[1..]
+            .iter()
+            .map(|expression| match &expression.effect.types {
+                EffectType::CodeBody(body) => body,
+                other => panic!("Expected a block, found {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        // The if body, the else if check, the else if body, then the else body
+        assert_eq!(blocks.len(), 4);
+
+        let end = top.label.clone() + "end";
+        let EffectType::CompareJump(_, if_body, if_failed) = &top.expressions[0].effect.types else {
+            panic!("The if doesn't start with its check: {:?}", top.expressions[0].effect.types);
+        };
+        assert_eq!(if_body, &blocks[0].label);
+        assert_eq!(if_failed, &blocks[1].label);
+        let EffectType::CompareJump(_, else_if_body, else_if_failed) = &blocks[1].expressions[0].effect.types else {
+            panic!("The else if doesn't start with its check: {:?}", blocks[1].expressions[0].effect.types);
+        };
+        assert_eq!(else_if_body, &blocks[2].label);
+        assert_eq!(else_if_failed, &blocks[3].label);
+
+        // Every branch jumps straight to the end, and none of them nest another block
+        for branch in [blocks[0], blocks[2], blocks[3]] {
+            assert!(matches!(&branch.expressions.last().unwrap().effect.types, EffectType::Jump(label) if *label == end));
+            assert!(branch.expressions.iter().all(|expression| !matches!(expression.effect.types, EffectType::CodeBody(_))));
+        }
+    }
+
+    /// Checks that has_attribute finds attributes of every kind by name
+    #[test]
+    pub fn test_has_attribute() {
+        let attributes = vec![
+            Attribute::Basic("copy".to_string()),
+            Attribute::Integer("priority".to_string(), 2),
+            Attribute::Bool("parse_left".to_string(), false),
+            Attribute::String("operation".to_string(), "{}+{}".to_string()),
+        ];
+        for name in ["copy", "priority", "parse_left", "operation"] {
+            assert!(Attribute::has_attribute(name, &attributes), "Didn't find {}", name);
+        }
+        assert!(!Attribute::has_attribute("deprecated", &attributes));
+        assert!(!Attribute::has_attribute("copy", &[]));
+    }
+
+    /// Checks that the operation modifier is parsed from the attribute, and that its displayed name parses back to it
+    #[test]
+    pub fn test_operation_modifier() {
+        for modifier in MODIFIERS {
+            assert_eq!(to_modifiers(get_modifier(&[modifier])), vec![modifier]);
+        }
+
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add(
+            "script",
+            "#[operation({}%%{})]\npub trait Weird<T> {\n    fn weird(self, other: T) -> T;\n}\n".to_string(),
+        );
+        let mut arguments = test_arguments("script::main");
+        let (syntax, _) = build_project::<()>(&mut arguments, &mut vec![Box::new(script)], false).unwrap();
+        let modifiers = syntax.lock().structures.types["script::Weird"].modifiers;
+        let displayed = to_modifiers(modifiers).iter().map(|modifier| modifier.to_string()).collect::<Vec<_>>();
+        assert_eq!(displayed, vec!["pub", "operation"]);
+
+        let parsed = displayed
+            .iter()
+            .map(|name| *MODIFIERS.iter().find(|modifier| modifier.to_string() == *name).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(get_modifier(&parsed), modifiers & !(Modifier::Trait as u8));
+    }
+
+    /// Checks that attributes are validated against the known attributes and the kinds of value they take
+    #[test]
+    pub fn test_validate_attributes() {
+        let validate = |attributes: Vec<Attribute>| Attribute::validate(&attributes, &Span::default());
+        let error = |attributes: Vec<Attribute>| validate(attributes).unwrap_err().message.to_string();
+        let warnings = |attributes: Vec<Attribute>| {
+            validate(attributes).unwrap().iter().map(|warning| warning.message.to_string()).collect::<Vec<_>>()
+        };
+
+        let known = vec![
+            Attribute::Basic("copy".to_string()),
+            Attribute::Integer("priority".to_string(), 2),
+            Attribute::Bool("parse_left".to_string(), false),
+            Attribute::String("operation".to_string(), "{}+{}".to_string()),
+        ];
+        assert!(warnings(known).is_empty());
+        // Deprecated works with or without a message
+        assert!(warnings(vec![Attribute::Basic("deprecated".to_string())]).is_empty());
+        assert!(warnings(vec![Attribute::String("deprecated".to_string(), "use other".to_string())]).is_empty());
+
+        assert_eq!(
+            error(vec![Attribute::Integer("deprecated".to_string(), 1)]),
+            "Attribute deprecated expects a flag or string value but found a integer"
+        );
+        assert_eq!(
+            error(vec![Attribute::String("copy".to_string(), "yes".to_string())]),
+            "Attribute copy expects a flag value but found a string"
+        );
+        assert_eq!(
+            warnings(vec![Attribute::Basic("deprecate".to_string())]),
+            vec!["Unknown attribute deprecate, did you mean deprecated?"]
+        );
+        assert!(warnings(vec![
+            Attribute::Basic("deprecate".to_string()),
+            Attribute::String("allow_attributes".to_string(), "deprecate".to_string()),
+        ])
+        .is_empty());
+    }
+
+    /// Checks that calling a deprecated function or creating a deprecated struct warns with the message at the use
+    #[test]
+    pub fn test_deprecated_warning() {
+        let source = "fn main() -> u64 {\n    let point = new OldPoint { x: 1 };\n    return old_value();\n}\n\n\
+            #[deprecated(\"use new_value instead\")]\nfn old_value() -> u64 {\n    return 1;\n}\n\n\
+            #[deprecated(\"use Point instead\")]\nstruct OldPoint {\n    pub x: u64;\n}\n";
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add("script", source.to_string());
+        let mut arguments = test_arguments("script::main");
+        let (syntax, result) = build_project::<u64>(&mut arguments, &mut vec![Box::new(script)], true).unwrap();
+        assert_eq!(result, Some(1));
+
+        let warnings = syntax.lock().warnings.clone();
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        for (expected, name, line) in [
+            ("script::OldPoint is deprecated: use Point instead", "OldPoint", 2),
+            ("script::old_value is deprecated: use new_value instead", "old_value", 3),
+        ] {
+            let warning = warnings.iter().find(|warning| warning.message.to_string() == expected);
+            let warning = warning.unwrap_or_else(|| panic!("Expected {:?}, found: {:?}", expected, warnings));
+            assert_eq!(tokens[warning.span.start].to_string(source.as_bytes()).trim(), name);
+            assert_eq!(tokens[warning.span.start].start.0, line);
+        }
+    }
+
+    /// Checks that using a function marked #[deprecated] without a message warns without one
+    #[test]
+    pub fn test_bare_deprecated() {
+        let source =
+            "fn main() -> u64 {\n    return old_value();\n}\n\n#[deprecated]\nfn old_value() -> u64 {\n    return 1;\n}\n";
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add("script", source.to_string());
+        let mut arguments = test_arguments("script::main");
+        let (syntax, result) = build_project::<u64>(&mut arguments, &mut vec![Box::new(script)], true).unwrap();
+        assert_eq!(result, Some(1));
+
+        let warnings = syntax.lock().warnings.clone();
+        let expected = "script::old_value is deprecated!";
+        let warning = warnings.iter().find(|warning| warning.message.to_string() == expected);
+        let warning = warning.unwrap_or_else(|| panic!("Expected {:?}, found: {:?}", expected, warnings));
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[warning.span.start].start.0, 2);
+    }
+
+    /// Checks that errors in the core library are labeled with <core> instead of a path
+    #[test]
+    pub fn test_core_error_label() {
+        let mut arguments = Arguments::build_args(
+            false,
+            RunnerSettings { sources: vec![], compiler_arguments: CompilerArguments::default() },
+        );
+        let mut sources: Vec<Box<dyn SourceSet>> = vec![];
+        setup_arguments(&mut arguments, &mut sources).unwrap();
+
+        let core = sources.iter().find(|source| source.name() == "<core>").expect("No core source set!");
+        let file = core.get_files().into_iter().find(|file| file.path() == "iter.rv").unwrap();
+        let error = ParsingError::new(Span::new(file.hash(), 0), ParsingMessage::UnexpectedToken);
+        let label = error.file_label(&sources).unwrap();
+        assert_eq!(label, format!("<core>/{}", file.path()));
+    }
+
+    /// Checks that a variable manager lists every variable it has with its type
+    #[test]
+    pub fn test_all_variables() {
+        let mut variables = SimpleVariableManager { variables: HashMap::default() };
+        assert!(variables.all_variables().is_empty());
+
+        variables.variables.insert("count".to_string(), FinalizedTypes::Struct(U64.clone()));
+        variables.variables.insert("done".to_string(), FinalizedTypes::Struct(BOOL.clone()));
+        let mut found =
+            variables.all_variables().into_iter().map(|(name, types)| (name.clone(), types.to_string())).collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, vec![("count".to_string(), "u64".to_string()), ("done".to_string(), "bool".to_string())]);
+    }
+
+    /// Checks that code bodies display as readable IR, with nested code bodies indented further
+    #[test]
+    pub fn test_display_code_body() {
+        let line = |types: FinalizedEffectType| {
+            FinalizedExpression::new(ExpressionType::Line, FinalizedEffects::new(Span::default(), types))
+        };
+        let returning = |types: FinalizedEffectType| {
+            FinalizedExpression::new(ExpressionType::Return(Span::default()), FinalizedEffects::new(Span::default(), types))
+        };
+        let effect = |types: FinalizedEffectType| Box::new(FinalizedEffects::new(Span::default(), types));
+
+        let then = FinalizedCodeBody::new(vec![returning(FinalizedEffectType::UInt(1))], "1".to_string(), true);
+        let body = FinalizedCodeBody::new(
+            vec![
+                line(FinalizedEffectType::CreateVariable(
+                    "count".to_string(),
+                    effect(FinalizedEffectType::UInt(5)),
+                    FinalizedTypes::Struct(U64.clone()),
+                )),
+                line(FinalizedEffectType::CompareJump(
+                    effect(FinalizedEffectType::LoadVariable("done".to_string())),
+                    "1".to_string(),
+                    "2".to_string(),
+                )),
+                line(FinalizedEffectType::CodeBody(then)),
+                returning(FinalizedEffectType::LoadVariable("count".to_string())),
+            ],
+            "0".to_string(),
+            true,
+        );
+        assert_eq!(
+            body.to_string(),
+            "0:\n  let count: u64 = 5\n  if done goto 1 else 2\n  1:\n    return 1\n  return count"
+        );
+    }
+
+    /// Checks that degenericing code bodies nested a thousand deep, both directly and in match arms,
+    /// finishes and puts every body back where it was
+    #[test]
+    pub fn test_deep_degeneric() {
+        let arguments = Arguments::build_args(
+            false,
+            RunnerSettings { sources: vec![], compiler_arguments: CompilerArguments::default() },
+        );
+        let syntax = create_syntax(&arguments);
+        let process_manager = syntax.lock().process_manager.cloned();
+        let effect = |types: FinalizedEffectType| FinalizedEffects::new(Span::default(), types);
+
+        let returning =
+            FinalizedExpression::new(ExpressionType::Return(Span::default()), effect(FinalizedEffectType::UInt(1)));
+        let mut body = FinalizedCodeBody::new(vec![returning], "1000".to_string(), true);
+        for depth in (0..1000).rev() {
+            let nested = if depth % 2 == 0 {
+                FinalizedEffectType::CodeBody(body)
+            } else {
+                let arm = (FinalizedTypes::Struct(BOOL.clone()), effect(FinalizedEffectType::CodeBody(body)));
+                FinalizedEffectType::MatchArm(Box::new(effect(FinalizedEffectType::Bool(true))), vec![arm])
+            };
+            body = FinalizedCodeBody::new(
+                vec![FinalizedExpression::new(ExpressionType::Line, effect(nested))],
+                depth.to_string(),
+                false,
+            );
+        }
+
+        let mut variables = SimpleVariableManager { variables: HashMap::default() };
+        arguments.cpu_runtime.block_on(degeneric_code_body(&mut body, &*process_manager, &mut variables, &syntax)).unwrap();
+
+        let mut current = &body;
+        for depth in 0..1000 {
+            assert_eq!(current.label, depth.to_string());
+            current = match &current.expressions[0].effect.types {
+                FinalizedEffectType::CodeBody(inner) => inner,
+                FinalizedEffectType::MatchArm(_, arms) => match &arms[0].1.types {
+                    FinalizedEffectType::CodeBody(inner) => inner,
+                    _ => panic!("Lost the match arm's body at depth {}", depth),
+                },
+                _ => panic!("Lost the body at depth {}", depth),
+            };
+        }
+        assert_eq!(current.label, "1000");
+        assert!(matches!(current.expressions[0].effect.types, FinalizedEffectType::UInt(1)));
+    }
+
+    /// Checks that a struct with two generics degenericed in two different ways is only registered once
+    #[test]
+    pub fn test_generic_struct_name() {
+        let arguments = Arguments::build_args(
+            false,
+            RunnerSettings { sources: vec![], compiler_arguments: CompilerArguments::default() },
+        );
+        let syntax = create_syntax(&arguments);
+        let mut pair = FinalizedStruct::empty_of(StructData::new(vec![], vec![], 0, Span::default(), "Pair".to_string()));
+        for generic in ["K", "V"] {
+            pair.generics.insert(generic.to_string(), FinalizedTypes::Generic(generic.to_string(), vec![]));
+        }
+        let generics = HashMap::from([
+            ("V".to_string(), FinalizedTypes::Struct(BOOL.clone())),
+            ("K".to_string(), FinalizedTypes::Struct(U64.clone())),
+        ]);
+
+        let (direct, through_type) = arguments.cpu_runtime.block_on(async {
+            let direct = degeneric_struct(pair.clone(), &generics, &syntax).await;
+            let mut through_type = FinalizedTypes::Struct(Arc::new(pair));
+            degeneric_type(&mut through_type, &generics, &syntax).await;
+            (direct, through_type)
+        });
+        assert_eq!(direct.data.name, "Pair$u64_bool");
+        assert!(Arc::ptr_eq(&direct, through_type.inner_struct()));
+        let registered = syntax.lock().structures.types.keys().filter(|name| name.starts_with("Pair")).count();
+        assert_eq!(registered, 1);
+    }
+
+    /// Checks that traits are found through references and generic types, and that generics are never traits
+    #[test]
+    pub fn test_is_trait() {
+        let data = Arc::new(StructData::new(vec![], vec![], Modifier::Trait as u8, Span::default(), "Trait".to_string()));
+        let traits = Types::Struct(data.clone());
+        assert!(traits.is_trait());
+        assert!(Types::Reference(Box::new(traits.clone())).is_trait());
+        assert!(Types::GenericType(Box::new(traits.clone()), vec![]).is_trait());
+        assert!(!Types::Generic("T".to_string(), vec![traits]).is_trait());
+        assert!(!Types::Struct(U64.data.clone()).is_trait());
+
+        let traits = FinalizedTypes::Struct(Arc::new(FinalizedStruct::empty_of(StructData::clone(&data))));
+        assert!(traits.is_trait());
+        assert!(FinalizedTypes::Reference(Box::new(traits.clone())).is_trait());
+        assert!(FinalizedTypes::GenericType(Box::new(traits.clone()), vec![]).is_trait());
+        assert!(!FinalizedTypes::Generic("T".to_string(), vec![traits]).is_trait());
+        assert!(!FinalizedTypes::Struct(U64.clone()).is_trait());
+    }
+
+    /// Checks that a program using generics, traits and iterators runs to completion on a single thread,
+    /// which deadlocks if any task holds a lock while waiting on another task
+    #[test]
+    pub fn test_single_threaded() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "for-loops.rv"].iter().collect();
+        let mut settings = test_settings("for-loops::test");
+        settings.compiler_arguments.timeout = Duration::from_secs(30);
+        let mut arguments = Arguments::build_args(true, settings);
+        match build_project::<bool>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))], true) {
+            Ok((_, result)) => assert_eq!(result, Some(true), "Test failed on a single thread!"),
+            Err(error) => panic!("Failed to compile on a single thread: {}", error),
+        }
+    }
+
+    /// Checks that cancelling a compile while it's being checked stops it with a cancellation error
+    #[test]
+    pub fn test_cancellation() {
+        static CANCELLING: OnceLock<CancellationToken> = OnceLock::new();
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "for-loops.rv"].iter().collect();
+        let mut arguments = test_arguments("for-loops::test");
+        arguments.runner_settings.compiler_arguments.timeout = Duration::from_secs(30);
+        setup_arguments(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))]).unwrap();
+        // Cancels at the first progress report with tasks still running
+        CANCELLING.get_or_init(|| arguments.cancellation.clone());
+        arguments.progress = Some(|running| {
+            if running > 0 {
+                CANCELLING.get().unwrap().cancel();
+            }
+        });
+
+        let syntax = create_syntax(&arguments);
+        let start = Instant::now();
+        let Err(errors) = arguments.cpu_runtime.block_on(run::<bool>(syntax, &arguments)) else {
+            panic!("Finished a cancelled compile!");
+        };
+        assert!(start.elapsed() < Duration::from_secs(10), "Cancelling took {}s", start.elapsed().as_secs());
+        let expected = "The compile was cancelled";
+        assert!(errors.iter().any(|error| error.message.to_string() == expected), "Wrong errors: {:?}", errors);
+    }
+
+    /// Records the trait its impl waiter was waiting for when woken
+    struct ImplWakeRecorder {
+        /// The trait being waited for
+        trait_name: String,
+        /// Every trait whose waiter was woken
+        woken: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Wake for ImplWakeRecorder {
+        fn wake(self: Arc<Self>) {
+            self.woken.lock().unwrap().push(self.trait_name.clone());
+        }
+    }
+
+    /// Checks that adding impls only wakes the waiters they could affect, so wakes grow linearly with the impls.
+    /// Each waiter goes back to waiting until its trait is implemented, like an ImplWaiter would.
+    #[test]
+    pub fn test_impl_wakes() {
+        let traits = 3000;
+        let woken = Arc::new(Mutex::new(Vec::default()));
+        let mut manager = GetterManager::default();
+        let mut wakes = 0;
+        let wait = |manager: &mut GetterManager, trait_name: String| {
+            let waker = Waker::from(Arc::new(ImplWakeRecorder { trait_name: trait_name.clone(), woken: woken.clone() }));
+            manager.wait_for_impl(Some(trait_name), waker);
+        };
+
+        // A blanket impl, whose waiters could be affected by any impl after it
+        manager.wake_impls_of(Some("Blanket".to_string()), true);
+        wait(&mut manager, "Blanket".to_string());
+        for i in 0..traits {
+            wait(&mut manager, format!("Trait{}", i));
+        }
+
+        for i in 0..traits {
+            manager.wake_impls_of(Some(format!("Trait{}", i)), false);
+            wakes += woken.lock().unwrap().len();
+            for trait_name in woken.lock().unwrap().drain(..) {
+                // Traits are implemented in order, so only later traits are still being waited for
+                let waiting = match trait_name.strip_prefix("Trait") {
+                    Some(index) => index.parse::<usize>().unwrap() > i,
+                    None => true,
+                };
+                if waiting {
+                    wait(&mut manager, trait_name);
+                }
+            }
+        }
+
+        // Every impl wakes its own trait's waiter and the blanket impl's waiter
+        assert_eq!(wakes, traits * 2);
+        assert!(manager.trait_waiters.keys().eq(["Blanket".to_string()].iter()));
+    }
+
+    /// Checks that IO tasks can be spawned on single-threaded arguments, which have no IO runtime
+    #[test]
+    pub fn test_single_threaded_io() {
+        let arguments = Arguments::build_args(
+            true,
+            RunnerSettings { sources: vec![], compiler_arguments: CompilerArguments::default() },
+        );
+        assert!(arguments.io_runtime.is_none());
+        let task = arguments.io_or_cpu().spawn(async { 5 });
+        assert_eq!(arguments.cpu_runtime.block_on(task).unwrap(), 5);
+    }
+
+    /// Checks that #[no_mangle] names a function by the last segment of its name, and #[export_name] renames it
+    #[test]
+    pub fn test_symbol_names() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "no-mangle.rv"].iter().collect();
+        let mut arguments = test_arguments("no-mangle::main");
+        let output = compile_project(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))]).unwrap();
+        assert!(output.contains("@main("), "No main symbol in:\n{}", output);
+        assert!(output.contains("@raven_exported("), "No exported symbol in:\n{}", output);
+        assert!(!output.contains("no-mangle::main"), "Main was mangled in:\n{}", output);
+    }
+
+    /// Checks that two functions compiled to the same symbol is an error at whichever is compiled second
+    #[test]
+    pub fn test_duplicate_symbol() {
+        let script = "fn main() -> u64 {\n    return shared() + other();\n}\n\n\
+            #[no_mangle]\npub fn shared() -> u64 {\n    return 1;\n}\n\n\
+            #[export_name = \"shared\"]\npub fn other() -> u64 {\n    return 2;\n}\n";
+        let mut builder = Engine::builder();
+        for source in std_sources(env::consts::OS) {
+            builder = builder.source_set(source);
+        }
+        let Err(errors) = builder.script("script", script).build() else {
+            panic!("Compiled with two functions named shared!");
+        };
+        let found = errors
+            .iter()
+            .filter(|error| error.message.to_string().starts_with("This function is compiled to the symbol shared,"))
+            .collect::<Vec<_>>();
+        assert_eq!(found.len(), 1, "Expected one duplicate symbol, found: {:?}", errors);
+
+        // The script's functions are compiled in any order, so either one can be the second
+        let tokens = tokenize(script.as_bytes()).collect::<Vec<_>>();
+        let other = match tokens[found[0].span.start].start.0 {
+            6 => "script::other",
+            11 => "script::shared",
+            line => panic!("Duplicate symbol at line {}", line),
+        };
+        let expected = format!("This function is compiled to the symbol shared, which {} already uses", other);
+        assert_eq!(found[0].message.to_string(), expected);
+    }
+
+    /// Checks that compiling for another target sets the module's triple, and unknown targets are rejected
+    #[test]
+    pub fn test_cross_compile_target() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "variables.rv"].iter().collect();
+        for (triple, valid) in [("x86_64-pc-windows-gnu", true), ("nonsense-pc-windows-gnu", false)] {
+            let mut arguments = test_arguments("variables::test");
+            arguments.runner_settings.compiler_arguments.target_triple = Some(triple.to_string());
+            let output = compile_project(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path.clone()))]);
+            if valid {
+                let output = output.unwrap();
+                assert!(output.contains("x86_64-pc-windows-gnu"), "Target triple not set in:\n{}", output);
+                assert_eq!(arguments.runner_settings.compiler_arguments.target_os, "windows");
+            } else {
+                assert!(output.is_err(), "Unknown target {} wasn't rejected!", triple);
+            }
+        }
+    }
+
+    /// Checks that setting up the arguments enables the #[cfg] conditions of the target and of tests
+    #[test]
+    pub fn test_cfg_arguments() {
+        let mut arguments = test_arguments("conditional-compilation::test");
+        arguments.runner_settings.compiler_arguments.target_triple = Some("x86_64-pc-windows-gnu".to_string());
+        setup_arguments(&mut arguments, &mut vec![]).unwrap();
+        assert_eq!(arguments.runner_settings.compiler_arguments.cfg, vec!["target_os=windows", "os=windows", "test"]);
+    }
+
+    /// Checks that a function only compiled for the host can be called on the host, but isn't found on other targets
+    #[test]
+    pub fn test_cfg_other_target() {
+        let source = format!(
+            "#[cfg(target_os = \"{}\")]\nfn host_only() -> u64 {{\n    return 1;\n}}\n\n\
+            fn main() -> u64 {{\n    return host_only();\n}}\n",
+            env::consts::OS
+        );
+        let script = || {
+            let mut script = StringSourceSet::new("scripts".to_string());
+            script.add("script", source.clone());
+            return vec![Box::new(script) as Box<dyn SourceSet>];
+        };
+
+        let mut arguments = test_arguments("script::main");
+        let (_, result) = build_project::<u64>(&mut arguments, &mut script(), true).unwrap();
+        assert_eq!(result, Some(1));
+
+        let mut arguments = test_arguments("script::main");
+        arguments.runner_settings.compiler_arguments.target_os =
+            if env::consts::OS == "windows" { "linux" } else { "windows" }.to_string();
+        setup_arguments(&mut arguments, &mut script()).unwrap();
+        let syntax = create_syntax(&arguments);
+        let errors = arguments.cpu_runtime.block_on(build(syntax, &arguments)).unwrap_err();
+        assert!(
+            errors.iter().any(|error| error.message.to_string() == "Unknown function!"),
+            "host_only was found on another target: {:?}",
+            errors
+        );
+    }
+
+    /// Checks that dumping a checked function prints its header and code
+    #[test]
+    pub fn test_dump_function() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "variables.rv"].iter().collect();
+        let mut arguments = test_arguments("variables::test");
+        let (syntax, _) = build_project::<()>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))], false).unwrap();
+        let dumped = syntax.lock().dump_function("variables::test", &arguments.runner_settings.sources).unwrap();
+        assert!(dumped.starts_with("fn variables::test()"), "Unexpected dump:\n{}", dumped);
+        assert!(dumped.contains("return "), "Unexpected dump:\n{}", dumped);
+        assert!(syntax.lock().dump_function("variables::missing", &arguments.runner_settings.sources).is_none());
+    }
+
+    /// Builds the app in the test workspace with the lib it depends on, then runs it to call the lib's function
+    #[test]
+    pub fn test_workspace_dependencies() {
+        let app: PathBuf = ["..", "..", "lib", "test", "workspace", "app"].iter().collect();
+        let mut arguments = test_arguments("main::test");
+        let project = build_project_file(&mut arguments, app.join("build.rv")).unwrap();
+        let mut sources: Vec<Box<dyn SourceSet>> = vec![Box::new(FileSourceSet::new(app.join("src")))];
+        add_dependencies(&mut arguments, &project, &app, &mut sources).unwrap();
+        let (_, result) = build_project::<bool>(&mut arguments, &mut sources, true).unwrap();
+        assert_eq!(result, Some(true));
+    }
+
+    /// Checks that start..end is parsed as a range and lowered to the core Range struct
+    #[test]
+    pub fn test_range_lowering() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "ranges.rv"].iter().collect();
+        let mut arguments = test_arguments("ranges::test");
+        let (syntax, _) = build_project::<()>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))], false).unwrap();
+        let dumped = syntax.lock().dump_function("ranges::test", &arguments.runner_settings.sources).unwrap();
+        assert!(dumped.contains("CreateStruct range::Range<u64>"), "Range not lowered in:\n{}", dumped);
+        assert!(dumped.contains("Number 10: u64"), "Range end not typed in:\n{}", dumped);
+    }
+
+    /// Checks that array literals are lowered to the core Array of their elements' type,
+    /// and that empty arrays take the type of the argument they're passed as
+    #[test]
+    pub fn test_array_literals() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "array-literals.rv"].iter().collect();
+        let mut arguments = test_arguments("array-literals::test");
+        let (syntax, _) = build_project::<()>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))], false).unwrap();
+        let dumped = syntax.lock().dump_function("array-literals::test", &arguments.runner_settings.sources).unwrap();
+        assert_eq!(dumped.matches("CreateArray types::array::Array<u64>").count(), 3, "Arrays not typed in:\n{}", dumped);
+        assert!(!dumped.contains("CreateArray ?"), "Empty array not typed in:\n{}", dumped);
+    }
+
+    /// Checks that arithmetic on integer literals is folded, that results below zero are i64s,
+    /// and that folding past a u64 is warned about once, even when the overflow is nested in another operation
+    #[test]
+    pub fn test_constant_folding() {
+        let path: PathBuf = ["..", "..", "lib", "test", "test", "constant-folding.rv"].iter().collect();
+        let mut arguments = test_arguments("constant-folding::test");
+        let (syntax, _) = build_project::<()>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))], false).unwrap();
+        let dumped = syntax.lock().dump_function("constant-folding::folded", &arguments.runner_settings.sources).unwrap();
+        assert!(dumped.contains("UInt 5"), "2 + 3 not folded in:\n{}", dumped);
+        assert!(!dumped.contains("FunctionCall"), "2 + 3 not folded in:\n{}", dumped);
+
+        let dumped = syntax.lock().dump_function("constant-folding::negative", &arguments.runner_settings.sources).unwrap();
+        assert!(dumped.contains("Number 18446744073709551615: i64"), "2 - 3 not folded to -1 in:\n{}", dumped);
+
+        let mut warnings = syntax.lock().warnings.iter().map(|warning| warning.message.to_string()).collect::<Vec<_>>();
+        warnings.retain(|warning| warning.contains("overflows"));
+        warnings.sort();
+        assert_eq!(
+            warnings,
+            vec![
+                "18446744073709551614 + 2 overflows, so it will wrap at runtime",
+                "18446744073709551615 + 1 overflows, so it will wrap at runtime"
+            ],
+            "Wrong warnings"
+        );
+    }
+
+    /// Checks that println writes to stdout. C writes straight to stdout instead of through Rust's captured output,
+    /// so the program is run by this test in a child process and the child's output is read.
+    #[test]
+    pub fn test_println() {
+        if env::var("RAVEN_PRINTLN_CHILD").is_ok() {
+            let path: PathBuf = ["..", "..", "lib", "test", "test", "println.rv"].iter().collect();
+            let mut arguments = test_arguments("println::test");
+            let (_, result) =
+                build_project::<bool>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))], true).unwrap();
+            assert_eq!(result, Some(true));
+            return;
+        }
+
+        let output = Command::new(env::current_exe().unwrap())
+            .args(["test_println", "--nocapture", "--test-threads=1"])
+            .env("RAVEN_PRINTLN_CHILD", "true")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "Child failed: {}", String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.lines().any(|line| line == "hi"), "Didn't print hi:\n{}", stdout);
+    }
+
+    /// An enum translated from Raven by the derive macro
+    #[derive(Debug, PartialEq, RavenExtern)]
+    enum TestEnum {
+        #[raven(name = "script::Empty")]
+        Empty,
+        #[raven(name = "script::Named")]
+        Named(String),
+    }
+
+    /// Checks that derived enums pick the variant from the type_id and translate its payload
+    #[test]
+    pub fn test_derive_enum() {
+        let empty = TestEnum_RavenType { type_id: data::type_id("script::Empty"), Named: AtomicPtr::default() };
+        assert_eq!(unsafe { TestEnum::translate_borrowed(&empty) }, TestEnum::Empty);
+
+        let value = CString::new("value").unwrap();
+        let named =
+            TestEnum_RavenType { type_id: data::type_id("script::Named"), Named: AtomicPtr::new(value.as_ptr() as *mut ()) };
+        assert_eq!(unsafe { TestEnum::translate_borrowed(&named) }, TestEnum::Named("value".to_string()));
+    }
+
+    /// A struct with both a pointer field and fields Raven stores inline
+    #[derive(Debug, PartialEq, RavenExtern, ToRaven)]
+    #[raven(name = "script::Mixed")]
+    struct TestMixed {
+        name: String,
+        count: u64,
+        letter: char,
+    }
+
+    /// Checks that derived structs read primitive fields directly instead of through a pointer
+    #[test]
+    pub fn test_derive_mixed_struct() {
+        let value = CString::new("mixed").unwrap();
+        let mixed =
+            TestMixed_RavenType { type_id: 0, name: AtomicPtr::new(value.as_ptr() as *mut ()), count: 42, letter: b'r' };
+        assert_eq!(
+            unsafe { TestMixed::translate_borrowed(&mixed) },
+            TestMixed { name: "mixed".to_string(), count: 42, letter: 'r' }
+        );
+    }
+
+    /// Checks that derived structs are copied into Raven's layout with the type ID the compiler gives the Raven struct,
+    /// so translating them back gives the same value and frees everything they allocated
+    #[test]
+    pub fn test_derive_to_raven() {
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add(
+            "script",
+            "pub struct Mixed {\n    pub name: str;\n    pub count: u64;\n    pub letter: char;\n}\n".to_string(),
+        );
+        let mut sources = std_sources(env::consts::OS);
+        sources.push(Box::new(script));
+        let arguments = Arguments::build_args(false, RunnerSettings { sources, ..test_settings("") });
+        let syntax = create_syntax(&arguments);
+        arguments.cpu_runtime.block_on(build(syntax.clone(), &arguments)).unwrap();
+        let id = syntax.lock().structures.types.get("script::Mixed").unwrap().id;
+
+        let arena = Arc::new(Arena::default());
+        let mixed = TestMixed { name: "mixed".to_string(), count: 42, letter: 'r' };
+        let pointer = {
+            let _entered = arena.enter();
+            unsafe { mixed.to_raven() }
+        };
+        assert_eq!(unsafe { (*pointer).type_id }, id);
+        assert_eq!(unsafe { TestMixed::translate(pointer, &arena) }, mixed);
+        assert_eq!(arena.allocations(), 0);
+    }
+
+    /// A struct holding another struct, translated from Raven by the derive macro
+    #[derive(Debug, PartialEq, RavenExtern)]
+    struct TestOuter {
+        inner: TestInner,
+    }
+
+    /// The struct inside TestOuter, with a string made at runtime
+    #[derive(Debug, PartialEq, RavenExtern)]
+    struct TestInner {
+        name: String,
+    }
+
+    /// Checks that a value returned by Raven is allocated in the arena, and that freeing it frees the value,
+    /// the struct in it and its string, leaving nothing once the rest of the arena is cleared
+    #[test]
+    pub fn test_arena_free() {
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add(
+            "script",
+            "import string;\n\n\
+            pub struct Inner {\n    pub name: str;\n}\n\n\
+            pub struct Outer {\n    pub inner: Inner;\n}\n\n\
+            fn main() -> Outer {\n    return new Outer { inner: new Inner { name: \"ar\" + \"ena\" } };\n}\n"
+                .to_string(),
+        );
+        let mut sources = std_sources(env::consts::OS);
+        sources.push(Box::new(script));
+        let arguments = Arguments::build_args(false, RunnerSettings { sources, ..test_settings("script::main") });
+        let arena = arguments.runner_settings.compiler_arguments.arena.clone();
+        let syntax = create_syntax(&arguments);
+        let result = arguments.cpu_runtime.block_on(run::<AtomicPtr<TestOuter_RavenType>>(syntax, &arguments)).unwrap();
+        let pointer = result.unwrap().load(Ordering::Relaxed);
+
+        let outer = unsafe { TestOuter::translate_borrowed(pointer) };
+        assert_eq!(outer, TestOuter { inner: TestInner { name: "arena".to_string() } });
+        let allocated = arena.allocations();
+        unsafe { TestOuter::free(pointer, &arena) };
+        assert_eq!(allocated - arena.allocations(), 3, "The value wasn't freed");
+        arena.clear();
+        assert_eq!(arena.allocations(), 0);
+    }
+
+    /// Checks that the arguments' arena is cleared at the start of a run and once magpie has translated the result,
+    /// so building with the same arguments over and over doesn't keep every run's memory
+    #[test]
+    pub fn test_arena_reset() {
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add("script", "import string;\n\nfn main() -> str {\n    return \"ar\" + \"ena\";\n}\n".to_string());
+        let mut arguments = test_arguments("script::main");
+        let arena = arguments.runner_settings.compiler_arguments.arena.clone();
+        // Memory an earlier run left in the arena
+        {
+            let _entered = arena.enter();
+            unsafe { "leftover".to_string().to_raven() };
+        }
+        assert_eq!(arena.allocations(), 1);
+
+        // The arena is cleared whether or not the program could be run
+        let _ = build_project::<String>(&mut arguments, &mut vec![Box::new(script)], true);
+        assert_eq!(arena.allocations(), 0);
+    }
+
+    /// Checks that a #[link] library that can't be loaded is warned about at the function the attribute is on
+    #[test]
+    pub fn test_library_not_loaded() {
+        let source = "fn main() -> bool {\n    return true;\n}\n\n\
+            #[link(name = \"raven_missing\")]\npub extern fn missing() -> u64 {\n\n}\n";
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add("script", source.to_string());
+        let mut arguments = test_arguments("script::main");
+        let (syntax, result) = build_project::<bool>(&mut arguments, &mut vec![Box::new(script)], true).unwrap();
+        assert_eq!(result, Some(true));
+
+        let warnings = syntax.lock().warnings.clone();
+        let expected = "Couldn't load the library raven_missing to link with";
+        let warning = warnings.iter().find(|warning| warning.message.to_string() == expected);
+        let warning = warning.unwrap_or_else(|| panic!("Expected {:?}, found: {:?}", expected, warnings));
+        let tokens = tokenize(source.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(tokens[warning.span.start].start.0, 6);
+    }
+
+    /// Runs the script's target with a number and a string passed in from Rust
+    fn run_script<R: RavenExtern>(target: &str) -> Option<R> {
+        let mut script = StringSourceSet::new("scripts".to_string());
+        script.add(
+            "script",
+            "pub fn weigh(count: u64, name: str) -> u64 {\n    return count + name.len();\n}\n\n\
+            pub fn pick(count: u64, name: str) -> str {\n    return name;\n}\n"
+                .to_string(),
+        );
+        let mut sources = std_sources(env::consts::OS);
+        sources.push(Box::new(script));
+        let arguments = Arguments::build_args(
+            false,
+            RunnerSettings {
+                sources,
+                compiler_arguments: CompilerArguments {
+                    compiler: "llvm".to_string(),
+                    target: target.to_string(),
+                    ..Default::default()
+                },
+            },
+        );
+        let syntax = create_syntax(&arguments);
+        return arguments
+            .cpu_runtime
+            .block_on(unsafe { run_with::<_, R>(syntax, &arguments, (3u64, "Raven".to_string())) })
+            .unwrap_or_else(|errors| panic!("Failed to run {}: {:?}", target, errors));
+    }
+
+    /// Checks that the target can be run with a number and a string passed in from Rust
+    #[test]
+    pub fn test_run_with_arguments() {
+        assert_eq!(run_script::<u64>("script::weigh"), Some(8));
+        assert_eq!(run_script::<String>("script::pick"), Some("Raven".to_string()));
+    }
+
+    /// Checks that edits mid-file give the same tokens as tokenizing the new file from scratch,
+    /// while only tokenizing again around the edit and keeping the tokens before and after it
+    #[test]
+    pub fn test_incremental_tokens() {
+        let source = "fn first() -> u64 {\n    return 1;\n}\n\nfn second() -> u64 {\n    return 2;\n}\n";
+        let line = "    let value = 4;\n";
+        // Inserting a digit, inserting a line, and replacing a line with a shorter one
+        let edits = [
+            (source.find("return 1").unwrap() + "return ".len(), 0, "3"),
+            (source.find("    return 1").unwrap(), 0, line),
+            (source.find("    return 1").unwrap(), "    return 1;\n".len(), "    1;\n"),
+        ];
+        for (changed_start, removed, replacement) in edits {
+            let changed_end = changed_start + removed;
+            let new_source = format!("{}{}{}", &source[..changed_start], replacement, &source[changed_end..]);
+
+            let mut tokenizer = Tokenizer::new(source.as_bytes());
+            tokenizer.tokenize_all();
+            let prefix = tokenizer.tokens.iter().take_while(|token| token.end_offset < changed_start).count() - 1;
+            let tokenized =
+                tokenizer.tokenize_incremental(new_source.as_bytes(), changed_start, changed_end, replacement.as_bytes());
+
+            let expected = tokenize(new_source.as_bytes()).collect::<Vec<_>>();
+            assert!(prefix > 0, "Edit should leave tokens before it alone!");
+            assert!(tokenized < 8, "Tokenized {} tokens again for {:?}, instead of reusing them", tokenized, replacement);
+            assert!(prefix + tokenized < expected.len(), "No tokens after {:?} were reused!", replacement);
+            assert_eq!(tokenizer.tokens.len(), expected.len());
+            for (found, expected) in tokenizer.tokens.iter().zip(&expected) {
+                assert_eq!(format!("{:?}", found), format!("{:?}", expected));
+            }
+        }
+    }
+
+    /// Checks that loading a saved tokenizer state resumes at the token after it, even after tokenizing further
+    #[test]
+    pub fn test_tokenizer_state() {
+        let source = "struct Pair<T> {\n    first: T,\n}\n\nfn main() -> u64 {\n    return (1 + 2) * 3;\n}\n";
+        let mut tokenizer = Tokenizer::new(source.as_bytes());
+        for _ in 0..12 {
+            tokenizer.next();
+        }
+        let state = tokenizer.serialize();
+        let expected = tokenizer.next();
+        for _ in 0..6 {
+            tokenizer.next();
+        }
+
+        tokenizer.load(&state);
+        assert_eq!(format!("{:?}", tokenizer.next()), format!("{:?}", expected));
+        let rest = tokenize(source.as_bytes()).skip(13).collect::<Vec<_>>();
+        let mut resumed = Vec::default();
+        loop {
+            let token = tokenizer.next();
+            resumed.push(token);
+            if token.token_type == TokenTypes::EOF {
+                break;
+            }
+        }
+        assert_eq!(format!("{:?}", resumed), format!("{:?}", rest));
+    }
+
+    /// Checks that peeking gets the token next would return without moving past it, all the way to the end of the file
+    #[test]
+    pub fn test_tokenizer_peek() {
+        let source = "fn main() -> u64 {\n    // Comment\n    let text = \"a\\n\";\n    return 1 + 2;\n}\n";
+        let mut tokenizer = Tokenizer::new(source.as_bytes());
+        let mut peeked = Vec::default();
+        loop {
+            let first = tokenizer.peek().clone();
+            assert_eq!(format!("{:?}", tokenizer.peek()), format!("{:?}", first), "Peeking twice gave different tokens");
+            let token = tokenizer.next();
+            assert_eq!(format!("{:?}", token), format!("{:?}", first));
+            peeked.push(token);
+            if token.token_type == TokenTypes::EOF {
+                break;
+            }
+        }
+        assert_eq!(format!("{:?}", peeked), format!("{:?}", tokenize(source.as_bytes()).collect::<Vec<_>>()));
+        assert_eq!(tokenizer.peek().token_type, TokenTypes::EOF);
+    }
+
+    /// Checks that formatting fixes spacing, indentation and blank lines, while keeping strings and comments as written
+    #[test]
+    pub fn test_format() {
+        let source = [
+            "import math;",
+            "pub struct Pair<T> {",
+            "  pub first:T;",
+            "}",
+            "// Adds the numbers",
+            "pub fn add(first:u64,second:u64) -> u64 {",
+            "        let total=first+second ;",
+            "    let message = \"keep   this\";",
+            "",
+            "",
+            "  return helper(total,",
+            "      -1",
+            "  ).first;",
+            "}",
+        ]
+        .join("\n");
+        let expected = [
+            "import math;",
+            "",
+            "pub struct Pair<T> {",
+            "    pub first: T;",
+            "}",
+            "",
+            "// Adds the numbers",
+            "pub fn add(first: u64, second: u64) -> u64 {",
+            "    let total = first + second;",
+            "    let message = \"keep   this\";",
+            "",
+            "    return helper(total,",
+            "        -1,",
+            "    ).first;",
+            "}",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(format_source(&source).unwrap(), expected);
+    }
+
+    /// Checks that each file in lib/test/format/unformatted formats to the file with the same name in
+    /// lib/test/format/formatted
+    #[test]
+    pub fn test_format_fixtures() {
+        let fixtures: PathBuf = ["..", "..", "lib", "test", "format"].iter().collect();
+        for entry in fs::read_dir(fixtures.join("unformatted")).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap();
+            let formatted = format_source(&fs::read_to_string(&path).unwrap()).unwrap();
+            let expected = fs::read_to_string(fixtures.join("formatted").join(name)).unwrap();
+            assert_eq!(formatted, expected, "{:?} formatted differently", name);
+        }
+    }
+
+    /// Checks that each core library file can be formatted, and that formatting it twice gives the same code
+    /// as formatting it once
+    #[test]
+    pub fn test_format_core() {
+        let core = std_sources(env::consts::OS).into_iter().find(|source| source.name() == "<core>").unwrap();
+        for file in core.get_files() {
+            let formatted = format_source(&file.contents()).unwrap_or_else(|error| panic!("{}: {}", file.path(), error));
+            assert_eq!(format_source(&formatted).unwrap(), formatted, "{} formatted differently twice", file.path());
+        }
+    }
+
     /// Recursively searches for files in the test folder to run as a test
     fn test_recursive(path: PathBuf) {
         for entry in fs::read_dir(path).unwrap() {
@@ -28,19 +1852,8 @@ mod test {
                 let mod_path =
                     format!("{}::test", &mod_path[path.parent().unwrap().to_str().unwrap().len() + 6..mod_path.len() - 3]);
                 println!("Running {}", mod_path);
-                let mut arguments = Arguments::build_args(
-                    false,
-                    RunnerSettings {
-                        sources: vec![],
-                        compiler_arguments: CompilerArguments {
-                            compiler: "llvm".to_string(),
-                            target: mod_path.clone(),
-                            temp_folder: env::current_dir().unwrap().join("target"),
-                        },
-                    },
-                );
-
-                match build_project::<bool>(&mut arguments, &mut vec![Box::new(FileSourceSet { root: path })], true) {
+                let mut arguments = test_arguments(&mod_path);
+                match build_project::<bool>(&mut arguments, &mut vec![Box::new(FileSourceSet::new(path))], true) {
                     Ok((_, inner)) => match inner {
                         Some(found) => {
                             if !found {